@@ -0,0 +1,78 @@
+use anyhow::{Context, Result};
+use reqwest::header::{HeaderName, HeaderValue};
+
+/// Where an API key is sent in each request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiKeyLocation {
+    /// In a header with the given name
+    Header,
+    /// In a query parameter with the given name
+    Query,
+}
+
+/// API key to be sent with each request, in a header or query parameter named `name`.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ApiKeyLogin {
+    pub name: String,
+    pub location: ApiKeyLocation,
+    pub value: String,
+}
+
+impl ApiKeyLogin {
+    /// Checks that the key can be sent in a header, if that is where it goes.
+    pub fn validate(&self) -> Result<()> {
+        if self.location == ApiKeyLocation::Header {
+            HeaderName::from_bytes(self.name.as_bytes())
+                .with_context(|| format!("Can't use {} as API key header name", self.name))?;
+            HeaderValue::from_str(&self.value).context("Can't use the API key as header value")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ApiKeyLocation, ApiKeyLogin};
+    use crate::authentication::Mode;
+
+    #[test]
+    fn test_header_key_round_trip() {
+        let yaml = "mode: apikey\nconfiguration:\n  name: X-API-Key\n  location: header\n  value: s3cr3t\n";
+        let Mode::ApiKey(login) = serde_yaml::from_str(yaml).unwrap() else {
+            panic!("Not parsed as API key mode");
+        };
+        assert_eq!(
+            login,
+            ApiKeyLogin {
+                name: "X-API-Key".to_owned(),
+                location: ApiKeyLocation::Header,
+                value: "s3cr3t".to_owned(),
+            }
+        );
+        assert!(login.validate().is_ok());
+
+        let reparsed: ApiKeyLogin =
+            serde_yaml::from_str(&serde_yaml::to_string(&login).unwrap()).unwrap();
+        assert_eq!(reparsed, login);
+    }
+
+    #[test]
+    fn test_query_key_round_trip() {
+        let login = ApiKeyLogin {
+            name: "api_key".to_owned(),
+            location: ApiKeyLocation::Query,
+            value: "s3cr3t".to_owned(),
+        };
+        let yaml = serde_yaml::to_string(&login).unwrap();
+        assert!(yaml.contains("location: query"));
+        assert_eq!(serde_yaml::from_str::<ApiKeyLogin>(&yaml).unwrap(), login);
+
+        let invalid = ApiKeyLogin {
+            name: "not a header".to_owned(),
+            location: ApiKeyLocation::Header,
+            value: "s3cr3t".to_owned(),
+        };
+        assert!(invalid.validate().is_err());
+    }
+}
@@ -7,9 +7,11 @@ use super::oauth;
 /// "accessToken" - contains the (OAuth) access token
 /// "refreshToken" - contains the refresh token
 /// "authentication" / "payload" / "exp": contains a unix timestamp
-/// at which the access token expires.
+/// at which the access token expires. Alternatively, "expires_in" contains
+/// the number of seconds until then.
 ///
-/// The .login() method yields an OAuth authentication object.
+/// The .login() method yields an OAuth authentication object. Shortly before
+/// the access token expires, it is refreshed at the same URL.
 #[derive(Debug, Clone, serde::Deserialize)]
 pub struct CustomLogin {
     /// URL to send an authentication request to
@@ -19,40 +21,19 @@ pub struct CustomLogin {
     // It's not a bug: you can deserialize from yaml into a json value!
     request_body: serde_json::Value,
 }
-#[derive(Debug, Clone, serde::Deserialize)]
-struct Response {
-    #[serde(rename = "accessToken")]
-    access_token: String,
-    #[serde(rename = "refreshToken")]
-    refresh_token: String,
-    authentication: Authentication,
-}
-
-#[derive(Debug, Clone, serde::Deserialize)]
-struct Authentication {
-    payload: Payload,
-}
-#[derive(Debug, Clone, serde::Deserialize)]
-struct Payload {
-    // Expiry Unix timestamp
-    exp: u64,
-}
-
 impl CustomLogin {
     pub fn login(self) -> anyhow::Result<oauth::Tokens> {
         let client = reqwest::blocking::Client::new();
-        let response: Response = client
+        let response: oauth::TokenResponse = client
             .post(&self.url)
             .json(&self.request_body)
             .header("User-Agent", "wuppiefuzz/0.1.0")
             .send()?
             .json()?;
 
-        Ok(oauth::Tokens {
-            access_token: response.access_token,
-            _refresh_token: response.refresh_token,
-            _refresh_url: reqwest::Url::parse(&self.url)?,
-            expiry_timestamp: response.authentication.payload.exp,
-        })
+        Ok(oauth::Tokens::from_response(
+            reqwest::Url::parse(&self.url)?,
+            response,
+        ))
     }
 }
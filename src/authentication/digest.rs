@@ -0,0 +1,357 @@
+//! HTTP Digest authentication (RFC 7616). The server first answers a request with a
+//! `401 Unauthorized` and a `WWW-Authenticate: Digest ...` challenge. Every following
+//! request carries an Authorization header answering that challenge, computed from the
+//! username, password, method and URI of the request, and a nonce count that increases
+//! with each request.
+
+use std::sync::{Arc, Mutex};
+
+use openssl::hash::{hash, MessageDigest};
+use reqwest::header::{HeaderMap, WWW_AUTHENTICATE};
+
+/// Login configuration for Digest authentication.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct DigestLogin {
+    username: String,
+    password: String,
+}
+
+/// Hash algorithm requested by the server
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Algorithm {
+    Md5,
+    Sha256,
+}
+
+impl Algorithm {
+    fn name(self) -> &'static str {
+        match self {
+            Algorithm::Md5 => "MD5",
+            Algorithm::Sha256 => "SHA-256",
+        }
+    }
+
+    /// Hex-encoded hash of the data
+    fn hash(self, data: &str) -> String {
+        let digest = match self {
+            Algorithm::Md5 => MessageDigest::md5(),
+            Algorithm::Sha256 => MessageDigest::sha256(),
+        };
+        hash(digest, data.as_bytes())
+            .expect("OpenSSL can compute MD5 and SHA-256 hashes")
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+}
+
+/// A Digest challenge from a `WWW-Authenticate` header
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Challenge {
+    realm: String,
+    nonce: String,
+    opaque: Option<String>,
+    algorithm: Algorithm,
+    /// Whether the `-sess` variant of the algorithm is used
+    session: bool,
+    /// Whether the server supports quality of protection `auth`. If not, the response
+    /// is computed as in RFC 2069.
+    qop_auth: bool,
+    stale: bool,
+}
+
+impl Challenge {
+    /// Parses the value of a `WWW-Authenticate` header. Returns None if it is not a
+    /// Digest challenge, or if it asks for an algorithm or quality of protection that is
+    /// not supported.
+    fn parse(header: &str) -> Option<Self> {
+        let (scheme, params) = header.trim().split_once(' ')?;
+        if !scheme.eq_ignore_ascii_case("digest") {
+            return None;
+        }
+        let params = parse_params(params);
+        let param = |name: &str| {
+            params
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.clone())
+        };
+        let algorithm_name = param("algorithm").unwrap_or_else(|| "MD5".to_owned());
+        let (algorithm_name, session) = match algorithm_name.to_ascii_uppercase() {
+            name if name.ends_with("-SESS") => (name.trim_end_matches("-SESS").to_owned(), true),
+            name => (name, false),
+        };
+        let algorithm = match algorithm_name.as_str() {
+            "MD5" => Algorithm::Md5,
+            "SHA-256" => Algorithm::Sha256,
+            _ => return None,
+        };
+        let qop_auth = match param("qop") {
+            None => false,
+            Some(qop) if qop.split(',').any(|option| option.trim() == "auth") => true,
+            // Only auth-int is offered, which would require hashing the body
+            Some(_) => return None,
+        };
+        Some(Self {
+            realm: param("realm")?,
+            nonce: param("nonce")?,
+            opaque: param("opaque"),
+            algorithm,
+            session,
+            qop_auth,
+            stale: param("stale").is_some_and(|stale| stale.eq_ignore_ascii_case("true")),
+        })
+    }
+}
+
+/// Splits the parameters of a challenge, `key=value` or `key="quoted, value"` separated
+/// by commas, into keys and unquoted values.
+fn parse_params(params: &str) -> Vec<(String, String)> {
+    let mut result = Vec::new();
+    let mut rest = params.trim();
+    while let Some((key, after_key)) = rest.split_once('=') {
+        let key = key.trim().trim_start_matches(',').trim().to_owned();
+        let after_key = after_key.trim_start();
+        let (value, after_value) = match after_key.strip_prefix('"') {
+            Some(quoted) => {
+                let mut value = String::new();
+                let mut chars = quoted.char_indices();
+                let mut end = quoted.len();
+                while let Some((index, c)) = chars.next() {
+                    match c {
+                        '\\' => value.extend(chars.next().map(|(_, escaped)| escaped)),
+                        '"' => {
+                            end = index + 1;
+                            break;
+                        }
+                        c => value.push(c),
+                    }
+                }
+                (value, &quoted[end..])
+            }
+            None => {
+                let end = after_key.find(',').unwrap_or(after_key.len());
+                (after_key[..end].trim().to_owned(), &after_key[end..])
+            }
+        };
+        result.push((key, value));
+        rest = after_value.trim_start().trim_start_matches(',');
+    }
+    result
+}
+
+/// The challenge currently answered, and the number of requests that answered it
+#[derive(Debug)]
+struct Session {
+    challenge: Challenge,
+    nonce_count: u32,
+}
+
+/// Digest authentication state, shared between clones so that all requests count
+/// towards the same nonce.
+#[derive(Debug, Clone)]
+pub struct DigestAuth {
+    login: DigestLogin,
+    session: Arc<Mutex<Option<Session>>>,
+}
+
+impl DigestAuth {
+    pub fn new(login: DigestLogin) -> Self {
+        Self {
+            login,
+            session: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub fn username(&self) -> &str {
+        &self.login.username
+    }
+
+    /// Takes the Digest challenge from the headers of a `401 Unauthorized` response.
+    /// Returns whether the request should be sent again: only if the challenge is new,
+    /// or the server indicated that the previous nonce went stale. A challenge that was
+    /// already answered means the credentials were rejected.
+    pub fn accept_challenge(&self, headers: &HeaderMap) -> bool {
+        let Some(challenge) = headers
+            .get_all(WWW_AUTHENTICATE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .filter_map(Challenge::parse)
+            // Prefer the strongest algorithm, then the order in which the server offers them
+            .min_by_key(|challenge| challenge.algorithm != Algorithm::Sha256)
+        else {
+            return false;
+        };
+        let mut session = self.session.lock().unwrap();
+        let is_new = session
+            .as_ref()
+            .is_none_or(|session| session.challenge.nonce != challenge.nonce || challenge.stale);
+        if is_new {
+            *session = Some(Session {
+                challenge,
+                nonce_count: 0,
+            });
+        }
+        is_new
+    }
+
+    /// The Authorization header value for a request with the given method and URI
+    /// (path and query), once a challenge has been received. Every call counts as a new
+    /// request for the nonce count.
+    pub fn header_value(&self, method: &str, uri: &str) -> Option<String> {
+        let mut session = self.session.lock().unwrap();
+        let session = session.as_mut()?;
+        session.nonce_count += 1;
+        let cnonce = format!("{:016x}", rand::random::<u64>());
+        Some(authorization(
+            &self.login,
+            &session.challenge,
+            method,
+            uri,
+            session.nonce_count,
+            &cnonce,
+        ))
+    }
+}
+
+/// The value of the Authorization header answering `challenge`
+fn authorization(
+    login: &DigestLogin,
+    challenge: &Challenge,
+    method: &str,
+    uri: &str,
+    nonce_count: u32,
+    cnonce: &str,
+) -> String {
+    let algorithm = challenge.algorithm;
+    let nc = format!("{nonce_count:08x}");
+    let mut ha1 = algorithm.hash(&format!(
+        "{}:{}:{}",
+        login.username, challenge.realm, login.password
+    ));
+    if challenge.session {
+        ha1 = algorithm.hash(&format!("{ha1}:{}:{cnonce}", challenge.nonce));
+    }
+    let ha2 = algorithm.hash(&format!("{method}:{uri}"));
+    let response = if challenge.qop_auth {
+        algorithm.hash(&format!(
+            "{ha1}:{}:{nc}:{cnonce}:auth:{ha2}",
+            challenge.nonce
+        ))
+    } else {
+        algorithm.hash(&format!("{ha1}:{}:{ha2}", challenge.nonce))
+    };
+
+    let algorithm_name = if challenge.session {
+        format!("{}-sess", algorithm.name())
+    } else {
+        algorithm.name().to_owned()
+    };
+    let mut value = format!(
+        "Digest username=\"{}\", realm=\"{}\", uri=\"{uri}\", algorithm={algorithm_name}, nonce=\"{}\"",
+        login.username, challenge.realm, challenge.nonce
+    );
+    if challenge.qop_auth {
+        value.push_str(&format!(", nc={nc}, cnonce=\"{cnonce}\", qop=auth"));
+    }
+    value.push_str(&format!(", response=\"{response}\""));
+    if let Some(opaque) = &challenge.opaque {
+        value.push_str(&format!(", opaque=\"{opaque}\""));
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::header::{HeaderMap, HeaderValue, WWW_AUTHENTICATE};
+
+    use super::{authorization, Algorithm, Challenge, DigestAuth, DigestLogin};
+
+    /// The credentials of the examples in RFC 7616, section 3.9.1
+    fn mufasa() -> DigestLogin {
+        DigestLogin {
+            username: "Mufasa".to_owned(),
+            password: "Circle of Life".to_owned(),
+        }
+    }
+
+    fn rfc_challenge(algorithm: &str) -> String {
+        format!(
+            "Digest realm=\"http-auth@example.org\", qop=\"auth, auth-int\", \
+             algorithm={algorithm}, nonce=\"7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v\", \
+             opaque=\"FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS\""
+        )
+    }
+
+    const RFC_CNONCE: &str = "f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ";
+
+    #[test]
+    fn test_rfc_7616_md5() {
+        let challenge = Challenge::parse(&rfc_challenge("MD5")).unwrap();
+        assert_eq!(challenge.algorithm, Algorithm::Md5);
+        let value = authorization(
+            &mufasa(),
+            &challenge,
+            "GET",
+            "/dir/index.html",
+            1,
+            RFC_CNONCE,
+        );
+        assert!(value.contains("response=\"8ca523f5e9506fed4657c9700eebdbec\""));
+        assert!(value.contains("nc=00000001"));
+        assert!(value.contains("opaque=\"FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS\""));
+    }
+
+    #[test]
+    fn test_rfc_7616_sha256() {
+        let challenge = Challenge::parse(&rfc_challenge("SHA-256")).unwrap();
+        assert_eq!(challenge.algorithm, Algorithm::Sha256);
+        let value = authorization(
+            &mufasa(),
+            &challenge,
+            "GET",
+            "/dir/index.html",
+            1,
+            RFC_CNONCE,
+        );
+        assert!(value.contains(
+            "response=\"753927fa0e85d155564e2e272a28d1802ca10daf4496794697cf8db5856cb6c1\""
+        ));
+    }
+
+    #[test]
+    fn test_challenge_is_answered_once() {
+        let digest = DigestAuth::new(mufasa());
+        assert_eq!(digest.header_value("GET", "/"), None);
+
+        let mut headers = HeaderMap::new();
+        headers.append(
+            WWW_AUTHENTICATE,
+            HeaderValue::from_static("Basic realm=\"x\""),
+        );
+        headers.append(
+            WWW_AUTHENTICATE,
+            HeaderValue::from_str(&rfc_challenge("MD5")).unwrap(),
+        );
+        assert!(digest.accept_challenge(&headers));
+        // The same nonce again means the credentials were rejected
+        assert!(!digest.accept_challenge(&headers));
+
+        let first = digest.header_value("GET", "/dir/index.html").unwrap();
+        let second = digest.header_value("POST", "/dir/index.html").unwrap();
+        assert!(first.starts_with("Digest username=\"Mufasa\""));
+        assert!(first.contains("nc=00000001"));
+        assert!(second.contains("nc=00000002"));
+    }
+
+    #[test]
+    fn test_unsupported_challenges() {
+        assert!(Challenge::parse("Basic realm=\"x\"").is_none());
+        assert!(
+            Challenge::parse("Digest realm=\"x\", nonce=\"y\", algorithm=SHA-512-256").is_none()
+        );
+        assert!(Challenge::parse("Digest realm=\"x\", nonce=\"y\", qop=\"auth-int\"").is_none());
+        let legacy = Challenge::parse("Digest realm=\"x\", nonce=\"y\"").unwrap();
+        assert!(!legacy.qop_auth);
+    }
+}
@@ -3,17 +3,24 @@ use std::{borrow::Cow, fs::File, path::Path};
 use anyhow::{Context, Result};
 use cookie_store::{Cookie, RawCookie};
 use openapiv3::OpenAPI;
-use reqwest::header::{HeaderMap, IntoHeaderName, AUTHORIZATION};
-use url::Url;
+use reqwest::{
+    blocking::{Client, Request},
+    header::{HeaderMap, HeaderName, HeaderValue, IntoHeaderName, AUTHORIZATION},
+    StatusCode,
+};
+use url::{Position, Url};
 
 use crate::configuration::Configuration;
 
+pub mod api_key;
 pub mod basic;
 pub mod bearer;
 pub mod cookie;
 pub mod custom;
+pub mod digest;
 pub mod oauth;
 pub mod raw;
+pub mod suggest;
 pub mod verify_auth;
 
 /// Authentication mode and configuration. The configuration data
@@ -34,6 +41,12 @@ pub enum Mode {
     Custom(custom::CustomLogin),
     #[serde(rename = "cookie")]
     Cookie(cookie::CookieLogin),
+    #[serde(rename = "apikey")]
+    ApiKey(api_key::ApiKeyLogin),
+    #[serde(rename = "digest")]
+    Digest(digest::DigestLogin),
+    #[serde(rename = "oauth")]
+    OAuth(oauth::OAuthLogin),
 }
 
 /// Authentication details received after logging in. Depending on the
@@ -54,9 +67,14 @@ pub enum Authentication {
     Bearer(String),
     /// Cookie; the contained value is an initial set of cookies
     Cookie(Vec<RawCookie<'static>>),
-    /// OAuth authentication: the contained value is an access token and a
-    /// refresh roken
+    /// OAuth authentication: the contained value is an access token, the scheme it
+    /// is sent with and a refresh roken
     OAuth(oauth::Tokens),
+    /// API key; the contained value is the key and where to send it
+    ApiKey(api_key::ApiKeyLogin),
+    /// Digest authentication; the contained value is the login and the last challenge
+    /// received from the server
+    Digest(digest::DigestAuth),
 }
 
 /// This function uses the command line configuration to log in to the API
@@ -64,18 +82,36 @@ pub enum Authentication {
 /// to configure a `reqwest::Client`.
 pub fn initialize() -> Result<Authentication> {
     let clargs = Configuration::must_get();
-    initialize_from_config(clargs.authentication.as_deref())
+    initialize_from_config(clargs.authentication.as_deref(), |name| {
+        std::env::var(name).ok()
+    })
 }
 
-pub fn initialize_from_config(config_path: Option<&Path>) -> Result<Authentication> {
+/// Logs in as configured in the authentication file at `config_path`, if any. Values in
+/// the file may contain `${NAME}` placeholders, which are replaced by the value of the
+/// environment variable NAME, so that secrets need not be stored in the file. `var`
+/// returns the value of an environment variable if it is set.
+pub fn initialize_from_config<F>(config_path: Option<&Path>, var: F) -> Result<Authentication>
+where
+    F: Fn(&str) -> Option<String>,
+{
     let auth_mode = match config_path {
         None => Mode::None,
-        Some(path) => serde_yaml::from_reader(File::open(path).with_context(|| {
-            format!("Error opening file given for --authentication, which is {path:?}")
-        })?)
-        .with_context(|| {
-            format!("Error parsing file given for --authentication, which is {path:?}")
-        })?,
+        Some(path) => {
+            let mut config: serde_yaml::Value =
+                serde_yaml::from_reader(File::open(path).with_context(|| {
+                    format!("Error opening file given for --authentication, which is {path:?}")
+                })?)
+                .with_context(|| {
+                    format!("Error parsing file given for --authentication, which is {path:?}")
+                })?;
+            expand_env_vars(&mut config, &var).with_context(|| {
+                format!("Error in file given for --authentication, which is {path:?}")
+            })?;
+            serde_yaml::from_value(config).with_context(|| {
+                format!("Error parsing file given for --authentication, which is {path:?}")
+            })?
+        }
     };
 
     Ok(match auth_mode {
@@ -100,9 +136,65 @@ pub fn initialize_from_config(config_path: Option<&Path>) -> Result<Authenticati
                 .map(|(name, value)| RawCookie::new(name, value))
                 .collect(),
         ),
+        Mode::ApiKey(config) => {
+            config
+                .validate()
+                .context("Error in API key authentication configuration")?;
+            Authentication::ApiKey(config)
+        }
+        Mode::Digest(config) => Authentication::Digest(digest::DigestAuth::new(config)),
+        Mode::OAuth(config) => Authentication::OAuth(
+            config
+                .login()
+                .context("Error during OAuth authentication with the server")?,
+        ),
     })
 }
 
+/// Replaces the `${NAME}` placeholders in all strings in the value by the value of the
+/// environment variable NAME, of which `var` returns the value. Fails if a variable is
+/// not set.
+fn expand_env_vars<F>(value: &mut serde_yaml::Value, var: &F) -> Result<()>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    lazy_static! {
+        static ref PLACEHOLDER: regex::Regex =
+            regex::Regex::new(r"\$\{([A-Za-z_][A-Za-z0-9_]*)\}").unwrap();
+    }
+    match value {
+        serde_yaml::Value::String(text) => {
+            let mut expanded = String::with_capacity(text.len());
+            let mut last_end = 0;
+            for placeholder in PLACEHOLDER.captures_iter(text) {
+                let range = placeholder.get(0).unwrap().range();
+                let name = &placeholder[1];
+                let variable = var(name).with_context(|| {
+                    format!("Environment variable {name} is used in the authentication configuration, but is not set")
+                })?;
+                expanded.push_str(&text[last_end..range.start]);
+                expanded.push_str(&variable);
+                last_end = range.end;
+            }
+            expanded.push_str(&text[last_end..]);
+            *text = expanded;
+        }
+        serde_yaml::Value::Sequence(values) => {
+            for value in values {
+                expand_env_vars(value, var)?;
+            }
+        }
+        serde_yaml::Value::Mapping(mapping) => {
+            for (_, value) in mapping.iter_mut() {
+                expand_env_vars(value, var)?;
+            }
+        }
+        serde_yaml::Value::Tagged(tagged) => expand_env_vars(&mut tagged.value, var)?,
+        serde_yaml::Value::Null | serde_yaml::Value::Bool(_) | serde_yaml::Value::Number(_) => (),
+    }
+    Ok(())
+}
+
 impl Authentication {
     /// Use the contained authentication data to configure a ClientBuilder.
     pub fn generate_headers(&mut self) -> HeaderMap {
@@ -116,13 +208,77 @@ impl Authentication {
                 single_header_force(AUTHORIZATION, &format!("Bearer {token}"))
             }
             Authentication::OAuth(tokens) => {
-                if let Ok(token) = tokens.access_token() {
-                    single_header_force(AUTHORIZATION, token)
+                if let Ok(value) = tokens.authorization() {
+                    single_header_force(AUTHORIZATION, &value)
                 } else {
                     Default::default()
                 }
             }
             Authentication::Cookie(_) => Default::default(),
+            Authentication::ApiKey(config) => match config.location {
+                api_key::ApiKeyLocation::Header => single_header_force(
+                    HeaderName::from_bytes(config.name.as_bytes())
+                        .expect("Could not build API key header"),
+                    &config.value,
+                ),
+                api_key::ApiKeyLocation::Query => Default::default(),
+            },
+            // Depends on the request, see `request_header`
+            Authentication::Digest(_) => Default::default(),
+        }
+    }
+
+    /// The query parameter to append to every request URL, as name and value, if the
+    /// authentication is sent in the query.
+    pub fn query_parameter(&self) -> Option<(&str, &str)> {
+        match self {
+            Authentication::ApiKey(config) if config.location == api_key::ApiKeyLocation::Query => {
+                Some((&config.name, &config.value))
+            }
+            _ => None,
+        }
+    }
+
+    /// The Authorization header value for a request with the given method to the given
+    /// URL, for authentication modes where it differs per request or can change during
+    /// a run, such as OAuth access tokens that are refreshed before they expire.
+    pub fn request_header(&self, method: &str, url: &Url) -> Option<HeaderValue> {
+        match self {
+            Authentication::OAuth(tokens) => match tokens.authorization() {
+                Ok(value) => HeaderValue::from_str(&value).ok(),
+                Err(err) => {
+                    log::warn!("{err}");
+                    None
+                }
+            },
+            Authentication::Digest(digest) => digest
+                .header_value(method, &url[Position::BeforePath..Position::AfterQuery])
+                .and_then(|value| HeaderValue::from_str(&value).ok()),
+            _ => None,
+        }
+    }
+
+    /// Sends the request. If the server answers with a challenge the authentication can
+    /// respond to, such as a Digest challenge, the request is sent once more with an
+    /// Authorization header answering it.
+    pub fn execute(
+        &self,
+        client: &Client,
+        request: Request,
+    ) -> reqwest::Result<reqwest::blocking::Response> {
+        let retry = request.try_clone();
+        let response = client.execute(request)?;
+        match (self, retry) {
+            (Authentication::Digest(digest), Some(mut retry))
+                if response.status() == StatusCode::UNAUTHORIZED
+                    && digest.accept_challenge(response.headers()) =>
+            {
+                if let Some(value) = self.request_header(retry.method().as_str(), retry.url()) {
+                    retry.headers_mut().insert(AUTHORIZATION, value);
+                }
+                client.execute(retry)
+            }
+            _ => Ok(response),
         }
     }
 
@@ -144,10 +300,25 @@ impl Authentication {
             Authentication::Raw(text) => Some(Cow::from(text)),
             Authentication::Basic(config) => Some(Cow::from(format!("Basic {config}"))),
             Authentication::Bearer(token) => Some(Cow::from(format!("Bearer {token}"))),
-            Authentication::OAuth(tokens) => Some(Cow::from(&tokens.access_token)),
+            Authentication::OAuth(tokens) => Some(Cow::from(tokens.last_authorization())),
             _ => None,
         }
     }
+
+    /// Return the name and last value of the header that carries the authentication,
+    /// if any, without refreshing it if expired.
+    pub fn last_named_header(&self) -> Option<(Cow<'_, str>, Cow<'_, str>)> {
+        match self {
+            Authentication::ApiKey(config)
+                if config.location == api_key::ApiKeyLocation::Header =>
+            {
+                Some((Cow::from(&config.name), Cow::from(&config.value)))
+            }
+            _ => self
+                .last_header()
+                .map(|value| (Cow::from(AUTHORIZATION.as_str()), value)),
+        }
+    }
 }
 
 fn single_header_force<K>(key: K, value: &str) -> HeaderMap
@@ -165,3 +336,33 @@ where
 pub fn verify_authentication(api: OpenAPI) -> Result<()> {
     verify_auth::verify_auth(api)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use base64::{engine::general_purpose::STANDARD as base64, Engine as _};
+
+    use super::{initialize_from_config, Authentication};
+
+    #[test]
+    fn test_basic_password_from_environment() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            file,
+            "mode: basic\nconfiguration:\n  username: fuzzer\n  password: ${{FUZZ_PASSWORD}}\n"
+        )
+        .unwrap();
+
+        let authentication = initialize_from_config(Some(file.path()), |name| {
+            (name == "FUZZ_PASSWORD").then(|| "s3cr3t".to_owned())
+        });
+        let unset = initialize_from_config(Some(file.path()), |_| None);
+
+        let Authentication::Basic(header_value) = authentication.unwrap() else {
+            panic!("Not basic authentication");
+        };
+        assert_eq!(header_value, base64.encode("fuzzer:s3cr3t"));
+        assert!(format!("{:#}", unset.unwrap_err()).contains("FUZZ_PASSWORD"));
+    }
+}
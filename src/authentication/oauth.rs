@@ -1,29 +1,360 @@
-use anyhow::Error;
+use std::{
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
 
+use anyhow::{anyhow, bail, Context, Error};
+
+/// Number of seconds before its expiry at which the access token is refreshed
+const REFRESH_MARGIN_SECS: u64 = 30;
+
+/// Scheme with which the access token of an OAuth 2.0 grant is sent, if the token
+/// endpoint does not say (RFC 6750)
+const DEFAULT_TOKEN_TYPE: &str = "Bearer";
+
+/// Login configuration for the OAuth 2.0 grants that need no user interaction. The
+/// access token is requested from the token endpoint at `token_url`.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct OAuthLogin {
+    token_url: String,
+    grant_type: GrantType,
+    client_id: String,
+    client_secret: String,
+    scope: Option<String>,
+}
+
+/// The OAuth 2.0 grant with which the access token is obtained
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GrantType {
+    /// The client authenticates as itself with its id and secret (RFC 6749, 4.4)
+    ClientCredentials,
+}
+
+impl OAuthLogin {
+    /// Requests an access token from the token endpoint. If the endpoint hands out no
+    /// refresh token, a new access token is requested the same way when it expires.
+    pub fn login(self) -> anyhow::Result<Tokens> {
+        let token_url = reqwest::Url::parse(&self.token_url)
+            .with_context(|| format!("Invalid token URL {}", self.token_url))?;
+        let mut response = self.request_token(&token_url)?;
+        response
+            .token_type
+            .get_or_insert_with(|| DEFAULT_TOKEN_TYPE.to_owned());
+        let mut tokens = Tokens::from_response(token_url, response);
+        tokens.login = Some(self);
+        Ok(tokens)
+    }
+
+    fn request_token(&self, token_url: &reqwest::Url) -> reqwest::Result<TokenResponse> {
+        let grant_type = match self.grant_type {
+            GrantType::ClientCredentials => "client_credentials",
+        };
+        let mut form_data = vec![
+            ("grant_type", grant_type),
+            ("client_id", &self.client_id),
+            ("client_secret", &self.client_secret),
+        ];
+        if let Some(scope) = &self.scope {
+            form_data.push(("scope", scope));
+        }
+        reqwest::blocking::Client::new()
+            .post(token_url.clone())
+            .form(&form_data)
+            .send()
+            .and_then(reqwest::blocking::Response::error_for_status)
+            .and_then(reqwest::blocking::Response::json)
+    }
+}
+
+/// Access and refresh tokens, shared between clones so that a refreshed access token
+/// is used by all of them.
 #[derive(Debug, Clone)]
 pub struct Tokens {
-    pub(crate) access_token: String,
-    pub(crate) _refresh_token: String,
-    pub(crate) _refresh_url: reqwest::Url,
-    pub(crate) expiry_timestamp: u64,
+    refresh_url: reqwest::Url,
+    current: Arc<Mutex<CurrentTokens>>,
+    /// The login with which a new access token is requested if there is no refresh token
+    login: Option<OAuthLogin>,
+}
+
+#[derive(Debug)]
+struct CurrentTokens {
+    access_token: String,
+    /// Scheme of the access token in the Authorization header, e.g. "Bearer". Without
+    /// it, the access token is the whole header value.
+    token_type: Option<String>,
+    refresh_token: Option<String>,
+    expiry_timestamp: u64,
+}
+
+/// A response from a token endpoint, either in the OAuth 2.0 format or in camel case.
+/// The expiry is given as a number of seconds in `expires_in`, or as a Unix timestamp
+/// in `authentication.payload.exp`.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct TokenResponse {
+    #[serde(rename = "accessToken", alias = "access_token")]
+    access_token: String,
+    #[serde(rename = "refreshToken", alias = "refresh_token")]
+    refresh_token: Option<String>,
+    #[serde(rename = "tokenType", alias = "token_type")]
+    token_type: Option<String>,
+    expires_in: Option<u64>,
+    authentication: Option<ExpiryAuthentication>,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+struct ExpiryAuthentication {
+    payload: Payload,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+struct Payload {
+    // Expiry Unix timestamp
+    exp: u64,
+}
+
+impl TokenResponse {
+    /// The Unix timestamp at which the access token expires. Tokens without a known
+    /// expiry are never refreshed.
+    fn expiry_timestamp(&self, now: u64) -> u64 {
+        match (&self.authentication, self.expires_in) {
+            (Some(authentication), _) => authentication.payload.exp,
+            (None, Some(expires_in)) => now.saturating_add(expires_in),
+            (None, None) => u64::MAX,
+        }
+    }
 }
 
 impl Tokens {
+    /// Tokens from the response of the token endpoint at `refresh_url`
+    pub fn from_response(refresh_url: reqwest::Url, response: TokenResponse) -> Self {
+        let expiry_timestamp = response.expiry_timestamp(now());
+        Self {
+            refresh_url,
+            current: Arc::new(Mutex::new(CurrentTokens {
+                access_token: response.access_token,
+                token_type: response.token_type,
+                refresh_token: response.refresh_token,
+                expiry_timestamp,
+            })),
+            login: None,
+        }
+    }
+
     /// Yields the access token, attempting to refresh it if (almost) expired
-    pub fn access_token(&mut self) -> Result<&str, Error> {
-        if self.expires_soon() {
-            // Refresh the tokens
-            unimplemented!("Can't refresh oauth tokens yet")
+    pub fn access_token(&self) -> Result<String, Error> {
+        self.access_token_at(now())
+    }
+
+    /// Yields the value of the Authorization header, with the access token refreshed if
+    /// (almost) expired
+    pub fn authorization(&self) -> Result<String, Error> {
+        self.access_token()?;
+        Ok(self.last_authorization())
+    }
+
+    /// Yields the value of the Authorization header as last obtained, without
+    /// refreshing the access token
+    pub fn last_authorization(&self) -> String {
+        let current = self.current.lock().unwrap();
+        match &current.token_type {
+            Some(token_type) => format!("{token_type} {}", current.access_token),
+            None => current.access_token.clone(),
+        }
+    }
+
+    fn access_token_at(&self, now: u64) -> Result<String, Error> {
+        let mut current = self.current.lock().unwrap();
+        if now.saturating_add(REFRESH_MARGIN_SECS) >= current.expiry_timestamp {
+            self.refresh(&mut current, now)?;
         }
-        Ok(&self.access_token)
+        Ok(current.access_token.clone())
+    }
+
+    /// Obtains a new access token from the token endpoint using the refresh token. The
+    /// refresh token is kept if the endpoint does not hand out a new one. Without a
+    /// refresh token, a new access token is requested with the login if there is one,
+    /// and otherwise the access token is kept as is.
+    fn refresh(&self, current: &mut CurrentTokens, now: u64) -> Result<(), Error> {
+        let Some(refresh_token) = current.refresh_token.take() else {
+            if let Some(login) = &self.login {
+                log::info!("Requesting a new access token at {}", self.refresh_url);
+                let response = login
+                    .request_token(&self.refresh_url)
+                    .map_err(|err| anyhow!("Could not renew the access token: {err}"))?;
+                current.expiry_timestamp = response.expiry_timestamp(now);
+                current.access_token = response.access_token;
+                current.token_type = response.token_type.or(current.token_type.take());
+                current.refresh_token = response.refresh_token;
+                return Ok(());
+            }
+            log::warn!("The access token expires, but there is no refresh token to renew it");
+            current.expiry_timestamp = u64::MAX;
+            return Ok(());
+        };
+        log::info!("Refreshing the access token at {}", self.refresh_url);
+        let response: TokenResponse = match reqwest::blocking::Client::new()
+            .post(self.refresh_url.clone())
+            .form(&[
+                ("grant_type", "refresh_token"),
+                ("refresh_token", &refresh_token),
+            ])
+            .send()
+            .and_then(reqwest::blocking::Response::error_for_status)
+            .and_then(reqwest::blocking::Response::json)
+        {
+            Ok(response) => response,
+            Err(err) => {
+                current.refresh_token = Some(refresh_token);
+                bail!("Could not refresh the access token: {err}");
+            }
+        };
+        current.expiry_timestamp = response.expiry_timestamp(now);
+        current.access_token = response.access_token;
+        current.token_type = response.token_type.or(current.token_type.take());
+        current.refresh_token = response.refresh_token.or(Some(refresh_token));
+        Ok(())
+    }
+}
+
+/// The current Unix timestamp in seconds
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    use reqwest::header::AUTHORIZATION;
+    use url::Url;
+
+    use super::{OAuthLogin, TokenResponse, Tokens};
+    use crate::authentication::Authentication;
+
+    /// Serves a single token response on a local port, and returns the URL and a handle
+    /// yielding the request that was received.
+    fn mock_token_endpoint(body: &'static str) -> (reqwest::Url, thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/token", listener.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0; 1024];
+            // Read until the end of the form body, of which the headers give the length
+            while !has_complete_body(&request) {
+                let read = stream.read(&mut buffer).unwrap();
+                if read == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buffer[..read]);
+            }
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            )
+            .unwrap();
+            String::from_utf8_lossy(&request).into_owned()
+        });
+        (url.parse().unwrap(), handle)
+    }
+
+    fn has_complete_body(request: &[u8]) -> bool {
+        let request = String::from_utf8_lossy(request);
+        let Some((headers, body)) = request.split_once("\r\n\r\n") else {
+            return false;
+        };
+        let content_length = headers
+            .lines()
+            .find_map(|line| {
+                line.to_ascii_lowercase()
+                    .strip_prefix("content-length:")?
+                    .trim()
+                    .parse()
+                    .ok()
+            })
+            .unwrap_or(0);
+        body.len() >= content_length
+    }
+
+    #[test]
+    fn test_token_is_refreshed_near_expiry() {
+        let (url, endpoint) =
+            mock_token_endpoint(r#"{"access_token": "fresh", "expires_in": 3600}"#);
+        let login_response: TokenResponse = serde_json::from_str(
+            r#"{"accessToken": "stale", "refreshToken": "renew-me", "authentication": {"payload": {"exp": 1000}}}"#,
+        )
+        .unwrap();
+        let tokens = Tokens::from_response(url, login_response);
+
+        // Well before expiry, the token endpoint is not contacted
+        assert_eq!(tokens.access_token_at(900).unwrap(), "stale");
+        // Within the margin, the token is refreshed and the refresh token kept
+        assert_eq!(tokens.access_token_at(980).unwrap(), "fresh");
+        let request = endpoint.join().unwrap();
+        assert!(request.starts_with("POST /token"));
+        assert!(request.contains("grant_type=refresh_token&refresh_token=renew-me"));
+
+        let current = tokens.current.lock().unwrap();
+        assert_eq!(current.expiry_timestamp, 980 + 3600);
+        assert_eq!(current.refresh_token.as_deref(), Some("renew-me"));
+    }
+
+    #[test]
+    fn test_client_credentials_grant() {
+        let login = |body| {
+            let (url, endpoint) = mock_token_endpoint(body);
+            let login: OAuthLogin = serde_yaml::from_str(&format!(
+                "token_url: {url}\n\
+                 grant_type: client_credentials\n\
+                 client_id: fuzzer\n\
+                 client_secret: s3cr3t\n\
+                 scope: pets:read pets:write\n"
+            ))
+            .unwrap();
+            let authentication = Authentication::OAuth(login.login().unwrap());
+            (authentication, endpoint.join().unwrap())
+        };
+        let target: Url = "http://localhost:8080/pets".parse().unwrap();
+
+        // Without a token type, the access token is a bearer token
+        let (mut authentication, request) = login(r#"{"access_token": "machine"}"#);
+        assert!(request.starts_with("POST /token"));
+        assert!(request.ends_with(
+            "grant_type=client_credentials&client_id=fuzzer&client_secret=s3cr3t\
+             &scope=pets%3Aread+pets%3Awrite"
+        ));
+        assert_eq!(
+            authentication.request_header("GET", &target).unwrap(),
+            "Bearer machine"
+        );
+        assert_eq!(
+            authentication.generate_headers()[AUTHORIZATION],
+            "Bearer machine"
+        );
+
+        let (authentication, _) = login(r#"{"access_token": "machine", "token_type": "DPoP"}"#);
+        assert_eq!(
+            authentication.request_header("GET", &target).unwrap(),
+            "DPoP machine"
+        );
     }
 
-    fn expires_soon(&self) -> bool {
-        let start = std::time::SystemTime::now();
-        let current_timestamp = start
-            .duration_since(std::time::UNIX_EPOCH)
-            .expect("Time went backwards")
-            .as_secs();
-        current_timestamp + 60 >= self.expiry_timestamp
+    #[test]
+    fn test_expiry_without_refresh_token_keeps_token() {
+        let response: TokenResponse =
+            serde_json::from_str(r#"{"access_token": "only", "expires_in": 10}"#).unwrap();
+        // Nothing listens here, so any refresh attempt would fail
+        let tokens = Tokens::from_response("http://127.0.0.1:1/token".parse().unwrap(), response);
+        assert_eq!(tokens.access_token().unwrap(), "only");
+        assert_eq!(tokens.current.lock().unwrap().expiry_timestamp, u64::MAX);
     }
 }
@@ -0,0 +1,229 @@
+//! Suggests an authentication configuration based on the security schemes declared in
+//! the OpenAPI specification, to help new users get started with `--authentication`.
+
+use openapiv3::{APIKeyLocation, OAuth2Flows, OpenAPI, SecurityScheme};
+
+/// Placeholder for values the user has to fill in
+const FILL_IN: &str = "<fill in>";
+
+/// Returns a skeleton authentication file for the security scheme the API requires,
+/// with comments explaining what to fill in.
+pub fn suggest_authentication(api: &OpenAPI) -> String {
+    match required_scheme(api) {
+        Some((name, scheme)) => suggestion_for_scheme(name, scheme),
+        None => "# The specification declares no security schemes, so no authentication is needed.\nmode: none\n".to_owned(),
+    }
+}
+
+/// Finds the security scheme the API requires: the first one in the global security
+/// requirements or, failing that, in those of the operations. If no requirements are
+/// given, the first declared scheme is used.
+fn required_scheme(api: &OpenAPI) -> Option<(&str, &SecurityScheme)> {
+    fn resolve<'a>(api: &'a OpenAPI, name: &'a String) -> Option<(&'a str, &'a SecurityScheme)> {
+        api.components
+            .security_schemes
+            .get(name)
+            .and_then(|ref_or_scheme| ref_or_scheme.as_item())
+            .map(|scheme| (name.as_str(), scheme))
+    }
+    api.security
+        .iter()
+        .chain(
+            api.operations()
+                .filter_map(|(_, _, operation, _)| operation.security.as_ref())
+                .flatten(),
+        )
+        .flat_map(|requirement| requirement.keys())
+        .find_map(|name| resolve(api, name))
+        .or_else(|| {
+            api.components
+                .security_schemes
+                .keys()
+                .find_map(|name| resolve(api, name))
+        })
+}
+
+fn suggestion_for_scheme(name: &str, scheme: &SecurityScheme) -> String {
+    match scheme {
+        SecurityScheme::HTTP { scheme, .. } if scheme.eq_ignore_ascii_case("basic") => format!(
+            "# This API uses HTTP basic authentication (security scheme '{name}').\n\
+             # Fill in the username and password.\n\
+             mode: basic\n\
+             configuration:\n  username: {FILL_IN}\n  password: {FILL_IN}\n"
+        ),
+        SecurityScheme::HTTP { scheme, .. } if scheme.eq_ignore_ascii_case("bearer") => {
+            bearer_skeleton(
+                &format!("This API uses bearer tokens (security scheme '{name}')."),
+                FILL_IN,
+                None,
+            )
+        }
+        SecurityScheme::HTTP { scheme, .. } if scheme.eq_ignore_ascii_case("digest") => format!(
+            "# This API uses HTTP digest authentication (security scheme '{name}').\n\
+             # Fill in the username and password.\n\
+             mode: digest\n\
+             configuration:\n  username: {FILL_IN}\n  password: {FILL_IN}\n"
+        ),
+        SecurityScheme::HTTP { scheme, .. } => format!(
+            "# This API uses HTTP '{scheme}' authentication (security scheme '{name}').\n\
+             # Put the full value of the Authorization header in the contents.\n\
+             mode: raw\n\
+             configuration:\n  contents: {FILL_IN}\n"
+        ),
+        SecurityScheme::OAuth2 { flows, .. } => oauth2_suggestion(name, flows),
+        SecurityScheme::OpenIDConnect {
+            open_id_connect_url,
+            ..
+        } => bearer_skeleton(
+            &format!(
+                "This API uses OpenID Connect (security scheme '{name}'), discoverable at {open_id_connect_url}."
+            ),
+            FILL_IN,
+            None,
+        ),
+        SecurityScheme::APIKey {
+            location: APIKeyLocation::Header,
+            name: header,
+            ..
+        } if header.eq_ignore_ascii_case("authorization") => format!(
+            "# This API expects an API key in the Authorization header (security scheme '{name}').\n\
+             mode: raw\n\
+             configuration:\n  contents: {FILL_IN}\n"
+        ),
+        SecurityScheme::APIKey {
+            location: APIKeyLocation::Header,
+            name: header,
+            ..
+        } => format!(
+            "# This API expects an API key in the '{header}' header (security scheme '{name}').\n\
+             mode: apikey\n\
+             configuration:\n  name: {header}\n  location: header\n  value: {FILL_IN}\n"
+        ),
+        SecurityScheme::APIKey {
+            location: APIKeyLocation::Cookie,
+            name: cookie,
+            ..
+        } => format!(
+            "# This API expects an API key in the '{cookie}' cookie (security scheme '{name}').\n\
+             mode: cookie\n\
+             configuration:\n  set_cookie:\n    {cookie}: {FILL_IN}\n"
+        ),
+        SecurityScheme::APIKey {
+            location: APIKeyLocation::Query,
+            name: parameter,
+            ..
+        } => format!(
+            "# This API expects an API key in the '{parameter}' query parameter (security scheme '{name}').\n\
+             mode: apikey\n\
+             configuration:\n  name: {parameter}\n  location: query\n  value: {FILL_IN}\n"
+        ),
+    }
+}
+
+fn oauth2_suggestion(name: &str, flows: &OAuth2Flows) -> String {
+    // Without a password flow, the fuzzer can log in as a client on its own
+    if let (None, Some(flow)) = (&flows.password, &flows.client_credentials) {
+        let scopes = flow.scopes.keys().cloned().collect::<Vec<_>>().join(" ");
+        return format!(
+            "# This API uses OAuth2 (clientCredentials flow, security scheme '{name}') with token URL {}.\n\
+             # Fill in the credentials of the client.\n\
+             mode: oauth\n\
+             configuration:\n  token_url: {}\n  grant_type: client_credentials\n  \
+             client_id: {FILL_IN}\n  client_secret: {FILL_IN}\n  scope: \"{scopes}\"\n",
+            flow.token_url, flow.token_url
+        );
+    }
+    let flow = [
+        ("password", flows.password.as_ref()),
+        ("clientCredentials", flows.client_credentials.as_ref()),
+    ]
+    .into_iter()
+    .find_map(|(flow_name, flow)| flow.map(|flow| (flow_name, &flow.token_url, &flow.scopes)))
+    .or_else(|| {
+        flows
+            .authorization_code
+            .as_ref()
+            .map(|flow| ("authorizationCode", &flow.token_url, &flow.scopes))
+    });
+    match flow {
+        Some((flow_name, token_url, scopes)) => {
+            let scopes = scopes.keys().cloned().collect::<Vec<_>>().join(" ");
+            bearer_skeleton(
+                &format!(
+                    "This API uses OAuth2 ({flow_name} flow, security scheme '{name}') with token URL {token_url}.\n\
+                     # The fuzzer logs in with the password grant; fill in client_id and the credentials."
+                ),
+                token_url,
+                Some(&scopes),
+            )
+        }
+        None => bearer_skeleton(
+            &format!("This API uses OAuth2 (security scheme '{name}') without a token URL."),
+            FILL_IN,
+            None,
+        ),
+    }
+}
+
+fn bearer_skeleton(explanation: &str, url: &str, scope: Option<&str>) -> String {
+    let mut skeleton = format!(
+        "# {explanation}\n\
+         # Fill in the URL of the login endpoint and the credentials.\n\
+         mode: bearer\n\
+         configuration:\n  url: {url}\n  username: {FILL_IN}\n  password: {FILL_IN}\n"
+    );
+    match scope {
+        Some(scope) if !scope.is_empty() => {
+            skeleton += &format!("  scope: \"{scope}\"\n  client_id: {FILL_IN}\n")
+        }
+        _ => skeleton += &format!("  client_id: {FILL_IN}  # optional\n"),
+    }
+    skeleton
+}
+
+#[cfg(test)]
+mod tests {
+    use openapiv3::{OpenAPI, VersionedOpenAPI};
+    use serde_json::json;
+
+    use super::suggest_authentication;
+    use crate::authentication::Mode;
+
+    #[test]
+    fn test_bearer_security_suggests_bearer_mode() {
+        let api: OpenAPI = serde_json::from_value(json!({
+            "openapi": "3.0.0",
+            "info": {"title": "pets", "version": "1"},
+            "paths": {},
+            "components": {
+                "securitySchemes": {
+                    "apiKey": {"type": "apiKey", "in": "query", "name": "key"},
+                    "token": {"type": "http", "scheme": "bearer", "bearerFormat": "JWT"}
+                }
+            },
+            "security": [{"token": []}]
+        }))
+        .unwrap();
+        let skeleton = suggest_authentication(&api);
+        assert!(skeleton.contains("security scheme 'token'"));
+        let mode: Mode = serde_yaml::from_str(&skeleton).unwrap();
+        assert!(matches!(mode, Mode::Bearer(_)));
+    }
+
+    #[test]
+    fn test_security_definitions_survive_conversion() {
+        let api: VersionedOpenAPI = serde_json::from_value(json!({
+            "swagger": "2.0",
+            "info": {"title": "pets", "version": "1"},
+            "paths": {},
+            "securityDefinitions": {
+                "login": {"type": "basic"}
+            },
+            "security": [{"login": []}]
+        }))
+        .unwrap();
+        let skeleton = suggest_authentication(&api.upgrade());
+        let mode: Mode = serde_yaml::from_str(&skeleton).unwrap();
+        assert!(matches!(mode, Mode::Basic(_)));
+    }
+}
@@ -7,10 +7,15 @@ use crate::header;
 
 fn send_request(
     client: reqwest::blocking::Client,
+    authentication: &super::Authentication,
     url: &str,
 ) -> Result<reqwest::blocking::Response, reqwest::Error> {
     // Send the GET request
-    let t_response = match client.get(url).send() {
+    let t_response = match client
+        .get(url)
+        .build()
+        .and_then(|request| authentication.execute(&client, request))
+    {
         Ok(r) => r,
         Err(e) => {
             eprintln!("Request error: {}", e);
@@ -51,13 +56,23 @@ pub fn verify_auth(api: OpenAPI) -> Result<()> {
 
     let client = client_builder.default_headers(default_headers).build()?;
 
+    let query_parameter = authentication
+        .query_parameter()
+        .map(|(name, value)| (name.to_owned(), value.to_owned()));
+
     // Print the response from the authentication
-    match authentication {
-        super::Authentication::None => print_response("None", "None"),
-        super::Authentication::Raw(contents) => print_response("Raw", &contents),
-        super::Authentication::Basic(config) => print_response("Basic", &config),
-        super::Authentication::Bearer(token) => print_response("Bearer", &token),
-        super::Authentication::Cookie(mut cookie_vector) => {
+    match &mut authentication {
+        super::Authentication::None => {
+            print_response("None", "None");
+            if !api.components.security_schemes.is_empty() {
+                println!("\nThe specification declares security schemes, but no authentication is configured.");
+                println!("Run \"wuppiefuzz suggest-auth\" to get a suggested authentication file.");
+            }
+        }
+        super::Authentication::Raw(contents) => print_response("Raw", contents),
+        super::Authentication::Basic(config) => print_response("Basic", config),
+        super::Authentication::Bearer(token) => print_response("Bearer", token),
+        super::Authentication::Cookie(cookie_vector) => {
             let mut cookies = String::new();
             for cookie_item in cookie_vector.iter_mut() {
                 let s = format!(
@@ -70,19 +85,26 @@ pub fn verify_auth(api: OpenAPI) -> Result<()> {
             }
             print_response("Cookie", cookies.as_str());
         }
-        super::Authentication::OAuth(mut tokens) => {
+        super::Authentication::OAuth(tokens) => {
             if let Ok(token) = tokens.access_token() {
-                print_response("OAuth", token);
+                print_response("OAuth", &token);
             } else {
                 print_response("OAuth", "Token");
             }
         }
+        super::Authentication::ApiKey(config) => print_response("ApiKey", &config.value),
+        super::Authentication::Digest(digest) => print_response("Digest", digest.username()),
     };
 
     // Check all paths for a "401 Unauthorized" error, which means authentication has failed
     for (path, _path_item) in api.paths.iter() {
-        let url = server.url.clone() + path;
-        let t_response = send_request(client.clone(), &url);
+        let mut url = server.url.clone() + path;
+        if let Some((name, value)) = &query_parameter {
+            url = Url::parse_with_params(&url, [(name, value)])
+                .map(String::from)
+                .unwrap_or(url);
+        }
+        let t_response = send_request(client.clone(), &authentication, &url);
         if log::log_enabled!(Info) {
             println!("Path: {}", path);
         }
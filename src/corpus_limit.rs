@@ -0,0 +1,131 @@
+//! Keeps the corpus within a maximum number of entries, so that long runs do not fill
+//! the disk. When the corpus grows beyond the limit, the entries that contribute least
+//! are evicted: first those that are not the best entry for any coverage index, oldest
+//! first, and only then the oldest favoured ones.
+
+use libafl::{
+    corpus::{Corpus, CorpusId, HasCurrentCorpusId},
+    schedulers::{minimizer::TopRatedsMetadata, RemovableScheduler},
+    state::HasCorpus,
+    Error, HasMetadata,
+};
+
+/// Removes entries from the corpus until it holds at most `max_entries` of them. The
+/// scheduler is notified of every removal, so it no longer refers to evicted entries.
+/// Returns the number of evicted entries.
+pub fn evict_excess_entries<S, CS>(
+    state: &mut S,
+    scheduler: &mut CS,
+    max_entries: usize,
+) -> Result<usize, Error>
+where
+    S: HasCorpus + HasMetadata + HasCurrentCorpusId,
+    CS: RemovableScheduler<<S::Corpus as Corpus>::Input, S>,
+{
+    let mut evicted = 0;
+    while state.corpus().count() > max_entries {
+        let Some(id) = lowest_value_entry(state)? else {
+            break;
+        };
+        let testcase = state.corpus_mut().remove(id)?;
+        scheduler.on_remove(state, id, &Some(testcase))?;
+        evicted += 1;
+    }
+    if evicted > 0 {
+        log::debug!("Evicted {evicted} corpus entries to stay within {max_entries} entries");
+    }
+    Ok(evicted)
+}
+
+/// The entry that is evicted first: the oldest one that is not the best entry for any
+/// coverage index, or the oldest one overall if all entries are. The entry that is being
+/// fuzzed is never chosen.
+fn lowest_value_entry<S>(state: &S) -> Result<Option<CorpusId>, Error>
+where
+    S: HasCorpus + HasMetadata + HasCurrentCorpusId,
+{
+    let in_use = [state.current_corpus_id()?, *state.corpus().current()];
+    let is_favoured = |id: &CorpusId| {
+        state
+            .metadata_map()
+            .get::<TopRatedsMetadata>()
+            .is_some_and(|top_rated| top_rated.map.values().any(|favoured| favoured == id))
+    };
+    // Ids are handed out in increasing order, so the first ones are the oldest
+    let candidates: Vec<CorpusId> = state
+        .corpus()
+        .ids()
+        .filter(|id| !in_use.contains(&Some(*id)))
+        .collect();
+    Ok(candidates
+        .iter()
+        .find(|id| !is_favoured(id))
+        .or_else(|| candidates.first())
+        .copied())
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl::{
+        corpus::{Corpus, CorpusId, InMemoryCorpus, Testcase},
+        schedulers::{minimizer::TopRatedsMetadata, QueueScheduler},
+        state::HasCorpus,
+        HasMetadata,
+    };
+    use libafl_bolts::rands::StdRand;
+    use openapiv3::OpenAPI;
+    use serde_json::json;
+
+    use super::evict_excess_entries;
+    use crate::{input::OpenApiInput, state::OpenApiFuzzerState};
+
+    #[test]
+    fn test_adding_beyond_limit_evicts_lowest_value() {
+        let api: OpenAPI = serde_json::from_value(json!({
+            "openapi": "3.0.0",
+            "info": {"title": "empty", "version": "1"},
+            "paths": {}
+        }))
+        .unwrap();
+        let mut state = OpenApiFuzzerState::new(
+            StdRand::with_seed(0),
+            InMemoryCorpus::<OpenApiInput>::new(),
+            InMemoryCorpus::<OpenApiInput>::new(),
+            &mut (),
+            &mut (),
+            api,
+        )
+        .unwrap();
+        let mut scheduler = QueueScheduler::new();
+
+        let max_entries = 3;
+        for _ in 0..max_entries {
+            state
+                .corpus_mut()
+                .add(Testcase::new(OpenApiInput(vec![])))
+                .unwrap();
+        }
+        // The oldest entry is the best one for some coverage index
+        let mut top_rated = TopRatedsMetadata::new();
+        top_rated.map.insert(0, CorpusId(0));
+        state.add_metadata(top_rated);
+        assert_eq!(
+            evict_excess_entries(&mut state, &mut scheduler, max_entries).unwrap(),
+            0
+        );
+
+        for _ in 0..2 {
+            state
+                .corpus_mut()
+                .add(Testcase::new(OpenApiInput(vec![])))
+                .unwrap();
+            assert_eq!(
+                evict_excess_entries(&mut state, &mut scheduler, max_entries).unwrap(),
+                1
+            );
+            assert_eq!(state.corpus().count(), max_entries);
+        }
+        let remaining: Vec<CorpusId> = state.corpus().ids().collect();
+        assert_eq!(remaining, vec![CorpusId(0), CorpusId(3), CorpusId(4)]);
+    }
+}
@@ -13,7 +13,6 @@ use reqwest::{
 };
 
 use super::CoverageClient;
-use crate::coverage_clients::MAP_SIZE;
 
 #[derive(Debug, serde::Deserialize)]
 struct CoverbandSegment {
@@ -25,11 +24,11 @@ struct CoverbandSegment {
 /// Coverband coverage client.
 pub struct CoverbandCoverageClient {
     /// The current coverage map
-    cov_map: [u8; MAP_SIZE],
+    cov_map: Vec<u8>,
     /// Baseline number of times each line has been covered. This is the last
     /// coverage array we received from the Coverband endpoint. If we receive a
     /// new one, this allows us to see whether there is new coverage.
-    cov_map_baseline: [u32; 32 * MAP_SIZE],
+    cov_map_baseline: Vec<u32>,
     /// Mapping from filename to spot in the coverage maps
     bit_idx_mapping: HashMap<String, usize>,
     /// First unused index in the coverage maps
@@ -43,11 +42,11 @@ pub struct CoverbandCoverageClient {
 
 impl CoverbandCoverageClient {
     /// Creates a mew Coverband coverage client, given an URL at which the coverage agent
-    /// can be reached.
-    pub fn new(url: Url) -> Self {
+    /// can be reached, with a coverage map of `map_size` bytes.
+    pub fn new(url: Url, map_size: usize) -> Self {
         Self {
-            cov_map: [0; MAP_SIZE],
-            cov_map_baseline: [0; 32 * MAP_SIZE],
+            cov_map: vec![0; map_size],
+            cov_map_baseline: vec![0; 32 * map_size],
             bit_idx_mapping: HashMap::new(),
             first_unused_idx: 0,
             url,
@@ -64,7 +63,7 @@ impl CoverbandCoverageClient {
                 // store the index of where the probe list of this class is stored
                 let stored_index = self.first_unused_idx;
                 self.first_unused_idx += length;
-                if self.first_unused_idx > MAP_SIZE {
+                if self.first_unused_idx > self.cov_map.len() {
                     return Err(libafl::Error::unknown("The map size is not large enough to hold all the probes from the coverage stream. The current size is ".to_string()));
                 }
                 log::info!(
@@ -118,6 +117,10 @@ impl CoverageClient for CoverbandCoverageClient {
         self.cov_map.as_mut_ptr()
     }
 
+    fn get_coverage_len(&self) -> usize {
+        self.cov_map.len()
+    }
+
     fn max_coverage_ratio(&mut self) -> (u64, u64) {
         let count = self
             .cov_map
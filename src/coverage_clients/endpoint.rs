@@ -20,8 +20,9 @@ use std::{
 use build_html::{escape_html, Container, ContainerType, Html, HtmlContainer, HtmlPage};
 use indexmap::{map::Entry, IndexMap};
 use openapiv3::{OpenAPI, StatusCode};
+use serde::{Deserialize, Serialize};
 
-use super::{CoverageClient, MAP_SIZE};
+use super::CoverageClient;
 use crate::input::Method;
 
 const HIT_SYMBOL: &str = "&#x2714;&#xfe0f;";
@@ -31,26 +32,33 @@ const SUPERFLUOUS_SYMBOL: &str = "&#x26a0;&#xfe0f";
 /// Endpoint coverage client.
 pub struct EndpointCoverageClient {
     endpoint_cov_map: IndexMap<(Method, String, StatusCode), Coverage>,
-    cov_map: [u8; MAP_SIZE],
-    cov_map_total: [u8; MAP_SIZE],
+    cov_map: Vec<u8>,
+    cov_map_total: Vec<u8>,
     len: usize,
     max_ratio: (u64, u64),
 }
 
-#[derive(Debug, Clone)]
+/// The coverage of each method-path-status combination, saved to resume a run later
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedEndpointCoverage(Vec<((Method, String, StatusCode), Coverage)>);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[allow(clippy::enum_variant_names)]
 enum Coverage {
     /// This status code occurs in the specification, but was not seen
     ExpectedNotFound,
-    /// This status code occurs in the specification, and was seen
-    ExpectedFound(String, String),
+    /// This status code, or a range such as 2XX that contains it, occurs in the
+    /// specification, and was seen. The status code seen first is kept.
+    ExpectedFound(u16, String, String),
     /// This status code was seen but does not occur in the specification
     UnexpectedFound(String, String),
 }
 
 impl EndpointCoverageClient {
-    /// Creates a new endpoint coverage client given an API specification.
-    pub fn new(api: &OpenAPI) -> Self {
+    /// Creates a new endpoint coverage client given an API specification. The coverage
+    /// map holds `map_size` bytes, or more if the responses in the specification need
+    /// more room.
+    pub fn new(api: &OpenAPI, map_size: usize) -> Self {
         let coverage_index_map: IndexMap<_, _> = api
             .operations()
             // Collect all method-path-status tuples from the API spec
@@ -68,15 +76,13 @@ impl EndpointCoverageClient {
             .collect();
 
         let len = coverage_index_map.len();
-        assert!(
-            len <= MAP_SIZE,
-            "Number of responses in API specification is larger than coverage MAP_SIZE"
-        );
+        // Each response takes one bit in the map
+        let map_size = map_size.max(len.div_ceil(8));
 
         Self {
             endpoint_cov_map: coverage_index_map,
-            cov_map: [0; MAP_SIZE],
-            cov_map_total: [0; MAP_SIZE],
+            cov_map: vec![0; map_size],
+            cov_map_total: vec![0; map_size],
             len,
             max_ratio: (0, len as u64),
         }
@@ -93,11 +99,20 @@ impl EndpointCoverageClient {
         input: String,
         output: String,
     ) {
-        // Get the coverage entry for the method-path-status combination.
+        // Get the coverage entry for the method-path-status combination. If the exact
+        // status is not specified but its range (e.g. 2XX) is, the response hits the range.
         // The entry may be Vacant or Occupied, see below for what this means.
-        let entry = self
-            .endpoint_cov_map
-            .entry((method, path, StatusCode::Code(status.as_u16())));
+        let code = status.as_u16();
+        let mut key = (method, path, StatusCode::Code(code));
+        if !self.endpoint_cov_map.contains_key(&key) {
+            let range = (method, key.1, StatusCode::Range(code / 100));
+            key = if self.endpoint_cov_map.contains_key(&range) {
+                range
+            } else {
+                (method, range.1, StatusCode::Code(code))
+            };
+        }
+        let entry = self.endpoint_cov_map.entry(key);
         // Must get the index before entry.insert below, which needs ownership of the entry
         let index = entry.index();
 
@@ -111,7 +126,7 @@ impl EndpointCoverageClient {
             Entry::Occupied(mut entry) => {
                 // Only new coverage needs to be inserted.
                 if matches!(entry.get(), Coverage::ExpectedNotFound) {
-                    entry.insert(Coverage::ExpectedFound(input, output));
+                    entry.insert(Coverage::ExpectedFound(code, input, output));
                 }
             }
         }
@@ -121,7 +136,12 @@ impl EndpointCoverageClient {
         // For this reason it is important NOT TO REMOVE entries from the endpoint_cov_map
         // during a run: once a triplet is inserted, its index must be unique since this
         // determines the mapping into the AFL-coverage maps.
-        assert!(index / 8 < MAP_SIZE, "Coverage map is full");
+        if index / 8 >= self.cov_map.len() {
+            log::debug!(
+                "Coverage map is full, coverage of {method} with status {status} will be ignored."
+            );
+            return;
+        }
         self.len = std::cmp::max(self.len, index);
         // Map the method-path-status triplets via their index to *bits*, not bytes.
         // Hence the bitwise operations, the first 8 indices get mapped into the first byte
@@ -130,6 +150,41 @@ impl EndpointCoverageClient {
         self.cov_map_total[index / 8] |= 0b10000000 >> (index % 8);
     }
 
+    /// The coverage found so far, to be restored with `restore`
+    pub fn save(&self) -> SavedEndpointCoverage {
+        SavedEndpointCoverage(
+            self.endpoint_cov_map
+                .iter()
+                .filter(|(_, coverage)| !matches!(coverage, Coverage::ExpectedNotFound))
+                .map(|(key, coverage)| (key.clone(), coverage.clone()))
+                .collect(),
+        )
+    }
+
+    /// Marks the coverage of an earlier run as found. Only the total coverage is
+    /// updated, so that the coverage of the first execution after restoring is not
+    /// mixed with it.
+    pub fn restore(&mut self, saved: SavedEndpointCoverage) {
+        for (key, coverage) in saved.0 {
+            let entry = self.endpoint_cov_map.entry(key);
+            let index = entry.index();
+            match entry {
+                Entry::Vacant(entry) => {
+                    entry.insert(coverage);
+                }
+                Entry::Occupied(mut entry) => {
+                    if matches!(entry.get(), Coverage::ExpectedNotFound) {
+                        entry.insert(coverage);
+                    }
+                }
+            }
+            if index / 8 < self.cov_map_total.len() {
+                self.len = std::cmp::max(self.len, index);
+                self.cov_map_total[index / 8] |= 0b10000000 >> (index % 8);
+            }
+        }
+    }
+
     fn export_filesystem(&self, base_path: &Path) -> Result<(), libafl::Error> {
         // Make a tree in the same structure as the html page will contain:
         // path -> method -> status codes
@@ -162,15 +217,23 @@ impl EndpointCoverageClient {
                             // the "input-link" class that reads this attribute's contents and
                             // puts it into the "input-pane".
                             |list, item| match item.1 {
-                                Coverage::ExpectedFound(request, response) => list.with_link_attr(
-                                    "#",
-                                    format!("{} {}", HIT_SYMBOL, item.0),
-                                    [
-                                        ("data-input", escape_html(request).as_str()),
-                                        ("data-output", escape_html(response).as_str()),
-                                        ("class", "input-link c-hit"),
-                                    ],
-                                ),
+                                Coverage::ExpectedFound(code, request, response) => list
+                                    .with_link_attr(
+                                        "#",
+                                        match item.0 {
+                                            StatusCode::Code(_) => {
+                                                format!("{} {}", HIT_SYMBOL, item.0)
+                                            }
+                                            StatusCode::Range(_) => {
+                                                format!("{} {} ({code})", HIT_SYMBOL, item.0)
+                                            }
+                                        },
+                                        [
+                                            ("data-input", escape_html(request).as_str()),
+                                            ("data-output", escape_html(response).as_str()),
+                                            ("class", "input-link c-hit"),
+                                        ],
+                                    ),
                                 Coverage::UnexpectedFound(request, response) => list
                                     .with_link_attr(
                                         "#",
@@ -258,7 +321,7 @@ impl EndpointCoverageClient {
                     .with_attributes([("id", "filters")])
                     .with_raw(
                         format!(r##"
-                        <input id="f-hit" type="checkbox" checked> <label for="f-hit">{HIT_SYMBOL} Hits</label>
+                        <input id="f-hit" type="checkbox" checked> <label for="f-hit">{HIT_SYMBOL} Hits (for a range such as 2XX, the first code seen)</label>
                         <input id="f-miss" type="checkbox" checked> <label for="f-miss">{MISS_SYMBOL} Misses</label>
                         <input id="f-extra" type="checkbox" checked> <label for="f-extra">{SUPERFLUOUS_SYMBOL} Unspecified</label>
                         "##),
@@ -272,6 +335,60 @@ impl EndpointCoverageClient {
 
         Ok(())
     }
+
+    /// Writes the coverage as an lcov tracefile next to the HTML report, for tools that
+    /// aggregate coverage in that format.
+    fn export_lcov(&self, base_path: &Path) -> Result<(), libafl::Error> {
+        create_dir_all(base_path)?;
+        let mut handle = File::create(base_path.join("endpoint_coverage.info"))?;
+        write!(handle, "{}", self.lcov_report())?;
+        Ok(())
+    }
+
+    /// The coverage in the lcov format. Each path is a source file, each operation on
+    /// the path a function, and each status code of an operation a line, which is hit
+    /// if the status code was seen. Lines are numbered per path, in the order of the
+    /// operations.
+    fn lcov_report(&self) -> String {
+        let mut operation_tree = IndexMap::<&String, IndexMap<Method, Vec<&Coverage>>>::new();
+        for ((method, path, _), cov_entry) in &self.endpoint_cov_map {
+            operation_tree
+                .entry(path)
+                .or_default()
+                .entry(*method)
+                .or_default()
+                .push(cov_entry);
+        }
+        operation_tree.sort_keys();
+
+        let mut report = String::new();
+        for (path, mut methods) in operation_tree {
+            methods.sort_keys();
+            let mut functions = String::new();
+            let mut lines = String::new();
+            let (mut functions_hit, mut lines_hit, mut line) = (0, 0, 0);
+            for (method, statuses) in &methods {
+                let name = format!("{method} {path}");
+                let hits = statuses
+                    .iter()
+                    .filter(|coverage| !matches!(coverage, Coverage::ExpectedNotFound))
+                    .count();
+                functions.push_str(&format!("FN:{},{name}\nFNDA:{hits},{name}\n", line + 1));
+                functions_hit += usize::from(hits > 0);
+                for coverage in statuses {
+                    line += 1;
+                    let hit = !matches!(coverage, Coverage::ExpectedNotFound);
+                    lines.push_str(&format!("DA:{line},{}\n", u8::from(hit)));
+                    lines_hit += usize::from(hit);
+                }
+            }
+            report.push_str(&format!(
+                "TN:\nSF:{path}\n{functions}FNF:{}\nFNH:{functions_hit}\n{lines}LF:{line}\nLH:{lines_hit}\nend_of_record\n",
+                methods.len()
+            ));
+        }
+        report
+    }
 }
 
 impl CoverageClient for Arc<Mutex<EndpointCoverageClient>> {
@@ -282,6 +399,10 @@ impl CoverageClient for Arc<Mutex<EndpointCoverageClient>> {
         self.lock().unwrap().cov_map.as_mut_ptr()
     }
 
+    fn get_coverage_len(&self) -> usize {
+        self.lock().unwrap().cov_map.len()
+    }
+
     /// Retrieve the coverage ratio: nodes hit and total number of nodes.
     fn max_coverage_ratio(&mut self) -> (u64, u64) {
         let mut guard = self.lock().unwrap();
@@ -299,7 +420,9 @@ impl CoverageClient for Arc<Mutex<EndpointCoverageClient>> {
     /// Write a format-dependent report to disk
     fn generate_coverage_report(&self, report_path: &Path) {
         let endpoint_path = report_path.join("endpointcoverage");
-        let _ = self.lock().unwrap().export_filesystem(&endpoint_path);
+        let client = self.lock().unwrap();
+        let _ = client.export_filesystem(&endpoint_path);
+        let _ = client.export_lcov(&endpoint_path);
     }
 }
 
@@ -418,3 +541,208 @@ article {
     right: 5pt;
 }
 "##;
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use openapiv3::{OpenAPI, StatusCode};
+    use serde_json::{json, Map, Value};
+
+    use super::{Coverage, EndpointCoverageClient, SavedEndpointCoverage};
+    use crate::{
+        coverage_clients::{CoverageClient, DEFAULT_MAP_SIZE},
+        input::Method,
+    };
+
+    #[test]
+    fn test_more_responses_than_default_map_size() {
+        let responses: Map<String, Value> = (100..600)
+            .map(|status| (status.to_string(), json!({"description": "A response"})))
+            .collect();
+        let paths: Map<String, Value> = (0..DEFAULT_MAP_SIZE / responses.len() + 1)
+            .map(|index| {
+                (
+                    format!("/path{index}"),
+                    json!({"get": {"responses": responses}}),
+                )
+            })
+            .collect();
+        let api: OpenAPI = serde_json::from_value(json!({
+            "openapi": "3.0.0",
+            "info": {"title": "large", "version": "1"},
+            "paths": paths
+        }))
+        .unwrap();
+        assert!(api.operations().count() * responses.len() > DEFAULT_MAP_SIZE);
+
+        // Also when the configured map is far too small for the specification
+        for map_size in [DEFAULT_MAP_SIZE, 1] {
+            let mut client = EndpointCoverageClient::new(&api, map_size);
+            client.cover(
+                Method::Get,
+                format!("/path{}", paths.len() - 1),
+                reqwest::StatusCode::from_u16(599).unwrap(),
+                String::new(),
+                String::new(),
+            );
+            assert_eq!(client.cov_map.iter().filter(|byte| **byte != 0).count(), 1);
+        }
+    }
+
+    #[test]
+    fn test_status_in_specified_range_is_expected() {
+        let api: OpenAPI = serde_json::from_value(json!({
+            "openapi": "3.0.0",
+            "info": {"title": "pets", "version": "1"},
+            "paths": {
+                "/pets": {
+                    "get": {
+                        "responses": {
+                            "2XX": {"description": "Success"},
+                            "404": {"description": "Not found"}
+                        }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+        let mut client = EndpointCoverageClient::new(&api, DEFAULT_MAP_SIZE);
+        let mut cover = |status: u16| {
+            client.cover(
+                Method::Get,
+                "/pets".to_owned(),
+                reqwest::StatusCode::from_u16(status).unwrap(),
+                String::new(),
+                String::new(),
+            )
+        };
+        cover(200);
+        cover(201);
+        cover(400);
+
+        let coverage = |status: StatusCode| {
+            client.endpoint_cov_map[&(Method::Get, "/pets".to_owned(), status)].clone()
+        };
+        assert!(matches!(
+            coverage(StatusCode::Range(2)),
+            Coverage::ExpectedFound(200, _, _)
+        ));
+        assert!(matches!(
+            coverage(StatusCode::Code(404)),
+            Coverage::ExpectedNotFound
+        ));
+        // A status in a range that is not specified is still unexpected
+        assert!(matches!(
+            coverage(StatusCode::Code(400)),
+            Coverage::UnexpectedFound(_, _)
+        ));
+        assert_eq!(client.endpoint_cov_map.len(), 3);
+    }
+
+    #[test]
+    fn test_saved_coverage_round_trips() {
+        let api: OpenAPI = serde_json::from_value(json!({
+            "openapi": "3.0.0",
+            "info": {"title": "pets", "version": "1"},
+            "paths": {
+                "/pets": {
+                    "get": {
+                        "responses": {
+                            "2XX": {"description": "Success"},
+                            "404": {"description": "Not found"}
+                        }
+                    },
+                    "post": {"responses": {"201": {"description": "Created"}}}
+                }
+            }
+        }))
+        .unwrap();
+        let mut client = EndpointCoverageClient::new(&api, DEFAULT_MAP_SIZE);
+        for (method, status) in [(Method::Get, 200), (Method::Post, 500)] {
+            client.cover(
+                method,
+                "/pets".to_owned(),
+                reqwest::StatusCode::from_u16(status).unwrap(),
+                "request".to_owned(),
+                "response".to_owned(),
+            );
+        }
+
+        let saved = serde_json::to_string(&client.save()).unwrap();
+        let mut restored = EndpointCoverageClient::new(&api, DEFAULT_MAP_SIZE);
+        restored.restore(serde_json::from_str::<SavedEndpointCoverage>(&saved).unwrap());
+
+        // Hits, unspecified responses and misses
+        let counts = |client: &EndpointCoverageClient| {
+            let mut counts = (0, 0, 0);
+            for coverage in client.endpoint_cov_map.values() {
+                match coverage {
+                    Coverage::ExpectedFound(..) => counts.0 += 1,
+                    Coverage::UnexpectedFound(..) => counts.1 += 1,
+                    Coverage::ExpectedNotFound => counts.2 += 1,
+                }
+            }
+            counts
+        };
+        assert_eq!(counts(&client), (1, 1, 2));
+        assert_eq!(counts(&restored), (1, 1, 2));
+        assert!(matches!(
+            &restored.endpoint_cov_map[&(Method::Get, "/pets".to_owned(), StatusCode::Range(2))],
+            Coverage::ExpectedFound(200, request, _) if request == "request"
+        ));
+        // Only the total coverage is restored
+        assert!(restored.cov_map.iter().all(|byte| *byte == 0));
+        assert_eq!(
+            Arc::new(Mutex::new(restored)).max_coverage_ratio(),
+            Arc::new(Mutex::new(client)).max_coverage_ratio()
+        );
+    }
+
+    #[test]
+    fn test_lcov_report_marks_hits_and_misses() {
+        let api: OpenAPI = serde_json::from_value(json!({
+            "openapi": "3.0.0",
+            "info": {"title": "pets", "version": "1"},
+            "paths": {
+                "/pets": {
+                    "get": {
+                        "responses": {
+                            "200": {"description": "The pets"},
+                            "404": {"description": "Not found"}
+                        }
+                    }
+                },
+                "/owners": {"get": {"responses": {"200": {"description": "The owners"}}}}
+            }
+        }))
+        .unwrap();
+        let mut client = EndpointCoverageClient::new(&api, DEFAULT_MAP_SIZE);
+        client.cover(
+            Method::Get,
+            "/pets".to_owned(),
+            reqwest::StatusCode::OK,
+            String::new(),
+            String::new(),
+        );
+
+        let report_path =
+            std::env::temp_dir().join(format!("wuppiefuzz-lcov-{}", std::process::id()));
+        Arc::new(Mutex::new(client)).generate_coverage_report(&report_path);
+        let report =
+            std::fs::read_to_string(report_path.join("endpointcoverage/endpoint_coverage.info"))
+                .unwrap();
+        std::fs::remove_dir_all(&report_path).unwrap();
+
+        let records: Vec<&str> = report.split_terminator("end_of_record\n").collect();
+        assert_eq!(
+            records,
+            [
+                "TN:\nSF:/owners\nFN:1,GET /owners\nFNDA:0,GET /owners\nFNF:1\nFNH:0\n\
+                 DA:1,0\nLF:1\nLH:0\n",
+                "TN:\nSF:/pets\nFN:1,GET /pets\nFNDA:1,GET /pets\nFNF:1\nFNH:1\n\
+                 DA:1,1\nDA:2,0\nLF:2\nLH:1\n",
+            ]
+        );
+    }
+}
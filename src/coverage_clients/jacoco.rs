@@ -19,7 +19,7 @@ use crate::{
     configuration::{Configuration, CoverageConfiguration},
     coverage_clients::{
         read_utilities::{read_bool_array, read_cesu8, read_char, read_u64be},
-        CoverageClient, MAP_SIZE,
+        CoverageClient,
     },
 };
 
@@ -50,8 +50,8 @@ enum Block<T> {
 #[allow(dead_code)]
 #[derive(Debug)]
 pub struct JacocoCoverageClient<'a> {
-    cov_map: [u8; MAP_SIZE],
-    cov_map_total: [u8; MAP_SIZE],
+    cov_map: Vec<u8>,
+    cov_map_total: Vec<u8>,
     bit_idx_mapping: HashMap<u64, usize>,
     first_unused_idx: usize,
 
@@ -112,10 +112,12 @@ impl<'a> JacocoCoverageClient<'a> {
     /// to be in the filesystem.
     /// Optionally, a prefix can be given; it's used to filter the coverage information so it
     /// only includes classes with a name that starts with the given prefix.
+    /// The coverage map holds `map_size` bytes.
     pub fn new<'c: 'a>(
         socket_address: &SocketAddr,
         jacoco_dump_output_dir: Option<PathBuf>,
         jacoco_prefix: &'c Option<String>,
+        map_size: usize,
     ) -> Result<Self, Error> {
         let conn = TeeStream {
             stream: TcpStream::connect(socket_address)?,
@@ -125,8 +127,8 @@ impl<'a> JacocoCoverageClient<'a> {
         create_or_clear_dump_directory(jacoco_dump_output_dir.as_ref())?;
 
         let result = Self {
-            cov_map: [0; MAP_SIZE],
-            cov_map_total: [0; MAP_SIZE],
+            cov_map: vec![0; map_size],
+            cov_map_total: vec![0; map_size],
             bit_idx_mapping: HashMap::new(),
             first_unused_idx: 0,
             stream: conn,
@@ -291,8 +293,8 @@ impl<'a> JacocoCoverageClient<'a> {
                 // store the index of where the probe list of this class is stored
                 let stored_index = self.first_unused_idx;
                 self.first_unused_idx += segment.probe_bytes.len();
-                if self.first_unused_idx > MAP_SIZE {
-                    return Err(libafl::Error::unknown("The map size is not large enough to hold all the probes from the coverage stream. The current size is ".to_string()));
+                if self.first_unused_idx > self.cov_map.len() {
+                    return Err(libafl::Error::unknown(format!("The map size is not large enough to hold all the probes from the coverage stream. The current size is {}", self.cov_map.len())));
                 }
                 log::debug!(
                     "New file found with name {} containing {} probes",
@@ -357,6 +359,10 @@ impl CoverageClient for JacocoCoverageClient<'_> {
         self.cov_map.as_mut_ptr()
     }
 
+    fn get_coverage_len(&self) -> usize {
+        self.cov_map.len()
+    }
+
     fn max_coverage_ratio(&mut self) -> (u64, u64) {
         let (count, total) = self.coverage_ratio();
         // update the max coverage ratio
@@ -18,7 +18,7 @@ use crate::{
     configuration::{Configuration, CoverageConfiguration},
     coverage_clients::{
         read_utilities::{read_byte_vec, read_char},
-        CoverageClient, MAP_SIZE,
+        CoverageClient,
     },
 };
 extern crate num;
@@ -49,8 +49,8 @@ enum Block<T> {
 #[allow(dead_code)]
 #[derive(Debug)]
 pub struct LcovCoverageClient {
-    cov_map: [u8; MAP_SIZE],
-    cov_map_total: [u8; MAP_SIZE],
+    cov_map: Vec<u8>,
+    cov_map_total: Vec<u8>,
     bit_idx_mapping: HashMap<SourceFileAndLineNum, usize>,
     first_unused_idx: usize,
 
@@ -98,8 +98,12 @@ impl Write for TeeStream {
 
 impl LcovCoverageClient {
     /// Creates a new LCOV coverage client given an IP address and port at which the agent
-    /// can be reached.
-    pub fn new(socket_address: &SocketAddr, report_path: Option<PathBuf>) -> Result<Self, Error> {
+    /// can be reached, with a coverage map of `map_size` bytes.
+    pub fn new(
+        socket_address: &SocketAddr,
+        report_path: Option<PathBuf>,
+        map_size: usize,
+    ) -> Result<Self, Error> {
         let conn = TeeStream {
             stream: TcpStream::connect(socket_address).map_err(|err| {
                 Error::unknown(format!(
@@ -109,8 +113,8 @@ impl LcovCoverageClient {
             bytes: Vec::new(),
         };
         let result = Self {
-            cov_map: [0; MAP_SIZE],
-            cov_map_total: [0; MAP_SIZE],
+            cov_map: vec![0; map_size],
+            cov_map_total: vec![0; map_size],
             bit_idx_mapping: HashMap::new(),
             first_unused_idx: 0,
             stream: conn,
@@ -214,14 +218,15 @@ impl LcovCoverageClient {
                 new_idx
             }
         };
-        match bit_idx.cmp(&MAP_SIZE) {
+        match bit_idx.cmp(&self.cov_map.len()) {
             cmp::Ordering::Less => {
                 self.cov_map[bit_idx] = val;
                 self.cov_map_total[bit_idx] = val;
             }
             cmp::Ordering::Equal => {
                 log::debug!(
-                    "Reached map size limit ({MAP_SIZE} bytes), remaining coverage will be ignored."
+                    "Reached map size limit ({} bytes), remaining coverage will be ignored.",
+                    self.cov_map.len()
                 );
             }
             _ => (),
@@ -356,6 +361,10 @@ impl CoverageClient for LcovCoverageClient {
         self.cov_map.as_mut_ptr()
     }
 
+    fn get_coverage_len(&self) -> usize {
+        self.cov_map.len()
+    }
+
     fn max_coverage_ratio(&mut self) -> (u64, u64) {
         let count = self
             .cov_map_total
@@ -0,0 +1,342 @@
+//! Coverage client for targets instrumented with LLVM source-based coverage
+//! (`-fprofile-instr-generate`), such as C, C++ and Rust programs. Instead of serving
+//! coverage over a socket, these targets write their counters to `.profraw` files. Let
+//! the target write them to a directory, e.g. with
+//! `LLVM_PROFILE_FILE=<dir>/%p-%c.profraw`, where `%c` makes it update the file
+//! continuously rather than only on exit.
+//!
+//! On every fetch, the `.profraw` and `.profdata` files in that directory are merged into
+//! a text profile with `llvm-profdata`. A counter covers something new if its value
+//! changed since the previous fetch.
+
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    fs::{create_dir_all, read_dir},
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{bail, Context};
+use build_html::{escape_html, Html, HtmlContainer, HtmlPage, Table};
+
+use super::CoverageClient;
+
+/// The tool used to merge profiles, which must be on the PATH
+const LLVM_PROFDATA: &str = "llvm-profdata";
+
+/// A function in the profile. Functions are identified by name and hash, since the hash
+/// changes when the function is compiled differently.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct FunctionKey {
+    name: String,
+    hash: u64,
+}
+
+/// The counters of one function in a profile
+#[derive(Debug, PartialEq, Eq)]
+struct FunctionCounters {
+    key: FunctionKey,
+    counters: Vec<u64>,
+}
+
+/// LLVM profile coverage client.
+pub struct LlvmProfCoverageClient {
+    /// Directory in which the target writes its profiles
+    profile_dir: PathBuf,
+    /// The coverage of the last fetch
+    cov_map: Vec<u8>,
+    /// The coverage of all fetches so far
+    cov_map_total: Vec<u8>,
+    /// Value of each counter at the last fetch that reset the coverage
+    baseline: Vec<u64>,
+    /// Index of the first counter of each function in the coverage maps, and the number
+    /// of counters of the function
+    bit_idx_mapping: HashMap<FunctionKey, (usize, usize)>,
+    /// First unused index in the coverage maps
+    first_unused_idx: usize,
+    max_ratio: (u64, u64),
+}
+
+impl LlvmProfCoverageClient {
+    /// Creates a new LLVM profile coverage client, which reads the profiles that the
+    /// target writes to `profile_dir`, with a coverage map of `map_size` bytes.
+    pub fn new(profile_dir: PathBuf, map_size: usize) -> Self {
+        Self {
+            profile_dir,
+            cov_map: vec![0; map_size],
+            cov_map_total: vec![0; map_size],
+            baseline: vec![0; map_size],
+            bit_idx_mapping: HashMap::new(),
+            first_unused_idx: 0,
+            max_ratio: (0, 0),
+        }
+    }
+
+    /// Merges all profiles in the profile directory into a single text profile
+    fn read_profiles(&self) -> Result<String, anyhow::Error> {
+        let mut profiles = Vec::new();
+        for entry in read_dir(&self.profile_dir)
+            .with_context(|| format!("Could not read profile directory {:?}", self.profile_dir))?
+        {
+            let path = entry?.path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "profraw" || extension == "profdata")
+            {
+                profiles.push(path);
+            }
+        }
+        if profiles.is_empty() {
+            log::debug!("No profiles in {:?} yet", self.profile_dir);
+            return Ok(String::new());
+        }
+        let output = Command::new(LLVM_PROFDATA)
+            .args(["merge", "-text", "-o", "-"])
+            .args(&profiles)
+            .output()
+            .with_context(|| format!("Could not run {LLVM_PROFDATA}, is it on the PATH?"))?;
+        if !output.status.success() {
+            bail!(
+                "{LLVM_PROFDATA} could not merge the profiles: {}",
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+        Ok(String::from_utf8(output.stdout)?)
+    }
+
+    /// Index of the first counter of the function in the coverage maps, if it fits
+    fn get_map_index(&mut self, function: &FunctionCounters) -> Option<usize> {
+        let length = function.counters.len();
+        match self.bit_idx_mapping.entry(function.key.clone()) {
+            Entry::Occupied(entry) => {
+                let (index, known_length) = *entry.get();
+                (known_length == length).then_some(index)
+            }
+            Entry::Vacant(entry) => {
+                if self.first_unused_idx + length > self.cov_map.len() {
+                    log::debug!(
+                        "Reached map size limit ({} bytes), coverage of {} will be ignored.",
+                        self.cov_map.len(),
+                        entry.key().name
+                    );
+                    return None;
+                }
+                let index = self.first_unused_idx;
+                self.first_unused_idx += length;
+                Some(entry.insert((index, length)).0)
+            }
+        }
+    }
+
+    fn process_profile(&mut self, profile: &str, reset: bool) {
+        self.cov_map.fill(0);
+        for function in parse_text_profile(profile) {
+            let Some(index) = self.get_map_index(&function) else {
+                continue;
+            };
+            for (offset, count) in function.counters.into_iter().enumerate() {
+                let bit = index + offset;
+                // A lower count than before means the target restarted
+                if count > 0 && count != self.baseline[bit] {
+                    self.cov_map[bit] = 1;
+                    self.cov_map_total[bit] = 1;
+                }
+                if reset {
+                    self.baseline[bit] = count;
+                }
+            }
+        }
+    }
+
+    fn generate_report(&self, report_path: &Path) -> Result<(), anyhow::Error> {
+        let mut functions: Vec<(&FunctionKey, usize, usize)> = self
+            .bit_idx_mapping
+            .iter()
+            .map(|(key, &(index, length))| {
+                let covered = self.cov_map_total[index..index + length]
+                    .iter()
+                    .filter(|bit| **bit != 0)
+                    .count();
+                (key, covered, length)
+            })
+            .collect();
+        functions.sort_by(|a, b| a.0.name.cmp(&b.0.name));
+
+        let covered: usize = functions.iter().map(|(_, covered, _)| covered).sum();
+        let total: usize = functions.iter().map(|(_, _, length)| length).sum();
+        let table = functions.iter().fold(
+            Table::new().with_header_row(["Function", "Covered counters", "Counters"]),
+            |table, (key, covered, length)| {
+                table.with_body_row([
+                    escape_html(&key.name),
+                    covered.to_string(),
+                    length.to_string(),
+                ])
+            },
+        );
+        let html = HtmlPage::new()
+            .with_title("LLVM coverage report")
+            .with_header(1, "LLVM coverage report")
+            .with_paragraph(format!(
+                "{covered} of {total} counters in {} functions were covered.",
+                functions.len()
+            ))
+            .with_table(table)
+            .to_html_string();
+
+        let llvm_prof_path = report_path.join("llvm_prof");
+        create_dir_all(&llvm_prof_path)?;
+        std::fs::write(llvm_prof_path.join("index.html"), html)?;
+        Ok(())
+    }
+}
+
+impl CoverageClient for LlvmProfCoverageClient {
+    fn fetch_coverage(&mut self, reset: bool) {
+        match self.read_profiles() {
+            Ok(profile) => self.process_profile(&profile, reset),
+            Err(err) => log::error!("Error reading LLVM profiles: {err:#}"),
+        }
+    }
+
+    fn get_coverage_ptr(&mut self) -> *mut u8 {
+        self.cov_map.as_mut_ptr()
+    }
+
+    fn get_coverage_len(&self) -> usize {
+        self.cov_map.len()
+    }
+
+    fn max_coverage_ratio(&mut self) -> (u64, u64) {
+        let count = self.cov_map_total.iter().filter(|bit| **bit != 0).count() as u64;
+        let total = self.first_unused_idx as u64;
+        self.max_ratio.0 = std::cmp::max(self.max_ratio.0, count);
+        self.max_ratio.1 = std::cmp::max(self.max_ratio.1, total);
+        self.max_ratio
+    }
+
+    fn generate_coverage_report(&self, report_path: &Path) {
+        if let Err(err) = self.generate_report(report_path) {
+            log::error!("{err}");
+        }
+    }
+}
+
+/// Parses a profile in the text format of `llvm-profdata`. Each function is a block,
+/// separated by empty lines, with its name followed by commented sections:
+///
+/// ```text
+/// handle_get_pet
+/// # Func Hash:
+/// 1000
+/// # Num Counters:
+/// 3
+/// # Counter Values:
+/// 5
+/// 0
+/// 2
+/// ```
+///
+/// Header lines (starting with `:`) and other sections, like value profiles, are skipped.
+fn parse_text_profile(profile: &str) -> Vec<FunctionCounters> {
+    let mut functions = Vec::new();
+    for block in profile.split("\n\n") {
+        let mut lines = block
+            .lines()
+            .map(str::trim)
+            .skip_while(|line| line.is_empty() || line.starts_with('#') || line.starts_with(':'));
+        let Some(name) = lines.next() else {
+            continue;
+        };
+        let mut hash = None;
+        let mut num_counters = None;
+        let mut counters = Vec::new();
+        while let Some(line) = lines.next() {
+            match line {
+                "# Func Hash:" => hash = lines.next().and_then(|hash| hash.parse().ok()),
+                "# Num Counters:" => {
+                    num_counters = lines.next().and_then(|count| count.parse::<usize>().ok())
+                }
+                "# Counter Values:" => {
+                    counters = lines
+                        .by_ref()
+                        .take(num_counters.unwrap_or_default())
+                        .map_while(|value| value.parse().ok())
+                        .collect()
+                }
+                _ => (),
+            }
+        }
+        match (hash, num_counters) {
+            (Some(hash), Some(num_counters)) if counters.len() == num_counters => {
+                functions.push(FunctionCounters {
+                    key: FunctionKey {
+                        name: name.to_owned(),
+                        hash,
+                    },
+                    counters,
+                })
+            }
+            _ => log::debug!("Skipping profile block without counters: {name}"),
+        }
+    }
+    functions
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::Path, process::Command};
+
+    use super::{parse_text_profile, LlvmProfCoverageClient, LLVM_PROFDATA};
+    use crate::coverage_clients::{CoverageClient, DEFAULT_MAP_SIZE};
+
+    #[test]
+    fn test_parse_text_profile() {
+        let profile = "# IR level Instrumentation Flag\n:ir\nmain\n# Func Hash:\n42\n# Num Counters:\n2\n# Counter Values:\n1\n0\n\nlib.c;helper\n# Func Hash:\n7\n# Num Counters:\n1\n# Counter Values:\n3\n# Num Value Kinds:\n0\n\n";
+        let functions = parse_text_profile(profile);
+        assert_eq!(functions.len(), 2);
+        assert_eq!(functions[0].key.name, "main");
+        assert_eq!(functions[0].key.hash, 42);
+        assert_eq!(functions[0].counters, vec![1, 0]);
+        assert_eq!(functions[1].key.name, "lib.c;helper");
+        assert_eq!(functions[1].counters, vec![3]);
+    }
+
+    #[test]
+    fn test_coverage_from_profdata_sample() {
+        if Command::new(LLVM_PROFDATA)
+            .arg("--version")
+            .output()
+            .is_err()
+        {
+            eprintln!("Skipping test: {LLVM_PROFDATA} is not installed");
+            return;
+        }
+        // The sample has two functions with three and two counters, of which two are hit
+        let profile_dir = tempfile::tempdir().unwrap();
+        std::fs::copy(
+            Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("src/coverage_clients/testdata/sample.profdata"),
+            profile_dir.path().join("sample.profdata"),
+        )
+        .unwrap();
+        let mut client =
+            LlvmProfCoverageClient::new(profile_dir.path().to_path_buf(), DEFAULT_MAP_SIZE);
+
+        client.fetch_coverage(true);
+        assert_eq!(client.max_coverage_ratio(), (2, 5));
+        assert_eq!(client.cov_map.iter().filter(|bit| **bit != 0).count(), 2);
+
+        // The counters did not change, so nothing new is covered
+        client.fetch_coverage(true);
+        assert!(client.cov_map.iter().all(|bit| *bit == 0));
+        assert_eq!(client.max_coverage_ratio(), (2, 5));
+
+        let report_dir = tempfile::tempdir().unwrap();
+        client.generate_coverage_report(report_dir.path());
+        let report =
+            std::fs::read_to_string(report_dir.path().join("llvm_prof/index.html")).unwrap();
+        assert!(report.contains("handle_get_pet"));
+        assert!(report.contains("2 of 5 counters in 2 functions were covered."));
+    }
+}
@@ -12,11 +12,12 @@ use anyhow::Context;
 
 use crate::configuration::{self, Configuration};
 
-/// Size of the coverage map. This is a bitmap containing a bit for each line in the target
-/// (or each endpoint, if using endpoint coverage as the guidance). The fuzzer will crash if
-/// it is too small, so we choose a rather large value. Might need tweaking if your target
-/// is larger or your memory is not large enough.
-pub const MAP_SIZE: usize = 4 * 8192;
+/// Default size of the coverage map in bytes. This is a bitmap containing a bit for each
+/// line in the target (or each endpoint, if using endpoint coverage as the guidance).
+/// Coverage that does not fit is ignored, so we choose a rather large value. It can be
+/// changed with `--coverage-map-size` if your target is larger or your memory is not large
+/// enough.
+pub const DEFAULT_MAP_SIZE: usize = 4 * 8192;
 
 pub mod read_utilities;
 
@@ -25,6 +26,8 @@ pub mod dummy;
 pub mod endpoint;
 pub mod jacoco;
 pub mod lcov_client;
+pub mod llvm_prof;
+pub mod raw_tcp;
 
 /// CoverageClient is a client (on the fuzzer side) responsible for communicating with the
 /// (coverage agent attached to the) program under test. It can be used to fetch the current
@@ -38,9 +41,7 @@ pub trait CoverageClient {
     fn get_coverage_ptr(&mut self) -> *mut u8;
 
     /// Retrieve the length of the array pointed to by `get_coverage_pointer`
-    fn get_coverage_len(&self) -> usize {
-        MAP_SIZE
-    }
+    fn get_coverage_len(&self) -> usize;
 
     /// Retrieve the coverage ratio: nodes hit and total number of nodes.
     fn max_coverage_ratio(&mut self) -> (u64, u64);
@@ -67,6 +68,7 @@ pub fn get_coverage_client<'c>(
                     .clone()
                     .map(|report_path| report_path.as_path().join("jacoco_exec")),
                 jacoco_class_prefix,
+                clargs.coverage_map_size,
             )
             .context("Could not construct JacocoCoverageClient")?,
         ),
@@ -78,6 +80,7 @@ pub fn get_coverage_client<'c>(
                 report_path
                     .clone()
                     .map(|report_path| report_path.as_path().join("lcov_exec")),
+                clargs.coverage_map_size,
             )
             .context("Could not construct LcovCoverageClient")?,
         ),
@@ -92,6 +95,18 @@ pub fn get_coverage_client<'c>(
                     .try_into()
                     .with_context(|| format!("Failed to parse the coverage_host URL: {url}"))
                     .context("Could not construct CoverbandCoverageClient")?,
+                clargs.coverage_map_size,
+            ))
+        }
+        configuration::CoverageConfiguration::LlvmProf { ref profraw_dir } => Box::new(
+            llvm_prof::LlvmProfCoverageClient::new(profraw_dir.clone(), clargs.coverage_map_size),
+        ),
+        configuration::CoverageConfiguration::RawTcp => {
+            Box::new(raw_tcp::RawTcpCoverageClient::new(
+                &clargs
+                    .coverage_host
+                    .unwrap_or_else(|| SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 6400)),
+                clargs.coverage_map_size,
             ))
         }
         configuration::CoverageConfiguration::Endpoint => {
@@ -0,0 +1,186 @@
+//! Coverage client for targets that expose their own coverage map, e.g. an edge map of
+//! a custom instrumentation, over a minimal TCP protocol. This is meant for languages
+//! without a Jacoco, LCOV or Coverband agent: the target only needs to implement the
+//! protocol below.
+//!
+//! On every fetch the client opens a connection to the coverage host (by default
+//! 127.0.0.1:6400) and sends a single command byte:
+//!
+//! | Command | Meaning                                            |
+//! |---------|----------------------------------------------------|
+//! | `0x00`  | Send the coverage map                              |
+//! | `0x01`  | Send the coverage map, then reset it to all zeroes |
+//!
+//! The target answers with the length of the map in bytes as a little-endian `u32`,
+//! followed by the map itself, and may then close the connection. Every byte of the map
+//! is one coverage entry, of which any value other than 0 means covered. The map is
+//! copied into the coverage map of the fuzzer as-is; entries that do not fit are
+//! ignored.
+
+use std::{
+    io::{Read, Write},
+    net::{SocketAddr, TcpStream},
+    path::Path,
+    time::Duration,
+};
+
+use anyhow::{bail, Context};
+
+use super::{read_utilities::read_u32le, CoverageClient};
+
+/// Command to send the coverage map
+const FETCH: u8 = 0x00;
+/// Command to send the coverage map and reset it afterwards
+const FETCH_AND_RESET: u8 = 0x01;
+
+/// How long the client waits for the target to send its map
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Raw TCP coverage client.
+pub struct RawTcpCoverageClient {
+    addr: SocketAddr,
+    /// The coverage of the last fetch
+    cov_map: Vec<u8>,
+    /// The coverage of all fetches so far
+    cov_map_total: Vec<u8>,
+    /// Size of the largest map the target sent, capped to the size of the coverage map
+    map_len: usize,
+    max_ratio: (u64, u64),
+}
+
+impl RawTcpCoverageClient {
+    /// Creates a new raw TCP coverage client, which fetches the map of the target at
+    /// `addr`, with a coverage map of `map_size` bytes.
+    pub fn new(addr: &SocketAddr, map_size: usize) -> Self {
+        Self {
+            addr: *addr,
+            cov_map: vec![0; map_size],
+            cov_map_total: vec![0; map_size],
+            map_len: 0,
+            max_ratio: (0, 0),
+        }
+    }
+
+    /// Requests the coverage map from the target
+    fn request_map(&self, reset: bool) -> Result<Vec<u8>, anyhow::Error> {
+        let mut stream = TcpStream::connect(self.addr)
+            .with_context(|| format!("Could not connect to coverage host {}", self.addr))?;
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+        stream.write_all(&[if reset { FETCH_AND_RESET } else { FETCH }])?;
+        let length = read_u32le(&mut stream).context("Could not read the map length")?;
+        let mut map = Vec::new();
+        (&mut stream)
+            .take(length.into())
+            .read_to_end(&mut map)
+            .context("Could not read the coverage map")?;
+        if map.len() != length as usize {
+            bail!(
+                "The coverage host announced a map of {length} bytes, but sent {}",
+                map.len()
+            );
+        }
+        Ok(map)
+    }
+
+    fn process_map(&mut self, map: &[u8]) {
+        if map.len() > self.cov_map.len() {
+            log::warn!(
+                "The coverage map of the target has {} entries, only the first {} are used. Increase --coverage-map-size to use all of them.",
+                map.len(),
+                self.cov_map.len()
+            );
+        }
+        let used = map.len().min(self.cov_map.len());
+        self.cov_map[..used].copy_from_slice(&map[..used]);
+        self.cov_map[used..].fill(0);
+        for (total, entry) in self.cov_map_total.iter_mut().zip(&map[..used]) {
+            *total |= *entry;
+        }
+        self.map_len = self.map_len.max(used);
+    }
+}
+
+impl CoverageClient for RawTcpCoverageClient {
+    fn fetch_coverage(&mut self, reset: bool) {
+        match self.request_map(reset) {
+            Ok(map) => self.process_map(&map),
+            Err(err) => log::error!("Error fetching raw coverage: {err:#}"),
+        }
+    }
+
+    fn get_coverage_ptr(&mut self) -> *mut u8 {
+        self.cov_map.as_mut_ptr()
+    }
+
+    fn get_coverage_len(&self) -> usize {
+        self.cov_map.len()
+    }
+
+    fn max_coverage_ratio(&mut self) -> (u64, u64) {
+        let count = self
+            .cov_map_total
+            .iter()
+            .filter(|entry| **entry != 0)
+            .count() as u64;
+        let total = self.map_len as u64;
+        self.max_ratio.0 = std::cmp::max(self.max_ratio.0, count);
+        self.max_ratio.1 = std::cmp::max(self.max_ratio.1, total);
+        self.max_ratio
+    }
+
+    fn generate_coverage_report(&self, _report_path: &Path) {
+        log::warn!("The raw TCP coverage format carries no source information, so no coverage report can be generated");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+    };
+
+    use super::RawTcpCoverageClient;
+    use crate::coverage_clients::CoverageClient;
+
+    #[test]
+    fn test_map_is_fetched_and_reset() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        // Serves a map of 6 entries, which grows with every fetch until it is reset
+        let server = std::thread::spawn(move || {
+            let mut map = [0u8, 1, 0, 0, 0, 3];
+            let mut commands = vec![];
+            for stream in listener.incoming().take(3) {
+                let mut stream = stream.unwrap();
+                let mut command = [0u8];
+                stream.read_exact(&mut command).unwrap();
+                commands.push(command[0]);
+                stream.write_all(&(map.len() as u32).to_le_bytes()).unwrap();
+                stream.write_all(&map).unwrap();
+                match command[0] {
+                    0x01 => map = [0; 6],
+                    _ => map[2] += 1,
+                }
+            }
+            commands
+        });
+
+        let mut client = RawTcpCoverageClient::new(&addr, 8);
+        let coverage = |client: &mut RawTcpCoverageClient| {
+            let ptr = client.get_coverage_ptr();
+            unsafe { std::slice::from_raw_parts(ptr, client.get_coverage_len()) }.to_vec()
+        };
+
+        client.fetch_coverage(false);
+        assert_eq!(coverage(&mut client), [0, 1, 0, 0, 0, 3, 0, 0]);
+        client.fetch_coverage(true);
+        assert_eq!(coverage(&mut client), [0, 1, 1, 0, 0, 3, 0, 0]);
+        client.fetch_coverage(false);
+        assert_eq!(coverage(&mut client), [0; 8]);
+        // Entries covered by any fetch count, out of the size of the target's map
+        assert_eq!(client.max_coverage_ratio(), (3, 6));
+
+        assert_eq!(server.join().unwrap(), [0x00, 0x01, 0x00]);
+    }
+}
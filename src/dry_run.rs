@@ -0,0 +1,192 @@
+//! A dry run shows what the fuzzer would send, before it is pointed at a target: the
+//! requests of the initial corpus are printed as curl commands, and nothing is sent.
+//!
+//! Values that a request takes from the response to an earlier request are not known
+//! without sending that request, so they are shown as placeholders such as `<id@0>`,
+//! the `id` from the response to the first request of the chain. Authentication is not
+//! set up either, so no authentication headers are shown.
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use openapiv3::OpenAPI;
+use reqwest_cookie_store::CookieStoreMutex;
+use serde_json::Value;
+
+use crate::{
+    authentication::Authentication,
+    configuration::Configuration,
+    initial_corpus::{inputs_from_api, load_starting_corpus},
+    input::{form::FormArrayStyle, OpenApiInput},
+    openapi::{
+        build_request::build_request_from_input, curl_request::CurlRequest, examples::UserExamples,
+    },
+    parameter_feedback::ParameterFeedback,
+};
+
+/// Prints the requests of the initial corpus as curl commands, one input after another
+pub fn print_curl_commands(
+    config: &Configuration,
+    api: &OpenAPI,
+    user_examples: &UserExamples,
+) -> Result<()> {
+    let inputs = match &config.initial_corpus {
+        Some(initial_corpus) => load_starting_corpus(initial_corpus)
+            .map_err(|err| anyhow!("Could not load the initial corpus: {err}"))?,
+        None => inputs_from_api(
+            &config.path_filter().filter_api(api),
+            user_examples,
+            config.pagination.as_ref(),
+            config.wrap_array_key.as_deref(),
+            &config.crud_order,
+            &config.synonyms,
+            config.max_corpus_combinations,
+        ),
+    };
+    for (input_index, commands) in
+        curl_commands(api, user_examples, config.form_array_style, &inputs)
+            .into_iter()
+            .enumerate()
+    {
+        println!("# Input {input_index}");
+        for command in commands {
+            println!("{command}\n");
+        }
+    }
+    Ok(())
+}
+
+/// The curl commands of the requests of each input
+fn curl_commands(
+    api: &OpenAPI,
+    user_examples: &UserExamples,
+    array_style: FormArrayStyle,
+    inputs: &[OpenApiInput],
+) -> Vec<Vec<String>> {
+    // Only used to build the requests, which are never sent
+    let client = reqwest::blocking::Client::new();
+    let cookie_store = Arc::new(CookieStoreMutex::default());
+    inputs
+        .iter()
+        .map(|input| {
+            let mut placeholders = ParameterFeedback::new(input.0.len());
+            for (_, _, _, request_index, parameter_name) in input.reference_parameters() {
+                let placeholder = format!("<{parameter_name}@{request_index}>");
+                placeholders.set(request_index, parameter_name, Value::String(placeholder));
+            }
+            input
+                .0
+                .iter()
+                .filter_map(|request| {
+                    let mut request = request.clone();
+                    if let Err(err) = request.resolve_parameter_references(&placeholders) {
+                        log::warn!("Could not fill in a reference of {request}: {err}");
+                        return None;
+                    }
+                    let request_built = build_request_from_input(
+                        &client,
+                        &cookie_store,
+                        &Authentication::None,
+                        api,
+                        user_examples,
+                        array_style,
+                        &request,
+                    )?
+                    .build()
+                    .inspect_err(|err| log::warn!("Could not build {request}: {err}"))
+                    .ok()?;
+                    Some(
+                        CurlRequest(&request_built, &Authentication::None, Some(&cookie_store))
+                            .to_string(),
+                    )
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use openapiv3::OpenAPI;
+    use serde_json::json;
+
+    use super::curl_commands;
+    use crate::{
+        initial_corpus::{
+            dependency_graph::{crud_order::CrudOrder, synonyms::Synonyms},
+            inputs_from_api,
+        },
+        input::form::FormArrayStyle,
+        openapi::examples::UserExamples,
+    };
+
+    #[test]
+    fn test_curl_commands_from_small_spec() {
+        let api: OpenAPI = serde_json::from_value(json!({
+            "openapi": "3.0.0",
+            "info": {"title": "pets", "version": "1"},
+            "servers": [{"url": "http://localhost:8080"}],
+            "paths": {
+                "/pets": {
+                    "post": {
+                        "responses": {
+                            "201": {
+                                "description": "The pet",
+                                "content": {
+                                    "application/json": {
+                                        "schema": {
+                                            "type": "object",
+                                            "properties": {"petId": {"type": "integer"}}
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                },
+                "/pets/{petId}": {
+                    "get": {
+                        "parameters": [{
+                            "name": "petId",
+                            "in": "path",
+                            "required": true,
+                            "schema": {"type": "integer"}
+                        }],
+                        "responses": {"200": {"description": "The pet"}}
+                    }
+                }
+            }
+        }))
+        .unwrap();
+        let inputs = inputs_from_api(
+            &api,
+            &UserExamples::default(),
+            None,
+            None,
+            &CrudOrder::default(),
+            &Synonyms::default(),
+            10,
+        );
+
+        let commands: Vec<String> = curl_commands(
+            &api,
+            &UserExamples::default(),
+            FormArrayStyle::default(),
+            &inputs,
+        )
+        .into_iter()
+        .flatten()
+        .collect();
+        assert!(!commands.is_empty());
+        assert!(commands
+            .iter()
+            .all(|command| command.contains("curl 'http://localhost:8080/pets")));
+        assert!(commands
+            .iter()
+            .any(|command| command.contains("--request POST")));
+        // The id of the created pet is only known after sending the first request
+        assert!(commands
+            .iter()
+            .any(|command| command.contains("/pets/%3CpetId%400%3E")));
+    }
+}
@@ -0,0 +1,187 @@
+//! APIs are expected to return their errors in a consistent envelope, e.g. always an
+//! object with `code` and `message`. The fuzzer learns the top-level keys of the
+//! first JSON error body (status 4xx or 5xx) of every operation and status, and flags
+//! later error responses with different keys, which indicate that some errors are
+//! handled by a different code path than the others.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt::Display,
+};
+
+use serde_json::Value;
+
+use crate::{
+    input::{Method, OpenApiRequest},
+    openapi::validate_response::Response,
+};
+
+/// Maximum number of (method, path, status) combinations whose error shape is learned.
+/// Error responses of combinations beyond these are not checked.
+pub const MAX_LEARNED_SHAPES: usize = 1024;
+
+/// The top-level keys of a JSON object
+type Shape = BTreeSet<String>;
+
+/// An error response with different top-level keys than the first error response of
+/// the same operation and status.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorShapeMismatch {
+    pub status: u16,
+    pub expected: Shape,
+    pub got: Shape,
+}
+
+impl ErrorShapeMismatch {
+    /// Short name of the kind of error, without any details of the particular case.
+    pub fn kind(&self) -> &'static str {
+        "inconsistent_error_shape"
+    }
+}
+
+impl Display for ErrorShapeMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "error response with HTTP status {} has top-level keys {:?}, while earlier ones had {:?}",
+            self.status, self.got, self.expected
+        )
+    }
+}
+
+/// The error shapes learned during a run.
+#[derive(Debug)]
+pub struct ErrorShapes {
+    shapes: HashMap<(Method, String, u16), Shape>,
+    max_shapes: usize,
+}
+
+impl Default for ErrorShapes {
+    fn default() -> Self {
+        Self::new(MAX_LEARNED_SHAPES)
+    }
+}
+
+impl ErrorShapes {
+    /// Error shapes that learn at most `max_shapes` (method, path, status) combinations
+    pub fn new(max_shapes: usize) -> Self {
+        Self {
+            shapes: HashMap::new(),
+            max_shapes,
+        }
+    }
+
+    /// Learns the shape of an error response if it is the first of its operation and
+    /// status, and otherwise compares it with the one learned. Responses that are no
+    /// error or have no JSON object body are ignored.
+    pub fn check(
+        &mut self,
+        request: &OpenApiRequest,
+        response: &Response,
+    ) -> Result<(), ErrorShapeMismatch> {
+        let status = response.status();
+        if !status.is_client_error() && !status.is_server_error() {
+            return Ok(());
+        }
+        let Ok(Value::Object(body)) = serde_json::from_slice::<Value>(response.body()) else {
+            return Ok(());
+        };
+        let got: Shape = body.into_iter().map(|(key, _)| key).collect();
+
+        let key = (request.method, request.path.clone(), status.as_u16());
+        match self.shapes.get(&key) {
+            Some(expected) if *expected != got => Err(ErrorShapeMismatch {
+                status: status.as_u16(),
+                expected: expected.clone(),
+                got,
+            }),
+            Some(_) => Ok(()),
+            None => {
+                if self.shapes.len() < self.max_shapes {
+                    self.shapes.insert(key, got);
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+    use reqwest::StatusCode;
+
+    use super::ErrorShapes;
+    use crate::{
+        input::{Body, Method, OpenApiRequest},
+        openapi::validate_response::Response,
+    };
+
+    fn request(path: &str) -> OpenApiRequest {
+        OpenApiRequest {
+            method: Method::Get,
+            path: path.to_owned(),
+            body: Body::Empty,
+            parameters: IndexMap::new(),
+        }
+    }
+
+    fn response(status: StatusCode, body: &str) -> Response {
+        Response::new(status, body.as_bytes().to_vec())
+    }
+
+    #[test]
+    fn test_divergent_error_shape_is_flagged() {
+        let mut shapes = ErrorShapes::default();
+        let pets = request("/pets/{id}");
+        let envelope = r#"{"code": 404, "message": "Pet not found"}"#;
+
+        assert!(shapes
+            .check(&pets, &response(StatusCode::NOT_FOUND, envelope))
+            .is_ok());
+        // Same keys with other values, and successful responses, are fine
+        assert!(shapes
+            .check(
+                &pets,
+                &response(StatusCode::NOT_FOUND, r#"{"message": "Gone", "code": 1}"#)
+            )
+            .is_ok());
+        assert!(shapes
+            .check(&pets, &response(StatusCode::OK, r#"{"id": 1}"#))
+            .is_ok());
+        // Another status or operation has its own baseline
+        assert!(shapes
+            .check(
+                &pets,
+                &response(StatusCode::BAD_REQUEST, r#"{"error": "x"}"#)
+            )
+            .is_ok());
+        assert!(shapes
+            .check(
+                &request("/owners"),
+                &response(StatusCode::NOT_FOUND, r#"{"error": "x"}"#)
+            )
+            .is_ok());
+
+        let mismatch = shapes
+            .check(
+                &pets,
+                &response(StatusCode::NOT_FOUND, r#"{"error": "Not Found"}"#),
+            )
+            .unwrap_err();
+        assert_eq!(mismatch.status, 404);
+        assert_eq!(mismatch.got, ["error".to_owned()].into());
+        assert_eq!(mismatch.kind(), "inconsistent_error_shape");
+    }
+
+    #[test]
+    fn test_learned_shapes_are_bounded() {
+        let mut shapes = ErrorShapes::new(1);
+        let error = |body| response(StatusCode::NOT_FOUND, body);
+        assert!(shapes.check(&request("/a"), &error(r#"{"a": 1}"#)).is_ok());
+        assert!(shapes.check(&request("/b"), &error(r#"{"b": 1}"#)).is_ok());
+        // The shape of /b was not learned, so it is not checked
+        assert!(shapes.check(&request("/b"), &error(r#"{"c": 1}"#)).is_ok());
+        assert!(shapes.check(&request("/a"), &error(r#"{"c": 1}"#)).is_err());
+    }
+}
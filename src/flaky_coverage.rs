@@ -0,0 +1,105 @@
+//! Some programs under test have non-deterministic coverage, e.g. lines that are only
+//! covered when a timestamp rolls over. Such coverage makes inputs look interesting
+//! while they are not, which causes churn in the corpus.
+//!
+//! The calibration stage executes every new corpus entry several times in a row. The
+//! coverage of these executions of the same input is compared, and the entries of the
+//! coverage map that differ are considered flaky. Flaky entries are cleared from the
+//! map after every execution, before the feedback decides whether the input is
+//! interesting, so that they no longer make any input interesting.
+
+use libafl::inputs::Input;
+
+use crate::input::OpenApiInput;
+
+/// The flaky entries of a coverage map found so far
+#[derive(Debug, Default)]
+pub struct FlakyCoverage {
+    flaky: Vec<bool>,
+    /// Name of the input executed last, and the coverage map it produced
+    last_execution: Option<(String, Vec<u8>)>,
+}
+
+impl FlakyCoverage {
+    /// Records the coverage map that executing `input` produced, and clears its flaky
+    /// entries. If the previous execution was of the same input, the entries that differ
+    /// from its coverage map are marked as flaky first.
+    pub fn process(&mut self, input: &OpenApiInput, map: &mut [u8]) {
+        let name = input.generate_name(None);
+        if let Some((_, last_map)) = self
+            .last_execution
+            .as_ref()
+            .filter(|(last_name, _)| *last_name == name)
+        {
+            if self.flaky.len() < map.len() {
+                self.flaky.resize(map.len(), false);
+            }
+            let mut newly_flaky = 0;
+            for (index, (now, before)) in map.iter().zip(last_map).enumerate() {
+                if now != before && !self.flaky[index] {
+                    self.flaky[index] = true;
+                    newly_flaky += 1;
+                }
+            }
+            if newly_flaky > 0 {
+                log::info!("Ignoring {newly_flaky} more coverage entries that are flaky");
+            }
+        }
+        self.last_execution = Some((name, map.to_vec()));
+
+        for (entry, _) in map.iter_mut().zip(&self.flaky).filter(|(_, flaky)| **flaky) {
+            *entry = 0;
+        }
+    }
+
+    /// Whether the entry at `index` of the coverage map is flaky
+    #[cfg(test)]
+    pub fn is_flaky(&self, index: usize) -> bool {
+        self.flaky.get(index).copied().unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+
+    use super::FlakyCoverage;
+    use crate::input::{Body, Method, OpenApiInput, OpenApiRequest};
+
+    #[test]
+    fn test_toggling_entry_is_flaky_and_not_interesting() {
+        let input = |path: &str| {
+            OpenApiInput(vec![OpenApiRequest {
+                method: Method::Get,
+                path: path.to_owned(),
+                body: Body::Empty,
+                parameters: IndexMap::new(),
+            }])
+        };
+        let mut flaky_coverage = FlakyCoverage::default();
+        // The maximum of every entry so far, as kept by the coverage feedback
+        let mut history = [0u8; 4];
+
+        // Executes the input with the given coverage, and returns whether it is
+        // interesting, i.e. it raises an entry above its maximum so far, and whether
+        // entry 2 is flaky.
+        let mut execute = |input: &OpenApiInput, mut map: [u8; 4]| {
+            flaky_coverage.process(input, &mut map);
+            let mut interesting = false;
+            for (max, entry) in history.iter_mut().zip(map) {
+                interesting |= entry > *max;
+                *max = entry.max(*max);
+            }
+            (interesting, flaky_coverage.is_flaky(2))
+        };
+
+        let (time, pets, owners) = (input("/time"), input("/pets"), input("/owners"));
+        // New coverage is interesting
+        assert_eq!(execute(&time, [1, 0, 0, 0]), (true, false));
+        // Executing the same input again covers entry 2 as well, so it is flaky
+        assert_eq!(execute(&time, [1, 0, 1, 0]), (false, true));
+        // Entry 2 no longer makes any input interesting, other new coverage still does
+        assert_eq!(execute(&pets, [0, 0, 1, 0]), (false, true));
+        assert_eq!(execute(&owners, [0, 1, 1, 0]), (true, true));
+    }
+}
@@ -5,7 +5,7 @@ use std::{
     borrow::Cow,
     fs::create_dir_all,
     ops::DerefMut,
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::{
         atomic::{AtomicBool, Ordering},
         Arc, Mutex,
@@ -23,28 +23,28 @@ use libafl::{
         hooks::inprocess::inprocess_get_event_manager, inprocess::InProcessExecutor, Executor,
         ExitKind, HasObservers,
     },
-    feedback_or,
+    feedback_and_fast, feedback_or,
     feedbacks::{
-        CrashFeedback, DifferentIsNovel, Feedback, MapFeedback, MaxMapFeedback, MaxReducer,
-        TimeFeedback,
+        ConstFeedback, CrashFeedback, DifferentIsNovel, EagerOrFeedback, FastAndFeedback, Feedback,
+        MapFeedback, MaxMapFeedback, MaxReducer, TimeFeedback,
     },
-    fuzzer::StdFuzzer,
-    inputs::{BytesInput, UsesInput},
+    fuzzer::{HasScheduler, StdFuzzer},
+    inputs::{BytesInput, Input, UsesInput},
     monitors::{AggregatorOps, UserStats, UserStatsValue},
-    mutators::StdScheduledMutator,
+    mutators::TuneableScheduledMutator,
     observers::{CanTrack, ExplicitTracking, MultiMapObserver, StdMapObserver, TimeObserver},
     schedulers::{
         powersched::PowerSchedule, IndexesLenTimeMinimizerScheduler, PowerQueueScheduler,
     },
     stages::{CalibrationStage, StdPowerMutationalStage},
-    state::{HasCorpus, HasExecutions, NopState, UsesState},
+    state::{HasCorpus, HasExecutions, HasRand, HasSolutions, NopState, UsesState},
     ExecuteInputResult, ExecutionProcessor, HasNamedMetadata,
 };
 use libafl_bolts::{
     current_nanos, current_time,
     prelude::OwnedMutSlice,
     rands::StdRand,
-    tuples::{tuple_list, MatchName},
+    tuples::{tuple_list, MatchName, NamedTuple},
 };
 use log::{debug, error, info};
 use openapiv3::OpenAPI;
@@ -52,17 +52,33 @@ use openapiv3::OpenAPI;
 use crate::{
     configuration::{Configuration, CrashCriterion},
     coverage_clients::{endpoint::EndpointCoverageClient, CoverageClient},
-    input::OpenApiInput,
+    error_shape::ErrorShapes,
+    flaky_coverage::FlakyCoverage,
+    header::{redacted_headers, redacted_url},
+    input::{OpenApiInput, OpenApiRequest},
+    metrics::{Metrics, MetricsServer},
     monitors::CoverageMonitor,
     openapi::{
-        build_request::build_request_from_input,
+        build_request::{build_request_from_input, stream_body_chunked},
         curl_request::CurlRequest,
-        validate_response::{validate_response, Response},
+        examples::UserExamples,
+        validate_response::{
+            validate_rate_limit_headers, validate_response, validate_response_time, Response,
+        },
     },
-    openapi_mutator::havoc_mutations_openapi,
+    openapi_mutator::{havoc_mutations_openapi, mutation_probabilities},
     parameter_feedback::ParameterFeedback,
-    reporting::Reporting,
+    reporting::{
+        coverage_attribution::CoverageAttribution,
+        lineage::LineageFeedback,
+        summary::{print_summary, RunSummary},
+        Reporting,
+    },
+    scheduler::CorpusScheduler,
     state::OpenApiFuzzerState,
+    state_file::FuzzerStateFile,
+    think_time::ThinkTime,
+    throttle::TokenBucket,
 };
 
 /// Main fuzzer function.
@@ -72,12 +88,39 @@ use crate::{
 pub fn fuzz() -> Result<()> {
     let config = &Configuration::get().map_err(anyhow::Error::msg)?;
     crate::setup_logging(config);
+    let run_start = Instant::now();
     let report_path = config.report.then(generate_report_path);
 
-    let api = crate::openapi::get_api_spec(config.openapi_spec.as_ref().unwrap())?;
+    let mut api = crate::openapi::get_api_spec(config.openapi_spec.as_ref().unwrap())?;
+    crate::openapi::override_servers(&mut api, config.target.as_deref());
+    crate::openapi::set_server_variables(&mut api, &config.server_variables);
+
+    let user_examples = UserExamples::load(
+        config.format_values.as_deref(),
+        config.examples_override.as_deref(),
+    )?;
+    crate::initial_corpus::dependency_graph::set_normalization_language(
+        config.normalization_language,
+    );
+
+    // A dry run only shows the requests of the initial corpus, before anything is sent
+    if config.dry_run {
+        return crate::dry_run::print_curl_commands(config, &api, &user_examples);
+    }
 
     // The Monitor trait define how the fuzzer stats are reported to the user
-    let mon = CoverageMonitor::new(|s| info!("{}", s));
+    let mut mon = CoverageMonitor::new(|s| info!("{}", s));
+
+    // Serve the stats for scraping if requested; the server stops when dropped at the
+    // end of the campaign
+    let _metrics_server = match config.metrics_addr {
+        Some(addr) => {
+            let metrics = Arc::new(Metrics::default());
+            mon = mon.with_metrics(metrics.clone());
+            Some(MetricsServer::start(addr, metrics)?)
+        }
+        None => None,
+    };
 
     // The event manager handle the various events generated during the fuzzing loop
     // such as the notification of the addition of a new item to the corpus
@@ -85,7 +128,29 @@ pub fn fuzz() -> Result<()> {
 
     // Set up endpoint coverage
     let (mut endpoint_coverage_client, endpoint_coverage_observer, endpoint_coverage_feedback) =
-        setup_endpoint_coverage(*api.clone())?;
+        setup_endpoint_coverage(*api.clone(), config.coverage_map_size)?;
+
+    // Resume from the state saved by an earlier campaign, if any
+    let saved_rand = match config
+        .state_file
+        .as_deref()
+        .map(FuzzerStateFile::load)
+        .transpose()?
+        .flatten()
+    {
+        Some(FuzzerStateFile {
+            rand,
+            endpoint_coverage,
+        }) => {
+            info!("Resuming from the saved state of an earlier campaign");
+            endpoint_coverage_client
+                .lock()
+                .unwrap()
+                .restore(endpoint_coverage);
+            Some(rand)
+        }
+        None => None,
+    };
 
     let (mut code_coverage_client, code_coverage_observer, code_coverage_feedback) =
         setup_line_coverage(config, &report_path)?;
@@ -99,25 +164,36 @@ pub fn fuzz() -> Result<()> {
         endpoint_coverage_feedback,
         code_coverage_feedback,
         TimeFeedback::new(&time_observer), // Time feedback, this one does not need a feedback state
+        LineageFeedback::new(config.lineage_log.as_deref(), "corpus"),
     );
 
     // A feedback to choose if an input is a solution or not
-    let mut objective = CrashFeedback::new();
-
-    // Initialize corpus normally.
-    let initial_corpus = crate::initial_corpus::initialize_corpus(
-        &api,
-        config.initial_corpus.as_deref(),
+    let mut objective = crash_objective(config.coverage_only, config.lineage_log.as_deref());
+
+    // Log the seed, so that the run can be repeated with --seed
+    let seed = config.seed.unwrap_or_else(current_nanos);
+    log::info!("Seeding the random number generator with {seed}");
+    crate::openapi::examples::seed_examples(seed);
+
+    // Initialize corpus normally, from the endpoints that may be fuzzed.
+    let corpus_api = config.path_filter().filter_api(&api);
+    let mut initial_corpus = crate::initial_corpus::initialize_corpus(
+        &corpus_api,
+        &user_examples,
+        config,
         &report_path.as_deref(),
     );
+    crate::initial_corpus::focus_on_paths(&mut initial_corpus, &config.focus_paths);
 
     // Needed to force load corpus
     let initial_corpus_cloned = initial_corpus.clone();
 
+    let rand = saved_rand.unwrap_or_else(|| StdRand::with_seed(seed));
+
     // Create a State from scratch
     let mut state = OpenApiFuzzerState::new(
         // RNG
-        StdRand::with_seed(current_nanos()),
+        rand,
         // Corpus that will be evolved, we keep it in memory for performance
         initial_corpus,
         // Corpus in which we store solutions (crashes in this example),
@@ -147,10 +223,14 @@ pub fn fuzz() -> Result<()> {
     })
     .track_indices();
 
-    // A minimization+queue policy to get testcases from the corpus
-    let scheduler = IndexesLenTimeMinimizerScheduler::new(
-        &combined_map_observer,
-        PowerQueueScheduler::new(&mut state, &combined_map_observer, PowerSchedule::fast()),
+    // A minimization+queue policy to get testcases from the corpus, unless another
+    // scheduling strategy is configured
+    let scheduler = CorpusScheduler::new(
+        config.scheduler,
+        IndexesLenTimeMinimizerScheduler::new(
+            &combined_map_observer,
+            PowerQueueScheduler::new(&mut state, &combined_map_observer, PowerSchedule::fast()),
+        ),
     );
 
     // A fuzzer with feedbacks and a corpus scheduler
@@ -163,20 +243,58 @@ pub fn fuzz() -> Result<()> {
         time_observer
     );
 
-    let mutator_openapi = StdScheduledMutator::new(havoc_mutations_openapi());
+    let injection_payloads = crate::openapi_mutator::injection::load_payloads(
+        config.injection,
+        config.injection_dictionary.as_deref(),
+    )?;
+    let mutations =
+        havoc_mutations_openapi(injection_payloads, config.max_chain_length, &user_examples);
+    let probabilities = mutation_probabilities(&mutations.names());
+    let mutator_openapi = TuneableScheduledMutator::new(&mut state, mutations);
+    mutator_openapi.set_mutation_probabilities(&mut state, probabilities)?;
 
     // The order of the stages matter!
     let power = StdPowerMutationalStage::new(mutator_openapi);
     let mut stages = tuple_list!(calibration, power);
 
     let (authentication, cookie_store, client) = crate::build_http_client()?;
+    let header_budget = crate::header_budget(&authentication)?;
 
     let reporter = crate::reporting::sqlite::get_reporter(config)?;
+    let har_reporter = crate::reporting::har::get_reporter(report_path.as_deref());
+    let webhook_reporter = crate::reporting::webhook::get_reporter(config)?;
+    let fixture_exporter = crate::reporting::fixtures::get_reporter(config);
+    let cassette_recorder = crate::reporting::cassette::get_reporter(config)?;
+    let crash_collection = crate::reporting::postman::get_reporter(config, &api);
+    let sarif_exporter = crate::reporting::sarif::get_reporter(config);
+    let report_crash = |request: &OpenApiRequest, response: &Response, error_kind: &str| {
+        if config.coverage_only {
+            return;
+        }
+        webhook_reporter.report_crash(request, response, error_kind);
+        crash_collection.report_crash(request, response, error_kind);
+        sarif_exporter.report_crash(request, response, error_kind);
+    };
 
     // Keep track of the number of inputs
     let mut inputs_tested = 0;
     // Logging the number of executed requests
     let mut stats = LoggingStats::new();
+    // Per-input coverage snapshots, only kept if a report is requested
+    let mut coverage_attribution = report_path.as_ref().map(|_| CoverageAttribution::new());
+    // Delays between the requests of a chain, if a think time is configured
+    let mut think_time = config.think_time.as_ref().map(ThinkTime::sampler);
+    // Error response shapes learned so far, if they are checked
+    let mut error_shapes = config.check_error_shapes.then(ErrorShapes::default);
+    // Limits the request rate, if a maximum is configured. It is shared by the copies of
+    // a raced request, which retry concurrently.
+    let throttle = config
+        .max_rps
+        .map(|max_rps| Mutex::new(TokenBucket::new(max_rps)));
+    // Coverage found to be flaky, if it is ignored
+    let mut flaky_coverage = config.ignore_flaky_coverage.then(FlakyCoverage::default);
+    // Retries requests after transient failures, if configured
+    let retry_policy = config.retry_policy();
 
     // The closure that we want to fuzz
     let mut harness = |inputs: &OpenApiInput| {
@@ -186,6 +304,9 @@ pub fn fuzz() -> Result<()> {
         let mut parameter_feedback = ParameterFeedback::new(inputs.0.len());
         log::debug!("Sending {} requests", inputs.0.len());
         'chain: for (request_index, request) in inputs.0.iter().enumerate() {
+            if let Some(think_time) = think_time.as_mut().filter(|_| request_index > 0) {
+                std::thread::sleep(think_time.sample());
+            }
             let mut request = request.clone();
             log::trace!("OpenAPI request:\n{:#?}", request);
             if let Err(error) = request.resolve_parameter_references(&parameter_feedback) {
@@ -195,13 +316,27 @@ pub fn fuzz() -> Result<()> {
                     );
                 break 'chain;
             };
-            let request_builder =
-                match build_request_from_input(&client, &cookie_store, &api, &request) {
-                    None => continue,
-                    Some(r) => r.timeout(Duration::from_millis(config.request_timeout)),
-                };
+            let request_builder = match build_request_from_input(
+                &client,
+                &cookie_store,
+                &authentication,
+                &api,
+                &user_examples,
+                config.form_array_style,
+                &request,
+            ) {
+                None => continue,
+                Some(r) => {
+                    let timeout = config
+                        .timeout_overrides
+                        .as_ref()
+                        .and_then(|overrides| overrides.timeout(request.method, &request.path))
+                        .unwrap_or(config.request_timeout);
+                    r.timeout(Duration::from_millis(timeout))
+                }
+            };
 
-            let request_built = match request_builder.build() {
+            let mut request_built = match request_builder.build() {
                 Ok(request) => request,
                 Err(err) => {
                     // We don't expect errors to occur in the reqwest builder. If one occurs,
@@ -210,16 +345,84 @@ pub fn fuzz() -> Result<()> {
                     break;
                 }
             };
+            if let Some(header_budget) = &header_budget {
+                header_budget.enforce(&api, &request, &mut request_built);
+            }
 
-            let curl_request = CurlRequest(&request_built, &authentication);
+            let curl_request = CurlRequest(&request_built, &authentication, Some(&cookie_store));
             let reporter_request_id =
                 reporter.report_request(&request, &curl_request, inputs_tested);
+            let har_entry = har_reporter.report_request(&request, &curl_request, inputs_tested);
+            let fixture_id =
+                fixture_exporter.report_request(&request, &curl_request, inputs_tested);
+            let cassette_id =
+                cassette_recorder.report_request(&request, &curl_request, inputs_tested);
             let curl_request = curl_request.to_string();
+            let traced_request = log::log_enabled!(log::Level::Trace).then(|| {
+                format!(
+                    "{} {} with headers [{}] and a body of {} bytes",
+                    request_built.method(),
+                    redacted_url(request_built.url(), &authentication),
+                    redacted_headers(request_built.headers(), &authentication),
+                    request_built
+                        .body()
+                        .and_then(reqwest::blocking::Body::as_bytes)
+                        .map_or(0, <[u8]>::len)
+                )
+            });
+
+            // Each copy of a raced request is sent like any other request
+            let send = |mut request| {
+                if config.chunked_bodies {
+                    stream_body_chunked(&mut request);
+                }
+                // The first attempt is throttled before sending, every retry takes a token
+                let mut retrying = false;
+                retry_policy.execute(request, |request| {
+                    if std::mem::replace(&mut retrying, true) {
+                        if let Some(throttle) = &throttle {
+                            throttle.lock().unwrap().wait(1);
+                        }
+                    }
+                    authentication.execute(&client, request)
+                })
+            };
+            let (response, race_anomalies, elapsed) = match config.race {
+                Some(count) if crate::race::is_write(request.method) => {
+                    if let Some(throttle) = &throttle {
+                        throttle.lock().unwrap().wait(count);
+                    }
+                    let sent = Instant::now();
+                    let (response, race_anomalies) =
+                        crate::race::race_request(request_built, request.method, count, send);
+                    (response, race_anomalies, sent.elapsed())
+                }
+                _ => {
+                    if let Some(throttle) = &throttle {
+                        throttle.lock().unwrap().wait(1);
+                    }
+                    let sent = Instant::now();
+                    let response = send(request_built).map(Response::from);
+                    (response, vec![], sent.elapsed())
+                }
+            };
+            if let Some(traced_request) = traced_request {
+                match &response {
+                    Ok(response) => log::trace!(
+                        "Sent {traced_request}: status {} after {} ms",
+                        response.status(),
+                        elapsed.as_millis()
+                    ),
+                    Err(err) => log::trace!(
+                        "Sent {traced_request}: {err} after {} ms",
+                        elapsed.as_millis()
+                    ),
+                }
+            }
 
-            match client.execute(request_built) {
+            match response {
                 Ok(response) => {
                     stats.performed_requests += 1;
-                    let response: Response = response.into();
 
                     endpoint_coverage_client.lock().unwrap().cover(
                         request.method,
@@ -231,6 +434,9 @@ pub fn fuzz() -> Result<()> {
                         }),
                     );
                     reporter.report_response(&response, reporter_request_id);
+                    har_reporter.report_response(&response, har_entry);
+                    fixture_exporter.report_response(&response, fixture_id);
+                    cassette_recorder.report_response(&response, cassette_id);
                     log::trace!("Got response {}", response.status());
 
                     if response.status() == 429 {
@@ -239,16 +445,68 @@ pub fn fuzz() -> Result<()> {
                         log::warn!("This hinders fuzz testing. Consider disabling it.");
                     }
 
+                    if !race_anomalies.is_empty() {
+                        for anomaly in &race_anomalies {
+                            log::warn!(
+                                "Race condition on {} {}: {anomaly}",
+                                request.method,
+                                request.path
+                            );
+                            report_crash(&request, &response, anomaly.kind());
+                        }
+                        exit_kind = ExitKind::Crash;
+                        log::debug!("OpenAPI-input resulted in inconsistent concurrent responses, ignoring rest of request chain.");
+                        break 'chain;
+                    }
+
+                    if config.check_rate_limit_headers {
+                        if let Err(validation_err) = validate_rate_limit_headers(&response) {
+                            log::debug!("OpenAPI-input resulted in validation error: {validation_err}, ignoring rest of request chain.");
+                            report_crash(&request, &response, validation_err.kind());
+                            exit_kind = ExitKind::Crash;
+                            break 'chain;
+                        }
+                    }
+
+                    if let Some(threshold) = config.slow_response_ms {
+                        if let Err(validation_err) =
+                            validate_response_time(elapsed, Duration::from_millis(threshold))
+                        {
+                            log::debug!("OpenAPI-input resulted in validation error: {validation_err}, ignoring rest of request chain.");
+                            report_crash(&request, &response, validation_err.kind());
+                            exit_kind = ExitKind::Crash;
+                            break 'chain;
+                        }
+                    }
+
+                    if let Some(error_shapes) = error_shapes.as_mut() {
+                        if let Err(mismatch) = error_shapes.check(&request, &response) {
+                            log::debug!("OpenAPI-input resulted in inconsistent error response: {mismatch}, ignoring rest of request chain.");
+                            report_crash(&request, &response, mismatch.kind());
+                            exit_kind = ExitKind::Crash;
+                            break 'chain;
+                        }
+                    }
+
                     if response.status().is_server_error() {
+                        report_crash(
+                            &request,
+                            &response,
+                            &format!("http_{}", response.status().as_u16()),
+                        );
                         exit_kind = ExitKind::Crash;
                         log::debug!("OpenAPI-input resulted in server error response, ignoring rest of request chain.");
                         break 'chain;
                     } else {
                         if config.crash_criterion == CrashCriterion::AllErrors {
-                            if let Err(validation_err) =
-                                validate_response(&api, &request, &response)
-                            {
+                            if let Err(validation_err) = validate_response(
+                                &api,
+                                &request,
+                                &response,
+                                config.check_response_constraints,
+                            ) {
                                 log::debug!("OpenAPI-input resulted in validation error: {validation_err}, ignoring rest of request chain.");
+                                report_crash(&request, &response, validation_err.kind());
                                 exit_kind = ExitKind::Crash;
                                 break 'chain;
                             }
@@ -260,6 +518,9 @@ pub fn fuzz() -> Result<()> {
                 }
                 Err(e) => {
                     reporter.report_response_error(&e.to_string(), reporter_request_id);
+                    har_reporter.report_response_error(&e.to_string(), har_entry);
+                    fixture_exporter.report_response_error(&e.to_string(), fixture_id);
+                    cassette_recorder.report_response_error(&e.to_string(), cassette_id);
                     error!("{}", e);
                     exit_kind = ExitKind::Timeout;
                     log::debug!(
@@ -277,6 +538,23 @@ pub fn fuzz() -> Result<()> {
             &mut stats,
             |s: String| info!("{}", s),
         );
+        if let Some(flaky_coverage) = flaky_coverage.as_mut() {
+            // Safety: LibAFL does not access the coverage map while the harness runs
+            let map = unsafe {
+                std::slice::from_raw_parts_mut(
+                    code_coverage_client.get_coverage_ptr(),
+                    code_coverage_client.get_coverage_len(),
+                )
+            };
+            flaky_coverage.process(inputs, map);
+        }
+        if let Some(coverage_attribution) = coverage_attribution.as_mut() {
+            coverage_attribution.record(
+                inputs.generate_name(None),
+                exit_kind != ExitKind::Ok,
+                &mut [code_coverage_client.as_mut(), &mut endpoint_coverage_client],
+            );
+        }
 
         exit_kind
     };
@@ -338,12 +616,30 @@ pub fn fuzz() -> Result<()> {
         match fuzzer.fuzz_one(&mut stages, &mut executor, &mut state, &mut mgr) {
             Ok(_) => (),
             Err(libafl_bolts::Error::ShuttingDown) => {
+                save_state(config, &state, &endpoint_coverage_client);
+                print_run_summary(
+                    config,
+                    &mut code_coverage_client,
+                    &mut endpoint_coverage_client,
+                    *state.executions(),
+                    state.solutions().count(),
+                    run_start.elapsed(),
+                );
+                minimize_crashes(config);
                 return Ok(());
             }
             Err(err) => {
                 return Err(err).context("Error in the fuzz loop");
             }
         };
+        if let Some(max_corpus_entries) = config.max_corpus_entries {
+            crate::corpus_limit::evict_excess_entries(
+                &mut state,
+                fuzzer.scheduler_mut(),
+                max_corpus_entries,
+            )
+            .context("Failed to evict corpus entries")?;
+        }
         // send update of execution data to the monitor
         let executions = *state.executions();
         if let Err(e) = mgr.fire(
@@ -364,14 +660,110 @@ pub fn fuzz() -> Result<()> {
         }
     }
 
+    save_state(config, &state, &endpoint_coverage_client);
+    print_run_summary(
+        config,
+        &mut code_coverage_client,
+        &mut endpoint_coverage_client,
+        *state.executions(),
+        state.solutions().count(),
+        run_start.elapsed(),
+    );
+    minimize_crashes(config);
+
     if let Some(report_path) = report_path {
         endpoint_coverage_client.generate_coverage_report(&report_path);
         code_coverage_client.generate_coverage_report(&report_path);
+        if let Some(coverage_attribution) = coverage_attribution {
+            if let Err(e) = coverage_attribution.write_report(&report_path) {
+                error!("Could not write coverage attribution report: {e}");
+            }
+        }
+        if let Some(reporter) = &reporter {
+            match reporter.executed_graph() {
+                Ok(graph) => {
+                    if let Err(e) = graph.write_report(&report_path) {
+                        error!("Could not write executed request graph: {e}");
+                    }
+                }
+                Err(e) => error!("Could not build executed request graph: {e}"),
+            }
+        }
     }
 
     Ok(())
 }
 
+/// Saves the state of the campaign to the state file, if one is configured, so that the
+/// next campaign can resume from it
+fn save_state<S: HasRand<Rand = StdRand>>(
+    config: &Configuration,
+    state: &S,
+    endpoint_coverage_client: &Arc<Mutex<EndpointCoverageClient>>,
+) {
+    if let Some(state_file) = &config.state_file {
+        let saved_state = FuzzerStateFile {
+            rand: *state.rand(),
+            endpoint_coverage: endpoint_coverage_client.lock().unwrap().save(),
+        };
+        match saved_state.save(state_file) {
+            Ok(()) => info!(
+                "Saved the state of the campaign to {}",
+                state_file.display()
+            ),
+            Err(e) => error!("Could not save the state of the campaign: {e:#}"),
+        }
+    }
+}
+
+/// Minimizes and deduplicates the crashes of the run, if configured
+fn minimize_crashes(config: &Configuration) {
+    if config.minimize_crashes {
+        if let Err(e) = crate::reproducer::minimize_crashes(
+            config,
+            Path::new("./crashes"),
+            Path::new("./crashes_minimized"),
+        ) {
+            error!("Could not minimize the crashes: {e:#}");
+        }
+    }
+}
+
+/// Prints the summary of the run, with the coverage reached so far, in the configured
+/// output format
+fn print_run_summary(
+    config: &Configuration,
+    code_coverage_client: &mut Box<dyn CoverageClient>,
+    endpoint_coverage_client: &mut Arc<Mutex<EndpointCoverageClient>>,
+    executions: u64,
+    crashes: usize,
+    elapsed: Duration,
+) {
+    let summary = RunSummary::new(
+        code_coverage_client.max_coverage_ratio(),
+        endpoint_coverage_client.max_coverage_ratio(),
+        executions,
+        crashes,
+        elapsed,
+    );
+    print_summary(&summary, config.output_format);
+}
+
+/// The feedback that decides whether an input is a solution: one that made the target
+/// crash. In a coverage-only run, no input is a solution.
+type CrashObjective =
+    FastAndFeedback<ConstFeedback, EagerOrFeedback<CrashFeedback, LineageFeedback>>;
+
+fn crash_objective(coverage_only: bool, lineage_log: Option<&Path>) -> CrashObjective {
+    feedback_and_fast!(
+        ConstFeedback::new(!coverage_only),
+        feedback_or!(
+            CrashFeedback::new(),
+            LineageFeedback::new(lineage_log, "crash")
+        )
+    )
+}
+
 /// Sets up the endpoint coverage client according to the configuration, and initializes it
 /// and constructs a LibAFL observer and feedback
 #[allow(clippy::type_complexity)]
@@ -383,6 +775,7 @@ fn setup_endpoint_coverage<
     OT: MatchName,
 >(
     api: OpenAPI,
+    map_size: usize,
 ) -> Result<
     (
         Arc<Mutex<EndpointCoverageClient>>,
@@ -391,7 +784,8 @@ fn setup_endpoint_coverage<
     ),
     anyhow::Error,
 > {
-    let mut endpoint_coverage_client = Arc::new(Mutex::new(EndpointCoverageClient::new(&api)));
+    let mut endpoint_coverage_client =
+        Arc::new(Mutex::new(EndpointCoverageClient::new(&api, map_size)));
     endpoint_coverage_client.fetch_coverage(true);
     // no-op for this particular CoverageClient
     // Safety: libafl wants to read the coverage map directly that we also update in the harness;
@@ -594,3 +988,34 @@ fn update_coverage<F: FnMut(String)>(
 
     reporter.report_coverage(covered, total, e_covered, e_total)
 }
+
+#[cfg(test)]
+mod tests {
+    use libafl::{
+        events::NopEventManager, executors::ExitKind, feedbacks::Feedback, state::NopState,
+    };
+
+    use super::crash_objective;
+    use crate::input::OpenApiInput;
+
+    /// Whether the objective deems an input that crashed the target a solution
+    fn is_solution(coverage_only: bool) -> bool {
+        let mut objective = crash_objective(coverage_only, None);
+        let mut state = NopState::<OpenApiInput>::new();
+        Feedback::<_, _, (), _>::is_interesting(
+            &mut objective,
+            &mut state,
+            &mut NopEventManager::<NopState<OpenApiInput>>::new(),
+            &OpenApiInput(vec![]),
+            &(),
+            &ExitKind::Crash,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_coverage_only_records_no_solutions() {
+        assert!(is_solution(false));
+        assert!(!is_solution(true));
+    }
+}
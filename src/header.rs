@@ -5,9 +5,13 @@
 use std::{collections::HashMap, fs::File, str::FromStr};
 
 use anyhow::{Context, Result};
-use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION};
+use url::Url;
 
-use crate::configuration::Configuration;
+use crate::{
+    authentication::{api_key::ApiKeyLocation, Authentication},
+    configuration::Configuration,
+};
 
 /// Load default headers from a file specified in configuration and apply
 /// them to the given ClientBuilder
@@ -49,3 +53,106 @@ pub fn get_default_headers() -> Result<HeaderMap> {
 
     Ok(default_headers)
 }
+
+/// Value that secrets are replaced by in logs
+const REDACTED: &str = "***";
+
+/// Formats the headers as `name: value` pairs for logging, with the values of the
+/// Authorization header and of an API key header replaced by `***`, so that credentials
+/// do not end up in logs.
+pub fn redacted_headers(headers: &HeaderMap, authentication: &Authentication) -> String {
+    let api_key_header = match authentication {
+        Authentication::ApiKey(config) if config.location == ApiKeyLocation::Header => {
+            Some(config.name.as_str())
+        }
+        _ => None,
+    };
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let secret = *name == AUTHORIZATION
+                || api_key_header.is_some_and(|header| name.as_str().eq_ignore_ascii_case(header));
+            if secret {
+                format!("{name}: {REDACTED}")
+            } else {
+                format!("{name}: {}", String::from_utf8_lossy(value.as_bytes()))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Formats the URL for logging, with the value of an API key query parameter replaced
+/// by `***`.
+pub fn redacted_url(url: &Url, authentication: &Authentication) -> String {
+    let Some((api_key, _)) = authentication.query_parameter() else {
+        return url.to_string();
+    };
+    let pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(name, value)| {
+            if name == api_key {
+                (name.into_owned(), REDACTED.to_owned())
+            } else {
+                (name.into_owned(), value.into_owned())
+            }
+        })
+        .collect();
+    let mut redacted = url.clone();
+    redacted.query_pairs_mut().clear().extend_pairs(pairs);
+    redacted.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::header::{HeaderMap, HeaderValue, ACCEPT, AUTHORIZATION};
+
+    use super::{redacted_headers, redacted_url};
+    use crate::authentication::{
+        api_key::{ApiKeyLocation, ApiKeyLogin},
+        Authentication,
+    };
+
+    fn api_key(location: ApiKeyLocation) -> Authentication {
+        Authentication::ApiKey(ApiKeyLogin {
+            name: "X-API-Key".to_owned(),
+            location,
+            value: "k3y".to_owned(),
+        })
+    }
+
+    #[test]
+    fn test_authorization_header_is_redacted() {
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT, HeaderValue::from_static("application/json"));
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer s3cr3t"));
+        headers.insert("x-trace", HeaderValue::from_static("abc"));
+
+        let redacted = redacted_headers(&headers, &Authentication::None);
+        assert_eq!(
+            redacted,
+            "accept: application/json, authorization: ***, x-trace: abc"
+        );
+        assert!(!redacted.contains("s3cr3t"));
+
+        headers.insert("x-api-key", HeaderValue::from_static("k3y"));
+        let redacted = redacted_headers(&headers, &api_key(ApiKeyLocation::Header));
+        assert!(redacted.ends_with("x-trace: abc, x-api-key: ***"));
+    }
+
+    #[test]
+    fn test_api_key_query_parameter_is_redacted() {
+        let url = "http://localhost:8080/pets?limit=10&X-API-Key=k3y"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            redacted_url(&url, &api_key(ApiKeyLocation::Query)),
+            "http://localhost:8080/pets?limit=10&X-API-Key=***"
+        );
+        // Other modes do not put secrets in the URL
+        assert_eq!(
+            redacted_url(&url, &Authentication::None),
+            "http://localhost:8080/pets?limit=10&X-API-Key=k3y"
+        );
+    }
+}
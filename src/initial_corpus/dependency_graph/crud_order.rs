@@ -0,0 +1,157 @@
+//! The order in which the methods of a REST API are expected to be used on a resource:
+//! typically it is created (POST) before it is read (GET), updated (PUT, PATCH) and
+//! finally deleted (DELETE). The dependency graph only links an operation to operations
+//! with a method that comes no earlier in this order.
+
+use std::{fmt::Display, str::FromStr};
+
+use anyhow::bail;
+
+use crate::input::Method;
+
+/// Order of the methods, written as a comma-separated list such as
+/// `POST,GET,PUT,PATCH,DELETE`. Methods that are not listed come after all listed ones.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+#[serde(try_from = "String")]
+pub struct CrudOrder(Vec<Method>);
+
+impl CrudOrder {
+    /// Position of the method in the order. Methods that are not listed share the last
+    /// position.
+    pub fn rank(&self, method: Method) -> usize {
+        self.0
+            .iter()
+            .position(|listed| *listed == method)
+            .unwrap_or(self.0.len())
+    }
+}
+
+impl Default for CrudOrder {
+    /// The order of `Method::cmp`
+    fn default() -> Self {
+        Self(vec![
+            Method::Post,
+            Method::Head,
+            Method::Trace,
+            Method::Get,
+            Method::Put,
+            Method::Patch,
+            Method::Delete,
+            Method::Options,
+            Method::Connect,
+            Method::Propfind,
+            Method::Proppatch,
+            Method::Mkcol,
+            Method::Copy,
+            Method::Move,
+            Method::Lock,
+            Method::Unlock,
+        ])
+    }
+}
+
+impl FromStr for CrudOrder {
+    type Err = anyhow::Error;
+
+    fn from_str(order: &str) -> Result<Self, Self::Err> {
+        let mut methods = Vec::new();
+        for name in order.split(',').map(str::trim) {
+            let method = match Method::try_from(name) {
+                Ok(method) => method,
+                Err(_) => bail!("Unknown method '{name}' in CRUD order '{order}'"),
+            };
+            if methods.contains(&method) {
+                bail!("Method {method} occurs more than once in CRUD order '{order}'");
+            }
+            methods.push(method);
+        }
+        Ok(Self(methods))
+    }
+}
+
+impl TryFrom<String> for CrudOrder {
+    type Error = anyhow::Error;
+
+    fn try_from(order: String) -> Result<Self, Self::Error> {
+        order.parse()
+    }
+}
+
+impl Display for CrudOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names: Vec<&str> = self.0.iter().map(Method::as_str).collect();
+        f.write_str(&names.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use openapiv3::OpenAPI;
+    use serde_json::json;
+
+    use super::CrudOrder;
+    use crate::{
+        initial_corpus::dependency_graph::{synonyms::Synonyms, DependencyGraph},
+        input::Method,
+    };
+
+    /// The methods of the operations at both ends of each edge of the graph
+    fn edges(graph: &DependencyGraph) -> Vec<(Method, Method)> {
+        let graph = &graph.graph;
+        let mut edges: Vec<_> = graph
+            .edge_indices()
+            .filter_map(|edge| graph.edge_endpoints(edge))
+            .map(|(from, to)| (graph[from].method, graph[to].method))
+            .collect();
+        edges.sort_by_key(|(from, to)| (from.as_str(), to.as_str()));
+        edges.dedup();
+        edges
+    }
+
+    #[test]
+    fn test_order_determines_edge_direction() {
+        let operation = json!({
+            "parameters": [
+                {"name": "id", "in": "path", "required": true, "schema": {"type": "integer"}}
+            ],
+            "responses": {
+                "200": {
+                    "description": "The pet",
+                    "content": {
+                        "application/json": {
+                            "schema": {
+                                "type": "object",
+                                "properties": {"id": {"type": "integer"}}
+                            }
+                        }
+                    }
+                }
+            }
+        });
+        let api: OpenAPI = serde_json::from_value(json!({
+            "openapi": "3.0.0",
+            "info": {"title": "pets", "version": "1"},
+            "paths": {
+                "/pets/{id}": {"get": operation, "delete": operation}
+            }
+        }))
+        .unwrap();
+
+        let default_order = DependencyGraph::new(&api);
+        assert_eq!(edges(&default_order), vec![(Method::Get, Method::Delete)]);
+
+        let delete_first: CrudOrder = "DELETE, GET".parse().unwrap();
+        let reversed = DependencyGraph::with_options(&api, &delete_first, &Synonyms::default());
+        assert_eq!(edges(&reversed), vec![(Method::Delete, Method::Get)]);
+    }
+
+    #[test]
+    fn test_parse_order() {
+        let order: CrudOrder = "post,GET,Delete".parse().unwrap();
+        assert_eq!(order.to_string(), "POST,GET,DELETE");
+        assert_eq!(order.rank(Method::Get), 1);
+        assert_eq!(order.rank(Method::Put), 3);
+        assert!("POST,GET,POST".parse::<CrudOrder>().is_err());
+        assert!("POST,FETCH".parse::<CrudOrder>().is_err());
+    }
+}
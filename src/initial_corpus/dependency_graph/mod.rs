@@ -1,4 +1,6 @@
+pub mod crud_order;
 mod normalize;
+pub mod synonyms;
 mod toposort;
 
 /// The fuzzer wants to use outputs of previous requests (POST artist -> artistid)
@@ -6,8 +8,7 @@ mod toposort;
 /// you need a graph that connects possible requests/operations (nodes) by parameters that carry
 /// the same meaning (edges). The dependency graph module attempts to build such a graph.
 use std::{
-    cmp::Ordering,
-    collections::{hash_map::DefaultHasher, HashMap},
+    collections::hash_map::DefaultHasher,
     fmt::Display,
     fs::{create_dir_all, File},
     hash::{Hash, Hasher},
@@ -15,77 +16,173 @@ use std::{
     path::Path,
 };
 
+use indexmap::IndexMap;
 use log::warn;
-use openapiv3::{OpenAPI, StatusCode};
+pub use normalize::{set_normalization_language, NormalizationLanguage};
+use openapiv3::{OpenAPI, Parameter, StatusCode};
 use petgraph::{
     prelude::{DiGraph, NodeIndex},
     stable_graph::DefaultIx,
     unionfind::UnionFind,
     visit::{EdgeRef, IntoNodeReferences, NodeIndexable},
+    Direction,
 };
 
 use self::{
+    crud_order::CrudOrder,
     normalize::{
-        normalize_parameters, normalize_request_body, normalize_response, ParameterNormalization,
+        normalize_parameter, normalize_parameters, normalize_request_body, normalize_response,
+        normalize_response_examples, ParameterNormalization,
     },
+    synonyms::Synonyms,
     toposort::{toposort, Cycle},
 };
 use crate::{
     input::{parameter::ParameterKind, Method, OpenApiInput, ParameterContents},
     openapi::{
-        examples::{example_from_qualified_operation, openapi_inputs_from_ops},
+        examples::{example_from_qualified_operation, openapi_inputs_from_ops, UserExamples},
         QualifiedOperation,
     },
 };
 
 /// Returns OpenApiInputs generated from a dependency graph derived from the OpenAPI
 /// specification. If rigorously generating parameter combinations would result in
-/// too many inputs, it just generates a single example.
-pub fn initial_corpus_from_api(api: &OpenAPI) -> Vec<OpenApiInput> {
-    let dependency_graph = DependencyGraph::new(api);
+/// more than `max_combinations` inputs for a chain, it just generates a single example.
+pub fn initial_corpus_from_api(
+    api: &OpenAPI,
+    user_examples: &UserExamples,
+    crud_order: &CrudOrder,
+    synonyms: &Synonyms,
+    max_combinations: usize,
+) -> Vec<OpenApiInput> {
+    let dependency_graph = DependencyGraph::with_options(api, crud_order, synonyms);
+    let response_values = response_example_values(api, synonyms);
+    for (operation, inputs) in dependency_graph.unproduced_inputs(api) {
+        warn!(
+            "No operation produces {} for {} {}, so it can only be fuzzed with made-up values",
+            inputs.join(", "),
+            operation.method,
+            operation.path
+        );
+    }
 
     // Turn all subgraphs into sorted lists of node indices
     dependency_graph
         .connected_components()
         .iter()
         .map(|nodes| dependency_graph.subgraph(nodes))
-        .map(|subgraph| match ops_from_subgraph(&subgraph) {
+        .flat_map(|mut subgraph| match ops_from_subgraph(&mut subgraph) {
             Ok((ops, idxs)) => {
                 // TODO: pass subgraph into openapi_inputs_from_ops to prevent generation of parameter values
                 // that will be replaced by references below anyway. The current implementation often
                 // massively overgenerates all the different combinations, most of which then get
                 // mapped back to the same OpenApiInput since all concrete parameter values get
                 // overwritten with references to the same parameter in an earlier response.
-                let mut inputs =
-                    openapi_inputs_from_ops(api, ops.clone().into_iter(), &subgraph, &idxs)
-                        .inspect_err(|err| {
-                            log::warn!("{} - falling back to single example generation.", err);
-                        })
-                        .unwrap_or(vec![openapi_example_input_from_ops(api, ops.into_iter())]);
+                let mut inputs = openapi_inputs_from_ops(
+                    api,
+                    user_examples,
+                    ops.clone().into_iter(),
+                    &subgraph,
+                    &idxs,
+                    &response_values,
+                    max_combinations,
+                )
+                .inspect_err(|err| {
+                    log::warn!("{} - falling back to single example generation.", err);
+                })
+                .unwrap_or(vec![openapi_example_input_from_ops(
+                    api,
+                    user_examples,
+                    ops.into_iter(),
+                )]);
                 inputs.iter_mut().for_each(|input| {
                     add_references_to_openapi_input(&subgraph, &idxs, input);
                 });
-                Ok(inputs)
+                // One set of variants per subgraph suffices, the inputs only differ
+                // in their parameter values.
+                if let Some(first) = inputs.first() {
+                    let variants = empty_and_missing_query_variants(first);
+                    inputs.extend(variants);
+                }
+                inputs
             }
-            Err(e) => Err(e),
+            // The cycles could not be broken, so each operation gets an example of its own
+            Err(_) => subgraph
+                .node_weights()
+                .map(|op| {
+                    openapi_example_input_from_ops(api, user_examples, std::iter::once(op.clone()))
+                })
+                .collect(),
         })
-        .filter_map(|result| result.ok())
-        .flatten()
         .collect()
 }
 
+/// Returns two variants of the input if it has concrete query parameters: one in which
+/// these parameters have an empty value, and one in which they are omitted. Servers
+/// often handle these cases differently from each other and from the regular case.
+fn empty_and_missing_query_variants(input: &OpenApiInput) -> Vec<OpenApiInput> {
+    let is_concrete_query = |(_, kind): &(String, ParameterKind), value: &ParameterContents| {
+        *kind == ParameterKind::Query && !value.is_reference()
+    };
+    if !input
+        .0
+        .iter()
+        .flat_map(|request| request.parameters.iter())
+        .any(|(key, value)| is_concrete_query(key, value))
+    {
+        return vec![];
+    }
+
+    let mut empty = input.clone();
+    for request in empty.0.iter_mut() {
+        for (key, value) in request.parameters.iter_mut() {
+            if is_concrete_query(key, value) {
+                *value = ParameterContents::from(String::new());
+            }
+        }
+    }
+    let mut missing = input.clone();
+    for request in missing.0.iter_mut() {
+        request
+            .parameters
+            .retain(|key, value| !is_concrete_query(key, value));
+    }
+    vec![empty, missing]
+}
+
 /// Creates a vector of topologically sorted QualifiedOperations (path, method, etc.)
 /// from the subgraph. To let the caller keep track of the sorting, this function also
 /// returns a Vec of the NodeIndex items corresponding to the QualifiedOperations.
+///
+/// If the subgraph has cycles, they are broken by removing an edge of each cycle from
+/// the subgraph, so that only the parameters of those edges are not linked.
 fn ops_from_subgraph<'a>(
-    subgraph: &DiGraph<QualifiedOperation<'a>, ParameterMatching<'a>, DefaultIx>,
+    subgraph: &mut DiGraph<QualifiedOperation<'a>, ParameterMatching<'a>, DefaultIx>,
 ) -> Result<(Vec<QualifiedOperation<'a>>, Vec<NodeIndex>), Cycle<NodeIndex>> {
-    let sorted_nodes = match toposort(subgraph, None) {
-        Ok(nodes) => nodes,
-        Err(cycle) => {
-            let operation = &subgraph[cycle.node_id()];
-            warn!("While building initial corpus from the API specification, found operation with a self-cycle {} {}", operation.method, operation.path);
-            return Err(cycle);
+    // Every retry has one edge less, so this ends once all cycles are broken
+    let sorted_nodes = loop {
+        match toposort(subgraph, None) {
+            Ok(nodes) => break nodes,
+            Err(cycle) => {
+                let (source, target) = cycle.edge();
+                let Some(matching) = subgraph
+                    .find_edge(source, target)
+                    .and_then(|edge| subgraph.remove_edge(edge))
+                else {
+                    let operation = &subgraph[cycle.node_id()];
+                    warn!("While building initial corpus from the API specification, could not break a cycle through {} {}", operation.method, operation.path);
+                    return Err(cycle);
+                };
+                warn!(
+                    "While building initial corpus from the API specification, found a cycle; not passing {} from {} {} to {} of {} {}",
+                    matching.name_output,
+                    subgraph[source].method,
+                    subgraph[source].path,
+                    matching.name_input,
+                    subgraph[target].method,
+                    subgraph[target].path
+                );
+            }
         }
     };
     Ok((
@@ -100,11 +197,12 @@ fn ops_from_subgraph<'a>(
 /// Creates an example OpenApiInput from the sequence of QualifiedOperations given by ops_iter for the given api.
 fn openapi_example_input_from_ops<'a>(
     api: &OpenAPI,
+    user_examples: &UserExamples,
     ops_iter: impl Iterator<Item = QualifiedOperation<'a>>,
 ) -> OpenApiInput {
     OpenApiInput(
         ops_iter
-            .map(|op| example_from_qualified_operation(api, op))
+            .map(|op| example_from_qualified_operation(api, user_examples, op))
             .collect(),
     )
 }
@@ -174,6 +272,13 @@ pub struct ParameterMatching<'a> {
 
 impl<'a> DependencyGraph<'a> {
     pub fn new(api: &'a OpenAPI) -> Self {
+        Self::with_options(api, &CrudOrder::default(), &Synonyms::default())
+    }
+
+    /// Builds the graph, only adding edges from an operation to operations whose method
+    /// comes no earlier in `crud_order`. Parameter names are matched after replacing
+    /// their words with `synonyms`.
+    pub fn with_options(api: &'a OpenAPI, crud_order: &CrudOrder, synonyms: &Synonyms) -> Self {
         let mut graph = DiGraph::new();
 
         // Add all operations to the graph as nodes
@@ -193,7 +298,7 @@ impl<'a> DependencyGraph<'a> {
         // (We save them by node index, node indices are a compact interval)
         let inout_params: Vec<_> = (0..graph.node_count())
             .map(NodeIndex::new)
-            .map(|n| inout_params(api, &graph[n]))
+            .map(|n| inout_params(api, &graph[n], synonyms))
             .collect();
 
         // Find edges (parameters in common) between all nodes (operations)
@@ -204,7 +309,8 @@ impl<'a> DependencyGraph<'a> {
                     continue;
                 }
                 // Enforce CRUD order
-                if (graph[op_left].method.cmp(&graph[op_right].method)) == Ordering::Greater {
+                if crud_order.rank(graph[op_left].method) > crud_order.rank(graph[op_right].method)
+                {
                     continue;
                 }
                 for link in find_links(
@@ -225,8 +331,9 @@ impl<'a> DependencyGraph<'a> {
 
         // The UnionFind will point you to a representative for each vertex.
         // The representative is a usize (from to_index), so we use that to
-        // keep track of our subgraphs.
-        let mut subgraphs = HashMap::new();
+        // keep track of our subgraphs, in the order of their first operation, so that
+        // the generated corpus is the same in every run.
+        let mut subgraphs = IndexMap::new();
         for operation in self.graph.node_indices() {
             let representative = vertex_sets.find_mut(operation);
             subgraphs
@@ -302,6 +409,40 @@ impl<'a> DependencyGraph<'a> {
         Ok(())
     }
 
+    /// Returns the operations with required identifying inputs that no other operation
+    /// produces, together with the names of those inputs. The fuzzer can only send
+    /// made-up values for them, so the valid flows of these operations remain untested.
+    /// Identifying inputs are the path parameters and the required parameters whose name
+    /// ends in "id"; body fields are not considered.
+    pub fn unproduced_inputs(
+        &self,
+        api: &'a OpenAPI,
+    ) -> Vec<(&QualifiedOperation<'a>, Vec<&'a str>)> {
+        self.graph
+            .node_indices()
+            .filter_map(|node| {
+                let operation = &self.graph[node];
+                let produced: Vec<_> = self
+                    .graph
+                    .edges_directed(node, Direction::Incoming)
+                    .map(|edge| (edge.weight().name_input, edge.weight().kind_input))
+                    .collect();
+                let unproduced: Vec<_> = operation
+                    .operation
+                    .parameters
+                    .iter()
+                    .filter_map(|ref_or_param| ref_or_param.resolve(api).ok())
+                    .filter(|param| is_identifying(param))
+                    .filter(|param| {
+                        !produced.contains(&(param.data.name.as_str(), (*param).into()))
+                    })
+                    .map(|param| param.data.name.as_str())
+                    .collect();
+                (!unproduced.is_empty()).then_some((operation, unproduced))
+            })
+            .collect()
+    }
+
     /// Given the graph and a subset of nodes, returns a new graph containing only nodes and
     /// edges that exist in the given subset of nodes.
     pub fn subgraph(
@@ -379,6 +520,7 @@ fn find_links<'a>(
 fn inout_params<'a>(
     api: &'a OpenAPI,
     op: &QualifiedOperation<'a>,
+    synonyms: &Synonyms,
 ) -> (
     Vec<(ParameterNormalization<'a>, ParameterKind)>,
     Vec<ParameterNormalization<'a>>,
@@ -391,12 +533,12 @@ fn inout_params<'a>(
         .iter()
         .filter(|(status_code, _)| status_is_2xx(status_code))
         .filter_map(|(_, ref_or_response)| ref_or_response.resolve(api).ok())
-        .filter_map(|response| normalize_response(api, op.path, response))
+        .filter_map(|response| normalize_response(api, op.path, response, synonyms))
         .flatten() // Combine all 2XX responses, if multiple
         .collect();
 
     // Inputs to a request are all parameters. Collect those.
-    let mut input_fields = normalize_parameters(api, op.path, op.operation);
+    let mut input_fields = normalize_parameters(api, op.path, op.operation, synonyms);
 
     // For POST requests, also consider input parameters as output parameters!
     // (Choose your own name or ID and still be able to use it in later GET requests etc)
@@ -409,7 +551,7 @@ fn inout_params<'a>(
         .request_body
         .iter()
         .filter_map(|ref_or_body| ref_or_body.resolve(api).ok())
-        .find_map(|body| normalize_request_body(api, op.path, body))
+        .find_map(|body| normalize_request_body(api, op.path, body, synonyms))
         .unwrap_or_default();
     if op.method == Method::Post {
         output_fields.extend(body_fields);
@@ -424,6 +566,59 @@ fn inout_params<'a>(
     (input_fields, output_fields)
 }
 
+/// Values of the fields in the examples of successful responses, by the normalized name
+/// of the field
+#[derive(Debug, Clone, Default)]
+pub struct ResponseExampleValues {
+    values: IndexMap<String, Vec<serde_json::Value>>,
+    /// The synonyms the field names were normalized with
+    synonyms: Synonyms,
+}
+
+impl ResponseExampleValues {
+    /// The candidate values for a parameter of the operation at `path`, by its
+    /// normalized name
+    pub fn for_parameter(&self, path: &str, parameter: &Parameter) -> Option<&[serde_json::Value]> {
+        self.values
+            .get(&normalize_parameter(path, parameter, &self.synonyms).normalized)
+            .map(Vec::as_slice)
+    }
+}
+
+/// Harvests the values in the examples of all successful responses in the specification.
+/// Response examples often contain valid identifiers, which make good candidate values
+/// for request parameters with the same normalized name.
+pub fn response_example_values(api: &OpenAPI, synonyms: &Synonyms) -> ResponseExampleValues {
+    let mut values = IndexMap::<String, Vec<serde_json::Value>>::new();
+    for (path, _, operation, _) in api.operations() {
+        let examples = operation
+            .responses
+            .responses
+            .iter()
+            .filter(|(status_code, _)| status_is_2xx(status_code))
+            .filter_map(|(_, ref_or_response)| ref_or_response.resolve(api).ok())
+            .flat_map(|response| normalize_response_examples(api, path, response, synonyms));
+        for (name, value) in examples {
+            let candidates = values.entry(name).or_default();
+            if !candidates.contains(&value) {
+                candidates.push(value);
+            }
+        }
+    }
+    ResponseExampleValues {
+        values,
+        synonyms: synonyms.clone(),
+    }
+}
+
+/// Whether a parameter identifies a resource that some operation has to produce
+fn is_identifying(parameter: &Parameter) -> bool {
+    match parameter.kind {
+        openapiv3::ParameterKind::Path { .. } => true,
+        _ => parameter.data.required && parameter.data.name.to_lowercase().ends_with("id"),
+    }
+}
+
 /// Checks if a StatusCode is 2XX
 fn status_is_2xx(status_code: &StatusCode) -> bool {
     // The implementation is currently (1.0.1) that the Code variant is just the
@@ -435,3 +630,215 @@ fn status_is_2xx(status_code: &StatusCode) -> bool {
         StatusCode::Range(n) => *n == 2,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use openapiv3::OpenAPI;
+    use serde_json::json;
+
+    use super::{
+        crud_order::CrudOrder, initial_corpus_from_api, synonyms::Synonyms, DependencyGraph,
+    };
+    use crate::{
+        input::Method,
+        openapi::examples::{UserExamples, DEFAULT_MAX_CORPUS_COMBINATIONS},
+    };
+
+    #[test]
+    fn test_inputs_without_producer_are_reported() {
+        let id_parameter = |name: &str| json!({"name": name, "in": "path", "required": true, "schema": {"type": "integer"}});
+        let pet = json!({
+            "description": "A pet",
+            "content": {
+                "application/json": {
+                    "schema": {
+                        "type": "object",
+                        "properties": {"id": {"type": "integer"}, "name": {"type": "string"}}
+                    }
+                }
+            }
+        });
+        let api: OpenAPI = serde_json::from_value(json!({
+            "openapi": "3.0.0",
+            "info": {"title": "pets", "version": "1"},
+            "paths": {
+                "/pets": {
+                    "post": {"responses": {"201": pet}}
+                },
+                "/pets/{id}": {
+                    "get": {"parameters": [id_parameter("id")], "responses": {"200": pet}}
+                },
+                "/owners/{ownerId}/pets": {
+                    "get": {"parameters": [id_parameter("ownerId")], "responses": {"200": pet}}
+                }
+            }
+        }))
+        .unwrap();
+
+        let graph = DependencyGraph::new(&api);
+        let unproduced: Vec<_> = graph
+            .unproduced_inputs(&api)
+            .into_iter()
+            .map(|(operation, inputs)| (operation.method, operation.path, inputs))
+            .collect();
+        // The pet id is produced by POST /pets, but nothing produces owners
+        assert_eq!(
+            unproduced,
+            vec![(Method::Get, "/owners/{ownerId}/pets", vec!["ownerId"])]
+        );
+    }
+
+    #[test]
+    fn test_low_combination_cap_falls_back_to_single_example() {
+        let api: OpenAPI = serde_json::from_value(json!({
+            "openapi": "3.0.0",
+            "info": {"title": "pets", "version": "1"},
+            "paths": {
+                "/pets": {
+                    "post": {
+                        "parameters": [
+                            {"name": "X-Owner", "in": "header", "schema": {"type": "string"}}
+                        ],
+                        "requestBody": {
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "type": "object",
+                                        "properties": {
+                                            "name": {"type": "string"},
+                                            "age": {"type": "integer"}
+                                        }
+                                    }
+                                }
+                            }
+                        },
+                        "responses": {"201": {"description": "Created"}}
+                    }
+                }
+            }
+        }))
+        .unwrap();
+
+        let combinations = initial_corpus_from_api(
+            &api,
+            &UserExamples::default(),
+            &CrudOrder::default(),
+            &Synonyms::default(),
+            DEFAULT_MAX_CORPUS_COMBINATIONS,
+        );
+        assert!(combinations.len() > 1, "{combinations:?}");
+
+        // A single example of the operation
+        let capped = initial_corpus_from_api(
+            &api,
+            &UserExamples::default(),
+            &CrudOrder::default(),
+            &Synonyms::default(),
+            1,
+        );
+        assert_eq!(capped.len(), 1, "{capped:?}");
+        assert_eq!(capped[0].0.len(), 1);
+        assert_eq!(capped[0].0[0].method, Method::Post);
+    }
+    #[test]
+    fn test_synonyms_link_differently_named_parameters() {
+        let api: OpenAPI = serde_json::from_value(json!({
+            "openapi": "3.0.0",
+            "info": {"title": "shop", "version": "1"},
+            "paths": {
+                "/customers": {
+                    "post": {
+                        "responses": {
+                            "201": {
+                                "description": "The customer",
+                                "content": {
+                                    "application/json": {
+                                        "schema": {
+                                            "type": "object",
+                                            "properties": {"customerId": {"type": "integer"}}
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                },
+                "/patrons/{patronId}": {
+                    "get": {
+                        "parameters": [{
+                            "name": "patronId",
+                            "in": "path",
+                            "required": true,
+                            "schema": {"type": "integer"}
+                        }],
+                        "responses": {"200": {"description": "The patron"}}
+                    }
+                }
+            }
+        }))
+        .unwrap();
+        assert_eq!(DependencyGraph::new(&api).graph.edge_count(), 0);
+
+        let synonyms =
+            Synonyms::from_classes(&[vec!["customer".to_owned(), "patron".to_owned()]]).unwrap();
+        let edges: Vec<_> = DependencyGraph::with_options(&api, &CrudOrder::default(), &synonyms)
+            .graph
+            .edge_weights()
+            .map(|matching| (matching.name_output, matching.name_input))
+            .collect();
+
+        assert_eq!(edges, vec![("customerId", "patronId")]);
+    }
+    #[test]
+    fn test_two_node_cycle_keeps_both_operations() {
+        let pet = json!({
+            "description": "A pet",
+            "content": {
+                "application/json": {
+                    "schema": {
+                        "type": "object",
+                        "properties": {"id": {"type": "integer"}, "name": {"type": "string"}}
+                    }
+                }
+            }
+        });
+        let path_parameter = |name: &str, kind: &str| json!({"name": name, "in": "path", "required": true, "schema": {"type": kind}});
+        // Each operation returns the parameter that the other one takes
+        let api: OpenAPI = serde_json::from_value(json!({
+            "openapi": "3.0.0",
+            "info": {"title": "pets", "version": "1"},
+            "paths": {
+                "/pets/{id}": {
+                    "get": {"parameters": [path_parameter("id", "integer")], "responses": {"200": pet}}
+                },
+                "/pets/byname/{name}": {
+                    "get": {"parameters": [path_parameter("name", "string")], "responses": {"200": pet}}
+                }
+            }
+        }))
+        .unwrap();
+
+        let inputs = initial_corpus_from_api(
+            &api,
+            &UserExamples::default(),
+            &CrudOrder::default(),
+            &Synonyms::default(),
+            DEFAULT_MAX_CORPUS_COMBINATIONS,
+        );
+        assert!(!inputs.is_empty());
+        for input in &inputs {
+            let mut paths: Vec<&str> = input
+                .0
+                .iter()
+                .map(|request| request.path.as_str())
+                .collect();
+            paths.sort();
+            assert_eq!(paths, vec!["/pets/byname/{name}", "/pets/{id}"]);
+            // The link that is kept passes a value from the first request to the second
+            assert_eq!(input.reference_parameters().count(), 1);
+            assert!(input
+                .reference_parameters()
+                .all(|(target, _, _, source, _)| source == 0 && target == 1));
+        }
+    }
+}
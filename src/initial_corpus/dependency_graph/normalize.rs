@@ -9,13 +9,49 @@
 //! later refers to an 'artist_id', there is an opportunity to match it to the 'id' found
 //! earlier.
 
+use std::cell::Cell;
+
+use clap::ValueEnum;
 use openapiv3::{
     MediaType, ObjectType, OpenAPI, Operation, Parameter, RequestBody, Response, SchemaKind,
 };
-use porter_stemmer::stem;
+use serde::Deserialize;
+use serde_json::Value;
 
+use super::synonyms::Synonyms;
 use crate::{input::parameter::ParameterKind, openapi::JsonContent};
 
+thread_local! {
+    // Thread-local rather than global, so that tests can use different languages at once
+    static LANGUAGE: Cell<NormalizationLanguage> = const { Cell::new(NormalizationLanguage::English) };
+}
+
+/// The language of the words in parameter names, which determines how they are reduced
+/// to a base form
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum NormalizationLanguage {
+    /// Reduce words with the Porter stemmer, so that e.g. `widgets` becomes `widget`
+    #[default]
+    English,
+    /// Only make words lowercase, for names in other languages, which the Porter
+    /// stemmer would mangle
+    None,
+}
+
+/// Sets the language of the names normalized from now on, on the current thread
+pub fn set_normalization_language(language: NormalizationLanguage) {
+    LANGUAGE.set(language);
+}
+
+/// Reduces a word to its base form, according to the normalization language
+pub fn stem(word: &str) -> String {
+    match LANGUAGE.get() {
+        NormalizationLanguage::English => porter_stemmer::stem(word),
+        NormalizationLanguage::None => word.to_lowercase(),
+    }
+}
+
 /// A parameter name saved in two variants: the canonical name appearing in the spec,
 /// and the normalized form used for matching input and output parameters
 #[derive(Debug, Clone, PartialEq)]
@@ -32,10 +68,14 @@ impl<'a> ParameterNormalization<'a> {
     /// context just normalizes to color.
     /// Both the name and the context are 'stemmed', i.e. reduced to a base grammatical
     /// form, so the normalization is the same if a word is sometimes plural, or British
-    /// and American spellings are mixed.
-    pub fn new(name: &'a str, context: Option<&str>) -> Self {
+    /// and American spellings are mixed. Before that, words with `synonyms` are replaced
+    /// by the first word of their class.
+    pub fn new(name: &'a str, context: Option<&str>, synonyms: &Synonyms) -> Self {
+        let original_name = name;
+        let name = &synonyms.apply(name);
         match context {
             Some(context) => {
+                let context = &synonyms.apply(context);
                 // Catch the case where the context word is also included in the name,
                 // like `widget_id` for context `widgets`. Often, this same value is then
                 // called `id` in other context, such as when part of a `Widget` object
@@ -49,12 +89,12 @@ impl<'a> ParameterNormalization<'a> {
                 };
 
                 Self {
-                    name,
+                    name: original_name,
                     normalized: stem(context) + "|" + &stem(no_context_name),
                 }
             }
             None => Self {
-                name,
+                name: original_name,
                 normalized: stem(name),
             },
         }
@@ -67,6 +107,7 @@ pub fn normalize_parameters<'a>(
     api: &'a OpenAPI,
     path: &str,
     operation: &'a Operation,
+    synonyms: &Synonyms,
 ) -> Vec<(ParameterNormalization<'a>, ParameterKind)> {
     operation
         .parameters
@@ -74,7 +115,7 @@ pub fn normalize_parameters<'a>(
         // Keep only concrete values and valid references
         .filter_map(|ref_or_param| ref_or_param.resolve(api).ok())
         // Convert to (parameter_normalization, parameter_kind) tuples
-        .map(|param| (normalize_parameter(path, param), param.into()))
+        .map(|param| (normalize_parameter(path, param, synonyms), param.into()))
         .collect()
 }
 
@@ -82,14 +123,22 @@ pub fn normalize_parameters<'a>(
 ///
 /// A suitable context word is taken from the corresponding operation, and
 /// its stem is prepended to the stemmed parameter name.
-fn normalize_parameter<'a>(path: &str, parameter: &'a Parameter) -> ParameterNormalization<'a> {
+pub fn normalize_parameter<'a>(
+    path: &str,
+    parameter: &'a Parameter,
+    synonyms: &Synonyms,
+) -> ParameterNormalization<'a> {
     // extract a context word if possible
     match parameter.kind {
         // For a query parameter /resource?id=18, we want to extract
         // the 'resource' part as the context word, and return as the name
         // stem('resource') + "id"
         openapiv3::ParameterKind::Query { .. } => {
-            return ParameterNormalization::new(&parameter.data.name, path_context_component(path));
+            return ParameterNormalization::new(
+                &parameter.data.name,
+                path_context_component(path),
+                synonyms,
+            );
         }
         // For a path parameter /resource/{id}/..., we want to extract
         // the 'resource' part as the context word, and return as the name
@@ -102,6 +151,7 @@ fn normalize_parameter<'a>(path: &str, parameter: &'a Parameter) -> ParameterNor
                 return ParameterNormalization::new(
                     &parameter.data.name,
                     path_context_component(&path[..end]),
+                    synonyms,
                 );
             }
         }
@@ -111,7 +161,7 @@ fn normalize_parameter<'a>(path: &str, parameter: &'a Parameter) -> ParameterNor
     // If we reach this point, either the spec didn't contain the data we
     // expect based on the OpenAPI specification, or it's a parameter kind
     // we can't find context for. Just return the "id" string.
-    ParameterNormalization::new(&parameter.data.name, None)
+    ParameterNormalization::new(&parameter.data.name, None, synonyms)
 }
 
 /// Normalizes response parameters.
@@ -125,8 +175,54 @@ pub fn normalize_response<'a>(
     api: &'a OpenAPI,
     path: &str,
     response: &'a Response,
+    synonyms: &Synonyms,
 ) -> Option<Vec<ParameterNormalization<'a>>> {
-    normalize_media_type(api, path, response.content.get_json_content()?)
+    normalize_media_type(api, path, response.content.get_json_content()?, synonyms)
+}
+
+/// Normalizes the fields in the examples of a response, and returns them with their
+/// values.
+///
+/// Like in `normalize_response`, the fields are those of a top-level object, or of the
+/// objects in a top-level array. Examples are taken from the application/json content
+/// and its schema. Fields whose value is an object, an array or null are skipped, as
+/// such values are not useful as a parameter.
+pub fn normalize_response_examples(
+    api: &OpenAPI,
+    path: &str,
+    response: &Response,
+    synonyms: &Synonyms,
+) -> Vec<(String, Value)> {
+    let Some(media_type) = response.content.get_json_content() else {
+        return vec![];
+    };
+    let schema_example = media_type
+        .schema
+        .as_ref()
+        .and_then(|schema| schema.resolve(api).data.example.as_ref());
+    let examples = media_type.example.iter().chain(schema_example).chain(
+        media_type
+            .examples
+            .values()
+            .filter_map(|example| example.as_item()?.value.as_ref()),
+    );
+
+    let context = path_context_component(path);
+    examples
+        .flat_map(|example| match example {
+            Value::Array(elements) => elements.iter().collect(),
+            other => vec![other],
+        })
+        .filter_map(Value::as_object)
+        .flatten()
+        .filter(|(_, value)| !(value.is_object() || value.is_array() || value.is_null()))
+        .map(|(name, value)| {
+            (
+                ParameterNormalization::new(name, context, synonyms).normalized,
+                value.clone(),
+            )
+        })
+        .collect()
 }
 
 /// Normalizes request body parameters.
@@ -140,8 +236,9 @@ pub fn normalize_request_body<'a>(
     api: &'a OpenAPI,
     path: &str,
     body: &'a RequestBody,
+    synonyms: &Synonyms,
 ) -> Option<Vec<ParameterNormalization<'a>>> {
-    normalize_media_type(api, path, body.content.get_json_content()?)
+    normalize_media_type(api, path, body.content.get_json_content()?, synonyms)
 }
 
 /// MediaType is the internal type used for objects, both input (POST) and
@@ -150,15 +247,18 @@ fn normalize_media_type<'a>(
     api: &'a OpenAPI,
     path: &str,
     media_type: &'a MediaType,
+    synonyms: &Synonyms,
 ) -> Option<Vec<ParameterNormalization<'a>>> {
     let schema = media_type.schema.as_ref()?.resolve(api);
     match schema.kind {
-        SchemaKind::Type(openapiv3::Type::Object(ref o)) => Some(normalize_object_type(path, o)),
+        SchemaKind::Type(openapiv3::Type::Object(ref o)) => {
+            Some(normalize_object_type(path, o, synonyms))
+        }
         SchemaKind::Type(openapiv3::Type::Array(ref a)) => {
             let inner_schema = a.items.as_ref()?.resolve(api);
             match inner_schema.kind {
                 SchemaKind::Type(openapiv3::Type::Object(ref o)) => {
-                    Some(normalize_object_type(path, o))
+                    Some(normalize_object_type(path, o, synonyms))
                 }
                 // No support for nested arrays - semantic meaning not obvious
                 _ => None,
@@ -171,11 +271,12 @@ fn normalize_media_type<'a>(
 fn normalize_object_type<'a>(
     path: &str,
     object_type: &'a ObjectType,
+    synonyms: &Synonyms,
 ) -> Vec<ParameterNormalization<'a>> {
     object_type
         .properties
         .keys()
-        .map(|key| ParameterNormalization::new(key, path_context_component(path)))
+        .map(|key| ParameterNormalization::new(key, path_context_component(path), synonyms))
         .collect()
 }
 
@@ -202,52 +303,64 @@ mod tests {
                 name: "widget",
                 normalized: "widget".into(),
             },
-            ParameterNormalization::new("widget", None)
+            ParameterNormalization::new("widget", None, &Synonyms::default())
         );
         assert_eq!(
             ParameterNormalization {
                 name: "widgets",
                 normalized: "widget".into(),
             },
-            ParameterNormalization::new("widgets", None)
+            ParameterNormalization::new("widgets", None, &Synonyms::default())
         );
         assert_eq!(
             ParameterNormalization {
                 name: "widget",
                 normalized: "aircraft|widget".into(),
             },
-            ParameterNormalization::new("widget", Some("aircraft"))
+            ParameterNormalization::new("widget", Some("aircraft"), &Synonyms::default())
         );
         assert_eq!(
             ParameterNormalization {
                 name: "widget",
                 normalized: "aircraft|widget".into(),
             },
-            ParameterNormalization::new("widget", Some("aircrafts"))
+            ParameterNormalization::new("widget", Some("aircrafts"), &Synonyms::default())
         );
         assert_eq!(
             ParameterNormalization {
                 name: "country_id",
                 normalized: "countri|id".into(),
             },
-            ParameterNormalization::new("country_id", Some("countries"))
+            ParameterNormalization::new("country_id", Some("countries"), &Synonyms::default())
         );
         assert_eq!(
             ParameterNormalization {
                 name: "id",
                 normalized: "countri|id".into(),
             },
-            ParameterNormalization::new("id", Some("countries"))
+            ParameterNormalization::new("id", Some("countries"), &Synonyms::default())
         );
         assert_eq!(
             ParameterNormalization {
                 name: "widget_id",
                 normalized: "countri|widget_id".into(),
             },
-            ParameterNormalization::new("widget_id", Some("countries"))
+            ParameterNormalization::new("widget_id", Some("countries"), &Synonyms::default())
         );
     }
 
+    #[test]
+    fn test_normalization_language() {
+        let normalized =
+            |name| ParameterNormalization::new(name, None, &Synonyms::default()).normalized;
+        assert_eq!(normalized("widgets"), normalized("widget"));
+
+        set_normalization_language(NormalizationLanguage::None);
+        assert_ne!(normalized("widgets"), normalized("widget"));
+        assert_eq!(normalized("Fahrzeug"), "fahrzeug");
+        set_normalization_language(NormalizationLanguage::English);
+    }
+
     #[test]
     fn test_path_last_component() {
         assert_eq!(Some("aaa"), path_context_component("/aaa/bbb"));
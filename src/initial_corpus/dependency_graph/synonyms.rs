@@ -0,0 +1,107 @@
+//! Parameters with the same meaning but unrelated names, like `ownerId` and `userId`,
+//! normalize to different names, so the dependency graph does not link them. Synonyms
+//! let the user declare such words equivalent. They are read from a YAML file with a
+//! list of equivalence classes, e.g.
+//!
+//! ```yaml
+//! - [owner, user, account]
+//! - [pet, animal]
+//! ```
+//!
+//! Before a name is normalized, every word in it that is in a class is replaced by the
+//! first word of the class, so `userId` and `accountId` both become `ownerId`. Words
+//! are separated by `_`, `-` and changes from lower to upper case, and they match the
+//! words of a class regardless of case and grammatical form.
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+
+use super::normalize::stem;
+
+/// Maps the stem of each word of an equivalence class to the first word of the class
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(try_from = "PathBuf")]
+pub struct Synonyms(HashMap<String, String>);
+
+impl Synonyms {
+    /// Reads the equivalence classes from a YAML file, see the module documentation
+    /// for the format
+    pub fn from_file(path: &Path) -> Result<Self, anyhow::Error> {
+        let file = File::open(path)
+            .with_context(|| format!("Could not open synonyms {}", path.to_string_lossy()))?;
+        let classes: Vec<Vec<String>> = serde_yaml::from_reader(file).with_context(|| {
+            format!(
+                "Synonyms {} must be a list of lists of words",
+                path.to_string_lossy()
+            )
+        })?;
+        Self::from_classes(&classes)
+    }
+
+    pub(crate) fn from_classes(classes: &[Vec<String>]) -> Result<Self, anyhow::Error> {
+        let mut synonyms = HashMap::new();
+        for class in classes {
+            let Some(first) = class.first() else {
+                continue;
+            };
+            for word in class {
+                if let Some(other) =
+                    synonyms.insert(stem(&word.to_lowercase()), first.to_lowercase())
+                {
+                    if other != first.to_lowercase() {
+                        bail!("Synonym '{word}' is in the classes of both '{other}' and '{first}'");
+                    }
+                }
+            }
+        }
+        Ok(Self(synonyms))
+    }
+
+    /// Replaces every word of `name` that has synonyms by the first word of its class.
+    pub fn apply(&self, name: &str) -> String {
+        if self.0.is_empty() {
+            return name.to_owned();
+        }
+        let mut replaced = String::with_capacity(name.len());
+        let mut word = String::new();
+        let mut previous_lowercase = false;
+        for c in name.chars() {
+            let separator = c == '_' || c == '-';
+            if separator || (previous_lowercase && c.is_uppercase()) {
+                replaced.push_str(self.replace(&word));
+                word.clear();
+            }
+            if separator {
+                replaced.push(c);
+            } else {
+                word.push(c);
+            }
+            previous_lowercase = c.is_lowercase();
+        }
+        replaced.push_str(self.replace(&word));
+        replaced
+    }
+
+    /// The word that replaces `word`, which is `word` itself if it has no synonyms
+    fn replace<'a>(&'a self, word: &'a str) -> &'a str {
+        if word.is_empty() {
+            return word;
+        }
+        self.0
+            .get(&stem(&word.to_lowercase()))
+            .map_or(word, String::as_str)
+    }
+}
+
+impl TryFrom<PathBuf> for Synonyms {
+    type Error = anyhow::Error;
+
+    fn try_from(path: PathBuf) -> Result<Self, Self::Error> {
+        Self::from_file(&path)
+    }
+}
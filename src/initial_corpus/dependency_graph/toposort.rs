@@ -12,7 +12,8 @@ use super::DepGraph;
 ///
 /// If the graph was acyclic, return a vector of nodes in topological order:
 /// each node is ordered before its successors.
-/// Otherwise, it will return a `Cycle` error. Self loops are also cycles.
+/// Otherwise, it will return a `Cycle` error with an edge that closes a cycle. Self
+/// loops are also cycles.
 ///
 /// To handle graphs with cycles, use the scc algorithms or `DfsPostOrder`
 /// instead of this function.
@@ -43,9 +44,10 @@ pub fn toposort<'a>(
                     let mut neighbors = graph.neighbors(nx).collect::<Vec<_>>();
                     neighbors.sort_by(|a, b| graph[*a].method.cmp(&graph[*b].method));
                     for succ in neighbors.iter() {
-                        if succ == &nx {
-                            // self cycle
-                            return Err(Cycle(nx));
+                        // WuppieFuzz addition: a successor that is discovered but not
+                        // finished is on the current path, so the edge closes a cycle
+                        if dfs.discovered.is_visited(succ) && !finished.is_visited(succ) {
+                            return Err(Cycle(nx, *succ));
                         }
                         if !dfs.discovered.is_visited(succ) {
                             dfs.stack.push(*succ);
@@ -68,7 +70,7 @@ pub fn toposort<'a>(
 
 /// An algorithm error: a cycle was found in the graph.
 #[derive(Clone, Debug, PartialEq, Eq)]
-pub struct Cycle<N>(N);
+pub struct Cycle<N>(N, N);
 
 impl<N> Cycle<N> {
     /// Return a node id that participates in the cycle
@@ -76,7 +78,15 @@ impl<N> Cycle<N> {
     where
         N: Copy,
     {
-        self.0
+        self.1
+    }
+
+    /// Return the source and target of an edge that closes the cycle
+    pub fn edge(&self) -> (N, N)
+    where
+        N: Copy,
+    {
+        (self.0, self.1)
     }
 }
 
@@ -1,6 +1,7 @@
 //! Helper functions for loading a corpus from disk. See module documentation of
 //! the `input` submodule for information on serialization.
 pub mod dependency_graph;
+pub mod pagination;
 
 use std::{
     collections::hash_map::DefaultHasher,
@@ -17,25 +18,61 @@ use libafl::{
 };
 use openapiv3::OpenAPI;
 
-use self::dependency_graph::DependencyGraph;
+use self::{
+    dependency_graph::{crud_order::CrudOrder, synonyms::Synonyms, DependencyGraph},
+    pagination::PaginationRule,
+};
 use crate::{
+    configuration::Configuration,
     initial_corpus::dependency_graph::initial_corpus_from_api,
     input::{OpenApiInput, OpenApiRequest},
+    openapi::{
+        examples::{UserExamples, DEFAULT_MAX_CORPUS_COMBINATIONS},
+        glob::glob_matches,
+    },
 };
 
-/// Loads an `OpenApiInput` from a yaml file.
+/// Loads the starting corpus from `corpus_path`: every file in it if it is a directory,
+/// or the single input in it if it is a file.
 pub fn load_starting_corpus(
-    corpus_dir: &Path,
+    corpus_path: &Path,
 ) -> Result<Vec<OpenApiInput>, Box<dyn std::error::Error>> {
-    let mut corpus_vec = vec![];
-    for file in fs::read_dir(corpus_dir)? {
-        let file = file?.path();
-        match serde_yaml::from_reader(std::fs::File::open(file)?) {
-            Ok(input) => corpus_vec.push(input),
-            Err(err) => return Err(err.into()),
-        }
+    Ok(load_named_inputs(corpus_path)?
+        .into_iter()
+        .map(|(_, input)| input)
+        .collect())
+}
+
+/// Like `load_starting_corpus`, but also returns the name of the file of each input.
+/// The inputs of a directory are sorted by file name.
+pub fn load_named_inputs(
+    corpus_path: &Path,
+) -> Result<Vec<(String, OpenApiInput)>, Box<dyn std::error::Error>> {
+    let file_name = |path: &Path| {
+        path.file_name()
+            .unwrap_or(path.as_os_str())
+            .to_string_lossy()
+            .into_owned()
+    };
+    if corpus_path.is_file() {
+        return Ok(vec![(
+            file_name(corpus_path),
+            load_input_from_file(corpus_path)?,
+        )]);
     }
-    Ok(corpus_vec)
+    let mut files = fs::read_dir(corpus_path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    files.sort();
+    files
+        .iter()
+        .map(|file| Ok((file_name(file), load_input_from_file(file)?)))
+        .collect()
+}
+
+/// Loads a single `OpenApiInput` from a yaml file, such as a saved crash.
+pub fn load_input_from_file(path: &Path) -> Result<OpenApiInput, Box<dyn std::error::Error>> {
+    Ok(serde_yaml::from_reader(File::open(path)?)?)
 }
 
 /// Generates a corpus and writes it to the path specified at `corpus_dir`.
@@ -44,7 +81,13 @@ pub fn load_starting_corpus(
 /// as the initial corpus) used to generate the initial corpus is then written
 /// to the `report_path`.
 pub fn generate_corpus_to_files(api: &OpenAPI, corpus_dir: &Path, report_path: Option<&Path>) {
-    let inputs = initial_corpus_from_api(api);
+    let inputs = initial_corpus_from_api(
+        api,
+        &UserExamples::default(),
+        &CrudOrder::default(),
+        &Synonyms::default(),
+        DEFAULT_MAX_CORPUS_COMBINATIONS,
+    );
     log::debug!("Writing corpus to file...");
     if let Err(e) = write_corpus_to_files(&inputs, corpus_dir) {
         log::warn!("Error writing corpus to file: {}", e);
@@ -87,23 +130,56 @@ pub fn print_starting_corpus(filename: &Path) {
 
 pub fn initialize_corpus(
     api: &OpenAPI,
-    initial_corpus_path: Option<&Path>,
+    user_examples: &UserExamples,
+    config: &Configuration,
     report_path: &Option<&Path>,
 ) -> InMemoryOnDiskCorpus<OpenApiInput> {
     let mut corpus = InMemoryOnDiskCorpus::new(PathBuf::from("./queue")).unwrap();
-    match initial_corpus_path {
+    match config.initial_corpus.as_deref() {
         Some(initial_corpus_path) => {
             log::info!("Filling corpus from file: {initial_corpus_path:?}");
             fill_corpus_from_file(&mut corpus, initial_corpus_path)
         }
         None => {
             log::info!("No corpus supplied, generating one based on the API");
-            fill_corpus_from_api(&mut corpus, api, report_path)
+            fill_corpus_from_api(&mut corpus, api, user_examples, config, report_path)
         }
     }
     corpus
 }
 
+/// Depth given to the corpus entries with a request to a focused path. The power
+/// schedule gives deeper entries more energy, five times as much from this depth on,
+/// and entries derived from a focused entry are deeper still.
+const FOCUS_DEPTH: u64 = 25;
+
+/// Makes the power schedule focus on the corpus entries with a request to a path that
+/// matches one of the `focus_paths` globs. The other entries are left as they are.
+pub fn focus_on_paths<C: Corpus<Input = OpenApiInput>>(corpus: &mut C, focus_paths: &[String]) {
+    if focus_paths.is_empty() {
+        return;
+    }
+    let mut focused = 0;
+    for id in corpus.ids() {
+        let Ok(testcase) = corpus.get(id) else {
+            continue;
+        };
+        let mut testcase = testcase.borrow_mut();
+        let is_focused = testcase.input().as_ref().is_some_and(|input| {
+            input.0.iter().any(|request| {
+                focus_paths
+                    .iter()
+                    .any(|glob| glob_matches(glob.as_bytes(), request.path.as_bytes()))
+            })
+        });
+        if is_focused {
+            testcase.add_metadata(SchedulerTestcaseMetadata::new(FOCUS_DEPTH));
+            focused += 1;
+        }
+    }
+    log::info!("Focusing on {focused} corpus entries with requests to the focus paths");
+}
+
 fn write_corpus_report(input_vector: &[OpenApiInput], report_path: &Path) -> std::io::Result<()> {
     let corpus_path = report_path.join("corpus");
     create_dir_all(&corpus_path)?;
@@ -179,16 +255,58 @@ fn fill_corpus_from_file(
     };
 }
 
+/// Generates the inputs of the initial corpus from the API, with array bodies wrapped
+/// and paging follow-ups added as configured.
+pub fn inputs_from_api(
+    api: &OpenAPI,
+    user_examples: &UserExamples,
+    pagination: Option<&PaginationRule>,
+    wrap_array_key: Option<&str>,
+    crud_order: &CrudOrder,
+    synonyms: &Synonyms,
+    max_combinations: usize,
+) -> Vec<OpenApiInput> {
+    let mut inputs =
+        initial_corpus_from_api(api, user_examples, crud_order, synonyms, max_combinations);
+    if let Some(key) = wrap_array_key {
+        for request in inputs.iter_mut().flat_map(|input| input.0.iter_mut()) {
+            request.body.wrap_json_array(key);
+        }
+    }
+    if let Some(pagination) = pagination {
+        // Add paging variants of the inputs, keeping the originals as well
+        let paged: Vec<_> = inputs
+            .iter()
+            .cloned()
+            .filter_map(|mut input| pagination.add_follow_ups(api, &mut input).then_some(input))
+            .collect();
+        log::info!("Added {} inputs following pagination cursors", paged.len());
+        inputs.extend(paged);
+    }
+    inputs
+}
+
 fn fill_corpus_from_api(
     corpus: &mut InMemoryOnDiskCorpus<OpenApiInput>,
     api: &OpenAPI,
+    user_examples: &UserExamples,
+    config: &Configuration,
     report_path: &Option<&Path>,
 ) {
-    let inputs = initial_corpus_from_api(api);
+    let inputs = inputs_from_api(
+        api,
+        user_examples,
+        config.pagination.as_ref(),
+        config.wrap_array_key.as_deref(),
+        &config.crud_order,
+        &config.synonyms,
+        config.max_corpus_combinations,
+    );
     if let Some(report_path) = report_path {
         // The dependency graph was already generated while creating it from the API
         // but it is cheap to build, so we can afford to do it again for reporting.
-        let dependency_graph = DependencyGraph::new(api);
+        let dependency_graph =
+            DependencyGraph::with_options(api, &config.crud_order, &config.synonyms);
         let _ = dependency_graph.write_report(report_path);
         let _ = write_corpus_report(&inputs, report_path);
     }
@@ -198,3 +316,207 @@ fn fill_corpus_from_api(
         let _ = corpus.add(testcase);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use indexmap::IndexMap;
+    use libafl::{
+        corpus::{Corpus, InMemoryCorpus, SchedulerTestcaseMetadata, Testcase},
+        HasMetadata,
+    };
+
+    use super::{
+        dependency_graph::{crud_order::CrudOrder, synonyms::Synonyms},
+        focus_on_paths, inputs_from_api, load_input_from_file, load_starting_corpus,
+        write_corpus_to_files,
+    };
+    use crate::{
+        input::{Body, Method, OpenApiInput, OpenApiRequest},
+        openapi::{
+            examples::{seed_examples, UserExamples},
+            path_filter::PathFilter,
+        },
+    };
+
+    fn input(path: &str) -> OpenApiInput {
+        OpenApiInput(vec![OpenApiRequest {
+            method: Method::Get,
+            path: path.to_owned(),
+            body: Body::Empty,
+            parameters: IndexMap::new(),
+        }])
+    }
+
+    fn paths(inputs: &[OpenApiInput]) -> Vec<&str> {
+        inputs
+            .iter()
+            .flat_map(|input| input.0.iter().map(|request| request.path.as_str()))
+            .collect()
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("wuppiefuzz-{name}-{}", std::process::id()))
+    }
+
+    #[test]
+    fn test_load_single_file() {
+        let path = temp_path("single-input.yaml");
+        fs::write(&path, serde_yaml::to_string(&input("/pets")).unwrap()).unwrap();
+
+        let loaded = load_input_from_file(&path).unwrap();
+        let corpus = load_starting_corpus(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(paths(&[loaded]), vec!["/pets"]);
+        assert_eq!(paths(&corpus), vec!["/pets"]);
+    }
+
+    #[test]
+    fn test_load_directory() {
+        let dir = temp_path("corpus-dir");
+        let inputs = vec![input("/pets"), input("/owners")];
+        write_corpus_to_files(&inputs, &dir).unwrap();
+
+        let corpus = load_starting_corpus(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let mut loaded_paths = paths(&corpus);
+        loaded_paths.sort();
+        assert_eq!(loaded_paths, vec!["/owners", "/pets"]);
+    }
+
+    #[test]
+    fn test_focused_inputs_get_higher_weight() {
+        let mut chain = input("/owners");
+        chain.0.extend(input("/pets/{petId}").0);
+        let inputs = [
+            input("/owners"),
+            input("/pets/{petId}"),
+            // A single request to a focused path suffices
+            chain,
+            // A * does not match across segments
+            input("/pets/{petId}/toys"),
+        ];
+        let mut corpus = InMemoryCorpus::new();
+        for input in inputs {
+            let mut testcase = Testcase::new(input);
+            testcase.add_metadata(SchedulerTestcaseMetadata::new(0));
+            corpus.add(testcase).unwrap();
+        }
+
+        focus_on_paths(&mut corpus, &["/pets/*".to_owned()]);
+
+        let weights: Vec<u64> = corpus
+            .ids()
+            .map(|id| {
+                let testcase = corpus.get(id).unwrap().borrow();
+                testcase
+                    .metadata::<SchedulerTestcaseMetadata>()
+                    .unwrap()
+                    .depth()
+            })
+            .collect();
+        let unfocused = weights[0];
+        assert!(weights[1] > unfocused);
+        assert!(weights[2] > unfocused);
+        assert_eq!(weights[3], unfocused);
+    }
+    #[test]
+    fn test_include_path_limits_generated_corpus() {
+        let api: openapiv3::OpenAPI = serde_json::from_value(serde_json::json!({
+            "openapi": "3.0.0",
+            "info": {"title": "pets", "version": "1"},
+            "paths": {
+                "/pets": {"get": {"responses": {"200": {"description": "The pets"}}}},
+                "/pets/{petId}": {
+                    "get": {
+                        "parameters": [{
+                            "name": "petId",
+                            "in": "path",
+                            "required": true,
+                            "schema": {"type": "integer"}
+                        }],
+                        "responses": {"200": {"description": "The pet"}}
+                    }
+                },
+                "/owners": {"get": {"responses": {"200": {"description": "The owners"}}}},
+                "/admin/reset": {"post": {"responses": {"204": {"description": "Reset"}}}}
+            }
+        }))
+        .unwrap();
+        // Include globs win from the exclude glob that matches everything
+        let filter = PathFilter::new(
+            vec!["/pets".to_owned(), "/pets/*".to_owned()],
+            vec!["/**".to_owned()],
+        );
+
+        let inputs = inputs_from_api(
+            &filter.filter_api(&api),
+            &UserExamples::default(),
+            None,
+            None,
+            &CrudOrder::default(),
+            &Synonyms::default(),
+            10,
+        );
+
+        let mut generated_paths = paths(&inputs);
+        generated_paths.sort();
+        generated_paths.dedup();
+        assert_eq!(generated_paths, vec!["/pets", "/pets/{petId}"]);
+    }
+
+    #[test]
+    fn test_same_seed_generates_same_corpus() {
+        let api: openapiv3::OpenAPI = serde_json::from_value(serde_json::json!({
+            "openapi": "3.0.0",
+            "info": {"title": "pets", "version": "1"},
+            "paths": {
+                "/pets": {
+                    "post": {
+                        "requestBody": {
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "type": "object",
+                                        "properties": {
+                                            "name": {"type": "string", "pattern": "^[a-z]{20}$"}
+                                        }
+                                    }
+                                }
+                            }
+                        },
+                        "responses": {"201": {"description": "Created"}}
+                    }
+                },
+                "/pets/photo": {
+                    "put": {
+                        "requestBody": {
+                            "content": {"application/octet-stream": {}}
+                        },
+                        "responses": {"204": {"description": "Uploaded"}}
+                    }
+                }
+            }
+        }))
+        .unwrap();
+        let corpus = |seed| {
+            seed_examples(seed);
+            serde_yaml::to_string(&inputs_from_api(
+                &api,
+                &UserExamples::default(),
+                None,
+                None,
+                &CrudOrder::default(),
+                &Synonyms::default(),
+                10,
+            ))
+            .unwrap()
+        };
+
+        assert_eq!(corpus(42), corpus(42));
+        assert_ne!(corpus(42), corpus(43));
+    }
+}
@@ -0,0 +1,198 @@
+//! List endpoints often return a cursor or token pointing to the next page of results,
+//! which the client passes back in a follow-up request. A `PaginationRule` describes
+//! where the cursor is found in the response and where it goes in the follow-up, so
+//! that the initial corpus can contain request series that page through a list.
+
+use std::{fmt::Display, str::FromStr};
+
+use openapiv3::OpenAPI;
+
+use crate::{
+    input::{parameter::ParameterKind, OpenApiInput, OpenApiRequest, ParameterContents},
+    openapi::find_operation,
+};
+
+/// Number of follow-up pages requested after each paginated request.
+const FOLLOW_UP_PAGES: usize = 2;
+
+/// Rule for following pagination cursors, written as
+/// `next:body:<response field> into:<query|header|path|cookie>:<parameter name>`,
+/// e.g. `next:body:nextCursor into:query:cursor`.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+#[serde(try_from = "String")]
+pub struct PaginationRule {
+    /// Name of the field in the response body that contains the cursor
+    pub next_field: String,
+    /// Name of the parameter the cursor is sent in
+    pub into_name: String,
+    /// Kind of the parameter the cursor is sent in
+    pub into_kind: ParameterKind,
+}
+
+impl FromStr for PaginationRule {
+    type Err = anyhow::Error;
+
+    fn from_str(rule: &str) -> Result<Self, Self::Err> {
+        let (next, into) = match rule.split_whitespace().collect::<Vec<_>>()[..] {
+            [next, into] => (next, into),
+            _ => bail!("Pagination rule must have the form 'next:body:<field> into:<kind>:<name>', got '{rule}'"),
+        };
+        let next_field = match next.splitn(3, ':').collect::<Vec<_>>()[..] {
+            ["next", "body", field] if !field.is_empty() => field.to_owned(),
+            _ => bail!("Pagination cursor must be given as 'next:body:<field>', got '{next}'"),
+        };
+        let (into_kind, into_name) = match into.splitn(3, ':').collect::<Vec<_>>()[..] {
+            ["into", kind, name] if !name.is_empty() => {
+                let kind = match kind {
+                    "query" => ParameterKind::Query,
+                    "header" => ParameterKind::Header,
+                    "path" => ParameterKind::Path,
+                    "cookie" => ParameterKind::Cookie,
+                    _ => bail!("Unknown parameter kind '{kind}' in pagination rule"),
+                };
+                (kind, name.to_owned())
+            }
+            _ => bail!("Pagination parameter must be given as 'into:<kind>:<name>', got '{into}'"),
+        };
+        Ok(Self {
+            next_field,
+            into_name,
+            into_kind,
+        })
+    }
+}
+
+impl TryFrom<String> for PaginationRule {
+    type Error = anyhow::Error;
+
+    fn try_from(rule: String) -> Result<Self, Self::Error> {
+        rule.parse()
+    }
+}
+
+impl Display for PaginationRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = match self.into_kind {
+            ParameterKind::Query => "query",
+            ParameterKind::Header => "header",
+            ParameterKind::Path => "path",
+            ParameterKind::Cookie => "cookie",
+            ParameterKind::Body => "body",
+        };
+        write!(
+            f,
+            "next:body:{} into:{kind}:{}",
+            self.next_field, self.into_name
+        )
+    }
+}
+
+impl PaginationRule {
+    /// Whether the specification declares the cursor parameter for this request's operation
+    fn is_paginated(&self, api: &OpenAPI, request: &OpenApiRequest) -> bool {
+        find_operation(api, &request.path, request.method).is_some_and(|operation| {
+            operation
+                .parameters
+                .iter()
+                .filter_map(|ref_or_parameter| ref_or_parameter.resolve(api).ok())
+                .any(|parameter| {
+                    parameter.data.name == self.into_name && self.into_kind.matches(parameter)
+                })
+        })
+    }
+
+    /// Appends follow-up requests to the input for the first paginated request in it.
+    /// Each follow-up repeats the previous page's request, with the cursor parameter
+    /// referring to the cursor field in the previous page's response.
+    /// Returns whether any follow-ups were added.
+    pub fn add_follow_ups(&self, api: &OpenAPI, input: &mut OpenApiInput) -> bool {
+        let Some(mut page_index) = input
+            .0
+            .iter()
+            .position(|request| self.is_paginated(api, request))
+        else {
+            return false;
+        };
+        for _ in 0..FOLLOW_UP_PAGES {
+            let mut follow_up = input.0[page_index].clone();
+            follow_up.parameters.insert(
+                (self.into_name.clone(), self.into_kind),
+                ParameterContents::Reference {
+                    request_index: page_index,
+                    parameter_name: self.next_field.clone(),
+                },
+            );
+            input.0.push(follow_up);
+            page_index = input.0.len() - 1;
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+    use openapiv3::OpenAPI;
+    use serde_json::json;
+
+    use super::PaginationRule;
+    use crate::{
+        input::{parameter::ParameterKind, Body, Method, OpenApiInput, OpenApiRequest},
+        parameter_feedback::ParameterFeedback,
+    };
+
+    fn api() -> OpenAPI {
+        serde_json::from_value(json!({
+            "openapi": "3.0.0",
+            "info": {"title": "pets", "version": "1"},
+            "paths": {
+                "/pets": {
+                    "get": {
+                        "parameters": [
+                            {"name": "cursor", "in": "query", "schema": {"type": "string"}}
+                        ],
+                        "responses": {}
+                    }
+                }
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parse_rule() {
+        let rule: PaginationRule = "next:body:nextCursor into:query:cursor".parse().unwrap();
+        assert_eq!(rule.next_field, "nextCursor");
+        assert_eq!(rule.into_name, "cursor");
+        assert_eq!(rule.into_kind, ParameterKind::Query);
+        assert_eq!(rule.to_string(), "next:body:nextCursor into:query:cursor");
+
+        assert!("next:body:nextCursor".parse::<PaginationRule>().is_err());
+        assert!("next:header:x into:query:cursor"
+            .parse::<PaginationRule>()
+            .is_err());
+    }
+
+    #[test]
+    fn test_cursor_is_installed_in_follow_up() {
+        let rule: PaginationRule = "next:body:nextCursor into:query:cursor".parse().unwrap();
+        let mut input = OpenApiInput(vec![OpenApiRequest {
+            method: Method::Get,
+            path: "/pets".to_owned(),
+            body: Body::Empty,
+            parameters: IndexMap::new(),
+        }]);
+        assert!(rule.add_follow_ups(&api(), &mut input));
+        assert_eq!(input.0.len(), 3);
+
+        // The first page returned a cursor, which the second page should send
+        let mut parameter_feedback = ParameterFeedback::new(input.0.len());
+        parameter_feedback.set(0, "nextCursor".to_owned(), json!("page-2"));
+        let mut follow_up = input.0[1].clone();
+        follow_up
+            .resolve_parameter_references(&parameter_feedback)
+            .unwrap();
+        let cursor = &follow_up.parameters[&("cursor".to_owned(), ParameterKind::Query)];
+        assert_eq!(cursor.to_value(), json!("page-2"));
+    }
+}
@@ -0,0 +1,98 @@
+//! Serialization of `application/x-www-form-urlencoded` request bodies.
+//!
+//! Each field of an object body becomes a `name=value` pair. Fields of nested objects
+//! become pairs of their own, and arrays become one pair per element, named according
+//! to the configured `FormArrayStyle`, because frameworks disagree on how arrays are
+//! written in forms.
+
+use clap::ValueEnum;
+use serde::Deserialize;
+
+use super::{parameter::SimpleValue, ParameterContents};
+
+/// How the elements of an array field are named in a form body
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FormArrayStyle {
+    /// `key=a&key=b`
+    #[default]
+    Repeat,
+    /// `key[]=a&key[]=b`, as expected by e.g. PHP and Rails
+    Brackets,
+    /// `key[0]=a&key[1]=b`
+    Indexed,
+}
+
+/// Serializes the body contents to a form body, writing arrays in `array_style`.
+pub fn encode(body: &ParameterContents, array_style: FormArrayStyle) -> String {
+    let mut encoded = url::form_urlencoded::Serializer::new(String::new());
+    match body {
+        ParameterContents::Object(obj_contents) => {
+            for (name, value) in obj_contents {
+                match value {
+                    // String must be handled separately, otherwise it gets surrounded by quotes.
+                    ParameterContents::LeafValue(SimpleValue::String(inner_str)) => {
+                        encoded.append_pair(name, inner_str)
+                    }
+                    ParameterContents::LeafValue(first_level_concrete) => {
+                        encoded.append_pair(name, first_level_concrete.to_string().as_str())
+                    }
+                    ParameterContents::Array(inner_array) => encoded.extend_pairs(
+                        inner_array.iter().enumerate().map(|(index, element)| {
+                            let key = match array_style {
+                                FormArrayStyle::Repeat => name.clone(),
+                                FormArrayStyle::Brackets => format!("{name}[]"),
+                                FormArrayStyle::Indexed => format!("{name}[{index}]"),
+                            };
+                            (key, element.to_string())
+                        }),
+                    ),
+                    ParameterContents::Object(inner_map) => encoded.extend_pairs(
+                        inner_map
+                            .iter()
+                            .map(|(field, value)| (field, value.to_string())),
+                    ),
+                    _ => &mut encoded,
+                };
+            }
+        }
+        ParameterContents::Reference { .. } => {
+            // References should have been resolved before the body is built
+            log::warn!("Unresolved reference as form body, sending an empty body instead: {body}");
+        }
+        ParameterContents::Bytes(val) => {
+            // Raw bytes are sent as a single value without a name
+            return url::form_urlencoded::byte_serialize(val).collect();
+        }
+        ParameterContents::Array(_) | ParameterContents::LeafValue(_) => {
+            panic!("Form bodies must not be of type array or leaf, but interpretable as key-value objects.\nOffending body: {}", body);
+        }
+    }
+    encoded.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{encode, FormArrayStyle};
+    use crate::input::ParameterContents;
+
+    #[test]
+    fn test_array_styles() {
+        let body = ParameterContents::from(json!({"ids": [3, 4, 5], "name": "rex"}));
+
+        assert_eq!(
+            encode(&body, FormArrayStyle::Repeat),
+            "ids=3&ids=4&ids=5&name=rex"
+        );
+        assert_eq!(
+            encode(&body, FormArrayStyle::Brackets),
+            "ids%5B%5D=3&ids%5B%5D=4&ids%5B%5D=5&name=rex"
+        );
+        assert_eq!(
+            encode(&body, FormArrayStyle::Indexed),
+            "ids%5B0%5D=3&ids%5B1%5D=4&ids%5B2%5D=5&name=rex"
+        );
+    }
+}
@@ -9,11 +9,20 @@ const HEAD: &str = "HEAD";
 const TRACE: &str = "TRACE";
 const OPTIONS: &str = "OPTIONS";
 const CONNECT: &str = "CONNECT";
+const PROPFIND: &str = "PROPFIND";
+const PROPPATCH: &str = "PROPPATCH";
+const MKCOL: &str = "MKCOL";
+const COPY: &str = "COPY";
+const MOVE: &str = "MOVE";
+const LOCK: &str = "LOCK";
+const UNLOCK: &str = "UNLOCK";
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "UPPERCASE", try_from = "String")]
 /// This enum represents the valid methods of a request made by the fuzzer,
-/// and supports conversions from and to strings.
+/// and supports conversions from and to strings. Besides the methods of HTTP
+/// itself, it includes the WebDAV methods (RFC 4918), which an OpenAPI specification
+/// cannot describe but a method mutation strategy can send.
 pub enum Method {
     Get,
     Post,
@@ -24,6 +33,13 @@ pub enum Method {
     Trace,
     Options,
     Connect,
+    Propfind,
+    Proppatch,
+    Mkcol,
+    Copy,
+    Move,
+    Lock,
+    Unlock,
 }
 
 impl Method {
@@ -44,6 +60,13 @@ impl Method {
             Method::Trace => TRACE,
             Method::Options => OPTIONS,
             Method::Connect => CONNECT,
+            Method::Propfind => PROPFIND,
+            Method::Proppatch => PROPPATCH,
+            Method::Mkcol => MKCOL,
+            Method::Copy => COPY,
+            Method::Move => MOVE,
+            Method::Lock => LOCK,
+            Method::Unlock => UNLOCK,
         }
     }
 }
@@ -60,6 +83,14 @@ impl From<Method> for reqwest::Method {
             Method::Trace => reqwest::Method::TRACE,
             Method::Options => reqwest::Method::OPTIONS,
             Method::Connect => reqwest::Method::CONNECT,
+            Method::Propfind
+            | Method::Proppatch
+            | Method::Mkcol
+            | Method::Copy
+            | Method::Move
+            | Method::Lock
+            | Method::Unlock => reqwest::Method::from_bytes(m.as_bytes())
+                .expect("WebDAV method names are valid HTTP methods"),
         }
     }
 }
@@ -95,6 +126,13 @@ impl Ord for Method {
                 Method::Delete => 6,
                 Method::Options => 7,
                 Method::Connect => 8,
+                Method::Propfind => 9,
+                Method::Proppatch => 10,
+                Method::Mkcol => 11,
+                Method::Copy => 12,
+                Method::Move => 13,
+                Method::Lock => 14,
+                Method::Unlock => 15,
             }
         }
         method_index(*self).cmp(&method_index(*other))
@@ -119,6 +157,13 @@ impl Display for Method {
             Method::Trace => fmt.write_str(TRACE),
             Method::Options => fmt.write_str(OPTIONS),
             Method::Connect => fmt.write_str(CONNECT),
+            Method::Propfind => fmt.write_str(PROPFIND),
+            Method::Proppatch => fmt.write_str(PROPPATCH),
+            Method::Mkcol => fmt.write_str(MKCOL),
+            Method::Copy => fmt.write_str(COPY),
+            Method::Move => fmt.write_str(MOVE),
+            Method::Lock => fmt.write_str(LOCK),
+            Method::Unlock => fmt.write_str(UNLOCK),
         }
     }
 }
@@ -140,6 +185,13 @@ impl TryFrom<&str> for Method {
             5 if s.eq_ignore_ascii_case(TRACE) => Ok(Self::Trace),
             7 if s.eq_ignore_ascii_case(OPTIONS) => Ok(Self::Options),
             7 if s.eq_ignore_ascii_case(CONNECT) => Ok(Self::Connect),
+            8 if s.eq_ignore_ascii_case(PROPFIND) => Ok(Self::Propfind),
+            9 if s.eq_ignore_ascii_case(PROPPATCH) => Ok(Self::Proppatch),
+            5 if s.eq_ignore_ascii_case(MKCOL) => Ok(Self::Mkcol),
+            4 if s.eq_ignore_ascii_case(COPY) => Ok(Self::Copy),
+            4 if s.eq_ignore_ascii_case(MOVE) => Ok(Self::Move),
+            4 if s.eq_ignore_ascii_case(LOCK) => Ok(Self::Lock),
+            6 if s.eq_ignore_ascii_case(UNLOCK) => Ok(Self::Unlock),
             _ => Err(InvalidMethodError(s.to_owned())),
         }
     }
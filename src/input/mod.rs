@@ -17,8 +17,8 @@
 //!   - method: POST
 //!     path: "/path/{name_of_parameter_in_path}/something"
 //!     body:
-//!       # The body can be a submitted form (as below), but also TextPlain or
-//!       # ApplicationJson, or it can be omitted.
+//!       # The body can be a submitted form (as below), but also TextPlain,
+//!       # ApplicationJson, Multipart or OctetStream, or it can be omitted.
 //!       XWwwFormUrlencoded:
 //!         # The contents of any parameter can be a leaf_value, shown below,
 //!         # or an object or array containing values of its own (again, leaf
@@ -70,12 +70,15 @@ use openapiv3::{OpenAPI, Operation, SchemaKind, Type};
 use self::parameter::ParameterKind;
 pub use self::{method::Method, parameter::ParameterContents};
 use crate::{
-    openapi::{find_operation, JsonContent, TextPlain, WwwForm},
+    openapi::{find_operation, JsonContent, Multipart, OctetStream, TextPlain, WwwForm},
+    openapi_mutator::header_injection::is_injected_header,
     parameter_feedback::ParameterFeedback,
     state::HasRandAndOpenAPI,
 };
 
+pub mod form;
 pub mod method;
+pub mod multipart;
 pub mod parameter;
 mod serde_helpers;
 
@@ -105,6 +108,8 @@ pub enum Body {
     TextPlain(ParameterContents),
     ApplicationJson(ParameterContents),
     XWwwFormUrlencoded(ParameterContents),
+    Multipart(ParameterContents),
+    OctetStream(Vec<u8>),
 }
 
 impl Body {
@@ -127,9 +132,23 @@ impl Body {
                 if body.content.has_www_form_content() {
                     return Body::XWwwFormUrlencoded(param_contents);
                 }
+                if body.content.has_multipart_content() {
+                    let media_type = body.content.get_multipart_content().unwrap();
+                    return Body::Multipart(multipart::binary_fields_to_bytes(
+                        api,
+                        media_type,
+                        param_contents,
+                    ));
+                }
                 if body.content.has_text_plain() {
                     return Body::TextPlain(param_contents.to_string().into());
                 }
+                if body.content.has_octet_stream_content() {
+                    return match param_contents {
+                        ParameterContents::Bytes(bytes) => Body::OctetStream(bytes),
+                        other => Body::OctetStream(other.to_string().into_bytes()),
+                    };
+                }
                 Body::Empty
             }
             Err(reference) => {
@@ -138,6 +157,37 @@ impl Body {
         }
     }
 
+    /// Nests a JSON array body in an object, under the given key.
+    /// Returns whether the body was a JSON array.
+    pub fn wrap_json_array(&mut self, key: &str) -> bool {
+        match self {
+            Body::ApplicationJson(contents @ ParameterContents::Array(_)) => {
+                let array = std::mem::replace(contents, ParameterContents::Object(IndexMap::new()));
+                *contents = ParameterContents::Object(IndexMap::from([(key.to_owned(), array)]));
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Replaces a JSON body consisting of an object with a single array under the given
+    /// key by the bare array. Returns whether the body had this wrapped form.
+    pub fn unwrap_json_array(&mut self, key: &str) -> bool {
+        let Body::ApplicationJson(contents) = self else {
+            return false;
+        };
+        match contents {
+            ParameterContents::Object(fields)
+                if fields.len() == 1
+                    && matches!(fields.get(key), Some(ParameterContents::Array(_))) =>
+            {
+                *contents = fields.shift_remove(key).unwrap();
+                true
+            }
+            _ => false,
+        }
+    }
+
     pub fn is_empty(&self) -> bool {
         matches!(self, Body::Empty)
     }
@@ -166,6 +216,11 @@ impl OpenApiRequest {
                         ))
                     })?;
                 *parameter = ParameterContents::from(resolved_backref.clone());
+            } else if let ParameterContents::Array(elements) = parameter {
+                // Array elements have no name of their own, but can still be references
+                for element in elements {
+                    resolve_single_parameter(element, parameter_values)?;
+                }
             }
 
             Ok(())
@@ -173,11 +228,13 @@ impl OpenApiRequest {
 
         // Resolve body parameters
         match &mut self.body {
-            Body::Empty => (), // No (reference) parameters in body, so nothing to resolve here!
+            // No (reference) parameters in body, so nothing to resolve here!
+            Body::Empty | Body::OctetStream(_) => (),
             Body::TextPlain(body)
             | Body::ApplicationJson(body)
-            | Body::XWwwFormUrlencoded(body) => match body {
-                ParameterContents::Reference { .. } => {
+            | Body::XWwwFormUrlencoded(body)
+            | Body::Multipart(body) => match body {
+                ParameterContents::Reference { .. } | ParameterContents::Array(_) => {
                     resolve_single_parameter(body, parameter_values)?;
                 }
                 ParameterContents::Object(obj_contents) => {
@@ -185,11 +242,6 @@ impl OpenApiRequest {
                         resolve_single_parameter(nested_parameter, parameter_values)?;
                     }
                 }
-                ParameterContents::Array(arr) => {
-                    for nested_parameter in arr {
-                        resolve_single_parameter(nested_parameter, parameter_values)?;
-                    }
-                }
                 ParameterContents::LeafValue(_) | ParameterContents::Bytes(_) => (),
             },
         }
@@ -201,8 +253,13 @@ impl OpenApiRequest {
         Ok(())
     }
 
-    /// Derive a body for a Reqwest request from this OpenApiRequest
-    pub fn reqwest_body(&self) -> Option<reqwest::blocking::Body> {
+    /// Derive a body for a Reqwest request from this OpenApiRequest, writing arrays in
+    /// form bodies in `array_style`. The parts of multipart bodies get default content
+    /// types, see `build_request_from_input` for the ones declared in the specification.
+    pub fn reqwest_body(
+        &self,
+        array_style: form::FormArrayStyle,
+    ) -> Option<reqwest::blocking::Body> {
         match &self.body {
             Body::Empty => None,
             Body::TextPlain(body) | Body::ApplicationJson(body) => {
@@ -210,59 +267,26 @@ impl OpenApiRequest {
                     .ok()
                     .map(reqwest::blocking::Body::from)
             }
-            Body::XWwwFormUrlencoded(body) => {
-                let mut encoded = url::form_urlencoded::Serializer::new(String::new());
-                match body {
-                    ParameterContents::Object(obj_contents) => {
-                        for pair in obj_contents.iter() {
-                            match pair.1 {
-                                ParameterContents::LeafValue(first_level_concrete) => {
-                                    match first_level_concrete {
-                                        // String must be handled separately, otherwise it gets surrounded by quotes.
-                                        parameter::SimpleValue::String(inner_str) => {
-                                            encoded.append_pair(pair.0, inner_str)
-                                        }
-                                        _ => encoded.append_pair(
-                                            pair.0,
-                                            first_level_concrete.to_string().as_str(),
-                                        ),
-                                    }
-                                }
-                                ParameterContents::Array(inner_array) => encoded.extend_pairs(
-                                    inner_array
-                                        .iter()
-                                        .map(|element| (pair.0, element.to_string())),
-                                ),
-                                ParameterContents::Object(inner_map) => encoded.extend_pairs(
-                                    inner_map
-                                        .iter()
-                                        .map(|(field, value)| (field, value.to_string())),
-                                ),
-                                _ => &mut encoded,
-                            };
-                        }
-                    }
-                    ParameterContents::Reference { .. } => {
-                        todo!("Trying to create form body from reference: {body}")
-                    }
-                    ParameterContents::Bytes(val) => {
-                        todo!("Trying to create form body from bytes: {:?}", val)
-                    }
-                    ParameterContents::Array(_) | ParameterContents::LeafValue(_) => {
-                        panic!("Form bodies must not be of type array or leaf, but interpretable as key-value objects.\nOffending body: {}", body);
-                    }
-                }
-                Some(reqwest::blocking::Body::from(encoded.finish()))
-            }
+            Body::XWwwFormUrlencoded(body) => Some(reqwest::blocking::Body::from(form::encode(
+                body,
+                array_style,
+            ))),
+            Body::Multipart(body) => Some(reqwest::blocking::Body::from(multipart::encode(
+                body,
+                &IndexMap::new(),
+            ))),
+            Body::OctetStream(bytes) => Some(reqwest::blocking::Body::from(bytes.clone())),
         }
     }
 
-    pub fn body_content_type(&self) -> &'static str {
-        match self.body {
-            Body::Empty => "",
-            Body::TextPlain(_) => "text/plain",
-            Body::ApplicationJson(_) => "application/json",
-            Body::XWwwFormUrlencoded(_) => "application/x-www-form-urlencoded",
+    pub fn body_content_type(&self) -> Cow<'static, str> {
+        match &self.body {
+            Body::Empty => "".into(),
+            Body::TextPlain(_) => "text/plain".into(),
+            Body::ApplicationJson(_) => "application/json".into(),
+            Body::XWwwFormUrlencoded(_) => "application/x-www-form-urlencoded".into(),
+            Body::Multipart(contents) => multipart::content_type(contents).into(),
+            Body::OctetStream(_) => "application/octet-stream".into(),
         }
     }
 
@@ -283,11 +307,13 @@ impl OpenApiRequest {
             | ParameterKind::Header
             | ParameterKind::Cookie => self.parameters.get_mut(&(name.to_owned(), kind)),
             ParameterKind::Body => match &mut self.body {
-                Body::Empty => None,
+                Body::Empty | Body::OctetStream(_) => None,
                 Body::TextPlain(text) => Some(text),
                 // For getting named parameters, we consider only first-level parameters in object values
                 // TODO: implement a way to address nested parameters and non-object parameters.
-                Body::ApplicationJson(parameters) | Body::XWwwFormUrlencoded(parameters) => {
+                Body::ApplicationJson(parameters)
+                | Body::XWwwFormUrlencoded(parameters)
+                | Body::Multipart(parameters) => {
                     if let ParameterContents::Object(obj_param) = parameters {
                         obj_param.get_mut(name)
                     } else {
@@ -315,7 +341,10 @@ impl std::fmt::Display for OpenApiRequest {
         match &self.body {
             Body::Empty => (),
             Body::TextPlain(text) => write!(fmt, "\n text body: {text}")?,
-            Body::ApplicationJson(body_content) | Body::XWwwFormUrlencoded(body_content) => {
+            Body::OctetStream(bytes) => write!(fmt, "\n binary body: {} bytes", bytes.len())?,
+            Body::ApplicationJson(body_content)
+            | Body::XWwwFormUrlencoded(body_content)
+            | Body::Multipart(body_content) => {
                 write!(fmt, "Contents in body: {body_content}")?;
             }
         }
@@ -384,12 +413,16 @@ impl OpenApiInput {
                     .map(|(_, v)| v)
                     // .. then add any fields from the body as well ..
                     .chain(match &mut openapi_request.body {
-                        Body::Empty => ParamContentsAtLevel0Wrapper::SimpleOption(None),
+                        // Raw bytes bodies are mutated separately, see `OpenApiMutator`
+                        Body::Empty | Body::OctetStream(_) => {
+                            ParamContentsAtLevel0Wrapper::SimpleOption(None)
+                        }
                         Body::TextPlain(text) => {
                             ParamContentsAtLevel0Wrapper::SimpleOption(Some(text))
                         }
                         Body::ApplicationJson(parameters)
-                        | Body::XWwwFormUrlencoded(parameters) => match parameters {
+                        | Body::XWwwFormUrlencoded(parameters)
+                        | Body::Multipart(parameters) => match parameters {
                             ParameterContents::Object(obj_param) => {
                                 ParamContentsAtLevel0Wrapper::InObject(obj_param.values_mut())
                             }
@@ -422,10 +455,11 @@ impl OpenApiInput {
                     //.. then add any fields from the body as well ..
                     .chain(
                         match &openapi_request.body {
-                            Body::Empty => IterWrapper::WithOption(None),
+                            Body::Empty | Body::OctetStream(_) => IterWrapper::WithOption(None),
                             Body::TextPlain(text) => IterWrapper::WithOption(Some(text)),
                             Body::ApplicationJson(contents)
-                            | Body::XWwwFormUrlencoded(contents) => match contents {
+                            | Body::XWwwFormUrlencoded(contents)
+                            | Body::Multipart(contents) => match contents {
                                 ParameterContents::Object(obj_params) => {
                                     IterWrapper::WithIter(obj_params.iter())
                                 }
@@ -490,14 +524,9 @@ impl OpenApiInput {
     {
         let to_replace: Vec<_> = self
             .reference_parameters()
-            .filter(
-                // Select broken references: target request does not exist or does not
-                // contain the referenced parameter name
-                |(_, _, _, target_idx, target_name)| match self.0.get(*target_idx) {
-                    None => true,
-                    Some(request) => !request.contains_parameter(target_name),
-                },
-            )
+            .filter(|(_, _, _, target_idx, target_name)| {
+                self.is_broken_reference(*target_idx, target_name)
+            })
             // Reference is broken - replace (later... borrow checker forbids doing it here
             // since it can't verify we don't mess up the loop from reference_parameters)
             .map(|(source_idx, source_name, source_kind, _, _)| {
@@ -508,12 +537,12 @@ impl OpenApiInput {
         for (idx, name, kind) in to_replace {
             match kind {
                 ParameterKind::Body => match &mut self.0[idx].body {
-                    Body::Empty | Body::TextPlain(_) => {
+                    Body::Empty | Body::TextPlain(_) | Body::OctetStream(_) => {
                         log::warn!("Marked body parameter in request {idx} with name {name} for replacement,
-                                    but the body is Empty or TextPlain!");
+                                    but the body is Empty, TextPlain or OctetStream!");
                         continue
                     },
-                    Body::ApplicationJson(contents) | Body::XWwwFormUrlencoded(contents) => {
+                    Body::ApplicationJson(contents) | Body::XWwwFormUrlencoded(contents) | Body::Multipart(contents) => {
                         match contents {
                             ParameterContents::Object(obj_param) => &mut obj_param[&name],
                             // Note that a Reference parameter is not by itself named, but must be the value in an Object parameter.
@@ -524,18 +553,9 @@ impl OpenApiInput {
                                         with name {parameter_name} in request {request_index}.");
                                 continue
                             }
-                            // Note that Array fields currently cannot be addressed as variable parameters.
-                            // Therefore, Arrays currently should not contain any references.
-                            // If they do, we do not resolve them here.
-                            ParameterContents::Array(arr_param) => {
-                                for elem in arr_param.iter() {
-                                    if let ParameterContents::Reference { .. } = elem {
-                                        log::warn!("Array contains reference, but we cannot identify this with
-                                        request_idx, name, parameter_kind triplet. Therefore we cannot resolve the reference.");
-                                    }
-                                }
-                                continue
-                            }
+                            // Array elements cannot be addressed as named parameters, they
+                            // are fixed below.
+                            ParameterContents::Array(_) => continue,
                             ParameterContents::LeafValue(_) => {
                                 log::warn!("Marked body parameter in request {idx} with name {name} for replacement,
                                             but the body is a LeafValue: {contents}");
@@ -553,6 +573,63 @@ impl OpenApiInput {
             }
             .break_reference_if_target(rand, |_| true);
         }
+
+        // References in array elements are not found by `reference_parameters`, since
+        // they have no name. The body is taken out of the chain while fixing them, so
+        // the rest of the chain can be inspected.
+        for idx in 0..self.0.len() {
+            let mut body = std::mem::take(&mut self.0[idx].body);
+            match &mut body {
+                Body::ApplicationJson(contents)
+                | Body::XWwwFormUrlencoded(contents)
+                | Body::Multipart(contents) => self.break_broken_array_references(contents, rand),
+                Body::Empty | Body::TextPlain(_) | Body::OctetStream(_) => (),
+            }
+            self.0[idx].body = body;
+        }
+    }
+
+    /// Whether a reference to the parameter with the given name in the given request is
+    /// broken: the target request does not exist or does not contain the parameter.
+    fn is_broken_reference(&self, target_idx: usize, target_name: &str) -> bool {
+        match self.0.get(target_idx) {
+            None => true,
+            Some(request) => !request.contains_parameter(target_name),
+        }
+    }
+
+    /// Replaces broken references among the elements of the arrays in `contents` by
+    /// random data.
+    fn break_broken_array_references<R>(&self, contents: &mut ParameterContents, rand: &mut R)
+    where
+        R: Rand,
+    {
+        match contents {
+            ParameterContents::Array(elements) => {
+                for element in elements {
+                    let is_broken = match element {
+                        ParameterContents::Reference {
+                            request_index,
+                            parameter_name,
+                        } => self.is_broken_reference(*request_index, parameter_name),
+                        _ => false,
+                    };
+                    if is_broken {
+                        element.break_reference_if_target(rand, |_| true);
+                    } else {
+                        self.break_broken_array_references(element, rand);
+                    }
+                }
+            }
+            ParameterContents::Object(fields) => {
+                for value in fields.values_mut() {
+                    self.break_broken_array_references(value, rand);
+                }
+            }
+            ParameterContents::Reference { .. }
+            | ParameterContents::LeafValue(_)
+            | ParameterContents::Bytes(_) => (),
+        }
     }
 
     /// Checks if a request chain is valid: no references to future requests.
@@ -596,7 +673,10 @@ impl Input for OpenApiInput {
             match &request.body {
                 Body::Empty => (),
                 Body::TextPlain(value) => hasher.write(value.to_string().as_bytes()),
-                Body::ApplicationJson(content) | Body::XWwwFormUrlencoded(content) => {
+                Body::OctetStream(bytes) => hasher.write(bytes),
+                Body::ApplicationJson(content)
+                | Body::XWwwFormUrlencoded(content)
+                | Body::Multipart(content) => {
                     hasher.write(content.to_string().as_bytes());
                 }
             }
@@ -666,6 +746,13 @@ where
             })
         })
         .collect();
+    // Headers added by the header injection mutator are never in the specification,
+    // but are kept so that they can be tried on other operations as well
+    for (key, value) in input.parameters.drain(..) {
+        if key.1 == ParameterKind::Header && is_injected_header(&key.0) {
+            new_params.entry(key).or_insert(value);
+        }
+    }
     new_params.sort_keys();
     input.parameters = new_params;
 }
@@ -682,9 +769,14 @@ impl std::fmt::Display for OpenApiInput {
 #[cfg(test)]
 mod tests {
     use indexmap::IndexMap;
+    use libafl_bolts::rands::StdRand;
+    use openapiv3::OpenAPI;
     use serde_json::json;
 
-    use super::{Body, Method, OpenApiRequest, ParameterContents};
+    use super::{
+        form::FormArrayStyle, Body, Method, OpenApiInput, OpenApiRequest, ParameterContents,
+    };
+    use crate::parameter_feedback::ParameterFeedback;
 
     #[test]
     fn test_reqwest_body() {
@@ -706,7 +798,7 @@ mod tests {
             parameters: IndexMap::new(),
         };
         let bodified = openapi_request
-            .reqwest_body()
+            .reqwest_body(FormArrayStyle::default())
             .expect("Failed to convert OpenApiRequest to a reqwest.Body");
         let query_pairs = bodified
             .as_bytes()
@@ -722,4 +814,127 @@ mod tests {
         assert!(query_pairs.contains(&&b"field1=2"[..]));
         assert!(query_pairs.contains(&&b"Field2=false"[..]));
     }
+
+    fn form_request(body: ParameterContents) -> OpenApiRequest {
+        OpenApiRequest {
+            method: Method::Post,
+            path: "/".to_owned(),
+            body: Body::XWwwFormUrlencoded(body),
+            parameters: IndexMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_reqwest_body_from_reference_is_empty() {
+        let request = form_request(ParameterContents::Reference {
+            request_index: 0,
+            parameter_name: "id".to_owned(),
+        });
+        let body = request.reqwest_body(FormArrayStyle::default()).unwrap();
+        assert_eq!(body.as_bytes(), Some(&b""[..]));
+    }
+
+    #[test]
+    fn test_reqwest_body_from_bytes_is_percent_encoded() {
+        let request = form_request(ParameterContents::Bytes(b"a b&c=\xff".to_vec()));
+        let body = request.reqwest_body(FormArrayStyle::default()).unwrap();
+        assert_eq!(body.as_bytes(), Some(&b"a+b%26c%3D%FF"[..]));
+    }
+
+    #[test]
+    fn test_octet_stream_body_is_sent_as_is() {
+        let api: OpenAPI = serde_json::from_value(json!({
+            "openapi": "3.0.0",
+            "info": {"title": "uploads", "version": "1"},
+            "paths": {
+                "/upload": {
+                    "put": {
+                        "requestBody": {
+                            "content": {
+                                "application/octet-stream": {
+                                    "schema": {"type": "string", "format": "binary"}
+                                }
+                            }
+                        },
+                        "responses": {}
+                    }
+                }
+            }
+        }))
+        .unwrap();
+        let operation = api.operations().next().unwrap().2;
+        let body = Body::build(
+            &api,
+            operation,
+            Some(ParameterContents::Bytes(b"\x00\xffPNG".to_vec())),
+        );
+        let request = OpenApiRequest {
+            method: Method::Put,
+            path: "/upload".to_owned(),
+            body,
+            parameters: IndexMap::new(),
+        };
+        assert_eq!(request.body_content_type(), "application/octet-stream");
+        let body = request.reqwest_body(FormArrayStyle::default()).unwrap();
+        assert_eq!(body.as_bytes(), Some(&b"\x00\xffPNG"[..]));
+    }
+
+    fn json_request(path: &str, body: ParameterContents) -> OpenApiRequest {
+        OpenApiRequest {
+            method: Method::Post,
+            path: path.to_owned(),
+            body: Body::ApplicationJson(body),
+            parameters: IndexMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_resolve_reference_in_array_element() {
+        let mut request = json_request(
+            "/owners",
+            ParameterContents::Array(vec![
+                ParameterContents::from(json!(1)),
+                ParameterContents::Reference {
+                    request_index: 0,
+                    parameter_name: "id".to_owned(),
+                },
+            ]),
+        );
+        let mut parameter_feedback = ParameterFeedback::new(2);
+        parameter_feedback.set(0, "id".to_owned(), json!(42));
+
+        request
+            .resolve_parameter_references(&parameter_feedback)
+            .unwrap();
+        let Body::ApplicationJson(body) = &request.body else {
+            panic!("Body is no longer JSON");
+        };
+        assert_eq!(body.to_value(), json!([1, 42]));
+    }
+
+    #[test]
+    fn test_fix_broken_reference_in_array_element() {
+        let mut input = OpenApiInput(vec![
+            json_request("/pets", ParameterContents::from(json!({}))),
+            json_request(
+                "/owners",
+                ParameterContents::from(IndexMap::from([(
+                    "pets".to_owned(),
+                    ParameterContents::Array(vec![ParameterContents::Reference {
+                        request_index: 0,
+                        parameter_name: "missing".to_owned(),
+                    }]),
+                )])),
+            ),
+        ]);
+
+        input.fix_broken_references(&mut StdRand::with_seed(0));
+        let Body::ApplicationJson(ParameterContents::Object(fields)) = &input.0[1].body else {
+            panic!("Body is no longer a JSON object");
+        };
+        let ParameterContents::Array(elements) = &fields["pets"] else {
+            panic!("Field is no longer an array");
+        };
+        assert!(matches!(elements[0], ParameterContents::Bytes(_)));
+    }
 }
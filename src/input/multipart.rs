@@ -0,0 +1,349 @@
+//! Serialization of `multipart/form-data` request bodies.
+//!
+//! Each field of an object body becomes a part. Raw bytes are sent as file parts, leaf
+//! values as text parts, and objects as JSON parts. Arrays are sent as one part per
+//! element, all with the name of the field. The content type of a part can be
+//! overridden, and headers can be added to it, by the `encoding` of the request body
+//! in the specification.
+
+use indexmap::IndexMap;
+use openapiv3::{MediaType, OpenAPI, SchemaKind, StringFormat, Type, VariantOrUnknownOrEmpty};
+
+use super::{parameter::SimpleValue, ParameterContents};
+
+/// Boundary between the parts of a multipart body, unless it occurs in the body. The
+/// boundary is derived from the contents alone, so that the body and its content type
+/// can be derived independently of each other.
+const BOUNDARY: &str = "WuppieFuzzFormBoundary7MA4YWxkTrZu0gW";
+
+/// The boundary between the parts of a multipart body with the given contents: the
+/// first of `BOUNDARY`, `BOUNDARY1`, `BOUNDARY2`, ... that does not occur in them.
+pub fn boundary(contents: &ParameterContents) -> String {
+    (0..)
+        .map(|attempt| match attempt {
+            0 => BOUNDARY.to_owned(),
+            n => format!("{BOUNDARY}{n}"),
+        })
+        .find(|boundary| !occurs_in(boundary.as_bytes(), contents))
+        .unwrap()
+}
+
+/// Content type of a multipart body with the given contents, including the boundary.
+pub fn content_type(contents: &ParameterContents) -> String {
+    format!("multipart/form-data; boundary={}", boundary(contents))
+}
+
+/// Whether `needle` occurs in a field name or value of the contents
+fn occurs_in(needle: &[u8], contents: &ParameterContents) -> bool {
+    let contains = |haystack: &[u8]| haystack.windows(needle.len()).any(|part| part == needle);
+    match contents {
+        ParameterContents::Object(fields) => fields
+            .iter()
+            .any(|(name, value)| contains(name.as_bytes()) || occurs_in(needle, value)),
+        ParameterContents::Array(items) => items.iter().any(|item| occurs_in(needle, item)),
+        ParameterContents::LeafValue(SimpleValue::String(text)) => contains(text.as_bytes()),
+        ParameterContents::LeafValue(leaf) => contains(leaf.to_string().as_bytes()),
+        ParameterContents::Bytes(bytes) => contains(bytes),
+        ParameterContents::Reference { .. } => false,
+    }
+}
+
+/// Name of the single part if the body is not an object.
+const UNNAMED_PART: &str = "body";
+
+/// How a part is encoded, as declared in the `encoding` of the request body in the
+/// specification
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PartEncoding {
+    /// Content type of the part, instead of the default for its value
+    pub content_type: Option<String>,
+    /// Extra headers of the part, by name
+    pub headers: IndexMap<String, String>,
+}
+
+/// Serializes the body contents to a multipart body. `encoding` gives the encoding of
+/// the parts declared in the specification, by part name.
+pub fn encode(contents: &ParameterContents, encoding: &IndexMap<String, PartEncoding>) -> Vec<u8> {
+    let boundary = boundary(contents);
+    let mut body = Vec::new();
+    match contents {
+        ParameterContents::Object(fields) => {
+            for (name, value) in fields {
+                write_field(&mut body, &boundary, name, value, encoding.get(name));
+            }
+        }
+        other => write_field(
+            &mut body,
+            &boundary,
+            UNNAMED_PART,
+            other,
+            encoding.get(UNNAMED_PART),
+        ),
+    }
+    body.extend_from_slice(format!("--{boundary}--\r\n").as_bytes());
+    body
+}
+
+fn write_field(
+    body: &mut Vec<u8>,
+    boundary: &str,
+    name: &str,
+    value: &ParameterContents,
+    encoding: Option<&PartEncoding>,
+) {
+    let content_type = encoding.and_then(|encoding| encoding.content_type.as_deref());
+    match value {
+        ParameterContents::Array(items) if !content_type.is_some_and(is_json) => {
+            for item in items {
+                write_part(body, boundary, name, item, encoding);
+            }
+        }
+        _ => write_part(body, boundary, name, value, encoding),
+    }
+}
+
+fn write_part(
+    body: &mut Vec<u8>,
+    boundary: &str,
+    name: &str,
+    value: &ParameterContents,
+    encoding: Option<&PartEncoding>,
+) {
+    let (data, default_content_type, is_file) = match value {
+        ParameterContents::Bytes(bytes) => (bytes.clone(), Some("application/octet-stream"), true),
+        ParameterContents::LeafValue(SimpleValue::String(text)) => {
+            (text.as_bytes().to_vec(), None, false)
+        }
+        ParameterContents::LeafValue(leaf) => (leaf.to_string().into_bytes(), None, false),
+        ParameterContents::Object(_) | ParameterContents::Array(_) => (
+            value.to_value().to_string().into_bytes(),
+            Some("application/json"),
+            false,
+        ),
+        ParameterContents::Reference { .. } => {
+            // References should have been resolved before the body is built
+            log::warn!("Unresolved reference in multipart field {name}, leaving it out: {value}");
+            return;
+        }
+    };
+
+    let name = escape_name(name);
+    body.extend_from_slice(format!("--{boundary}\r\n").as_bytes());
+    if is_file {
+        body.extend_from_slice(
+            format!("Content-Disposition: form-data; name=\"{name}\"; filename=\"{name}\"\r\n")
+                .as_bytes(),
+        );
+    } else {
+        body.extend_from_slice(
+            format!("Content-Disposition: form-data; name=\"{name}\"\r\n").as_bytes(),
+        );
+    }
+    let content_type = encoding.and_then(|encoding| encoding.content_type.as_deref());
+    if let Some(content_type) = content_type.or(default_content_type) {
+        body.extend_from_slice(format!("Content-Type: {content_type}\r\n").as_bytes());
+    }
+    // The content disposition and type are written above, and not taken from the headers
+    let headers = encoding.into_iter().flat_map(|encoding| &encoding.headers);
+    for (header, value) in headers.filter(|(header, _)| {
+        !header.eq_ignore_ascii_case("Content-Type")
+            && !header.eq_ignore_ascii_case("Content-Disposition")
+    }) {
+        body.extend_from_slice(format!("{header}: {value}\r\n").as_bytes());
+    }
+    body.extend_from_slice(b"\r\n");
+    body.extend_from_slice(&data);
+    body.extend_from_slice(b"\r\n");
+}
+
+/// Escapes the characters that can not appear in a quoted name in a part header,
+/// the way browsers do.
+fn escape_name(name: &str) -> String {
+    name.replace('"', "%22")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+fn is_json(content_type: &str) -> bool {
+    content_type.starts_with("application/json")
+}
+
+/// Turns the string values of top-level fields that the schema declares as binary into
+/// raw bytes, so that they are sent as file parts.
+pub fn binary_fields_to_bytes(
+    api: &OpenAPI,
+    media_type: &MediaType,
+    contents: ParameterContents,
+) -> ParameterContents {
+    let properties = match media_type
+        .schema
+        .as_ref()
+        .map(|schema| &schema.resolve(api).kind)
+    {
+        Some(SchemaKind::Type(Type::Object(object))) => &object.properties,
+        _ => return contents,
+    };
+    let ParameterContents::Object(mut fields) = contents else {
+        return contents;
+    };
+    for (name, value) in fields.iter_mut() {
+        let is_binary = properties.get(name).is_some_and(|schema| {
+            matches!(
+                &schema.resolve(api).kind,
+                SchemaKind::Type(Type::String(string))
+                    if string.format == VariantOrUnknownOrEmpty::Item(StringFormat::Binary)
+            )
+        });
+        if let (true, ParameterContents::LeafValue(SimpleValue::String(text))) = (is_binary, &value)
+        {
+            *value = ParameterContents::Bytes(text.as_bytes().to_vec());
+        }
+    }
+    ParameterContents::Object(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+    use serde_json::json;
+
+    use super::{boundary, content_type, encode, PartEncoding, BOUNDARY};
+    use crate::input::ParameterContents;
+
+    /// A part of a multipart body: its headers and its data
+    #[derive(Debug, PartialEq)]
+    struct Part {
+        headers: Vec<String>,
+        data: Vec<u8>,
+    }
+
+    /// Splits a multipart body with the given boundary into its parts
+    fn decode(body: &[u8], boundary: &str) -> Vec<Part> {
+        let delimiter = format!("--{boundary}");
+        let body = String::from_utf8_lossy(body);
+        let (parts, rest) = body.rsplit_once(&format!("{delimiter}--\r\n")).unwrap();
+        assert!(rest.is_empty());
+        parts
+            .split(&format!("{delimiter}\r\n"))
+            .skip(1)
+            .map(|part| {
+                let (headers, data) = part.split_once("\r\n\r\n").unwrap();
+                Part {
+                    headers: headers.split("\r\n").map(str::to_owned).collect(),
+                    data: data.strip_suffix("\r\n").unwrap().as_bytes().to_vec(),
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_boundary_does_not_occur_in_body() {
+        let contents = ParameterContents::from(json!({"name": "Wuppie"}));
+        assert_eq!(boundary(&contents), BOUNDARY);
+        assert_eq!(
+            content_type(&contents),
+            format!("multipart/form-data; boundary={BOUNDARY}")
+        );
+
+        // A body that contains the boundary, e.g. after a mutation, gets another one
+        let contents = ParameterContents::Object(IndexMap::from([
+            (
+                "name".to_owned(),
+                ParameterContents::from(format!("--{BOUNDARY}--")),
+            ),
+            (
+                "photo".to_owned(),
+                ParameterContents::Bytes(format!("{BOUNDARY}1").into_bytes()),
+            ),
+        ]));
+        let other = boundary(&contents);
+        assert_eq!(other, format!("{BOUNDARY}2"));
+        let parts = decode(&encode(&contents, &IndexMap::new()), &other);
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].data, format!("--{BOUNDARY}--").as_bytes());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let contents = ParameterContents::Object(IndexMap::from([
+            ("name".to_owned(), ParameterContents::from(json!("Wuppie"))),
+            ("age".to_owned(), ParameterContents::from(json!(3))),
+            (
+                "tags".to_owned(),
+                ParameterContents::from(json!(["a", "b"])),
+            ),
+            ("meta".to_owned(), ParameterContents::from(json!({"x": 1}))),
+            (
+                "photo".to_owned(),
+                ParameterContents::Bytes(b"PNG".to_vec()),
+            ),
+        ]));
+        let parts = decode(&encode(&contents, &IndexMap::new()), BOUNDARY);
+        let text_part = |name: &str, data: &str| Part {
+            headers: vec![format!("Content-Disposition: form-data; name=\"{name}\"")],
+            data: data.as_bytes().to_vec(),
+        };
+        assert_eq!(
+            parts,
+            vec![
+                text_part("name", "Wuppie"),
+                text_part("age", "3"),
+                text_part("tags", "a"),
+                text_part("tags", "b"),
+                Part {
+                    headers: vec![
+                        "Content-Disposition: form-data; name=\"meta\"".to_owned(),
+                        "Content-Type: application/json".to_owned(),
+                    ],
+                    data: b"{\"x\":1}".to_vec(),
+                },
+                Part {
+                    headers: vec![
+                        "Content-Disposition: form-data; name=\"photo\"; filename=\"photo\""
+                            .to_owned(),
+                        "Content-Type: application/octet-stream".to_owned(),
+                    ],
+                    data: b"PNG".to_vec(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_declared_encoding() {
+        let contents = ParameterContents::Object(IndexMap::from([
+            ("ids".to_owned(), ParameterContents::from(json!([1, 2]))),
+            (
+                "photo".to_owned(),
+                ParameterContents::Bytes(b"PNG".to_vec()),
+            ),
+        ]));
+        let encoding = IndexMap::from([
+            (
+                "ids".to_owned(),
+                PartEncoding {
+                    content_type: Some("application/json".to_owned()),
+                    headers: IndexMap::new(),
+                },
+            ),
+            (
+                "photo".to_owned(),
+                PartEncoding {
+                    content_type: Some("image/png".to_owned()),
+                    headers: IndexMap::from([
+                        ("X-Rate-Limit".to_owned(), "10".to_owned()),
+                        ("Content-Type".to_owned(), "text/plain".to_owned()),
+                    ]),
+                },
+            ),
+        ]);
+        let parts = decode(&encode(&contents, &encoding), BOUNDARY);
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].headers[1], "Content-Type: application/json");
+        assert_eq!(parts[0].data, b"[1,2]");
+        assert_eq!(
+            parts[1].headers[1..],
+            ["Content-Type: image/png", "X-Rate-Limit: 10"]
+        );
+    }
+}
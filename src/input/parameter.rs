@@ -105,6 +105,21 @@ impl ParameterContents {
         matches!(self, ParameterContents::Reference { .. })
     }
 
+    /// Returns whether the `ParameterContents` is a reference or holds one somewhere in
+    /// its fields or elements. Such contents can not be turned into a value.
+    pub fn contains_reference(&self) -> bool {
+        match self {
+            ParameterContents::Reference { .. } => true,
+            ParameterContents::Object(fields) => {
+                fields.values().any(ParameterContents::contains_reference)
+            }
+            ParameterContents::Array(elements) => {
+                elements.iter().any(ParameterContents::contains_reference)
+            }
+            ParameterContents::LeafValue(_) | ParameterContents::Bytes(_) => false,
+        }
+    }
+
     /// Returns the bytes-representation of this `ParameterContents`.
     /// If this is the `bytes` variant, a reference is returned.
     /// If this is the `contents` variant, the contained object is serialized into
@@ -205,10 +220,12 @@ impl ParameterContents {
     }
 
     /// Returns the parameter value for use as a cookie value:
-    /// the Bytes variant is mime-encoded, any other value is formatted to a String.
+    /// the Bytes variant is mime-encoded, strings are used as-is, and any other value is
+    /// formatted to a String.
     pub fn to_cookie_value(&self) -> String {
         match self {
             ParameterContents::Bytes(bytes) => mime_encode_bytes(bytes),
+            ParameterContents::LeafValue(SimpleValue::String(string)) => string.clone(),
             _ => self.to_string(),
         }
     }
@@ -0,0 +1,248 @@
+//! Live statistics of a fuzzing campaign, served over HTTP in the Prometheus text format
+//! so that long campaigns can be scraped and graphed. The coverage monitor updates the
+//! metrics whenever the event manager reports new statistics, and a small server thread
+//! answers `GET /metrics` with their current values.
+
+use std::{
+    io::{BufRead, BufReader, ErrorKind, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+
+/// How often the server checks whether it should shut down while no scrapes arrive
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The statistics of the campaign so far
+#[derive(Debug)]
+pub struct Metrics {
+    start_time: Instant,
+    executions: AtomicU64,
+    crashes: AtomicU64,
+    corpus_size: AtomicU64,
+    lines_covered: AtomicU64,
+    lines_total: AtomicU64,
+    endpoints_covered: AtomicU64,
+    endpoints_total: AtomicU64,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self {
+            start_time: Instant::now(),
+            executions: AtomicU64::new(0),
+            crashes: AtomicU64::new(0),
+            corpus_size: AtomicU64::new(0),
+            lines_covered: AtomicU64::new(0),
+            lines_total: AtomicU64::new(0),
+            endpoints_covered: AtomicU64::new(0),
+            endpoints_total: AtomicU64::new(0),
+        }
+    }
+}
+
+impl Metrics {
+    /// Records the totals of the executions, crashes and corpus entries
+    pub fn update_totals(&self, executions: u64, crashes: u64, corpus_size: u64) {
+        self.executions.store(executions, Ordering::Relaxed);
+        self.crashes.store(crashes, Ordering::Relaxed);
+        self.corpus_size.store(corpus_size, Ordering::Relaxed);
+    }
+
+    /// Records the number of covered lines, out of the total
+    pub fn update_line_coverage(&self, covered: u64, total: u64) {
+        self.lines_covered.store(covered, Ordering::Relaxed);
+        self.lines_total.store(total, Ordering::Relaxed);
+    }
+
+    /// Records the number of covered endpoints, out of the total
+    pub fn update_endpoint_coverage(&self, covered: u64, total: u64) {
+        self.endpoints_covered.store(covered, Ordering::Relaxed);
+        self.endpoints_total.store(total, Ordering::Relaxed);
+    }
+
+    /// The metrics in the Prometheus text exposition format
+    pub fn format(&self) -> String {
+        let executions = self.executions.load(Ordering::Relaxed);
+        let elapsed = self.start_time.elapsed().as_secs_f64();
+        let execs_per_sec = if elapsed > 0.0 {
+            executions as f64 / elapsed
+        } else {
+            0.0
+        };
+        let metrics: [(&str, &str, &str, String); 8] = [
+            (
+                "wuppiefuzz_executions_total",
+                "counter",
+                "Number of request sequences executed",
+                executions.to_string(),
+            ),
+            (
+                "wuppiefuzz_crashes_total",
+                "counter",
+                "Number of crashes found",
+                self.crashes.load(Ordering::Relaxed).to_string(),
+            ),
+            (
+                "wuppiefuzz_execs_per_second",
+                "gauge",
+                "Request sequences executed per second, on average",
+                execs_per_sec.to_string(),
+            ),
+            (
+                "wuppiefuzz_corpus_size",
+                "gauge",
+                "Number of entries in the corpus",
+                self.corpus_size.load(Ordering::Relaxed).to_string(),
+            ),
+            (
+                "wuppiefuzz_lines_covered",
+                "gauge",
+                "Number of lines of the target covered",
+                self.lines_covered.load(Ordering::Relaxed).to_string(),
+            ),
+            (
+                "wuppiefuzz_lines_total",
+                "gauge",
+                "Number of lines of the target",
+                self.lines_total.load(Ordering::Relaxed).to_string(),
+            ),
+            (
+                "wuppiefuzz_endpoints_covered",
+                "gauge",
+                "Number of endpoints of the specification covered",
+                self.endpoints_covered.load(Ordering::Relaxed).to_string(),
+            ),
+            (
+                "wuppiefuzz_endpoints_total",
+                "gauge",
+                "Number of endpoints in the specification",
+                self.endpoints_total.load(Ordering::Relaxed).to_string(),
+            ),
+        ];
+        metrics
+            .iter()
+            .map(|(name, kind, help, value)| {
+                format!("# HELP {name} {help}\n# TYPE {name} {kind}\n{name} {value}\n")
+            })
+            .collect()
+    }
+}
+
+/// Server that exposes the metrics on `/metrics` until it is dropped
+pub struct MetricsServer {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl MetricsServer {
+    /// Starts serving `metrics` on `addr` in a separate thread
+    pub fn start(addr: SocketAddr, metrics: Arc<Metrics>) -> Result<Self> {
+        let listener = TcpListener::bind(addr)
+            .with_context(|| format!("Could not listen for metrics scrapes on {addr}"))?;
+        // Non-blocking, so that the thread notices when it has to stop
+        listener.set_nonblocking(true)?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let stop = stop.clone();
+            std::thread::spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    match listener.accept() {
+                        Ok((stream, _)) => {
+                            if let Err(err) = respond(stream, &metrics) {
+                                log::debug!("Failed to answer metrics scrape: {err}");
+                            }
+                        }
+                        Err(err) if err.kind() == ErrorKind::WouldBlock => {
+                            std::thread::sleep(POLL_INTERVAL)
+                        }
+                        Err(err) => log::warn!("Failed to accept metrics scrape: {err}"),
+                    }
+                }
+            })
+        };
+        log::info!("Serving metrics on http://{addr}/metrics");
+        Ok(Self {
+            stop,
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Drop for MetricsServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            if thread.join().is_err() {
+                log::warn!("The metrics server panicked");
+            }
+        }
+    }
+}
+
+/// Answers a single HTTP request with the metrics, or 404 for any other path
+fn respond(mut stream: TcpStream, metrics: &Metrics) -> std::io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let (status, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => ("200 OK", metrics.format()),
+        _ => ("404 Not Found", String::new()),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Metrics;
+
+    #[test]
+    fn test_format_declares_type_of_every_metric() {
+        let metrics = Metrics::default();
+        metrics.update_totals(120, 2, 7);
+        metrics.update_line_coverage(30, 100);
+        metrics.update_endpoint_coverage(3, 4);
+        let text = metrics.format();
+
+        for (name, kind) in [
+            ("wuppiefuzz_executions_total", "counter"),
+            ("wuppiefuzz_crashes_total", "counter"),
+            ("wuppiefuzz_execs_per_second", "gauge"),
+            ("wuppiefuzz_corpus_size", "gauge"),
+            ("wuppiefuzz_lines_covered", "gauge"),
+            ("wuppiefuzz_lines_total", "gauge"),
+            ("wuppiefuzz_endpoints_covered", "gauge"),
+            ("wuppiefuzz_endpoints_total", "gauge"),
+        ] {
+            let type_line = format!("# TYPE {name} {kind}");
+            assert_eq!(text.lines().filter(|line| *line == type_line).count(), 1);
+            // The sample follows its declaration
+            let sample = text
+                .lines()
+                .skip_while(|line| *line != type_line)
+                .nth(1)
+                .unwrap();
+            let value = sample.strip_prefix(&format!("{name} ")).unwrap();
+            assert!(value.parse::<f64>().is_ok(), "{sample}");
+        }
+        assert!(text.contains("wuppiefuzz_executions_total 120\n"));
+        assert!(text.contains("wuppiefuzz_lines_covered 30\n"));
+        // Every line is a comment or a sample
+        assert!(text.lines().all(|line| line.starts_with("# HELP ")
+            || line.starts_with("# TYPE ")
+            || line.starts_with("wuppiefuzz_")));
+    }
+}
@@ -0,0 +1,222 @@
+//! Minimization of the crashes found in a run. A crash is often a long chain of requests
+//! of which only a few are needed to trigger it. Requests are left out one at a time, and
+//! a removal is kept if the reduced chain still triggers the same problem. Crashes that
+//! are identical after minimization are only kept once.
+//!
+//! Requests that later requests take a parameter from are never left out, as the later
+//! requests would then be sent with a different value.
+
+use std::{fs, path::Path};
+
+use indexmap::IndexMap;
+use libafl::inputs::Input;
+
+use crate::input::{OpenApiInput, ParameterContents};
+
+/// Loads the crashes from the directory the fuzzer writes them to, skipping the hidden
+/// metadata and lock files that are stored next to them.
+pub fn load_crashes(crashes_dir: &Path) -> Result<Vec<(String, OpenApiInput)>, anyhow::Error> {
+    let mut files = fs::read_dir(crashes_dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    files.sort();
+    files
+        .into_iter()
+        .filter(|file| file.is_file())
+        .filter_map(|file| {
+            let name = file.file_name()?.to_string_lossy().into_owned();
+            (!name.starts_with('.')).then_some((name, file))
+        })
+        .map(|(name, file)| Ok((name, OpenApiInput::from_file(&file)?)))
+        .collect()
+}
+
+/// Leaves out the requests of `input` that are not needed for `reproduces` to hold,
+/// trying each request once, from the last to the first.
+pub fn minimize<F>(mut input: OpenApiInput, mut reproduces: F) -> OpenApiInput
+where
+    F: FnMut(&OpenApiInput) -> bool,
+{
+    for index in (0..input.0.len()).rev() {
+        if input.0.len() < 2 {
+            break;
+        }
+        if let Some(reduced) = without_request(&input, index) {
+            if reproduces(&reduced) {
+                input = reduced;
+            }
+        }
+    }
+    input
+}
+
+/// The input without the request at `index`, with the references to later requests
+/// fixed. `None` if another request takes a parameter from the request.
+fn without_request(input: &OpenApiInput, index: usize) -> Option<OpenApiInput> {
+    let mut reduced = input.clone();
+    let mut referenced = false;
+    for (_, param) in reduced.parameter_filter(&|v| v.contains_reference()) {
+        for_each_reference_index(param, &mut |target| referenced |= *target == index);
+    }
+    if referenced {
+        return None;
+    }
+    reduced.0.remove(index);
+    for (_, param) in reduced.parameter_filter(&|v| v.contains_reference()) {
+        for_each_reference_index(param, &mut |target| {
+            if *target > index {
+                *target -= 1
+            }
+        });
+    }
+    Some(reduced)
+}
+
+/// Calls `f` with the request index of every reference in `contents`, including the
+/// references nested in the fields of objects and the elements of arrays.
+fn for_each_reference_index<F>(contents: &mut ParameterContents, f: &mut F)
+where
+    F: FnMut(&mut usize),
+{
+    match contents {
+        ParameterContents::Reference { request_index, .. } => f(request_index),
+        ParameterContents::Object(fields) => {
+            for field in fields.values_mut() {
+                for_each_reference_index(field, f);
+            }
+        }
+        ParameterContents::Array(elements) => {
+            for element in elements {
+                for_each_reference_index(element, f);
+            }
+        }
+        ParameterContents::LeafValue(_) | ParameterContents::Bytes(_) => (),
+    }
+}
+
+/// Keeps one input of each group of inputs with the same name, which are the same
+/// requests with the same parameters, together with that name.
+pub fn dedup_by_name(inputs: Vec<OpenApiInput>) -> Vec<(String, OpenApiInput)> {
+    let mut unique = IndexMap::new();
+    for input in inputs {
+        unique.entry(input.generate_name(None)).or_insert(input);
+    }
+    unique.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+    use libafl::inputs::Input;
+
+    use super::{dedup_by_name, minimize};
+    use crate::input::{
+        parameter::ParameterKind, Body, Method, OpenApiInput, OpenApiRequest, ParameterContents,
+    };
+
+    fn get(path: &str) -> OpenApiRequest {
+        OpenApiRequest {
+            method: Method::Get,
+            path: path.to_owned(),
+            body: Body::Empty,
+            parameters: IndexMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_dedup_by_name_keeps_one_of_each_name() {
+        let crash = OpenApiInput(vec![get("/crash")]);
+        let other = OpenApiInput(vec![get("/pets"), get("/crash")]);
+        let inputs = vec![crash.clone(), other.clone(), crash.clone(), other.clone()];
+        assert_ne!(crash.generate_name(None), other.generate_name(None));
+
+        let unique = dedup_by_name(inputs);
+        let names: Vec<&str> = unique.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(
+            names,
+            [crash.generate_name(None), other.generate_name(None)]
+        );
+        assert_eq!(unique[0].1 .0.len(), 1);
+        assert_eq!(unique[1].1 .0.len(), 2);
+    }
+
+    #[test]
+    fn test_minimize_keeps_referenced_requests() {
+        let mut referencing = get("/pets/{petId}");
+        referencing.parameters.insert(
+            ("petId".to_owned(), ParameterKind::Path),
+            ParameterContents::Reference {
+                request_index: 1,
+                parameter_name: "petId".to_owned(),
+            },
+        );
+        let input = OpenApiInput(vec![
+            get("/users"),
+            get("/pets"),
+            get("/owners"),
+            referencing,
+            get("/crash"),
+        ]);
+
+        // The crash needs the request for a pet, which needs the pet it refers to
+        let minimized = minimize(input, |reduced| {
+            ["/pets/{petId}", "/crash"]
+                .iter()
+                .all(|path| reduced.0.iter().any(|request| request.path == *path))
+        });
+        let paths: Vec<&str> = minimized.0.iter().map(|r| r.path.as_str()).collect();
+        assert_eq!(paths, ["/pets", "/pets/{petId}", "/crash"]);
+        assert_eq!(
+            minimized.0[1].parameters[&("petId".to_owned(), ParameterKind::Path)]
+                .clone()
+                .reference_index()
+                .copied(),
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn test_minimize_keeps_requests_referenced_from_nested_body_fields() {
+        let reference = |request_index| ParameterContents::Reference {
+            request_index,
+            parameter_name: "id".to_owned(),
+        };
+        let mut adoption = get("/adoptions");
+        adoption.method = Method::Post;
+        adoption.body = Body::ApplicationJson(ParameterContents::Object(IndexMap::from([
+            (
+                "pet".to_owned(),
+                ParameterContents::Object(IndexMap::from([("id".to_owned(), reference(1))])),
+            ),
+            (
+                "owners".to_owned(),
+                ParameterContents::Array(vec![reference(2)]),
+            ),
+        ])));
+        let input = OpenApiInput(vec![
+            get("/users"),
+            get("/pets"),
+            get("/owners"),
+            adoption,
+            get("/crash"),
+        ]);
+
+        let minimized = minimize(input, |reduced| {
+            ["/adoptions", "/crash"]
+                .iter()
+                .all(|path| reduced.0.iter().any(|request| request.path == *path))
+        });
+        let paths: Vec<&str> = minimized.0.iter().map(|r| r.path.as_str()).collect();
+        assert_eq!(paths, ["/pets", "/owners", "/adoptions", "/crash"]);
+        let Body::ApplicationJson(ParameterContents::Object(fields)) = &minimized.0[2].body else {
+            panic!("The body is no longer an object");
+        };
+        let (ParameterContents::Object(pet), ParameterContents::Array(owners)) =
+            (&fields["pet"], &fields["owners"])
+        else {
+            panic!("The nested fields changed their type");
+        };
+        assert_eq!(pet["id"].clone().reference_index().copied(), Some(0));
+        assert_eq!(owners[0].clone().reference_index().copied(), Some(1));
+    }
+}
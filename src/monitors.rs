@@ -2,7 +2,7 @@
 //! while fuzzing, and tracks the time consumed.
 
 use core::{time, time::Duration};
-use std::{borrow::Cow, fmt};
+use std::{borrow::Cow, fmt, sync::Arc};
 
 use libafl::{
     alloc::fmt::Debug,
@@ -11,7 +11,10 @@ use libafl::{
 use libafl_bolts::{current_time, format_duration_hms, ClientId};
 use serde_json::json;
 
-use crate::configuration::{Configuration, OutputFormat};
+use crate::{
+    configuration::{Configuration, OutputFormat},
+    metrics::Metrics,
+};
 
 /// Tracking monitor during fuzzing.
 #[derive(Clone)]
@@ -24,6 +27,8 @@ where
     client_stats: Vec<ClientStats>,
     execs_per_sec: String,
     last_execs: u64,
+    /// Metrics to keep up to date for scraping, if served
+    metrics: Option<Arc<Metrics>>,
 }
 
 impl<F> Debug for CoverageMonitor<F>
@@ -63,6 +68,7 @@ where
     }
 
     fn display(&mut self, event_msg: &str, _sender_id: ClientId) {
+        self.update_metrics();
         let config = Configuration::must_get();
         let total_time = current_time() - self.start_time;
         let output_string = match config.output_format {
@@ -132,6 +138,7 @@ where
             client_stats: vec![],
             execs_per_sec: "NaN".to_string(),
             last_execs: 0,
+            metrics: None,
         }
     }
 
@@ -143,6 +150,37 @@ where
             client_stats: vec![],
             execs_per_sec: "NaN".to_string(),
             last_execs: 0,
+            metrics: None,
+        }
+    }
+
+    /// Makes the monitor keep `metrics` up to date with the statistics it receives.
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    fn update_metrics(&mut self) {
+        let Some(metrics) = self.metrics.clone() else {
+            return;
+        };
+        metrics.update_totals(
+            self.total_execs(),
+            self.objective_size(),
+            self.corpus_size(),
+        );
+        let Some(client_stats) = self.client_stats().first() else {
+            return;
+        };
+        let ratio = |name| match client_stats.get_user_stats(name).map(UserStats::value) {
+            Some(UserStatsValue::Ratio(covered, total)) => Some((*covered, *total)),
+            _ => None,
+        };
+        if let Some((covered, total)) = ratio("wuppiefuzz_code_coverage") {
+            metrics.update_line_coverage(covered, total);
+        }
+        if let Some((covered, total)) = ratio("wuppiefuzz_endpoint_coverage") {
+            metrics.update_endpoint_coverage(covered, total);
         }
     }
 
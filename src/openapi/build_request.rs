@@ -1,25 +1,37 @@
 use cookie::Cookie;
-use openapiv3::OpenAPI;
+use openapiv3::{OpenAPI, Server};
 use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
 
-use crate::input::{parameter::ParameterKind, OpenApiRequest};
+use super::{examples::UserExamples, find_operation, multipart_encoding, response_media_types};
+use crate::{
+    authentication::Authentication,
+    input::{form::FormArrayStyle, multipart, parameter::ParameterKind, Body, OpenApiRequest},
+};
 
 /// Build a request to a path from the API using the input values.
 pub fn build_request_from_input(
     client: &reqwest::blocking::Client,
     cookie_store: &std::sync::Arc<reqwest_cookie_store::CookieStoreMutex>,
+    authentication: &Authentication,
     api: &OpenAPI,
+    user_examples: &UserExamples,
+    array_style: FormArrayStyle,
     input: &OpenApiRequest,
 ) -> Option<reqwest::blocking::RequestBuilder> {
     let server = &api
         .servers.first()
         .expect("API specification contains no usable servers. If you did specify any, consult logs for attempts to connect to them.");
-    let mut path = server.url.to_owned() + &input.path;
+    let mut path = input.path.clone();
+    let operation = find_operation(api, &input.path, input.method);
     let mut header_params = HeaderMap::new();
-    header_params.insert(
-        reqwest::header::ACCEPT,
-        HeaderValue::from_static("application/json"),
-    );
+    // Ask for the representations the specification declares, so that the response can
+    // be validated against it
+    let accept = operation
+        .map(|operation| response_media_types(api, operation))
+        .filter(|media_types| !media_types.is_empty())
+        .and_then(|media_types| HeaderValue::from_str(&media_types.join(", ")).ok())
+        .unwrap_or(HeaderValue::from_static("application/json"));
+    header_params.insert(reqwest::header::ACCEPT, accept);
     let mut query_params = Vec::new();
     let mut cookie_params = Vec::new();
     for ((name, kind), value) in input // voor elke parameter in openapirequest
@@ -27,7 +39,7 @@ pub fn build_request_from_input(
         .iter()
     {
         match kind {
-            ParameterKind::Query => query_params.push((name, value.to_url_encoding())),
+            ParameterKind::Query => query_params.push((name.as_str(), value.to_url_encoding())),
             ParameterKind::Header => {
                 if let Ok(header_name) = HeaderName::from_bytes(name.as_bytes()) {
                     header_params.insert(header_name, value.to_header_value());
@@ -47,9 +59,19 @@ pub fn build_request_from_input(
         }
     }
 
+    if let Some((name, value)) = authentication.query_parameter() {
+        query_params.push((name, value.into()));
+    }
+
     // Deserialize the path into a Url
+    let path = join_url(&server_url(server), &path);
     let path_with_query_params =
         reqwest::Url::parse_with_params(&path, query_params).expect("Invalid URL");
+    if let Some(value) =
+        authentication.request_header(input.method.as_str(), &path_with_query_params)
+    {
+        header_params.insert(reqwest::header::AUTHORIZATION, value);
+    }
 
     // Add any collected cookie parameters to the cookie store
     {
@@ -63,10 +85,633 @@ pub fn build_request_from_input(
     let mut builder = client
         .request(input.method.into(), path_with_query_params)
         .headers(header_params);
-    if let Some(contents) = input.reqwest_body() {
-        builder = builder
-            .body(contents)
-            .header(reqwest::header::CONTENT_TYPE, input.body_content_type());
+    let body = match &input.body {
+        // Multipart bodies can declare the content types of their parts in the specification
+        Body::Multipart(contents) => {
+            let encoding = operation
+                .map(|operation| multipart_encoding(api, user_examples, operation))
+                .unwrap_or_default();
+            Some(reqwest::blocking::Body::from(multipart::encode(
+                contents, &encoding,
+            )))
+        }
+        _ => input.reqwest_body(array_style),
+    };
+    if let Some(contents) = body {
+        builder = builder.body(contents).header(
+            reqwest::header::CONTENT_TYPE,
+            input.body_content_type().as_ref(),
+        );
     }
     Some(builder)
 }
+
+/// Makes the request stream its body, so that it is sent with `Transfer-Encoding: chunked`
+/// instead of a `Content-Length`. This probes how the server handles chunked framing.
+/// A streamed body can not be cloned, so the request can not be sent again, e.g. to
+/// answer a Digest authentication challenge.
+pub fn stream_body_chunked(request: &mut reqwest::blocking::Request) {
+    let Some(body) = request.body_mut() else {
+        return;
+    };
+    if let Some(bytes) = body.as_bytes() {
+        *body = reqwest::blocking::Body::new(std::io::Cursor::new(bytes.to_vec()));
+    }
+}
+
+/// Limit on the total size of the headers of a request, to stay below the limits of the
+/// server and avoid 431 Request Header Fields Too Large caused by the fuzzer itself
+/// rather than the program under test.
+#[derive(Debug, Clone, Copy)]
+pub struct HeaderBudget {
+    max_bytes: usize,
+    /// Bytes taken by the headers that the client adds to every request, such as the
+    /// static and authentication headers
+    reserved: usize,
+}
+
+impl HeaderBudget {
+    /// A budget of `max_bytes` bytes, part of which is taken by `default_headers`
+    pub fn new(max_bytes: usize, default_headers: &HeaderMap) -> Self {
+        Self {
+            max_bytes,
+            reserved: headers_size(default_headers),
+        }
+    }
+
+    /// Removes fuzzed headers from the request until all its headers fit in the budget.
+    /// Only header parameters of the input that the operation does not require are
+    /// removed, largest first; headers for authentication, content negotiation and
+    /// required parameters are kept. Returns the names of the removed headers.
+    pub fn enforce(
+        &self,
+        api: &OpenAPI,
+        input: &OpenApiRequest,
+        request: &mut reqwest::blocking::Request,
+    ) -> Vec<HeaderName> {
+        let mut size = self.reserved + headers_size(request.headers());
+        if size <= self.max_bytes {
+            return vec![];
+        }
+
+        let required: Vec<&str> = find_operation(api, &input.path, input.method)
+            .into_iter()
+            .flat_map(|operation| &operation.parameters)
+            .filter_map(|parameter| parameter.resolve(api).ok())
+            .filter(|parameter| {
+                ParameterKind::from(*parameter) == ParameterKind::Header && parameter.data.required
+            })
+            .map(|parameter| parameter.data.name.as_str())
+            .collect();
+        let mut removable: Vec<(HeaderName, usize)> = input
+            .parameters
+            .keys()
+            .filter(|(name, kind)| {
+                *kind == ParameterKind::Header
+                    && !required.iter().any(|r| r.eq_ignore_ascii_case(name))
+            })
+            .filter_map(|(name, _)| HeaderName::from_bytes(name.as_bytes()).ok())
+            .filter(|name| *name != reqwest::header::AUTHORIZATION)
+            .filter_map(|name| {
+                let value = request.headers().get(&name)?;
+                let header_size = header_size(&name, value);
+                Some((name, header_size))
+            })
+            .collect();
+        removable.sort_by_key(|(_, header_size)| std::cmp::Reverse(*header_size));
+
+        let mut removed = vec![];
+        for (name, header_size) in removable {
+            if size <= self.max_bytes {
+                break;
+            }
+            request.headers_mut().remove(&name);
+            size -= header_size;
+            removed.push(name);
+        }
+        if !removed.is_empty() {
+            log::info!(
+                "Removed headers {removed:?} from {} {} to stay within the header budget of {} bytes",
+                input.method,
+                input.path,
+                self.max_bytes
+            );
+        }
+        if size > self.max_bytes {
+            log::debug!(
+                "Headers of {} {} take {size} bytes, more than the budget of {} bytes",
+                input.method,
+                input.path,
+                self.max_bytes
+            );
+        }
+        removed
+    }
+}
+
+/// Size of a header as sent: its name and value, the `: ` between them and the CRLF
+/// after them
+fn header_size(name: &HeaderName, value: &HeaderValue) -> usize {
+    name.as_str().len() + value.len() + 4
+}
+
+/// Total size of the headers as sent
+fn headers_size(headers: &HeaderMap) -> usize {
+    headers
+        .iter()
+        .map(|(name, value)| header_size(name, value))
+        .sum()
+}
+
+/// The URL of the server, with its variables replaced by their default values.
+pub fn server_url(server: &Server) -> String {
+    let mut url = server.url.clone();
+    for (name, variable) in server.variables.iter().flatten() {
+        url = url.replace(&format!("{{{name}}}"), &variable.default);
+    }
+    url
+}
+
+/// Appends the path of an operation to the URL of the server, which may have a path of
+/// its own, with a single slash between them.
+fn join_url(server_url: &str, path: &str) -> String {
+    format!(
+        "{}/{}",
+        server_url.trim_end_matches('/'),
+        path.trim_start_matches('/')
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        sync::Arc,
+    };
+
+    use indexmap::IndexMap;
+    use openapiv3::{OpenAPI, Server, ServerVariable};
+    use reqwest_cookie_store::CookieStoreMutex;
+    use serde_json::json;
+
+    use super::{build_request_from_input, stream_body_chunked, HeaderBudget};
+    use crate::{
+        authentication::{
+            api_key::{ApiKeyLocation, ApiKeyLogin},
+            Authentication,
+        },
+        input::{
+            form::FormArrayStyle, parameter::ParameterKind, Body, Method, OpenApiRequest,
+            ParameterContents,
+        },
+        openapi::{examples::UserExamples, find_operation},
+    };
+
+    #[test]
+    fn test_fuzzed_headers_are_trimmed_to_budget() {
+        let mut api: OpenAPI = serde_json::from_value(json!({
+            "openapi": "3.0.0",
+            "info": {"title": "pets", "version": "1"},
+            "paths": {
+                "/pets": {
+                    "get": {
+                        "parameters": [
+                            {"name": "X-Tenant", "in": "header", "required": true,
+                             "schema": {"type": "string"}},
+                            {"name": "X-Trace", "in": "header", "schema": {"type": "string"}},
+                            {"name": "X-Flag", "in": "header", "schema": {"type": "string"}}
+                        ],
+                        "responses": {}
+                    }
+                }
+            }
+        }))
+        .unwrap();
+        api.servers = vec![Server {
+            url: "http://localhost:8080".to_owned(),
+            ..Default::default()
+        }];
+        let header = |name: &str, value: String| {
+            (
+                (name.to_owned(), ParameterKind::Header),
+                ParameterContents::from(value),
+            )
+        };
+        let request = OpenApiRequest {
+            method: Method::Get,
+            path: "/pets".to_owned(),
+            body: Body::Empty,
+            parameters: IndexMap::from([
+                header("Authorization", "Bearer token".to_owned()),
+                header("X-Tenant", "t".repeat(500)),
+                header("X-Trace", "x".repeat(2000)),
+                header("X-Flag", "on".to_owned()),
+            ]),
+        };
+        let client = reqwest::blocking::Client::new();
+        let cookie_store = Arc::new(CookieStoreMutex::default());
+        let build = || {
+            build_request_from_input(
+                &client,
+                &cookie_store,
+                &Authentication::None,
+                &api,
+                &UserExamples::default(),
+                FormArrayStyle::default(),
+                &request,
+            )
+            .unwrap()
+            .build()
+            .unwrap()
+        };
+        let mut default_headers = reqwest::header::HeaderMap::new();
+        default_headers.insert(
+            reqwest::header::USER_AGENT,
+            reqwest::header::HeaderValue::from_static("wuppiefuzz"),
+        );
+
+        // Within the budget, nothing is removed
+        let mut built = build();
+        assert!(HeaderBudget::new(8192, &default_headers)
+            .enforce(&api, &request, &mut built)
+            .is_empty());
+
+        // Removing the largest optional header suffices
+        let mut built = build();
+        let removed = HeaderBudget::new(1024, &default_headers).enforce(&api, &request, &mut built);
+        assert_eq!(removed, vec!["x-trace"]);
+        for kept in ["authorization", "x-tenant", "x-flag", "accept"] {
+            assert!(built.headers().contains_key(kept), "{kept} was removed");
+        }
+
+        // Authentication and required headers are kept even if over budget
+        let mut built = build();
+        let removed = HeaderBudget::new(100, &default_headers).enforce(&api, &request, &mut built);
+        assert_eq!(removed, vec!["x-trace", "x-flag"]);
+        assert!(built.headers().contains_key("authorization"));
+        assert!(built.headers().contains_key("x-tenant"));
+    }
+
+    #[test]
+    fn test_empty_query_parameter_is_present() {
+        let api = OpenAPI {
+            servers: vec![Server {
+                url: "http://localhost:8080".to_owned(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let request = OpenApiRequest {
+            method: Method::Get,
+            path: "/pets".to_owned(),
+            body: Body::Empty,
+            parameters: IndexMap::from([(
+                ("x".to_owned(), ParameterKind::Query),
+                ParameterContents::from(String::new()),
+            )]),
+        };
+
+        let client = reqwest::blocking::Client::new();
+        let cookie_store = Arc::new(CookieStoreMutex::default());
+        let built = build_request_from_input(
+            &client,
+            &cookie_store,
+            &Authentication::None,
+            &api,
+            &UserExamples::default(),
+            FormArrayStyle::default(),
+            &request,
+        )
+        .unwrap()
+        .build()
+        .unwrap();
+        assert_eq!(built.url().query(), Some("x="));
+
+        let mut omitted = request.clone();
+        omitted.parameters.clear();
+        let built = build_request_from_input(
+            &client,
+            &cookie_store,
+            &Authentication::None,
+            &api,
+            &UserExamples::default(),
+            FormArrayStyle::default(),
+            &omitted,
+        )
+        .unwrap()
+        .build()
+        .unwrap();
+        assert!(!built.url().as_str().contains("x="));
+    }
+
+    #[test]
+    fn test_api_key_is_added_to_query() {
+        let api = OpenAPI {
+            servers: vec![Server {
+                url: "http://localhost:8080".to_owned(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let request = OpenApiRequest {
+            method: Method::Get,
+            path: "/pets".to_owned(),
+            body: Body::Empty,
+            parameters: IndexMap::new(),
+        };
+        let authentication = Authentication::ApiKey(ApiKeyLogin {
+            name: "api_key".to_owned(),
+            location: ApiKeyLocation::Query,
+            value: "s3cr3t".to_owned(),
+        });
+
+        let client = reqwest::blocking::Client::new();
+        let cookie_store = Arc::new(CookieStoreMutex::default());
+        let built = build_request_from_input(
+            &client,
+            &cookie_store,
+            &authentication,
+            &api,
+            &UserExamples::default(),
+            FormArrayStyle::default(),
+            &request,
+        )
+        .unwrap()
+        .build()
+        .unwrap();
+        assert_eq!(built.url().query(), Some("api_key=s3cr3t"));
+    }
+
+    #[test]
+    fn test_server_path_is_prepended() {
+        let request = OpenApiRequest {
+            method: Method::Get,
+            path: "/pets/{id}".to_owned(),
+            body: Body::Empty,
+            parameters: IndexMap::from([(
+                ("id".to_owned(), ParameterKind::Path),
+                ParameterContents::from(json!(7)),
+            )]),
+        };
+        let client = reqwest::blocking::Client::new();
+        let cookie_store = Arc::new(CookieStoreMutex::default());
+
+        for server in [
+            "https://api.example.com/v2",
+            "https://api.example.com/v2/",
+            "https://api.example.com/{version}",
+        ] {
+            let api = OpenAPI {
+                servers: vec![Server {
+                    url: server.to_owned(),
+                    variables: Some(IndexMap::from([(
+                        "version".to_owned(),
+                        ServerVariable {
+                            default: "v2".to_owned(),
+                            ..Default::default()
+                        },
+                    )])),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            };
+            let built = build_request_from_input(
+                &client,
+                &cookie_store,
+                &Authentication::None,
+                &api,
+                &UserExamples::default(),
+                FormArrayStyle::default(),
+                &request,
+            )
+            .unwrap()
+            .build()
+            .unwrap();
+            assert_eq!(built.url().host_str(), Some("api.example.com"));
+            assert_eq!(built.url().path(), "/v2/pets/7");
+        }
+    }
+
+    #[test]
+    fn test_multipart_parts_use_declared_encoding() {
+        let mut api: OpenAPI = serde_json::from_value(json!({
+            "openapi": "3.0.0",
+            "info": {"title": "pets", "version": "1"},
+            "paths": {
+                "/pets": {
+                    "post": {
+                        "requestBody": {
+                            "content": {
+                                "multipart/form-data": {
+                                    "schema": {
+                                        "type": "object",
+                                        "properties": {
+                                            "meta": {"type": "object"},
+                                            "photo": {"type": "string", "format": "binary"}
+                                        }
+                                    },
+                                    "encoding": {
+                                        "meta": {"contentType": "application/json"},
+                                        "photo": {
+                                            "contentType": "image/png",
+                                            "headers": {
+                                                "X-Rate-Limit": {
+                                                    "schema": {"type": "integer", "example": 10}
+                                                },
+                                                "X-Origin": {"$ref": "#/components/headers/Origin"}
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        },
+                        "responses": {}
+                    }
+                }
+            },
+            "components": {
+                "headers": {
+                    "Origin": {"schema": {"type": "string"}, "example": "camera"}
+                }
+            }
+        }))
+        .unwrap();
+        api.servers = vec![Server {
+            url: "http://localhost:8080".to_owned(),
+            ..Default::default()
+        }];
+        let operation = find_operation(&api, "/pets", Method::Post).unwrap();
+        let body = Body::build(
+            &api,
+            operation,
+            Some(ParameterContents::from(
+                json!({"meta": {"name": "Wuppie"}, "photo": "PNG"}),
+            )),
+        );
+        let request = OpenApiRequest {
+            method: Method::Post,
+            path: "/pets".to_owned(),
+            body,
+            parameters: IndexMap::new(),
+        };
+
+        let client = reqwest::blocking::Client::new();
+        let cookie_store = Arc::new(CookieStoreMutex::default());
+        let built = build_request_from_input(
+            &client,
+            &cookie_store,
+            &Authentication::None,
+            &api,
+            &UserExamples::default(),
+            FormArrayStyle::default(),
+            &request,
+        )
+        .unwrap()
+        .build()
+        .unwrap();
+        assert_eq!(
+            built.headers()[reqwest::header::CONTENT_TYPE],
+            request.body_content_type().as_ref()
+        );
+        let body = String::from_utf8_lossy(built.body().unwrap().as_bytes().unwrap()).into_owned();
+        assert!(body.contains(
+            "name=\"meta\"\r\nContent-Type: application/json\r\n\r\n{\"name\":\"Wuppie\"}\r\n"
+        ));
+        assert!(body.contains(
+            "name=\"photo\"; filename=\"photo\"\r\nContent-Type: image/png\r\nX-Origin: camera\r\nX-Rate-Limit: 10\r\n\r\nPNG\r\n"
+        ));
+    }
+
+    #[test]
+    fn test_accept_header_lists_response_media_types() {
+        let mut api: OpenAPI = serde_json::from_value(json!({
+            "openapi": "3.0.0",
+            "info": {"title": "pets", "version": "1"},
+            "paths": {
+                "/pets": {
+                    "get": {
+                        "responses": {
+                            "200": {
+                                "description": "The pets",
+                                "content": {"application/json": {"schema": {"type": "array"}}}
+                            },
+                            "404": {
+                                "description": "Not found",
+                                "content": {"application/json": {"schema": {"type": "object"}}}
+                            }
+                        }
+                    }
+                },
+                "/pets/export": {
+                    "get": {
+                        "responses": {
+                            "200": {
+                                "description": "The pets",
+                                "content": {"application/xml": {}, "text/csv": {}}
+                            },
+                            "default": {
+                                "description": "An error",
+                                "content": {"application/problem+json": {}}
+                            }
+                        }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+        api.servers = vec![Server {
+            url: "http://localhost:8080".to_owned(),
+            ..Default::default()
+        }];
+
+        let client = reqwest::blocking::Client::new();
+        let cookie_store = Arc::new(CookieStoreMutex::default());
+        let accept = |path: &str| {
+            let request = OpenApiRequest {
+                method: Method::Get,
+                path: path.to_owned(),
+                body: Body::Empty,
+                parameters: IndexMap::new(),
+            };
+            build_request_from_input(
+                &client,
+                &cookie_store,
+                &Authentication::None,
+                &api,
+                &UserExamples::default(),
+                FormArrayStyle::default(),
+                &request,
+            )
+            .unwrap()
+            .build()
+            .unwrap()
+            .headers()[reqwest::header::ACCEPT]
+                .clone()
+        };
+        assert_eq!(accept("/pets"), "application/json");
+        assert_eq!(
+            accept("/pets/export"),
+            "application/xml, text/csv, application/problem+json"
+        );
+    }
+
+    #[test]
+    fn test_chunked_body_is_streamed() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let api = OpenAPI {
+            servers: vec![Server {
+                url: format!("http://{}", listener.local_addr().unwrap()),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let request = OpenApiRequest {
+            method: Method::Post,
+            path: "/pets".to_owned(),
+            body: Body::ApplicationJson(ParameterContents::from(json!({"name": "Rex"}))),
+            parameters: IndexMap::new(),
+        };
+        let client = reqwest::blocking::Client::new();
+        let cookie_store = Arc::new(CookieStoreMutex::default());
+        let mut built = build_request_from_input(
+            &client,
+            &cookie_store,
+            &Authentication::None,
+            &api,
+            &UserExamples::default(),
+            FormArrayStyle::default(),
+            &request,
+        )
+        .unwrap()
+        .build()
+        .unwrap();
+        assert!(built.body().unwrap().as_bytes().is_some());
+
+        stream_body_chunked(&mut built);
+        // A streamed body has no bytes in memory, and hence no known length
+        assert!(built.body().unwrap().as_bytes().is_none());
+
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut received = Vec::new();
+            let mut buffer = [0; 1024];
+            // The last chunk has size zero
+            while !received.ends_with(b"0\r\n\r\n") {
+                let read = stream.read(&mut buffer).unwrap();
+                if read == 0 {
+                    break;
+                }
+                received.extend_from_slice(&buffer[..read]);
+            }
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                .unwrap();
+            String::from_utf8(received).unwrap().to_lowercase()
+        });
+        client.execute(built).unwrap();
+        let received = server.join().unwrap();
+        assert!(received.contains("transfer-encoding: chunked"));
+        assert!(!received.contains("content-length"));
+        assert!(received.contains("{\"name\":\"rex\"}"));
+    }
+}
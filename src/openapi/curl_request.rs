@@ -1,8 +1,14 @@
+use std::borrow::Cow;
+
 use base64::{display::Base64Display, engine::general_purpose::STANDARD};
+use reqwest_cookie_store::CookieStoreMutex;
 
+/// A request as it is sent, with the authentication and, if given, the cookie store it
+/// is sent with. Its `Display` gives a curl command that sends the same request.
 pub struct CurlRequest<'a>(
     pub &'a reqwest::blocking::Request,
     pub &'a crate::authentication::Authentication,
+    pub Option<&'a CookieStoreMutex>,
 );
 
 impl CurlRequest<'_> {
@@ -16,28 +22,87 @@ impl CurlRequest<'_> {
     pub fn body(&self) -> Option<&[u8]> {
         self.0.body().and_then(reqwest::blocking::Body::as_bytes)
     }
+
+    /// Gives the HTTP method of this request
+    pub fn method(&self) -> &str {
+        self.0.method().as_str()
+    }
+
+    /// Gives the headers of this request, including the authorization header, with
+    /// values that are not valid text decoded lossily
+    pub fn headers(&self) -> Vec<(String, String)> {
+        let authentication = self
+            .authentication_header()
+            .map(|(name, value)| (name.into_owned(), value.into_owned()));
+        authentication
+            .into_iter()
+            .chain(self.0.headers().iter().map(|(key, value)| {
+                (
+                    key.to_string(),
+                    String::from_utf8_lossy(value.as_bytes()).into_owned(),
+                )
+            }))
+            .collect()
+    }
+
+    /// The header carrying the authentication, unless the request already has it
+    fn authentication_header(&self) -> Option<(Cow<'_, str>, Cow<'_, str>)> {
+        self.1
+            .last_named_header()
+            .filter(|(name, _)| !self.0.headers().contains_key(name.as_ref()))
+    }
+
+    /// The cookies from the cookie store that are sent with this request, as the value
+    /// of a Cookie header. Like reqwest, the store is not used if the request already
+    /// has a Cookie header.
+    fn cookies(&self) -> Option<String> {
+        if self.0.headers().contains_key(reqwest::header::COOKIE) {
+            return None;
+        }
+        let cookie_store = self.2?.lock().unwrap();
+        let cookies = cookie_store
+            .get_request_values(self.0.url())
+            .map(|(name, value)| format!("{name}={value}"))
+            .collect::<Vec<_>>()
+            .join("; ");
+        (!cookies.is_empty()).then_some(cookies)
+    }
+}
+
+/// Quotes the text as a single shell word, so it is passed to curl unchanged
+fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
 }
 
 impl std::fmt::Display for CurlRequest<'_> {
     fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
-        if let Some(body) = self.0.body() {
-            let wrapper = Base64Display::new(
-                body.as_bytes()
-                    .expect("stream body not expected from static request"),
-                &STANDARD,
-            );
-            writeln!(fmt, "echo {} | \\", wrapper)?;
+        let body = self.0.body().map(|body| {
+            body.as_bytes()
+                .expect("stream body not expected from static request")
+        });
+        // Text bodies are passed as an argument, others are decoded from base64 first
+        let text_body = body.and_then(|body| std::str::from_utf8(body).ok());
+        if let (Some(body), None) = (body, text_body) {
+            writeln!(fmt, "echo {} | \\", Base64Display::new(body, &STANDARD))?;
             writeln!(fmt, "base64 --decode | \\")?;
         }
 
-        write!(fmt, "curl {}", self.0.url())?;
+        write!(fmt, "curl {}", shell_quote(self.0.url().as_str()))?;
         write!(fmt, " \\\n    --request {}", self.0.method())?;
-        if let Some(token) = self.1.last_header() {
-            write!(fmt, " \\\n    --header 'Authorization: {token}'")?;
+        if let Some((name, value)) = self.authentication_header() {
+            write!(
+                fmt,
+                " \\\n    --header {}",
+                shell_quote(&format!("{name}: {value}"))
+            )?;
         }
         for (key, value) in self.0.headers() {
             if let Ok(text) = value.to_str() {
-                write!(fmt, " \\\n    --header '{}: {}'", key, text)?
+                write!(
+                    fmt,
+                    " \\\n    --header {}",
+                    shell_quote(&format!("{key}: {text}"))
+                )?
             } else {
                 write!(
                     fmt,
@@ -47,9 +112,91 @@ impl std::fmt::Display for CurlRequest<'_> {
                 )?
             }
         }
-        if self.0.body().is_some() {
-            write!(fmt, " \\\n    --data @-")?;
+        if let Some(cookies) = self.cookies() {
+            write!(fmt, " \\\n    --cookie {}", shell_quote(&cookies))?;
+        }
+        match (body, text_body) {
+            (_, Some(text)) => write!(fmt, " \\\n    --data-raw {}", shell_quote(text))?,
+            (Some(_), None) => write!(fmt, " \\\n    --data-binary @-")?,
+            (None, None) => (),
         }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use indexmap::IndexMap;
+    use openapiv3::{OpenAPI, Server};
+    use reqwest_cookie_store::CookieStoreMutex;
+    use serde_json::json;
+
+    use super::CurlRequest;
+    use crate::{
+        authentication::Authentication,
+        input::{
+            form::FormArrayStyle, parameter::ParameterKind, Body, Method, OpenApiRequest,
+            ParameterContents,
+        },
+        openapi::{build_request::build_request_from_input, examples::UserExamples},
+    };
+
+    #[test]
+    fn test_post_with_json_body_is_complete_command() {
+        let mut api: OpenAPI = serde_json::from_value(json!({
+            "openapi": "3.0.0",
+            "info": {"title": "pets", "version": "1"},
+            "paths": {}
+        }))
+        .unwrap();
+        api.servers = vec![Server {
+            url: "http://localhost:8080".to_owned(),
+            ..Default::default()
+        }];
+        let request = OpenApiRequest {
+            method: Method::Post,
+            path: "/pets".to_owned(),
+            body: Body::ApplicationJson(ParameterContents::from(json!({"name": "Rex's"}))),
+            parameters: IndexMap::from([
+                (
+                    ("owner".to_owned(), ParameterKind::Query),
+                    ParameterContents::from("rex".to_owned()),
+                ),
+                (
+                    ("limit".to_owned(), ParameterKind::Query),
+                    ParameterContents::from(json!(1)),
+                ),
+                (
+                    ("cartId".to_owned(), ParameterKind::Cookie),
+                    ParameterContents::from("c4rt".to_owned()),
+                ),
+            ]),
+        };
+        let client = reqwest::blocking::Client::new();
+        let cookie_store = Arc::new(CookieStoreMutex::default());
+        let built = build_request_from_input(
+            &client,
+            &cookie_store,
+            &Authentication::None,
+            &api,
+            &UserExamples::default(),
+            FormArrayStyle::default(),
+            &request,
+        )
+        .unwrap()
+        .build()
+        .unwrap();
+
+        assert_eq!(
+            CurlRequest(&built, &Authentication::None, Some(&cookie_store)).to_string(),
+            r#"curl 'http://localhost:8080/pets?owner=rex&limit=1' \
+    --request POST \
+    --header 'accept: application/json' \
+    --header 'content-type: application/json' \
+    --cookie 'cartId=c4rt' \
+    --data-raw '{"name":"Rex'\''s"}'"#
+        );
+    }
+}
@@ -0,0 +1,126 @@
+//! Support for the JSON Schema `dependentRequired` keyword, which states that if a field
+//! is present in an object, some other fields are required as well.
+//!
+//! The OpenAPI parser drops keywords it does not know, except for extensions. The
+//! keyword is therefore renamed to the `x-dependentRequired` extension when the
+//! specification is loaded, and read back from the extensions of the schema.
+
+use openapiv3::Schema;
+use serde_yaml::{Mapping, Value};
+
+/// Keyword as it appears in the specification
+const KEYWORD: &str = "dependentRequired";
+
+/// Name of the extension the keyword is kept in after parsing
+const EXTENSION: &str = "x-dependentRequired";
+
+/// Renames all `dependentRequired` keywords in the raw specification to the extension
+/// that survives parsing. Property names are left alone.
+pub fn preserve_keyword(value: &mut Value) {
+    match value {
+        Value::Mapping(mapping) => {
+            if mapping.get(KEYWORD).is_some_and(is_dependents_map) {
+                let dependents = mapping.shift_remove(KEYWORD).unwrap();
+                mapping.insert(EXTENSION.into(), dependents);
+            }
+            for (key, child) in mapping.iter_mut() {
+                match child {
+                    // The keys of `properties` are names, only the schemas are searched
+                    Value::Mapping(properties) if key.as_str() == Some("properties") => {
+                        properties.values_mut().for_each(preserve_keyword)
+                    }
+                    _ => preserve_keyword(child),
+                }
+            }
+        }
+        Value::Sequence(sequence) => sequence.iter_mut().for_each(preserve_keyword),
+        _ => (),
+    }
+}
+
+/// Whether the value has the shape of the keyword: a map from field names to lists of
+/// field names
+fn is_dependents_map(value: &Value) -> bool {
+    let is_name_list = |value: &Value| {
+        value
+            .as_sequence()
+            .is_some_and(|names| names.iter().all(Value::is_string))
+    };
+    value
+        .as_mapping()
+        .is_some_and(|mapping: &Mapping| mapping.values().all(is_name_list))
+}
+
+/// Returns the fields that each field requires according to the `dependentRequired`
+/// keyword of the schema.
+pub fn dependents(schema: &Schema) -> Vec<(&str, Vec<&str>)> {
+    let Some(serde_json::Value::Object(dependents)) = schema.data.extensions.get(EXTENSION) else {
+        return vec![];
+    };
+    dependents
+        .iter()
+        .map(|(field, required)| {
+            let required = required
+                .as_array()
+                .map(|names| names.iter().filter_map(serde_json::Value::as_str).collect())
+                .unwrap_or_default();
+            (field.as_str(), required)
+        })
+        .collect()
+}
+
+/// Returns, for each field present in an object, the fields it requires that are not
+/// present. Fields that require nothing or whose dependents are all present are left
+/// out.
+pub fn missing_dependents(
+    schema: &Schema,
+    has_field: impl Fn(&str) -> bool,
+) -> Vec<(String, Vec<String>)> {
+    dependents(schema)
+        .into_iter()
+        .filter(|(field, _)| has_field(field))
+        .filter_map(|(field, required)| {
+            let missing: Vec<String> = required
+                .into_iter()
+                .filter(|dependent| !has_field(dependent))
+                .map(str::to_owned)
+                .collect();
+            (!missing.is_empty()).then(|| (field.to_owned(), missing))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use openapiv3::Schema;
+
+    use super::{missing_dependents, preserve_keyword};
+
+    #[test]
+    fn test_keyword_survives_parsing() {
+        let mut raw: serde_yaml::Value = serde_yaml::from_str(
+            "
+            type: object
+            properties:
+              dependentRequired:
+                type: string
+              card:
+                type: string
+              address:
+                type: string
+            dependentRequired:
+              card: [address]
+            ",
+        )
+        .unwrap();
+        preserve_keyword(&mut raw);
+        let schema: Schema = serde_yaml::from_value(raw).unwrap();
+
+        assert_eq!(
+            missing_dependents(&schema, |field| field == "card"),
+            vec![("card".to_owned(), vec!["address".to_owned()])]
+        );
+        assert!(missing_dependents(&schema, |_| true).is_empty());
+        assert!(missing_dependents(&schema, |field| field == "address").is_empty());
+    }
+}
@@ -2,59 +2,215 @@
 //! fuzzing target during normal fuzzing operation. These functions need an OpenAPI struct
 //! to generate realistic requests for the given target.
 
-use std::{borrow::Cow, collections::VecDeque, f64::consts::PI};
+use std::{borrow::Cow, cell::RefCell, collections::VecDeque, f64::consts::PI, path::Path};
 
+use anyhow::{anyhow, Context};
 use indexmap::IndexMap;
+use libafl_bolts::rands::StdRand;
 use openapiv3::{
-    OpenAPI, Operation, Parameter, ParameterData, RefOr, Schema, SchemaKind, StringFormat, Type,
+    AdditionalProperties, IntegerFormat, IntegerType, ObjectType, OpenAPI, Operation, Parameter,
+    ParameterData, RefOr, Schema, SchemaKind, StringFormat, Type, VariantOrUnknownOrEmpty,
 };
 use petgraph::{csr::DefaultIx, graph::DiGraph, prelude::NodeIndex, visit::EdgeRef};
-use rand::{prelude::Distribution, Rng};
+use rand::{prelude::Distribution, rngs::StdRng, Rng, SeedableRng};
 use regex::Regex;
 use serde_json::Value;
 use unicode_truncate::UnicodeTruncateStr;
 
-use super::{JsonContent, QualifiedOperation, WwwForm};
+use super::{
+    dependent_required::{dependents, missing_dependents},
+    prefix_items::prefix_items,
+    JsonContent, Multipart, OctetStream, QualifiedOperation, WwwForm,
+};
 use crate::{
-    initial_corpus::dependency_graph::ParameterMatching,
-    input::{parameter::ParameterKind, Body, OpenApiInput, OpenApiRequest, ParameterContents},
+    initial_corpus::dependency_graph::{ParameterMatching, ResponseExampleValues},
+    input::{
+        new_rand_input, parameter::ParameterKind, Body, Method, OpenApiInput, OpenApiRequest,
+        ParameterContents,
+    },
 };
 
+thread_local! {
+    // Thread-local rather than global, so that tests can generate examples at once
+    static EXAMPLE_RNG: RefCell<StdRng> = RefCell::new(StdRng::seed_from_u64(DEFAULT_EXAMPLE_SEED));
+}
+
+/// Seed of the random number generator of the examples, unless `seed_examples` is called
+const DEFAULT_EXAMPLE_SEED: u64 = 0;
+
+/// Default maximum number of inputs generated for a chain of operations. Chains with
+/// more combinations of interesting values get a single example input instead.
+pub const DEFAULT_MAX_CORPUS_COMBINATIONS: usize = 10000;
+
+/// Maximum number of combinations of interesting parameter values of one operation
+const MAX_PARAMETER_COMBINATIONS: usize = 100;
+
+/// Values supplied by the user that examples are made of, in addition to those derived
+/// from the specification
+#[derive(Debug, Clone, Default)]
+pub struct UserExamples {
+    /// Values of string formats by format name, which extend those of
+    /// `strings_from_format`
+    format_values: IndexMap<String, Vec<String>>,
+    /// Values for specific operations, which take precedence over the values derived
+    /// from the specification
+    overrides: IndexMap<(Method, String), OperationExamples>,
+}
+
+impl UserExamples {
+    /// Loads the values of string formats from the YAML file at `format_values`, if
+    /// given, which maps format names to lists of values, e.g.
+    /// `date: ["2024-02-29", "1970-01-01"]`, and the values for specific operations
+    /// from the YAML file at `examples_override`, see `load_example_overrides`.
+    pub fn load(
+        format_values: Option<&Path>,
+        examples_override: Option<&Path>,
+    ) -> Result<Self, anyhow::Error> {
+        let mut user_examples = Self::default();
+        if let Some(path) = format_values {
+            let contents = std::fs::read_to_string(path)
+                .with_context(|| format!("Could not read format values from {path:?}"))?;
+            user_examples.format_values = serde_yaml::from_str(&contents)
+                .with_context(|| format!("Could not parse format values from {path:?}"))?;
+        }
+        if let Some(path) = examples_override {
+            user_examples.overrides = load_example_overrides(path)?;
+        }
+        Ok(user_examples)
+    }
+
+    /// The user-supplied example values for the operation, if any
+    fn overrides(&self, method: Method, path: &str) -> OperationExamples {
+        self.overrides
+            .get(&(method, path.to_owned()))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// The values for a string format: the built-in ones followed by the user-supplied
+    /// ones.
+    fn format_values(&self, str_format: &VariantOrUnknownOrEmpty<StringFormat>) -> Vec<String> {
+        let extra = self
+            .format_values
+            .get(str_format.as_str())
+            .into_iter()
+            .flatten();
+        strings_from_format(str_format)
+            .iter()
+            .map(|value| value.to_string())
+            .chain(extra.cloned())
+            .collect()
+    }
+}
+
+/// Seeds the random number generator that picks the random parts of the examples made
+/// from now on, on the current thread, such as format values, pattern samples and raw
+/// bytes. Corpora generated with the same seed are the same, so that runs can be
+/// reproduced.
+pub fn seed_examples(seed: u64) {
+    EXAMPLE_RNG.set(StdRng::seed_from_u64(seed));
+}
+
+/// Runs `f` with the random number generator of the examples
+fn with_example_rng<T>(f: impl FnOnce(&mut StdRng) -> T) -> T {
+    EXAMPLE_RNG.with_borrow_mut(f)
+}
+
+/// Example values for a single operation, supplied by the user
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct OperationExamples {
+    /// Values of parameters, by name
+    #[serde(default)]
+    parameters: IndexMap<String, Value>,
+    /// Value of the request body
+    body: Option<Value>,
+}
+
+/// Loads example values for specific operations from the given YAML file, which maps
+/// `METHOD path` to values of parameters and the body, e.g.
+/// `GET /pets/{id}: {parameters: {id: 42}}`. These take precedence over the values
+/// derived from the specification.
+fn load_example_overrides(
+    path: &Path,
+) -> Result<IndexMap<(Method, String), OperationExamples>, anyhow::Error> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Could not read example overrides from {path:?}"))?;
+    let raw: IndexMap<String, OperationExamples> = serde_yaml::from_str(&contents)
+        .with_context(|| format!("Could not parse example overrides from {path:?}"))?;
+    raw.into_iter()
+        .map(|(operation, examples)| {
+            let (method, op_path) = operation.split_once(' ').ok_or_else(|| {
+                anyhow!("Operation \"{operation}\" in {path:?} is not of the form \"METHOD path\"")
+            })?;
+            let method = Method::try_from(method)
+                .map_err(|err| anyhow!("Operation \"{operation}\" in {path:?}: {err}"))?;
+            Ok(((method, op_path.trim().to_owned()), examples))
+        })
+        .collect()
+}
+
+/// Takes one of the given values at random, so that not every example of a format
+/// gets the same value.
+fn pick_format_value(mut values: Vec<Value>) -> Option<Value> {
+    if values.is_empty() {
+        return None;
+    }
+    let index = with_example_rng(|rng| rng.gen_range(0..values.len()));
+    Some(values.swap_remove(index))
+}
+
 /// Takes a (path, method, operation) tuple and produces an OpenApiRequest
 /// filled with example values from the API specification, and default values
-/// for parameters with no explicit examples.
+/// for parameters with no explicit examples. User-supplied example overrides take
+/// precedence over both.
 pub fn example_from_qualified_operation(
     api: &OpenAPI,
+    user_examples: &UserExamples,
     operation: QualifiedOperation,
 ) -> OpenApiRequest {
+    let overrides = user_examples.overrides(operation.method, operation.path);
+    let body = match overrides.body {
+        Some(body) => Some(ParameterContents::from(body)),
+        None => example_body_contents(api, user_examples, operation.operation),
+    };
     OpenApiRequest {
         method: operation.method,
         path: operation.path.to_owned(),
-        body: Body::build(
+        body: Body::build(api, operation.operation, body),
+        parameters: example_parameters(
             api,
+            user_examples,
             operation.operation,
-            example_body_contents(api, operation.operation),
+            &overrides.parameters,
         ),
-        parameters: example_parameters(api, operation.operation),
     }
 }
 
 /// Generates body parameter values for the given operation if the operation has a supported
 /// body type, otherwise None. Examples can be based on various sources, such as being
 /// provided directly in the OpenAPI-spec or as defaults based on their type.
-fn example_body_contents(api: &OpenAPI, operation: &Operation) -> Option<ParameterContents> {
+fn example_body_contents(
+    api: &OpenAPI,
+    user_examples: &UserExamples,
+    operation: &Operation,
+) -> Option<ParameterContents> {
     let body = operation.request_body.as_ref()?.resolve(api).ok()?;
 
     // Get either application/json or form content, if neither is present this function will return an empty body.
-    let media_type = None
+    let Some(media_type) = None
         .or_else(|| body.content.get_json_content())
-        .or_else(|| body.content.get_www_form_content())?;
+        .or_else(|| body.content.get_www_form_content())
+        .or_else(|| body.content.get_multipart_content())
+    else {
+        return body.content.has_octet_stream_content().then(random_bytes);
+    };
 
     let schema = media_type.schema.as_ref()?.resolve(api);
 
     match &schema.kind {
         SchemaKind::Type(Type::Object(ref obj)) => {
-            let body_map: IndexMap<String, ParameterContents> = obj
+            let mut body_map: IndexMap<String, ParameterContents> = obj
                 .properties
                 .iter()
                 .filter_map(|(param, ref_or_schema)| {
@@ -62,17 +218,50 @@ fn example_body_contents(api: &OpenAPI, operation: &Operation) -> Option<Paramet
                         param.clone(),
                         ParameterContents::from(example_from_schema(
                             api,
+                            user_examples,
                             ref_or_schema.resolve(api),
                         )?),
                     ))
                 })
                 .collect();
+            body_map.extend(
+                additional_property_examples(api, user_examples, obj)
+                    .into_iter()
+                    .map(|(key, example)| (key, ParameterContents::from(example))),
+            );
+            // Added fields may require more fields in turn
+            loop {
+                let pending =
+                    dependent_examples(api, user_examples, schema, |f| body_map.contains_key(f));
+                if pending.is_empty() {
+                    break;
+                }
+                for (field, examples) in pending {
+                    match examples {
+                        Some(examples) => body_map.extend(
+                            examples
+                                .into_iter()
+                                .map(|(name, example)| (name, ParameterContents::from(example))),
+                        ),
+                        None => {
+                            body_map.shift_remove(&field);
+                        }
+                    }
+                }
+            }
             Some(body_map.into())
         }
+        SchemaKind::Type(Type::Array(_)) if !prefix_items(schema).is_empty() => Some(
+            ParameterContents::from(example_from_schema(api, user_examples, schema)?),
+        ),
         SchemaKind::Type(Type::Array(ref arr)) => match &arr.items {
             Some(items) => {
                 let result = items.resolve(api);
-                Some(ParameterContents::from(example_from_schema(api, result)?))
+                Some(ParameterContents::from(example_from_schema(
+                    api,
+                    user_examples,
+                    result,
+                )?))
             }
             None => None,
         },
@@ -91,37 +280,91 @@ fn example_body_contents(api: &OpenAPI, operation: &Operation) -> Option<Paramet
 /// Generates all interesting body contents
 fn all_interesting_body_contents(
     api: &OpenAPI,
+    user_examples: &UserExamples,
     operation: &Operation,
 ) -> Option<Vec<ParameterContents>> {
     let body = operation.request_body.as_ref()?.resolve(api).ok()?;
 
     // Get either application/json or form content, if neither is present this function will return an empty body.
-    let media_type = None
+    let Some(media_type) = None
         .or_else(|| body.content.get_json_content())
-        .or_else(|| body.content.get_www_form_content())?;
+        .or_else(|| body.content.get_www_form_content())
+        .or_else(|| body.content.get_multipart_content())
+    else {
+        return body
+            .content
+            .has_octet_stream_content()
+            .then(|| vec![random_bytes()]);
+    };
 
     Some(
-        interesting_params_from_media_type(api, media_type)
+        interesting_params_from_media_type(api, user_examples, media_type)
             .into_iter()
             .map(ParameterContents::from)
             .collect(),
     )
 }
 
+/// Random contents for a raw binary body, which has no schema to derive an example from.
+/// The mutators take it from there.
+fn random_bytes() -> ParameterContents {
+    let seed = with_example_rng(|rng| rng.gen());
+    ParameterContents::Bytes(new_rand_input(&mut StdRand::with_seed(seed)))
+}
+
+/// Example entries for an object whose `additionalProperties` has a schema, such as a
+/// map from names to values, under made-up keys. Without them, such objects would be
+/// empty. Two entries are made, unless `maxProperties` allows fewer.
+fn additional_property_examples(
+    api: &OpenAPI,
+    user_examples: &UserExamples,
+    object: &ObjectType,
+) -> FieldExamples {
+    let Some(AdditionalProperties::Schema(schema)) = &object.additional_properties else {
+        return vec![];
+    };
+    let Some(example) = example_from_schema(api, user_examples, schema.resolve(api)) else {
+        return vec![];
+    };
+    let count = object
+        .max_properties
+        .map_or(2, |max| max.saturating_sub(object.properties.len()).min(2));
+    let mut rand = StdRand::with_seed(with_example_rng(|rng| rng.gen()));
+    (0..count)
+        .map(|_| {
+            // Lowercase letters, which any key pattern of a map is likely to accept
+            let key = new_rand_input(&mut rand)
+                .into_iter()
+                .map(|byte| char::from(b'a' + byte % 26))
+                .collect();
+            (key, example.clone())
+        })
+        .filter(|(key, _)| !object.properties.contains_key(key))
+        .collect()
+}
+
 /// Create an example body from an operation. This function is meant for requests that do
 /// not have a structured body object, but a simple value.
 #[allow(unused)]
-fn example_plain_body(operation: &Operation, api: &OpenAPI) -> Option<ParameterContents> {
+fn example_plain_body(
+    operation: &Operation,
+    api: &OpenAPI,
+    user_examples: &UserExamples,
+) -> Option<ParameterContents> {
     operation
         .request_body
         .as_ref()
         .and_then(|ref_or_body| ref_or_body.resolve(api).ok())
         .and_then(|body| body.content.get_json_content())
-        .and_then(|media_type| example_from_media_type(api, media_type))
+        .and_then(|media_type| example_from_media_type(api, user_examples, media_type))
         .map(ParameterContents::from)
 }
 
-fn example_parameter_value(api: &OpenAPI, par_data: &ParameterData) -> Result<Value, String> {
+fn example_parameter_value(
+    api: &OpenAPI,
+    user_examples: &UserExamples,
+    par_data: &ParameterData,
+) -> Result<Value, String> {
     let example = par_data.example.clone();
     if example.is_some() {
         example.ok_or("".to_owned())
@@ -131,12 +374,12 @@ fn example_parameter_value(api: &OpenAPI, par_data: &ParameterData) -> Result<Va
         // to extract any useful value that may exist.
         match &(par_data.format) {
             openapiv3::ParameterSchemaOrContent::Schema(ref_or_schema) => {
-                example_from_schema(api, ref_or_schema.resolve(api))
+                example_from_schema(api, user_examples, ref_or_schema.resolve(api))
                     .ok_or("Could not create example from schema".to_owned())
             }
             openapiv3::ParameterSchemaOrContent::Content(content) => content
                 .get_json_content()
-                .and_then(|media_type| example_from_media_type(api, media_type))
+                .and_then(|media_type| example_from_media_type(api, user_examples, media_type))
                 .ok_or("Could not create example from content".to_owned()),
         }
     }
@@ -144,7 +387,9 @@ fn example_parameter_value(api: &OpenAPI, par_data: &ParameterData) -> Result<Va
 
 fn example_parameters(
     api: &OpenAPI,
+    user_examples: &UserExamples,
     operation: &Operation,
+    overrides: &IndexMap<String, Value>,
 ) -> IndexMap<(String, ParameterKind), ParameterContents> {
     operation
         .parameters
@@ -152,30 +397,41 @@ fn example_parameters(
         .filter_map(|ref_or_parameter| ref_or_parameter.resolve(api).ok())
         .map(|parameter| (parameter.into(), &parameter.data))
         .filter_map(|(par_kind, par_data)| {
-            example_parameter_value(api, par_data)
-                .map(|value| {
-                    (
-                        (par_data.name.clone(), par_kind),
-                        ParameterContents::from(value),
-                    )
-                })
-                .ok()
+            match overrides.get(&par_data.name) {
+                Some(value) => Ok(value.clone()),
+                None => example_parameter_value(api, user_examples, par_data),
+            }
+            .map(|value| {
+                (
+                    (par_data.name.clone(), par_kind),
+                    ParameterContents::from(value),
+                )
+            })
+            .ok()
         })
         .collect()
 }
 
 /// Returns all combinations of interesting values for parameters
-/// for this operation, as well as the examples that may be provided by the spec.
+/// for this operation, as well as the examples that may be provided by the spec and the
+/// values in response examples for parameters with the same normalized name.
+/// Parameters with a user-supplied example override only get that value.
 /// Parameters that should only get a single value may be specified in
 /// `single_valued`, which we use to avoid generating multiple values that
-/// would be replaced by references later.
+/// would be replaced by references later. At most `max_combinations` combinations
+/// are returned, and never more than `MAX_PARAMETER_COMBINATIONS`.
 fn all_interesting_parameters(
-    operation: &Operation,
+    operation: &QualifiedOperation,
     api: &OpenAPI,
+    user_examples: &UserExamples,
     single_valued: &[&Parameter],
+    response_values: &ResponseExampleValues,
+    max_combinations: usize,
 ) -> Vec<IndexMap<(String, ParameterKind), ParameterContents>> {
+    let overrides = user_examples.overrides(operation.method, operation.path);
     // For each parameter in the operation, generate a list of plausible values
     let param_combinations: IndexMap<(String, ParameterKind), Vec<ParameterContents>> = operation
+        .operation
         .parameters
         .iter()
         .filter_map(|ref_or_parameter| ref_or_parameter.resolve(api).ok())
@@ -183,11 +439,13 @@ fn all_interesting_parameters(
             let par_kind: ParameterKind = parameter.into();
             let par_data = &parameter.data;
             let mut interesting_combinations: Vec<Value> = vec![];
-            if single_valued.contains(&parameter) {
+            if let Some(value) = overrides.parameters.get(&par_data.name) {
+                interesting_combinations.push(value.clone());
+            } else if single_valued.contains(&parameter) {
                 if par_data.example.is_some() {
                     interesting_combinations.push(par_data.example.clone().unwrap());
                 } else {
-                    match example_parameter_value(api, par_data) {
+                    match example_parameter_value(api, user_examples, par_data) {
                         Ok(value) => interesting_combinations.push(value),
                         Err(err) => {
                             log::warn!(
@@ -204,18 +462,25 @@ fn all_interesting_parameters(
                 if let Some(example) = par_data.example.clone() {
                     interesting_combinations.push(example);
                 };
+                if let Some(candidates) = response_values.for_parameter(operation.path, parameter) {
+                    interesting_combinations.extend(candidates.iter().cloned());
+                }
                 match &(par_data.format) {
                     openapiv3::ParameterSchemaOrContent::Schema(ref_or_schema) => {
                         interesting_combinations.extend(interesting_params_from_schema(
                             api,
+                            user_examples,
                             ref_or_schema,
                             &[],
                         ));
                     }
                     openapiv3::ParameterSchemaOrContent::Content(content) => {
                         if let Some(media_type) = content.get("application/json") {
-                            interesting_combinations
-                                .extend(interesting_params_from_media_type(api, media_type));
+                            interesting_combinations.extend(interesting_params_from_media_type(
+                                api,
+                                user_examples,
+                                media_type,
+                            ));
                         }
                     }
                 };
@@ -235,7 +500,8 @@ fn all_interesting_parameters(
     // We'd like this to be sort-of bounded. Experimentally, that means a maximum of
     // 100 combinations. So each `param_values` must have a len such that the
     // product of all lengths is less than this 100.
-    let max_param_values = 100f64.powf(1.0 / param_combinations.len() as f64).floor() as usize;
+    let budget = max_combinations.min(MAX_PARAMETER_COMBINATIONS) as f64;
+    let max_param_values = budget.powf(1.0 / param_combinations.len() as f64).floor() as usize;
     let mut maps = vec![IndexMap::new()];
     for (key, param_values) in param_combinations.into_iter() {
         let mut new_maps = vec![];
@@ -254,17 +520,21 @@ fn all_interesting_parameters(
     maps
 }
 
-fn example_from_media_type(api: &OpenAPI, contents: &openapiv3::MediaType) -> Option<Value> {
+fn example_from_media_type(
+    api: &OpenAPI,
+    user_examples: &UserExamples,
+    contents: &openapiv3::MediaType,
+) -> Option<Value> {
     contents.example.clone().or_else(|| {
-        contents
-            .schema
-            .as_ref()
-            .and_then(|ref_or_schema| example_from_schema(api, ref_or_schema.resolve(api)))
+        contents.schema.as_ref().and_then(|ref_or_schema| {
+            example_from_schema(api, user_examples, ref_or_schema.resolve(api))
+        })
     })
 }
 
 fn interesting_params_from_media_type(
     api: &OpenAPI,
+    user_examples: &UserExamples,
     contents: &openapiv3::MediaType,
 ) -> Vec<Value> {
     let mut result = vec![];
@@ -274,15 +544,19 @@ fn interesting_params_from_media_type(
     if let Some(more_examples) = contents
         .schema
         .as_ref()
-        .map(|ref_or_schema| interesting_params_from_schema(api, ref_or_schema, &[]))
+        .map(|ref_or_schema| interesting_params_from_schema(api, user_examples, ref_or_schema, &[]))
     {
         result.extend(more_examples);
     }
     result
 }
 
-// Attempts to build a value that matches the given schema using default values
-fn example_from_schema(api: &OpenAPI, schema: &Schema) -> Option<Value> {
+/// Attempts to build a value that matches the given schema using default values
+pub fn example_from_schema(
+    api: &OpenAPI,
+    user_examples: &UserExamples,
+    schema: &Schema,
+) -> Option<Value> {
     if schema.data.read_only {
         return None;
     }
@@ -292,23 +566,149 @@ fn example_from_schema(api: &OpenAPI, schema: &Schema) -> Option<Value> {
     if schema.data.example.is_some() {
         return schema.data.example.clone();
     }
+    if let Some(variant) = enum_variants(schema).into_iter().next() {
+        return Some(variant);
+    }
     match &schema.kind {
-        openapiv3::SchemaKind::Type(t) => example_from_type(api, t),
+        openapiv3::SchemaKind::Type(t) => {
+            let mut example = match tuple_example(api, user_examples, schema) {
+                Some(tuple) => tuple?,
+                None => example_from_type(api, user_examples, t)?,
+            };
+            complete_dependents(api, user_examples, schema, &mut example);
+            Some(example)
+        }
         openapiv3::SchemaKind::OneOf { one_of }
         | openapiv3::SchemaKind::AnyOf { any_of: one_of } => one_of
             .iter()
-            .filter_map(|ref_or_schema| example_from_schema(api, ref_or_schema.resolve(api)))
+            .filter_map(|ref_or_schema| {
+                example_from_schema(api, user_examples, ref_or_schema.resolve(api))
+            })
             .next(),
+        // The example has the fields of the examples of all variants, so also the
+        // fields that any of them requires
+        openapiv3::SchemaKind::AllOf { all_of } => all_of
+            .iter()
+            .filter_map(|ref_or_schema| {
+                example_from_schema(api, user_examples, ref_or_schema.resolve(api))
+            })
+            .reduce(merge_object_values),
         _ => None,
     }
 }
 
+/// Examples of fields to add to an object, as (field name, example) pairs
+type FieldExamples = Vec<(String, Value)>;
+
+/// Examples for the fields that the `dependentRequired` keyword of the schema requires
+/// along with the fields that are present, by the field that requires them. A field
+/// maps to None if one of its dependents can not be included, so it is better left out.
+fn dependent_examples(
+    api: &OpenAPI,
+    user_examples: &UserExamples,
+    schema: &Schema,
+    has_field: impl Fn(&str) -> bool,
+) -> Vec<(String, Option<FieldExamples>)> {
+    let SchemaKind::Type(Type::Object(object)) = &schema.kind else {
+        return vec![];
+    };
+    let all_dependents = dependents(schema);
+    missing_dependents(schema, has_field)
+        .into_iter()
+        .map(|(field, missing)| {
+            let examples = missing
+                .into_iter()
+                .map(|dependent| {
+                    let property = object.properties.get(&dependent)?.resolve(api);
+                    let example = example_from_schema(api, user_examples, property)?;
+                    includable(
+                        api,
+                        user_examples,
+                        object,
+                        &all_dependents,
+                        &dependent,
+                        &mut vec![],
+                    )
+                    .then_some((dependent, example))
+                })
+                .collect();
+            (field, examples)
+        })
+        .collect()
+}
+
+/// Whether all fields that `field` requires, directly or indirectly, have an example.
+/// `visited` holds the fields whose requirements are being checked.
+fn includable<'a>(
+    api: &OpenAPI,
+    user_examples: &UserExamples,
+    object: &ObjectType,
+    all_dependents: &[(&'a str, Vec<&'a str>)],
+    field: &'a str,
+    visited: &mut Vec<&'a str>,
+) -> bool {
+    // A cycle of dependents does not make a field any less includable
+    if visited.contains(&field) {
+        return true;
+    }
+    let Some((_, required)) = all_dependents.iter().find(|(name, _)| *name == field) else {
+        return true;
+    };
+    visited.push(field);
+    let result = required.iter().all(|dependent| {
+        object
+            .properties
+            .get(*dependent)
+            .and_then(|property| example_from_schema(api, user_examples, property.resolve(api)))
+            .is_some()
+            && includable(
+                api,
+                user_examples,
+                object,
+                all_dependents,
+                dependent,
+                visited,
+            )
+    });
+    visited.pop();
+    result
+}
+
+/// Adds the fields that the `dependentRequired` keyword of the schema requires to an
+/// example object, or removes the fields that require them if that is not possible.
+fn complete_dependents(
+    api: &OpenAPI,
+    user_examples: &UserExamples,
+    schema: &Schema,
+    example: &mut Value,
+) {
+    let Value::Object(fields) = example else {
+        return;
+    };
+    // Added fields may require more fields in turn
+    loop {
+        let pending = dependent_examples(api, user_examples, schema, |f| fields.contains_key(f));
+        if pending.is_empty() {
+            break;
+        }
+        for (field, examples) in pending {
+            match examples {
+                Some(examples) => fields.extend(examples),
+                None => {
+                    fields.remove(&field);
+                }
+            }
+        }
+    }
+}
+
 // Returns all interesting (default, example) values for the given schema.
 // ignore_reference may specify a reference that is not followed, this is used
 // when resolving discriminator variants, which may refer back to their parent object
 // circularly.
 fn interesting_params_from_schema(
     api: &OpenAPI,
+    user_examples: &UserExamples,
     schema: &RefOr<Schema>,
     ignore_names: &[&str],
 ) -> Vec<Value> {
@@ -323,6 +723,11 @@ fn interesting_params_from_schema(
         // schema property may only be sent in responses, never in requests.
         return vec![];
     }
+    // If the values are restricted to an enumeration, any other value is not interesting
+    let variants = enum_variants(schema);
+    if !variants.is_empty() {
+        return variants;
+    }
     let mut result = vec![];
     if schema.data.default.is_some() {
         result.push(schema.data.default.clone().unwrap());
@@ -331,11 +736,26 @@ fn interesting_params_from_schema(
         result.push(schema.data.example.clone().unwrap());
     }
     if schema.data.discriminator.is_some() {
-        result.extend(all_discriminator_variants(api, schema, &ignore_reference));
+        result.extend(all_discriminator_variants(
+            api,
+            user_examples,
+            schema,
+            &ignore_reference,
+        ));
     } else {
         match &schema.kind {
+            openapiv3::SchemaKind::Type(_) if !prefix_items(schema).is_empty() => {
+                result.extend(tuple_example(api, user_examples, schema).flatten());
+            }
             openapiv3::SchemaKind::Type(t) => {
-                result.extend(interesting_params_from_type(api, t));
+                result.extend(
+                    interesting_params_from_type(api, user_examples, t)
+                        .into_iter()
+                        .map(|mut example| {
+                            complete_dependents(api, user_examples, schema, &mut example);
+                            example
+                        }),
+                );
             }
             openapiv3::SchemaKind::OneOf { one_of }
             | openapiv3::SchemaKind::AnyOf { any_of: one_of } => {
@@ -350,7 +770,12 @@ fn interesting_params_from_schema(
                         {
                             Vec::new()
                         }
-                        _ => interesting_params_from_schema(api, ref_or_schema, &ignore_reference),
+                        _ => interesting_params_from_schema(
+                            api,
+                            user_examples,
+                            ref_or_schema,
+                            &ignore_reference,
+                        ),
                     }
                 }));
             }
@@ -370,6 +795,7 @@ fn interesting_params_from_schema(
                             }
                             _ => Some(interesting_params_from_schema(
                                 api,
+                                user_examples,
                                 ref_or_schema,
                                 &ignore_reference,
                             )),
@@ -385,9 +811,46 @@ fn interesting_params_from_schema(
             _ => (),
         }
     }
+    // Targets may not handle null, even if the specification allows it
+    if schema.data.nullable {
+        result.push(Value::Null);
+    }
     result
 }
 
+/// Returns the variants of the enumeration declared in the schema, of any type, or an
+/// empty Vec if the schema does not declare one.
+pub fn enum_variants(schema: &Schema) -> Vec<Value> {
+    match &schema.kind {
+        SchemaKind::Type(Type::String(string)) => string
+            .enumeration
+            .iter()
+            .cloned()
+            .map(Value::String)
+            .collect(),
+        SchemaKind::Type(Type::Number(number)) => number
+            .enumeration
+            .iter()
+            .map(|variant| match variant {
+                Some(num) => serde_json::Number::from_f64(*num)
+                    .map(Value::Number)
+                    .unwrap_or(Value::Null),
+                None => Value::Null,
+            })
+            .collect(),
+        SchemaKind::Type(Type::Integer(integer)) => integer
+            .enumeration
+            .iter()
+            .map(|variant| match variant {
+                Some(num) => Value::Number(serde_json::Number::from(*num)),
+                None => Value::Null,
+            })
+            .collect(),
+        SchemaKind::Any(any) => any.enumeration.clone(),
+        _ => vec![],
+    }
+}
+
 /// Moves all fields from `right` into `left`.
 /// If either Value is not an Object, nothing happens.
 /// Fields from the `right` object take precendence
@@ -428,7 +891,12 @@ fn carthesian_product_values(xs: Vec<Value>, ys: Vec<Value>) -> Vec<Value> {
 /// discriminator parameter.
 ///
 /// * https://swagger.io/specification/#discriminator-object
-fn all_discriminator_variants(api: &OpenAPI, schema: &Schema, ignore_names: &[&str]) -> Vec<Value> {
+pub fn all_discriminator_variants(
+    api: &OpenAPI,
+    user_examples: &UserExamples,
+    schema: &Schema,
+    ignore_names: &[&str],
+) -> Vec<Value> {
     // There is a strong assumption from here on that we're dealing with an
     // object schema, with the fields collected from the variant specified by
     // the discriminator, and merged with the fields from the parent type.
@@ -473,6 +941,7 @@ fn all_discriminator_variants(api: &OpenAPI, schema: &Schema, ignore_names: &[&s
         all_examples.extend(
             interesting_params_from_schema(
                 api,
+                user_examples,
                 &RefOr::<Schema>::Reference { reference: path },
                 ignore_names,
             )
@@ -485,9 +954,16 @@ fn all_discriminator_variants(api: &OpenAPI, schema: &Schema, ignore_names: &[&s
     all_examples
 }
 
+/// A password of 73 bytes, one more than bcrypt uses, so that implementations that hash
+/// it with bcrypt silently ignore its last character
+const LONG_PASSWORD: &str =
+    "WuppieFuzz-password-0123456789-0123456789-0123456789-0123456789-abcdefgh!";
+
 /// Gives a slice of example string references based on the StringFormat given.
 /// The examples are correct values for their type, if perhaps surprising.
-fn strings_from_format(str_format: &openapiv3::VariantOrUnknownOrEmpty<StringFormat>) -> &[&str] {
+pub fn strings_from_format(
+    str_format: &openapiv3::VariantOrUnknownOrEmpty<StringFormat>,
+) -> &[&str] {
     match str_format {
         openapiv3::VariantOrUnknownOrEmpty::Item(StringFormat::Date) => {
             &["1981-09-05", "0000-01-01", "9999999-12-31"]
@@ -499,6 +975,18 @@ fn strings_from_format(str_format: &openapiv3::VariantOrUnknownOrEmpty<StringFor
             "2016-12-31T23:59:60Z",    // Valid leap second
         ],
         openapiv3::VariantOrUnknownOrEmpty::Item(StringFormat::Byte) => &["V3VwcGllRnV6elROTyE=="],
+        openapiv3::VariantOrUnknownOrEmpty::Item(StringFormat::Password) => &[
+            "",                             // No password at all
+            "correct horse battery staple", // Contains spaces
+            "pässwörd🔑",                   // Multi-byte characters
+            LONG_PASSWORD,
+        ],
+        openapiv3::VariantOrUnknownOrEmpty::Item(StringFormat::Binary) => &[
+            "\0",              // A single NUL byte
+            "\u{7f}ELF",       // Magic number of an executable
+            "\u{1}\u{2}\u{3}", // Control characters
+            "\u{ff}\u{fe}",    // The UTF-16 byte order mark, as characters
+        ],
         // Though the specification allows for other StringFormats, like email,
         // the openapi crate does not. Just in case, we default to an email-like
         // value.
@@ -515,10 +1003,76 @@ fn strings_from_format(str_format: &openapiv3::VariantOrUnknownOrEmpty<StringFor
         openapiv3::VariantOrUnknownOrEmpty::Unknown(s) if s == "hostname" => {
             &["example.com", "localhost", "router.local"]
         }
+        openapiv3::VariantOrUnknownOrEmpty::Unknown(s) if s == "json-pointer" => &[
+            "/a/b/0",
+            "",           // The whole document
+            "/",          // The member with an empty name
+            "/a~1b/m~0n", // Escaped "/" and "~"
+        ],
+        openapiv3::VariantOrUnknownOrEmpty::Unknown(s) if s == "relative-json-pointer" => {
+            &["0", "1/a/b", "2#", "0/a~1b"]
+        }
         _ => &["", "A", "🎵"],
     }
 }
 
+/// Gives a slice of strings that are almost, but not quite, valid for the given
+/// StringFormat, to test how robustly the API rejects them.
+pub fn invalid_strings_from_format(
+    str_format: &openapiv3::VariantOrUnknownOrEmpty<StringFormat>,
+) -> &[&str] {
+    match str_format {
+        openapiv3::VariantOrUnknownOrEmpty::Unknown(s) if s == "json-pointer" => &[
+            "a/b",   // No leading slash
+            "/a~2b", // Invalid escape
+            "/a~",   // Unfinished escape
+            "#/c",   // URI fragment instead of a plain pointer
+        ],
+        openapiv3::VariantOrUnknownOrEmpty::Unknown(s) if s == "relative-json-pointer" => {
+            &["-1/a", "01/a", "/a", "0#/a"]
+        }
+        _ => &[],
+    }
+}
+
+/// The shortest valid pointer for the JSON Pointer formats, or None for other formats.
+fn json_pointer_prefix(
+    str_format: &openapiv3::VariantOrUnknownOrEmpty<StringFormat>,
+) -> Option<&'static str> {
+    match str_format {
+        openapiv3::VariantOrUnknownOrEmpty::Unknown(s) if s == "json-pointer" => Some("/"),
+        openapiv3::VariantOrUnknownOrEmpty::Unknown(s) if s == "relative-json-pointer" => {
+            Some("0/")
+        }
+        _ => None,
+    }
+}
+
+/// Keeps the pointers within the given length bounds. Padding or truncating would break
+/// their syntax, so if none fit, a pointer to a single member of the right length is made
+/// by extending `prefix`.
+fn json_pointers_within_bounds(
+    pointers: Vec<String>,
+    prefix: &str,
+    min_length: Option<usize>,
+    max_length: Option<usize>,
+) -> Vec<Value> {
+    let min_length = min_length.unwrap_or(0);
+    let max_length = max_length.unwrap_or(usize::MAX);
+    let fits = |pointer: &String| (min_length..=max_length).contains(&pointer.chars().count());
+    let mut pointers: Vec<String> = pointers.into_iter().filter(fits).collect();
+    if pointers.is_empty() {
+        let pointer = format!(
+            "{prefix}{}",
+            "a".repeat(min_length.saturating_sub(prefix.len()))
+        );
+        if fits(&pointer) {
+            pointers.push(pointer);
+        }
+    }
+    pointers.into_iter().map(Value::String).collect()
+}
+
 /// Enforces the given minimum and maximum length on the input, by padding it
 /// with "A"s or truncating it.
 fn enforce_length_bounds(
@@ -536,13 +1090,39 @@ fn enforce_length_bounds(
     result
 }
 
+/// Brings a sample of the pattern of `string` within its length bounds. If the bounded
+/// sample no longer matches the pattern, the sample is kept as it is.
+fn bound_pattern_sample(sample: String, pattern: &str, string: &openapiv3::StringType) -> String {
+    let bounded = enforce_length_bounds(&sample, string.min_length, string.max_length);
+    if bounded == sample {
+        return sample;
+    }
+    match Regex::new(pattern) {
+        Ok(regex) if regex.is_match(&bounded) => bounded.into_owned(),
+        _ => {
+            log::debug!(
+                "Sample {sample:?} of pattern {pattern} does not match the pattern within length bounds {:?}..={:?}, keeping it unbounded",
+                string.min_length,
+                string.max_length
+            );
+            sample
+        }
+    }
+}
+
 /// Generate parameters based on the type specified. The values returned
 /// should adhere to any constraints from the spec, any deviations to
 /// test robustness of the server should be introduced by fuzzing.
-fn interesting_params_from_type(api: &OpenAPI, openapi_type: &Type) -> Vec<Value> {
+/// Integers without a minimum are often unsigned in practice (e.g. IDs), so
+/// negative boundaries are included for them to probe how the sign is handled.
+fn interesting_params_from_type(
+    api: &OpenAPI,
+    user_examples: &UserExamples,
+    openapi_type: &Type,
+) -> Vec<Value> {
     // For numeric types, take exclusive_minimum and -maximum bools into account.
     match openapi_type {
-        Type::String(string) => interesting_params_from_string_type(string),
+        Type::String(string) => interesting_params_from_string_type(user_examples, string),
         Type::Number(number) => {
             let interesting = match (number.minimum, number.maximum, number.multiple_of) {
                 (Some(min), Some(max), Some(base)) => {
@@ -613,6 +1193,7 @@ fn interesting_params_from_type(api: &OpenAPI, openapi_type: &Type) -> Vec<Value
                 .collect()
         }
         Type::Integer(integer) => {
+            let (format_min, format_max) = integer_format_bounds(integer);
             let interesting = match (integer.minimum, integer.maximum, integer.multiple_of) {
                 (Some(min), Some(max), Some(base)) => {
                     let mut constrained = vec![];
@@ -636,7 +1217,7 @@ fn interesting_params_from_type(api: &OpenAPI, openapi_type: &Type) -> Vec<Value
                 }
                 (Some(min), None, Some(base)) => {
                     let mut constrained = vec![];
-                    for val in [-base, 0, base] {
+                    for val in [-base, 0, base, highest_multiple(base, format_max)] {
                         if min <= val {
                             constrained.push(val);
                         }
@@ -645,7 +1226,7 @@ fn interesting_params_from_type(api: &OpenAPI, openapi_type: &Type) -> Vec<Value
                 }
                 (Some(min), None, None) => {
                     let mut constrained = vec![min];
-                    for val in [min, -1, 0, 1] {
+                    for val in [min, -1, 0, 1, format_max] {
                         if min <= val {
                             constrained.push(val);
                         }
@@ -654,7 +1235,7 @@ fn interesting_params_from_type(api: &OpenAPI, openapi_type: &Type) -> Vec<Value
                 }
                 (None, Some(max), Some(base)) => {
                     let mut constrained = vec![max];
-                    for val in [-base, 0, base] {
+                    for val in [lowest_multiple(base, format_min), -base, 0, base] {
                         if max >= val {
                             constrained.push(val);
                         }
@@ -663,7 +1244,7 @@ fn interesting_params_from_type(api: &OpenAPI, openapi_type: &Type) -> Vec<Value
                 }
                 (None, Some(max), None) => {
                     let mut constrained = vec![max];
-                    for val in [-1, 0, 1, max] {
+                    for val in [format_min, -1, 0, 1, max] {
                         if max >= val {
                             constrained.push(val);
                         }
@@ -671,15 +1252,23 @@ fn interesting_params_from_type(api: &OpenAPI, openapi_type: &Type) -> Vec<Value
                     constrained
                 }
                 (None, None, Some(base)) => {
-                    vec![-base, 0, base]
+                    vec![
+                        lowest_multiple(base, format_min),
+                        -base,
+                        0,
+                        base,
+                        highest_multiple(base, format_max),
+                    ]
                 }
                 (None, None, None) => {
-                    vec![-1, 0, 1]
+                    vec![format_min, -1, 0, 1, format_max]
                 }
             };
             // For simplicity we unwrap the Number; if this would panic, we will consider this a bug in the code above.
             interesting
                 .iter()
+                // Bounds in the schema may exceed what its format can hold
+                .filter(|num| !is_int32(integer) || i32::try_from(**num).is_ok())
                 .map(|num| Value::Number(serde_json::Number::from(*num)))
                 .collect()
         }
@@ -687,15 +1276,25 @@ fn interesting_params_from_type(api: &OpenAPI, openapi_type: &Type) -> Vec<Value
             object
                 .properties
                 .iter()
-                .filter_map(|(k, v)| Some((k.clone(), example_from_schema(api, v.resolve(api))?)))
+                .filter_map(|(k, v)| {
+                    Some((
+                        k.clone(),
+                        example_from_schema(api, user_examples, v.resolve(api))?,
+                    ))
+                })
+                .chain(additional_property_examples(api, user_examples, object))
                 .collect(),
         )],
         Type::Array(array) => {
             // The 'items' specification is required according to the spec, but
             // we still get an Option and a possibly broken reference and what not.
             // Extract any usable specification of an item, and make an example.
-            let item =
-                example_from_schema(api, array.items.as_ref().unwrap().resolve(api)).unwrap();
+            let item = example_from_schema(
+                api,
+                user_examples,
+                array.items.as_ref().unwrap().resolve(api),
+            )
+            .unwrap();
             // Repeat the example. If a maximum number of array elements is specified,
             // we use that many, otherwise the minimum number, otherwise 3.
             vec![Value::Array(vec![
@@ -710,9 +1309,68 @@ fn interesting_params_from_type(api: &OpenAPI, openapi_type: &Type) -> Vec<Value
     }
 }
 
-fn example_from_type(api: &OpenAPI, t: &Type) -> Option<Value> {
+/// The lowest and highest values of the format of an integer, used as boundary values.
+/// Integers without a format get 32-bit boundaries, since many servers parse them as such.
+fn integer_format_bounds(integer: &IntegerType) -> (i64, i64) {
+    match integer.format {
+        VariantOrUnknownOrEmpty::Item(IntegerFormat::Int64) => (i64::MIN, i64::MAX),
+        _ => (i32::MIN.into(), i32::MAX.into()),
+    }
+}
+
+/// Whether the integer is declared to fit in 32 bits
+fn is_int32(integer: &IntegerType) -> bool {
+    integer.format == VariantOrUnknownOrEmpty::Item(IntegerFormat::Int32)
+}
+
+/// The multiple of `base` closest to `min` that is not lower than `min`
+fn lowest_multiple(base: i64, min: i64) -> i64 {
+    min.checked_div(base)
+        .map_or(min, |quotient| quotient * base)
+}
+
+/// The multiple of `base` closest to `max` that is not higher than `max`
+fn highest_multiple(base: i64, max: i64) -> i64 {
+    max.checked_div(base)
+        .map_or(max, |quotient| quotient * base)
+}
+
+/// An example of a tuple, with an example of each of the `prefixItems` schemas in order,
+/// or None if the schema is not a tuple. The inner None means that one of the elements
+/// has no example.
+fn tuple_example(
+    api: &OpenAPI,
+    user_examples: &UserExamples,
+    schema: &Schema,
+) -> Option<Option<Value>> {
+    let prefix_items = prefix_items(schema);
+    if prefix_items.is_empty() {
+        return None;
+    }
+    Some(
+        prefix_items
+            .iter()
+            .map(|ref_or_schema| {
+                example_from_schema(api, user_examples, ref_or_schema.resolve(api))
+            })
+            .collect::<Option<Vec<_>>>()
+            .map(Value::Array),
+    )
+}
+
+fn example_from_type(api: &OpenAPI, user_examples: &UserExamples, t: &Type) -> Option<Value> {
     match t {
-        Type::String(string) => interesting_params_from_string_type(string).pop(),
+        Type::String(string) => {
+            let mut values = interesting_params_from_string_type(user_examples, string);
+            if string.enumeration.is_empty()
+                && string.pattern.is_none()
+                && string.format != VariantOrUnknownOrEmpty::Empty
+            {
+                pick_format_value(values)
+            } else {
+                values.pop()
+            }
+        }
         Type::Number(number) => {
             let value = match (number.minimum, number.maximum, number.multiple_of) {
                 (Some(min), Some(max), Some(base)) => ((min + max) / 2.0 / base).round() * base,
@@ -737,20 +1395,32 @@ fn example_from_type(api: &OpenAPI, t: &Type) -> Option<Value> {
                 (None, None, Some(base)) => base,
                 (None, None, None) => 0,
             };
+            // Bounds in the schema may exceed what its format can hold
+            let value = if is_int32(integer) {
+                value.clamp(i32::MIN.into(), i32::MAX.into())
+            } else {
+                value
+            };
             Some(Value::Number(serde_json::Number::from(value)))
         }
         Type::Object(object) => Some(Value::Object(
             object
                 .properties
                 .iter()
-                .filter_map(|(k, v)| Some((k.clone(), example_from_schema(api, v.resolve(api))?)))
+                .filter_map(|(k, v)| {
+                    Some((
+                        k.clone(),
+                        example_from_schema(api, user_examples, v.resolve(api))?,
+                    ))
+                })
+                .chain(additional_property_examples(api, user_examples, object))
                 .collect(),
         )),
         Type::Array(array) => {
             // The 'items' specification is required according to the spec, but
             // we still get an Option and a possibly broken reference and what not.
             // Extract any usable specification of an item, and make an example.
-            let item = example_from_schema(api, array.items.as_ref()?.resolve(api))?;
+            let item = example_from_schema(api, user_examples, array.items.as_ref()?.resolve(api))?;
             // Repeat the example. If a maximum number of array elements is specified,
             // we use that many, otherwise the minimum number, otherwise 2.
             Some(Value::Array(vec![
@@ -767,7 +1437,10 @@ fn example_from_type(api: &OpenAPI, t: &Type) -> Option<Value> {
 
 /// We return all variants if an enumeration is present, try the pattern regex if one is present,
 /// or fall back to some defaults based on the StringFormat. Returns a serde_json::Value::String.
-fn interesting_params_from_string_type(string: &openapiv3::StringType) -> Vec<serde_json::Value> {
+fn interesting_params_from_string_type(
+    user_examples: &UserExamples,
+    string: &openapiv3::StringType,
+) -> Vec<serde_json::Value> {
     // Enumeration present? Return the first variant
     if !string.enumeration.is_empty() {
         return string
@@ -781,9 +1454,11 @@ fn interesting_params_from_string_type(string: &openapiv3::StringType) -> Vec<se
     // Regex (without anchors) present? Attempt to compile it, and generate a string that matches it
     if let Some(pattern) = &string.pattern {
         if let Ok(compiled_regex) = rand_regex::Regex::compile(pattern, 100) {
-            return vec![serde_json::Value::String(
-                compiled_regex.sample(&mut rand::thread_rng()),
-            )];
+            return vec![serde_json::Value::String(bound_pattern_sample(
+                with_example_rng(|rng| compiled_regex.sample(rng)),
+                pattern,
+                string,
+            ))];
         }
 
         // The regex does have anchors, which the generator can not work with
@@ -797,12 +1472,14 @@ fn interesting_params_from_string_type(string: &openapiv3::StringType) -> Vec<se
 
                 // Generate 1000 sample strings from the regex pattern without anchors
                 // and test if one matches the regex with the anchors
-                if let Some(sample) = rand::thread_rng()
-                    .sample_iter::<String, _>(&compiled_regex)
-                    .take(1000)
-                    .find(|s| filter_regex.is_match(s))
-                {
-                    return vec![serde_json::Value::String(sample)];
+                if let Some(sample) = with_example_rng(|rng| {
+                    rng.sample_iter::<String, _>(&compiled_regex)
+                        .take(1000)
+                        .find(|s| filter_regex.is_match(s))
+                }) {
+                    return vec![serde_json::Value::String(bound_pattern_sample(
+                        sample, pattern, string,
+                    ))];
                 }
                 log::warn!(
                     "Could not generate an example string that matches the regex {}",
@@ -815,10 +1492,20 @@ fn interesting_params_from_string_type(string: &openapiv3::StringType) -> Vec<se
         }
     }
 
+    if let Some(prefix) = json_pointer_prefix(&string.format) {
+        return json_pointers_within_bounds(
+            user_examples.format_values(&string.format),
+            prefix,
+            string.min_length,
+            string.max_length,
+        );
+    }
+
     // Attempt to generate a string based on other format hints
-    strings_from_format(&string.format)
+    user_examples
+        .format_values(&string.format)
         .iter()
-        .map(|&example| {
+        .map(|example| {
             serde_json::Value::String(
                 enforce_length_bounds(example, string.min_length, string.max_length).into_owned(),
             )
@@ -832,12 +1519,16 @@ fn interesting_params_from_string_type(string: &openapiv3::StringType) -> Vec<se
 /// it may for example return (true, 0), (true, 1), (false, 0) and (false, 1).
 ///
 /// This allows the fuzzer to start with all the inputs that we might a priori consider promising, and then
-/// continue with random mutations (picking seeds based on coverage feedback).
+/// continue with random mutations (picking seeds based on coverage feedback). If the product has more than
+/// `max_combinations` inputs, an error is returned instead.
 pub fn openapi_inputs_from_ops<'a>(
     api: &OpenAPI,
+    user_examples: &UserExamples,
     ops_iter: impl Iterator<Item = QualifiedOperation<'a>>,
     subgraph: &DiGraph<QualifiedOperation, ParameterMatching, DefaultIx>,
     sorted_nodes: &[NodeIndex],
+    response_values: &ResponseExampleValues,
+    max_combinations: usize,
 ) -> Result<Vec<OpenApiInput>, String> {
     // First create all interesting requests per QualifiedOperation independently.
     // We will create request chains from their cartesian product in the next step.
@@ -860,14 +1551,21 @@ pub fn openapi_inputs_from_ops<'a>(
                         })
                 })
                 .collect();
-            all_interesting_inputs_for_qualified_operation(api, op, &single_valued)
+            all_interesting_inputs_for_qualified_operation(
+                api,
+                user_examples,
+                op,
+                &single_valued,
+                response_values,
+                max_combinations,
+            )
         })
         .collect();
     // deduplicate_same_reference_requests(&mut concrete_requests, &subgraph, &sorted_nodes);
     let total_combinations: usize = concrete_requests
         .iter()
         .fold(1, |acc, elem| acc * elem.len());
-    if total_combinations > 10000 {
+    if total_combinations > max_combinations {
         return Err(format!(
             "Corpus generation would try to create {} inputs, fall back to simple examples.",
             total_combinations
@@ -909,15 +1607,32 @@ pub fn openapi_inputs_from_ops<'a>(
 /// Returns a NON-EMPTY vector of interesting requests that can be made for the given operation.
 fn all_interesting_inputs_for_qualified_operation(
     api: &OpenAPI,
+    user_examples: &UserExamples,
     operation: QualifiedOperation,
     single_valued: &[&Parameter],
+    response_values: &ResponseExampleValues,
+    max_combinations: usize,
 ) -> Vec<OpenApiRequest> {
     // There may be multiple parameters, create an OpenApiRequest for each combination
     // of interesting values for these parameters.
-    let combinations = all_interesting_parameters(operation.operation, api, single_valued);
+    let combinations = all_interesting_parameters(
+        &operation,
+        api,
+        user_examples,
+        single_valued,
+        response_values,
+        max_combinations,
+    );
+    let bodies = match user_examples
+        .overrides(operation.method, operation.path)
+        .body
+    {
+        Some(body) => Some(vec![ParameterContents::from(body)]),
+        None => all_interesting_body_contents(api, user_examples, operation.operation),
+    };
     if combinations.is_empty() {
         // There are no parameters, return the interesting bodies.
-        match all_interesting_body_contents(api, operation.operation) {
+        match bodies {
             Some(bodies) => bodies
                 .into_iter()
                 .map(|body| OpenApiRequest {
@@ -935,7 +1650,7 @@ fn all_interesting_inputs_for_qualified_operation(
             }],
         }
     } else {
-        match all_interesting_body_contents(api, operation.operation) {
+        match bodies {
             Some(bodies) => bodies
                 .into_iter()
                 .flat_map(|body| std::iter::repeat(body).zip(&combinations))
@@ -958,3 +1673,559 @@ fn all_interesting_inputs_for_qualified_operation(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, io::Write};
+
+    use openapiv3::{
+        IntegerType, OpenAPI, RefOr, Schema, SchemaData, SchemaKind, StringFormat, StringType,
+        Type, VariantOrUnknownOrEmpty,
+    };
+    use regex::Regex;
+    use serde_json::{json, Value};
+
+    use super::{
+        all_interesting_parameters, example_from_qualified_operation, example_from_schema,
+        example_from_type, interesting_params_from_schema, interesting_params_from_string_type,
+        interesting_params_from_type, invalid_strings_from_format, seed_examples,
+        strings_from_format, UserExamples, DEFAULT_MAX_CORPUS_COMBINATIONS,
+    };
+    use crate::{
+        initial_corpus::dependency_graph::{response_example_values, synonyms::Synonyms},
+        input::parameter::ParameterKind,
+        openapi::{prefix_items::preserve_keyword, QualifiedOperation},
+    };
+
+    #[test]
+    fn test_integer_enum_generates_only_variants() {
+        let api = OpenAPI::default();
+        let schema = Schema {
+            data: SchemaData::default(),
+            kind: SchemaKind::Type(Type::Integer(IntegerType {
+                minimum: Some(0),
+                maximum: Some(100),
+                enumeration: vec![Some(2), Some(4), Some(8)],
+                ..Default::default()
+            })),
+        };
+
+        let generated = interesting_params_from_schema(
+            &api,
+            &UserExamples::default(),
+            &RefOr::Item(schema.clone()),
+            &[],
+        );
+        assert_eq!(generated, vec![json!(2), json!(4), json!(8)]);
+        assert_eq!(
+            example_from_schema(&api, &UserExamples::default(), &schema),
+            Some(json!(2))
+        );
+    }
+
+    #[test]
+    fn test_unconstrained_integer_includes_negative_boundaries() {
+        let api = OpenAPI::default();
+        let generated = interesting_params_from_type(
+            &api,
+            &UserExamples::default(),
+            &Type::Integer(IntegerType::default()),
+        );
+        assert!(generated.contains(&json!(-1)));
+        assert!(generated.contains(&json!(i32::MIN)));
+
+        let multiple_of_ten = Type::Integer(IntegerType {
+            multiple_of: Some(10),
+            ..Default::default()
+        });
+        let generated =
+            interesting_params_from_type(&api, &UserExamples::default(), &multiple_of_ten);
+        assert!(generated.contains(&json!(-2147483640)));
+
+        let non_negative = Type::Integer(IntegerType {
+            minimum: Some(0),
+            ..Default::default()
+        });
+        let generated = interesting_params_from_type(&api, &UserExamples::default(), &non_negative);
+        assert!(generated.iter().all(|value| value.as_i64().unwrap() >= 0));
+    }
+
+    #[test]
+    fn test_integer_format_boundaries() {
+        let api = OpenAPI::default();
+        let int32: Type = serde_json::from_value(
+            json!({"type": "integer", "format": "int32", "maximum": 10_000_000_000_i64}),
+        )
+        .unwrap();
+        let generated = interesting_params_from_type(&api, &UserExamples::default(), &int32);
+        assert!(generated.contains(&json!(i32::MIN)));
+        assert!(generated
+            .iter()
+            .all(|value| i32::try_from(value.as_i64().unwrap()).is_ok()));
+        let Type::Integer(int32) = int32 else {
+            unreachable!()
+        };
+        let unbounded_int32 = Type::Integer(IntegerType {
+            maximum: None,
+            ..int32
+        });
+        assert!(
+            interesting_params_from_type(&api, &UserExamples::default(), &unbounded_int32)
+                .contains(&json!(i32::MAX))
+        );
+
+        let int64: Type =
+            serde_json::from_value(json!({"type": "integer", "format": "int64"})).unwrap();
+        let generated = interesting_params_from_type(&api, &UserExamples::default(), &int64);
+        assert!(generated.contains(&json!(i64::MIN)));
+        assert!(generated.contains(&json!(i64::MAX)));
+    }
+
+    #[test]
+    fn test_any_enum_generates_only_variants() {
+        let api = OpenAPI::default();
+        let schema: Schema = serde_json::from_value(json!({"enum": [true, {"a": 1}]})).unwrap();
+
+        let generated = interesting_params_from_schema(
+            &api,
+            &UserExamples::default(),
+            &RefOr::Item(schema),
+            &[],
+        );
+        assert_eq!(generated, vec![Value::Bool(true), json!({"a": 1})]);
+    }
+
+    #[test]
+    fn test_dependent_fields_are_generated_together() {
+        let api = OpenAPI::default();
+        let mut raw: serde_yaml::Value = serde_yaml::from_str(
+            "
+            type: object
+            properties:
+              name: {type: string}
+              email: {type: string}
+              card: {type: string}
+              address: {type: string}
+              pin: {type: integer, readOnly: true}
+            dependentRequired:
+              name: [email]
+              card: [address]
+              address: [pin]
+            ",
+        )
+        .unwrap();
+        crate::openapi::dependent_required::preserve_keyword(&mut raw);
+        let schema: Schema = serde_yaml::from_value(raw).unwrap();
+
+        // The pin can not be sent, so neither can the address that requires it, nor the
+        // card that requires the address.
+        let examples = interesting_params_from_schema(
+            &api,
+            &UserExamples::default(),
+            &RefOr::Item(schema.clone()),
+            &[],
+        )
+        .into_iter()
+        .chain(example_from_schema(&api, &UserExamples::default(), &schema));
+        for example in examples {
+            let fields = example.as_object().unwrap();
+            assert!(fields.contains_key("name") && fields.contains_key("email"));
+            assert!(!fields.contains_key("card"));
+            assert!(!fields.contains_key("address"));
+        }
+    }
+
+    #[test]
+    fn test_nullable_integer_includes_null() {
+        let api = OpenAPI::default();
+        let schema: Schema =
+            serde_json::from_value(json!({"type": "integer", "nullable": true})).unwrap();
+
+        let generated = interesting_params_from_schema(
+            &api,
+            &UserExamples::default(),
+            &RefOr::Item(schema),
+            &[],
+        );
+        assert!(generated.iter().any(Value::is_i64));
+        assert!(generated.contains(&Value::Null));
+    }
+
+    #[test]
+    fn test_additional_properties_get_example_entries() {
+        let api = OpenAPI::default();
+        let schema: Schema = serde_json::from_value(json!({
+            "type": "object",
+            "properties": {"name": {"type": "string", "example": "rex"}},
+            "additionalProperties": {"type": "integer"}
+        }))
+        .unwrap();
+
+        let examples = interesting_params_from_schema(
+            &api,
+            &UserExamples::default(),
+            &RefOr::Item(schema.clone()),
+            &[],
+        )
+        .into_iter()
+        .chain(example_from_schema(&api, &UserExamples::default(), &schema));
+        for example in examples {
+            let fields = example.as_object().unwrap();
+            assert_eq!(fields["name"], "rex");
+            assert!(fields
+                .iter()
+                .any(|(key, value)| key != "name" && value.is_i64()));
+        }
+
+        // No room for more properties
+        let schema: Schema = serde_json::from_value(json!({
+            "type": "object",
+            "properties": {"name": {"type": "string"}},
+            "additionalProperties": {"type": "integer"},
+            "maxProperties": 1
+        }))
+        .unwrap();
+        assert_eq!(
+            example_from_schema(&api, &UserExamples::default(), &schema)
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_additional_property_keys_follow_the_seed() {
+        let api = OpenAPI::default();
+        let schema: Schema = serde_json::from_value(json!({
+            "type": "object",
+            "additionalProperties": {"type": "integer"}
+        }))
+        .unwrap();
+        let example = |seed| {
+            seed_examples(seed);
+            example_from_schema(&api, &UserExamples::default(), &schema).unwrap()
+        };
+
+        assert_eq!(example(7), example(7));
+        assert_ne!(example(7), example(8));
+    }
+
+    #[test]
+    fn test_prefix_items_give_tuple_example() {
+        let api = OpenAPI::default();
+        let mut raw: serde_yaml::Value = serde_yaml::from_str(
+            "
+            type: array
+            prefixItems:
+              - type: string
+                example: rex
+              - type: integer
+                example: 4
+            ",
+        )
+        .unwrap();
+        preserve_keyword(&mut raw);
+        let schema: Schema = serde_yaml::from_value(raw).unwrap();
+
+        assert_eq!(
+            example_from_schema(&api, &UserExamples::default(), &schema),
+            Some(json!(["rex", 4]))
+        );
+        assert_eq!(
+            interesting_params_from_schema(
+                &api,
+                &UserExamples::default(),
+                &RefOr::Item(schema),
+                &[]
+            ),
+            vec![json!(["rex", 4])]
+        );
+    }
+
+    #[test]
+    fn test_all_of_example_merges_variants() {
+        let api: OpenAPI = serde_yaml::from_str(
+            "
+            openapi: 3.0.0
+            info: {title: Pets, version: 1.0.0}
+            paths: {}
+            components:
+              schemas:
+                Pet:
+                  type: object
+                  required: [name]
+                  properties:
+                    name: {type: string}
+                    id: {type: integer}
+            ",
+        )
+        .unwrap();
+        let schema: Schema = serde_yaml::from_str(
+            "
+            allOf:
+              - $ref: '#/components/schemas/Pet'
+              - type: object
+                required: [owner]
+                properties:
+                  owner: {type: string}
+            ",
+        )
+        .unwrap();
+
+        let example = example_from_schema(&api, &UserExamples::default(), &schema).unwrap();
+        let fields = example.as_object().unwrap();
+        for field in ["name", "id", "owner"] {
+            assert!(
+                fields.contains_key(field),
+                "{field} is missing from {example}"
+            );
+        }
+    }
+
+    /// Whether the string is a valid JSON Pointer (RFC 6901)
+    fn is_json_pointer(pointer: &str) -> bool {
+        let valid_escapes = pointer
+            .split('~')
+            .skip(1)
+            .all(|after| after.starts_with(['0', '1']));
+        (pointer.is_empty() || pointer.starts_with('/')) && valid_escapes
+    }
+
+    /// Whether the string is a valid Relative JSON Pointer
+    fn is_relative_json_pointer(pointer: &str) -> bool {
+        let digits = pointer.len()
+            - pointer
+                .trim_start_matches(|c: char| c.is_ascii_digit())
+                .len();
+        let (prefix, rest) = pointer.split_at(digits);
+        let valid_prefix = prefix == "0" || (!prefix.is_empty() && !prefix.starts_with('0'));
+        valid_prefix && (rest == "#" || is_json_pointer(rest))
+    }
+
+    #[test]
+    fn test_json_pointer_formats_generate_valid_pointers() {
+        let formats = [
+            ("json-pointer", is_json_pointer as fn(&str) -> bool),
+            ("relative-json-pointer", is_relative_json_pointer),
+        ];
+        for (format, is_valid) in formats {
+            let format = VariantOrUnknownOrEmpty::Unknown(format.to_owned());
+            for (min_length, max_length) in [
+                (None, None),
+                (Some(10), None),
+                (None, Some(2)),
+                (Some(8), Some(8)),
+            ] {
+                let string = StringType {
+                    format: format.clone(),
+                    min_length,
+                    max_length,
+                    ..Default::default()
+                };
+                let pointers =
+                    interesting_params_from_string_type(&UserExamples::default(), &string);
+                assert!(!pointers.is_empty());
+                for pointer in pointers {
+                    let pointer = pointer.as_str().unwrap();
+                    assert!(is_valid(pointer), "{pointer:?} is not a valid {format:?}");
+                    assert!(pointer.len() >= min_length.unwrap_or(0));
+                    assert!(pointer.len() <= max_length.unwrap_or(usize::MAX));
+                }
+            }
+            for pointer in invalid_strings_from_format(&format) {
+                assert!(!is_valid(pointer), "{pointer:?} is a valid {format:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_pattern_sample_respects_length_bounds() {
+        let string_with_pattern = |pattern: &str, min_length| StringType {
+            pattern: Some(pattern.to_owned()),
+            min_length: Some(min_length),
+            ..Default::default()
+        };
+
+        let regex = Regex::new("[A-Z]{2,4}").unwrap();
+        for _ in 0..10 {
+            let generated = interesting_params_from_string_type(
+                &UserExamples::default(),
+                &string_with_pattern("[A-Z]{2,4}", 8),
+            );
+            let sample = generated[0].as_str().unwrap();
+            assert!(sample.len() >= 8, "{sample}");
+            assert!(regex.is_match(sample));
+        }
+
+        // Lengthening would break the anchored pattern, so the sample stays as it is
+        let generated = interesting_params_from_string_type(
+            &UserExamples::default(),
+            &string_with_pattern("^[a-z]{2}$", 8),
+        );
+        let sample = generated[0].as_str().unwrap();
+        assert!(
+            Regex::new("^[a-z]{2}$").unwrap().is_match(sample),
+            "{sample}"
+        );
+    }
+
+    #[test]
+    fn test_password_and_binary_formats_have_examples() {
+        let fallback = VariantOrUnknownOrEmpty::Empty;
+        let password = VariantOrUnknownOrEmpty::Item(StringFormat::Password);
+        let binary = VariantOrUnknownOrEmpty::Item(StringFormat::Binary);
+        for format in [&password, &binary] {
+            assert!(!strings_from_format(format).is_empty());
+            assert_ne!(strings_from_format(format), strings_from_format(&fallback));
+        }
+        assert!(strings_from_format(&password)
+            .iter()
+            .any(|password| password.len() > 72));
+    }
+
+    #[test]
+    fn test_format_examples_vary_and_include_user_values() {
+        let api = OpenAPI::default();
+        let string_of_format = |format| {
+            Type::String(StringType {
+                format,
+                ..Default::default()
+            })
+        };
+
+        let date = string_of_format(VariantOrUnknownOrEmpty::Item(StringFormat::Date));
+        let dates: HashSet<Value> = (0..20)
+            .filter_map(|_| example_from_type(&api, &UserExamples::default(), &date))
+            .collect();
+        assert!(dates.len() > 1, "always the same date: {dates:?}");
+
+        let mut pool = tempfile::NamedTempFile::new().unwrap();
+        writeln!(pool, "iban: [NL91ABNA0417164300]").unwrap();
+        let user_examples = UserExamples::load(Some(pool.path()), None).unwrap();
+        let iban = string_of_format(VariantOrUnknownOrEmpty::Unknown("iban".to_owned()));
+        assert!(interesting_params_from_type(&api, &user_examples, &iban)
+            .contains(&json!("NL91ABNA0417164300")));
+        assert!((0..50)
+            .filter_map(|_| example_from_type(&api, &user_examples, &iban))
+            .any(|example| example == json!("NL91ABNA0417164300")));
+    }
+
+    #[test]
+    fn test_response_example_values_are_parameter_candidates() {
+        let api: OpenAPI = serde_json::from_value(json!({
+            "openapi": "3.0.0",
+            "info": {"title": "pets", "version": "1"},
+            "paths": {
+                "/pets": {
+                    "get": {
+                        "responses": {
+                            "200": {
+                                "description": "All pets",
+                                "content": {"application/json": {
+                                    "schema": {"type": "array", "items": {
+                                        "type": "object",
+                                        "properties": {
+                                            "id": {"type": "integer"},
+                                            "name": {"type": "string"}
+                                        }
+                                    }},
+                                    "example": [{"id": 4711, "name": "Rex"}]
+                                }}
+                            }
+                        }
+                    }
+                },
+                "/pets/{id}": {
+                    "get": {
+                        "parameters": [{"name": "id", "in": "path", "required": true,
+                                        "schema": {"type": "integer"}}],
+                        "responses": {}
+                    }
+                }
+            }
+        }))
+        .unwrap();
+        let response_values = response_example_values(&api, &Synonyms::default());
+        let (path, method, operation, path_item) = api
+            .operations()
+            .find(|(path, ..)| *path == "/pets/{id}")
+            .unwrap();
+        let operation = QualifiedOperation::new(path, method, operation, path_item).unwrap();
+
+        let combinations = all_interesting_parameters(
+            &operation,
+            &api,
+            &UserExamples::default(),
+            &[],
+            &response_values,
+            DEFAULT_MAX_CORPUS_COMBINATIONS,
+        );
+        let key = ("id".to_owned(), ParameterKind::Path);
+        assert!(combinations
+            .iter()
+            .any(|parameters| parameters[&key].to_value() == json!(4711)));
+        // Without the response examples, the value is not generated
+        let combinations = all_interesting_parameters(
+            &operation,
+            &api,
+            &UserExamples::default(),
+            &[],
+            &Default::default(),
+            DEFAULT_MAX_CORPUS_COMBINATIONS,
+        );
+        assert!(combinations
+            .iter()
+            .all(|parameters| parameters[&key].to_value() != json!(4711)));
+    }
+
+    #[test]
+    fn test_example_override_replaces_derived_value() {
+        let api: OpenAPI = serde_json::from_value(json!({
+            "openapi": "3.0.0",
+            "info": {"title": "Pets", "version": "1.0.0"},
+            "paths": {
+                "/owners/{id}": {
+                    "get": {
+                        "parameters": [
+                            {"name": "id", "in": "path", "required": true,
+                             "schema": {"type": "integer"}},
+                            {"name": "verbose", "in": "query",
+                             "schema": {"type": "boolean"}}
+                        ],
+                        "responses": {}
+                    }
+                }
+            }
+        }))
+        .unwrap();
+        let (path, method, operation, path_item) = api.operations().next().unwrap();
+        let operation = QualifiedOperation::new(path, method, operation, path_item).unwrap();
+
+        let mut overrides = tempfile::NamedTempFile::new().unwrap();
+        writeln!(
+            overrides,
+            "GET /owners/{{id}}:\n  parameters:\n    id: 4711"
+        )
+        .unwrap();
+        let user_examples = UserExamples::load(None, Some(overrides.path())).unwrap();
+
+        let id = ("id".to_owned(), ParameterKind::Path);
+        let verbose = ("verbose".to_owned(), ParameterKind::Query);
+        let example = example_from_qualified_operation(&api, &user_examples, operation.clone());
+        assert_eq!(example.parameters[&id].to_value(), json!(4711));
+        assert_eq!(example.parameters[&verbose].to_value(), json!(true));
+        let combinations = all_interesting_parameters(
+            &operation,
+            &api,
+            &user_examples,
+            &[],
+            &Default::default(),
+            DEFAULT_MAX_CORPUS_COMBINATIONS,
+        );
+        assert!(combinations
+            .iter()
+            .all(|parameters| parameters[&id].to_value() == json!(4711)));
+    }
+}
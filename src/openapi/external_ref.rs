@@ -0,0 +1,360 @@
+//! Resolution of references to other files, such as
+//! `$ref: "./common.yaml#/components/schemas/Pet"`, which the OpenAPI parser does not
+//! follow. They are resolved in the raw specification when it is loaded, relative to
+//! the directory of the file that contains the reference.
+//!
+//! A reference to a component of another file, `#/components/<kind>/<name>`, is hoisted:
+//! the component is copied into the components of the main specification and the
+//! reference is made local, so that components may refer to themselves. Any other
+//! reference, e.g. to a whole file of path items, is replaced by the referenced value.
+//! References to remote URLs are left alone.
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+use indexmap::IndexMap;
+use serde_yaml::{Mapping, Value};
+
+/// A value in a file: the canonical path of the file and a JSON Pointer into it
+type Location = (PathBuf, String);
+
+/// Resolves all references to other files in `spec`, the raw specification loaded from
+/// `spec_path`.
+pub fn resolve_external_refs(spec: &mut Value, spec_path: &Path) -> Result<()> {
+    let spec_path = spec_path
+        .canonicalize()
+        .with_context(|| format!("Could not find {}", spec_path.to_string_lossy()))?;
+    let mut resolver = Resolver::new(spec, spec_path.clone());
+    resolver.resolve(spec, &spec_path)?;
+
+    if resolver.hoisted_components.is_empty() {
+        return Ok(());
+    }
+    let Value::Mapping(spec) = spec else {
+        bail!("The specification is not an object");
+    };
+    let components = spec
+        .entry("components".into())
+        .or_insert_with(|| Value::Mapping(Mapping::new()));
+    let Value::Mapping(components) = components else {
+        bail!("The components of the specification are not an object");
+    };
+    for (kind, hoisted) in resolver.hoisted_components {
+        let Value::Mapping(of_kind) = components
+            .entry(kind.clone().into())
+            .or_insert_with(|| Value::Mapping(Mapping::new()))
+        else {
+            bail!("The {kind} components of the specification are not an object");
+        };
+        of_kind.extend(
+            hoisted
+                .into_iter()
+                .map(|(name, value)| (name.into(), value)),
+        );
+    }
+    Ok(())
+}
+
+struct Resolver {
+    spec_path: PathBuf,
+    /// The files loaded so far, by canonical path
+    documents: HashMap<PathBuf, Value>,
+    /// Local references to the components hoisted so far, by their original location
+    hoisted: HashMap<Location, String>,
+    /// Components copied from other files, by kind and name
+    hoisted_components: IndexMap<String, IndexMap<String, Value>>,
+    /// Component names in use, by kind, so that hoisted components get unique names
+    taken_names: HashMap<String, HashSet<String>>,
+    /// The values being inlined, to detect references that include themselves
+    inlining: Vec<Location>,
+}
+
+impl Resolver {
+    fn new(spec: &Value, spec_path: PathBuf) -> Self {
+        let taken_names = match spec.get("components") {
+            Some(Value::Mapping(components)) => components
+                .iter()
+                .filter_map(|(kind, of_kind)| {
+                    let names = of_kind
+                        .as_mapping()?
+                        .keys()
+                        .filter_map(Value::as_str)
+                        .map(str::to_owned)
+                        .collect();
+                    Some((kind.as_str()?.to_owned(), names))
+                })
+                .collect(),
+            _ => HashMap::new(),
+        };
+        Self {
+            spec_path,
+            documents: HashMap::new(),
+            hoisted: HashMap::new(),
+            hoisted_components: IndexMap::new(),
+            taken_names,
+            inlining: vec![],
+        }
+    }
+
+    /// Resolves the references to other files in `value`, which is part of `file`
+    fn resolve(&mut self, value: &mut Value, file: &Path) -> Result<()> {
+        match value {
+            Value::Mapping(mapping) => {
+                if let Some(Value::String(reference)) = mapping.get("$ref") {
+                    if let Some(resolved) = self.resolve_ref(&reference.clone(), file)? {
+                        *value = resolved;
+                    }
+                    return Ok(());
+                }
+                for child in mapping.values_mut() {
+                    self.resolve(child, file)?;
+                }
+            }
+            Value::Sequence(sequence) => {
+                for element in sequence {
+                    self.resolve(element, file)?;
+                }
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    /// The value replacing the reference found in `file`, or None if it can stay
+    fn resolve_ref(&mut self, reference: &str, file: &Path) -> Result<Option<Value>> {
+        let (target_file, pointer) = reference.split_once('#').unwrap_or((reference, ""));
+        if target_file.contains("://") {
+            return Ok(None);
+        }
+        let target_file = match target_file {
+            "" => file.to_owned(),
+            relative => {
+                let path = file.parent().unwrap_or(Path::new("")).join(relative);
+                path.canonicalize().with_context(|| {
+                    format!(
+                        "Could not find {}, referenced from {}",
+                        path.to_string_lossy(),
+                        file.to_string_lossy()
+                    )
+                })?
+            }
+        };
+        let local_ref = |pointer: &str| {
+            let mut reference = Mapping::new();
+            reference.insert("$ref".into(), format!("#{pointer}").into());
+            Value::Mapping(reference)
+        };
+        if target_file == self.spec_path {
+            return Ok(if file == self.spec_path {
+                None
+            } else {
+                Some(local_ref(pointer))
+            });
+        }
+
+        let location = (target_file, pointer.to_owned());
+        match pointer.split('/').collect::<Vec<_>>()[..] {
+            ["", "components", kind, name] => {
+                let pointer = self.hoist(location, kind, &unescape(name))?;
+                Ok(Some(local_ref(&pointer)))
+            }
+            _ => self.inline(location).map(Some),
+        }
+    }
+
+    /// Copies the component at `location` into the main specification, and returns the
+    /// pointer to the copy
+    fn hoist(&mut self, location: Location, kind: &str, name: &str) -> Result<String> {
+        if let Some(pointer) = self.hoisted.get(&location) {
+            return Ok(pointer.clone());
+        }
+        let taken = self.taken_names.entry(kind.to_owned()).or_default();
+        let unique_name = (1..)
+            .map(|n| match n {
+                1 => name.to_owned(),
+                n => format!("{name}{n}"),
+            })
+            .find(|candidate| !taken.contains(candidate))
+            .expect("There are infinitely many candidates");
+        taken.insert(unique_name.clone());
+        let pointer = format!(
+            "/components/{kind}/{}",
+            unique_name.replace('~', "~0").replace('/', "~1")
+        );
+        // Registered before resolving the component, so that it may refer to itself
+        self.hoisted.insert(location.clone(), pointer.clone());
+
+        let mut component = self.fragment(&location)?;
+        self.resolve(&mut component, &location.0)?;
+        self.hoisted_components
+            .entry(kind.to_owned())
+            .or_default()
+            .insert(unique_name, component);
+        Ok(pointer)
+    }
+
+    /// The value at `location`, with the references in it resolved
+    fn inline(&mut self, location: Location) -> Result<Value> {
+        if self.inlining.contains(&location) {
+            bail!(
+                "The reference to {}#{} includes itself",
+                location.0.to_string_lossy(),
+                location.1
+            );
+        }
+        let mut value = self.fragment(&location)?;
+        self.inlining.push(location);
+        let file = self.inlining.last().unwrap().0.clone();
+        self.resolve(&mut value, &file)?;
+        self.inlining.pop();
+        Ok(value)
+    }
+
+    /// The unresolved value at `location`, loading its file if needed
+    fn fragment(&mut self, (file, pointer): &Location) -> Result<Value> {
+        if !self.documents.contains_key(file) {
+            let contents = std::fs::File::open(file)
+                .with_context(|| format!("Could not open {}", file.to_string_lossy()))?;
+            let document: Value = serde_yaml::from_reader(contents)
+                .with_context(|| format!("Error parsing {}", file.to_string_lossy()))?;
+            self.documents.insert(file.clone(), document);
+        }
+        pointer
+            .split('/')
+            .skip(1)
+            .try_fold(&self.documents[file], |value, token| match value {
+                Value::Sequence(sequence) => {
+                    token.parse::<usize>().ok().and_then(|i| sequence.get(i))
+                }
+                _ => value.get(unescape(token)),
+            })
+            .cloned()
+            .with_context(|| format!("{} does not contain {pointer}", file.to_string_lossy()))
+    }
+}
+
+/// Unescapes a reference token of a JSON Pointer
+fn unescape(token: &str) -> String {
+    token.replace("~1", "/").replace("~0", "~")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use openapiv3::{RefOr, SchemaKind, Type};
+
+    use crate::openapi::get_api_spec;
+
+    fn write(dir: &Path, name: &str, contents: &str) {
+        std::fs::write(dir.join(name), contents).unwrap();
+    }
+
+    #[test]
+    fn test_external_components_are_hoisted() {
+        let dir = tempfile::tempdir().unwrap();
+        write(
+            dir.path(),
+            "openapi.yaml",
+            r##"
+openapi: 3.0.0
+info:
+  title: Pets
+  version: 1.0.0
+paths:
+  /pets:
+    $ref: "./paths/pets.yaml"
+"##,
+        );
+        std::fs::create_dir(dir.path().join("paths")).unwrap();
+        write(
+            dir.path(),
+            "paths/pets.yaml",
+            r##"
+get:
+  responses:
+    "200":
+      description: A pet
+      content:
+        application/json:
+          schema:
+            $ref: "../common.yaml#/components/schemas/Pet"
+"##,
+        );
+        write(
+            dir.path(),
+            "common.yaml",
+            r##"
+components:
+  schemas:
+    Pet:
+      type: object
+      properties:
+        name:
+          type: string
+        tag:
+          $ref: "#/components/schemas/Tag"
+        children:
+          type: array
+          items:
+            $ref: "#/components/schemas/Pet"
+    Tag:
+      type: string
+"##,
+        );
+
+        let api = get_api_spec(&dir.path().join("openapi.yaml")).unwrap();
+        let schemas = &api.components.schemas;
+        let RefOr::Item(pet) = &schemas["Pet"] else {
+            panic!("Pet is not inlined");
+        };
+        let SchemaKind::Type(Type::Object(pet)) = &pet.kind else {
+            panic!("Pet is not an object");
+        };
+        assert!(pet.properties.contains_key("name"));
+        assert!(schemas.contains_key("Tag"));
+
+        let (_, _, operation, _) = api.operations().next().unwrap();
+        let response = operation.responses.responses.values().next().unwrap();
+        let RefOr::Item(response) = response else {
+            panic!("The response is a reference");
+        };
+        let schema = response.content["application/json"].schema.as_ref();
+        assert_eq!(
+            schema.and_then(RefOr::as_ref_str),
+            Some("#/components/schemas/Pet")
+        );
+    }
+
+    #[test]
+    fn test_self_including_reference_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        write(
+            dir.path(),
+            "openapi.yaml",
+            r##"
+openapi: 3.0.0
+info:
+  title: Loop
+  version: 1.0.0
+paths:
+  /loop:
+    $ref: "./loop.yaml#/path"
+"##,
+        );
+        write(
+            dir.path(),
+            "loop.yaml",
+            r##"
+path:
+  get:
+    $ref: "#/path"
+"##,
+        );
+        let error = get_api_spec(&dir.path().join("openapi.yaml")).unwrap_err();
+        assert!(format!("{error:#}").contains("includes itself"));
+    }
+}
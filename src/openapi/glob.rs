@@ -0,0 +1,16 @@
+//! Matching of paths against the globs with which options such as the path filter and
+//! time-out overrides select endpoints.
+
+/// Whether `text` matches the glob `pattern`, in which `*` matches any characters except
+/// `/` and `**` any characters at all.
+pub fn glob_matches(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        [b'*', b'*', rest @ ..] => (0..=text.len()).any(|skip| glob_matches(rest, &text[skip..])),
+        [b'*', rest @ ..] => {
+            let segment_end = text.iter().position(|c| *c == b'/').unwrap_or(text.len());
+            (0..=segment_end).any(|skip| glob_matches(rest, &text[skip..]))
+        }
+        [c, rest @ ..] => text.first() == Some(c) && glob_matches(rest, &text[1..]),
+    }
+}
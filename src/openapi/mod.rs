@@ -1,14 +1,23 @@
-use std::{convert::TryFrom, fmt::Debug, path::Path};
+use std::{convert::TryFrom, fmt::Debug, path::Path, str::FromStr};
 
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use indexmap::IndexMap;
-use openapiv3::{MediaType, OpenAPI, Operation, PathItem, VersionedOpenAPI};
+use openapiv3::{
+    Header, MediaType, OpenAPI, Operation, ParameterSchemaOrContent, PathItem, RefOr, Server,
+    ServerVariable, VersionedOpenAPI,
+};
 
-use crate::input::{method::InvalidMethodError, Method};
+use self::examples::UserExamples;
+use crate::input::{method::InvalidMethodError, multipart::PartEncoding, Method};
 
 pub mod build_request;
 pub mod curl_request;
+pub mod dependent_required;
 pub mod examples;
+pub mod external_ref;
+pub mod glob;
+pub mod path_filter;
+pub mod prefix_items;
 pub mod validate_response;
 
 /// Loads the OpenAPI specification from the given path
@@ -18,6 +27,76 @@ pub fn get_api_spec(path: &Path) -> Result<Box<OpenAPI>, anyhow::Error> {
         .with_context(|| format!("Error parsing OpenAPI-file at {}", path.to_string_lossy()))
 }
 
+/// Replaces the servers listed in the specification by `target`, if it is given, so
+/// that all requests are sent there.
+pub fn override_servers(api: &mut OpenAPI, target: Option<&str>) {
+    if let Some(url) = target {
+        api.servers = vec![Server {
+            url: url.to_owned(),
+            ..Default::default()
+        }];
+    }
+}
+
+/// A value for a variable in the server URL, such as `{region}` in
+/// `https://{region}.api.example.com`, written as `name=value`.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+#[serde(try_from = "String")]
+pub struct ServerVariableValue {
+    pub name: String,
+    pub value: String,
+}
+
+impl FromStr for ServerVariableValue {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((name, value)) if !name.is_empty() => Ok(Self {
+                name: name.to_owned(),
+                value: value.to_owned(),
+            }),
+            _ => Err(anyhow!(
+                "Server variable \"{s}\" is not of the form name=value"
+            )),
+        }
+    }
+}
+
+impl TryFrom<String> for ServerVariableValue {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+/// Sets the values of variables in the server URLs, overriding their defaults. A value
+/// applies to every server whose URL contains the variable, also if the specification
+/// does not declare it.
+pub fn set_server_variables(api: &mut OpenAPI, values: &[ServerVariableValue]) {
+    for ServerVariableValue { name, value } in values {
+        let placeholder = format!("{{{name}}}");
+        let mut used = false;
+        for server in api
+            .servers
+            .iter_mut()
+            .filter(|server| server.url.contains(&placeholder))
+        {
+            server
+                .variables
+                .get_or_insert_with(IndexMap::new)
+                .entry(name.clone())
+                .or_insert_with(ServerVariable::default)
+                .default = value.clone();
+            used = true;
+        }
+        if !used {
+            log::warn!("Server variable {name} does not occur in any server URL");
+        }
+    }
+}
+
 /// A QualifiedOperation is the (path, method, operation) tuple returned from
 /// `api.operations()`, and is used to identify an operation uniquely in the graph.
 #[allow(dead_code)]
@@ -48,7 +127,11 @@ impl<'a> QualifiedOperation<'a> {
 
 pub fn openapi_from_yaml_file(filename: &Path) -> Result<OpenAPI> {
     let file = std::fs::File::open(filename)?;
-    let open_api: VersionedOpenAPI = serde_yaml::from_reader(file)?;
+    let mut raw: serde_yaml::Value = serde_yaml::from_reader(file)?;
+    external_ref::resolve_external_refs(&mut raw, filename)?;
+    dependent_required::preserve_keyword(&mut raw);
+    prefix_items::preserve_keyword(&mut raw);
+    let open_api: VersionedOpenAPI = serde_yaml::from_value(raw)?;
     Ok(open_api.upgrade())
 }
 
@@ -100,6 +183,107 @@ impl WwwForm for IndexMap<String, MediaType> {
     }
 }
 
+pub trait Multipart {
+    fn get_multipart_content(&self) -> Option<&MediaType>;
+    fn has_multipart_content(&self) -> bool;
+}
+
+impl Multipart for IndexMap<String, MediaType> {
+    fn get_multipart_content(&self) -> Option<&MediaType> {
+        self.iter()
+            .find_map(|(key, value)| key.starts_with("multipart/form-data").then_some(value))
+    }
+    fn has_multipart_content(&self) -> bool {
+        self.iter()
+            .any(|(key, _value)| key.starts_with("multipart/form-data"))
+    }
+}
+
+/// Returns the media types of all responses that the operation declares, in order of
+/// appearance and without duplicates.
+pub fn response_media_types(api: &OpenAPI, operation: &Operation) -> Vec<String> {
+    let mut media_types: Vec<String> = Vec::new();
+    for response in operation
+        .responses
+        .responses
+        .values()
+        .chain(operation.responses.default.as_ref())
+        .filter_map(|ref_or_response| ref_or_response.resolve(api).ok())
+    {
+        for media_type in response.content.keys() {
+            if !media_types.contains(media_type) {
+                media_types.push(media_type.clone());
+            }
+        }
+    }
+    media_types
+}
+
+/// Returns the encoding that the multipart request body of the operation declares for
+/// its parts, by part name. The values of the part headers are taken from their
+/// examples or schemas; headers without either are left out.
+pub fn multipart_encoding(
+    api: &OpenAPI,
+    user_examples: &UserExamples,
+    operation: &Operation,
+) -> IndexMap<String, PartEncoding> {
+    operation
+        .request_body
+        .as_ref()
+        .and_then(|ref_or_body| ref_or_body.resolve(api).ok())
+        .and_then(|body| body.content.get_multipart_content())
+        .map(|media_type| {
+            media_type
+                .encoding
+                .iter()
+                .map(|(name, encoding)| {
+                    let headers = encoding
+                        .headers
+                        .iter()
+                        .filter_map(|(header, ref_or_header)| {
+                            Some((
+                                header.clone(),
+                                header_example(api, user_examples, ref_or_header)?,
+                            ))
+                        })
+                        .collect();
+                    let part = PartEncoding {
+                        content_type: encoding.content_type.clone(),
+                        headers,
+                    };
+                    (name.clone(), part)
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// An example value of the header, from its example or its schema
+fn header_example(
+    api: &OpenAPI,
+    user_examples: &UserExamples,
+    ref_or_header: &RefOr<Header>,
+) -> Option<String> {
+    let header = match ref_or_header {
+        RefOr::Item(header) => header,
+        RefOr::Reference { reference } => {
+            let name = reference.strip_prefix("#/components/headers/")?;
+            api.headers.get(name)?.as_item()?
+        }
+    };
+    let example = match (&header.example, &header.format) {
+        (Some(example), _) => example.clone(),
+        (None, ParameterSchemaOrContent::Schema(schema)) => {
+            examples::example_from_schema(api, user_examples, schema.resolve(api))?
+        }
+        (None, ParameterSchemaOrContent::Content(_)) => return None,
+    };
+    Some(match example {
+        serde_json::Value::String(text) => text,
+        other => other.to_string(),
+    })
+}
+
 pub trait TextPlain {
     #[allow(dead_code)]
     fn get_text_plain(&self) -> Option<&MediaType>;
@@ -116,3 +300,61 @@ impl TextPlain for IndexMap<String, MediaType> {
             .any(|(key, _value)| key.starts_with("text/plain"))
     }
 }
+
+pub trait OctetStream {
+    #[allow(dead_code)]
+    fn get_octet_stream_content(&self) -> Option<&MediaType>;
+    fn has_octet_stream_content(&self) -> bool;
+}
+
+impl OctetStream for IndexMap<String, MediaType> {
+    fn get_octet_stream_content(&self) -> Option<&MediaType> {
+        self.iter()
+            .find_map(|(key, value)| key.starts_with("application/octet-stream").then_some(value))
+    }
+    fn has_octet_stream_content(&self) -> bool {
+        self.iter()
+            .any(|(key, _value)| key.starts_with("application/octet-stream"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+    use openapiv3::{OpenAPI, Server, ServerVariable};
+
+    use super::{build_request::server_url, set_server_variables, ServerVariableValue};
+
+    #[test]
+    fn test_server_variables_are_substituted() {
+        let variable = |default: &str| ServerVariable {
+            default: default.to_owned(),
+            ..Default::default()
+        };
+        let mut api = OpenAPI {
+            servers: vec![Server {
+                url: "https://{region}.api.example.com/{basePath}".to_owned(),
+                variables: Some(IndexMap::from([
+                    ("region".to_owned(), variable("eu")),
+                    ("basePath".to_owned(), variable("v1")),
+                ])),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        assert_eq!(server_url(&api.servers[0]), "https://eu.api.example.com/v1");
+
+        let values: Vec<ServerVariableValue> = vec![
+            "region=us".parse().unwrap(),
+            "basePath=v2/beta".parse().unwrap(),
+        ];
+        set_server_variables(&mut api, &values);
+        assert_eq!(
+            server_url(&api.servers[0]),
+            "https://us.api.example.com/v2/beta"
+        );
+
+        assert!("region".parse::<ServerVariableValue>().is_err());
+        assert!("=us".parse::<ServerVariableValue>().is_err());
+    }
+}
@@ -0,0 +1,73 @@
+//! Restricts fuzzing to a subset of the endpoints of a large API, with path globs of
+//! endpoints to include and to exclude. In the globs, `*` matches any characters except
+//! `/` and `**` also matches `/`.
+
+use openapiv3::OpenAPI;
+
+use crate::openapi::glob::glob_matches;
+
+/// Which paths of the API are fuzzed. A path is fuzzed if it matches one of the include
+/// globs, even if it matches an exclude glob as well. Other paths are fuzzed if there are
+/// no include globs and they match none of the exclude globs.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PathFilter {
+    include: Vec<String>,
+    exclude: Vec<String>,
+}
+
+impl PathFilter {
+    pub fn new(include: Vec<String>, exclude: Vec<String>) -> Self {
+        Self { include, exclude }
+    }
+
+    /// Whether requests to `path` may be fuzzed
+    pub fn allows(&self, path: &str) -> bool {
+        let matches = |globs: &[String]| {
+            globs
+                .iter()
+                .any(|glob| glob_matches(glob.as_bytes(), path.as_bytes()))
+        };
+        if matches(&self.include) {
+            return true;
+        }
+        self.include.is_empty() && !matches(&self.exclude)
+    }
+
+    /// The indices, in `api.operations()`, of the operations that may be fuzzed
+    pub fn allowed_operations(&self, api: &OpenAPI) -> Vec<usize> {
+        api.operations()
+            .enumerate()
+            .filter(|(_, (path, _, _, _))| self.allows(path))
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// A copy of the specification without the paths that may not be fuzzed
+    pub fn filter_api(&self, api: &OpenAPI) -> OpenAPI {
+        let mut filtered = api.clone();
+        filtered.paths.paths.retain(|path, _| self.allows(path));
+        filtered
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PathFilter;
+
+    #[test]
+    fn test_include_overrides_exclude() {
+        let filter = PathFilter::new(
+            vec!["/admin/health".to_owned()],
+            vec!["/admin/**".to_owned()],
+        );
+        assert!(filter.allows("/admin/health"));
+        assert!(!filter.allows("/admin/users"));
+        // With include globs, paths that match neither are not fuzzed
+        assert!(!filter.allows("/pets"));
+
+        let filter = PathFilter::new(vec![], vec!["/admin/**".to_owned()]);
+        assert!(!filter.allows("/admin/users/1"));
+        assert!(filter.allows("/pets"));
+        assert!(PathFilter::default().allows("/anything"));
+    }
+}
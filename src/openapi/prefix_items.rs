@@ -0,0 +1,50 @@
+//! Support for the `prefixItems` keyword of OpenAPI 3.1, with which an array is a tuple:
+//! each of its first elements has a schema of its own, e.g. `[string, integer]`.
+//!
+//! The OpenAPI parser drops keywords it does not know, except for extensions. The
+//! keyword is therefore renamed to the `x-prefixItems` extension when the specification
+//! is loaded, and read back from the extensions of the schema.
+
+use openapiv3::{RefOr, Schema};
+use serde_yaml::Value;
+
+/// Keyword as it appears in the specification
+const KEYWORD: &str = "prefixItems";
+
+/// Name of the extension the keyword is kept in after parsing
+const EXTENSION: &str = "x-prefixItems";
+
+/// Renames all `prefixItems` keywords in the raw specification to the extension that
+/// survives parsing. Property names are left alone.
+pub fn preserve_keyword(value: &mut Value) {
+    match value {
+        Value::Mapping(mapping) => {
+            if mapping.get(KEYWORD).is_some_and(Value::is_sequence) {
+                let prefix_items = mapping.shift_remove(KEYWORD).unwrap();
+                mapping.insert(EXTENSION.into(), prefix_items);
+            }
+            for (key, child) in mapping.iter_mut() {
+                match child {
+                    // The keys of `properties` are names, only the schemas are searched
+                    Value::Mapping(properties) if key.as_str() == Some("properties") => {
+                        properties.values_mut().for_each(preserve_keyword)
+                    }
+                    _ => preserve_keyword(child),
+                }
+            }
+        }
+        Value::Sequence(sequence) => sequence.iter_mut().for_each(preserve_keyword),
+        _ => (),
+    }
+}
+
+/// Returns the schemas of the first elements of the array, in order, according to the
+/// `prefixItems` keyword of the schema. Empty if the array is not a tuple.
+pub fn prefix_items(schema: &Schema) -> Vec<RefOr<Schema>> {
+    schema
+        .data
+        .extensions
+        .get(EXTENSION)
+        .and_then(|prefix_items| serde_json::from_value(prefix_items.clone()).ok())
+        .unwrap_or_default()
+}
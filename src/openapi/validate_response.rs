@@ -1,12 +1,26 @@
-use std::{error::Error, str::Utf8Error};
+use std::{
+    collections::HashMap, error::Error, fmt::Display, str::Utf8Error, sync::Mutex, time::Duration,
+};
 
 use anyhow::Result;
-use openapiv3::{OpenAPI, ReferenceOr, Schema, Type};
-use reqwest::StatusCode;
+use openapiv3::{ObjectType, OpenAPI, ReferenceOr, Schema, Type};
+use regex::Regex;
+use reqwest::{
+    header::{HeaderMap, RETRY_AFTER},
+    StatusCode,
+};
 use serde_json::Value;
 
-use super::JsonContent;
-use crate::input::{Method, OpenApiRequest};
+use super::{dependent_required::missing_dependents, JsonContent};
+use crate::{
+    input::{parameter::ParameterKind, Method, OpenApiRequest},
+    openapi_mutator::prefer::PREFER_HEADER,
+};
+
+lazy_static! {
+    /// Compiled `pattern`s of string schemas, or None if they are not a valid regex
+    static ref PATTERNS: Mutex<HashMap<String, Option<Regex>>> = Mutex::new(HashMap::new());
+}
 
 /// The Response object provided by Reqwest is unwieldy, since its body contents
 /// can only be obtained once by consuming the object. This prevents later reading
@@ -16,15 +30,53 @@ use crate::input::{Method, OpenApiRequest};
 /// body contents by reference.
 pub struct Response {
     status: reqwest::StatusCode,
+    headers: HeaderMap,
     cookies: Vec<(String, String)>,
     body: Vec<u8>,
 }
 
 impl Response {
+    /// Creates a response without cookies, for testing
+    #[cfg(test)]
+    pub fn new(status: reqwest::StatusCode, body: Vec<u8>) -> Self {
+        Self {
+            status,
+            headers: HeaderMap::new(),
+            cookies: vec![],
+            body,
+        }
+    }
+
+    /// Creates a response without cookies from its parts, e.g. as recorded earlier
+    pub fn from_parts(status: reqwest::StatusCode, headers: HeaderMap, body: Vec<u8>) -> Self {
+        Self {
+            status,
+            headers,
+            cookies: vec![],
+            body,
+        }
+    }
+
+    /// Adds a header to a response, for testing
+    #[cfg(test)]
+    pub fn with_header(mut self, name: reqwest::header::HeaderName, value: &str) -> Self {
+        self.headers
+            .append(name, reqwest::header::HeaderValue::from_str(value).unwrap());
+        self
+    }
+
     pub fn status(&self) -> reqwest::StatusCode {
         self.status
     }
 
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
     /// This returns the length of the decompressed contents, even if no content-length
     /// was sent by the server.
     pub fn content_length(&self) -> u64 {
@@ -46,6 +98,7 @@ impl From<reqwest::blocking::Response> for Response {
     fn from(resp: reqwest::blocking::Response) -> Self {
         Self {
             status: resp.status(),
+            headers: resp.headers().clone(),
             cookies: resp
                 .cookies()
                 .map(|c| (c.name().to_owned(), c.value().to_owned()))
@@ -97,6 +150,26 @@ pub enum ValidationError {
     /// If this variant is returned, the API does not behave as specified.
     ResponseEnumIncorrect { incorrect_variant: String },
 
+    /// A string in the response body does not match the `pattern` of its schema. Only
+    /// checked if constraints are checked.
+    ///
+    /// If this variant is returned, the API does not behave as specified.
+    ResponsePatternMismatch {
+        field: String,
+        value: String,
+        pattern: String,
+    },
+
+    /// A number in the response body lies outside the `minimum` or `maximum` of its
+    /// schema. Only checked if constraints are checked.
+    ///
+    /// If this variant is returned, the API does not behave as specified.
+    ResponseBoundViolation {
+        field: String,
+        value: String,
+        bound: String,
+    },
+
     /// The response body returned by the API can not be parsed as JSON.
     ///
     /// If this variant is returned, the API might contain a bug, or it might
@@ -109,6 +182,15 @@ pub enum ValidationError {
     /// If this variant is returned, the API does not behave as specified.
     UnexpectedContent { content_length: u64 },
 
+    /// The API returned a response body with a status that does not allow one, such
+    /// as 204 No Content or 304 Not Modified.
+    ///
+    /// If this variant is returned, the API violates the HTTP specification.
+    UnexpectedBodyForNoContent {
+        status: StatusCode,
+        content_length: u64,
+    },
+
     /// The API contains a media type "application/json" with no schema for
     /// the data inside the json object. We can't validate the response if no
     /// model is given.
@@ -117,9 +199,47 @@ pub enum ValidationError {
     /// The schema can be anything (occurs e.g. when it does not specify a type)
     /// We cannot validate schemas that are this flexible.
     SchemaIsAny(String),
+
+    /// The API returned 429 Too Many Requests or 503 Service Unavailable without a
+    /// well-formed `Retry-After` header, so clients cannot know when to retry. The
+    /// header is None if it was missing.
+    ///
+    /// If this variant is returned, the API violates the HTTP specification.
+    RateLimitHeaderInvalid {
+        status: StatusCode,
+        retry_after: Option<String>,
+    },
+
+    /// The API took longer than the configured threshold to respond. The response
+    /// itself may be fine, but slow endpoints can point to pathological inputs, such
+    /// as regular expressions with catastrophic backtracking or unbounded queries.
+    ResponseTooSlow {
+        elapsed: Duration,
+        threshold: Duration,
+    },
 }
 
 impl ValidationError {
+    /// Short name of the kind of error, without any details of the particular case.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::OperationNotInSpec { .. } => "operation_not_in_spec",
+            Self::StatusNotSpecified { .. } => "status_not_specified",
+            Self::ResponseReferenceBroken { .. } => "response_reference_broken",
+            Self::ResponseObjectIncorrect { .. } => "response_object_incorrect",
+            Self::ResponseEnumIncorrect { .. } => "response_enum_incorrect",
+            Self::ResponsePatternMismatch { .. } => "response_pattern_mismatch",
+            Self::ResponseBoundViolation { .. } => "response_bound_violation",
+            Self::ResponseMalformedJSON { .. } => "response_malformed_json",
+            Self::UnexpectedContent { .. } => "unexpected_content",
+            Self::UnexpectedBodyForNoContent { .. } => "unexpected_body_for_no_content",
+            Self::MediaTypeContainsNoSchema => "media_type_contains_no_schema",
+            Self::SchemaIsAny(_) => "schema_is_any",
+            Self::RateLimitHeaderInvalid { .. } => "rate_limit_header_invalid",
+            Self::ResponseTooSlow { .. } => "response_too_slow",
+        }
+    }
+
     /// Validation happens recursively, and if a deeply nested field contains an
     /// error, it is nice if the validation error that is eventually returned
     /// pinpoints the path to the field that is incorrect.
@@ -139,6 +259,8 @@ impl ValidationError {
             Self::ResponseEnumIncorrect {
                 ref mut incorrect_variant,
             } => nest(incorrect_variant),
+            Self::ResponsePatternMismatch { ref mut field, .. }
+            | Self::ResponseBoundViolation { ref mut field, .. } => nest(field),
             _ => (),
         };
         self
@@ -170,12 +292,24 @@ impl std::fmt::Display for ValidationError {
                 fmt,
                 "Unexpected response body content. content-length: {content_length}"
             ),
+            ValidationError::UnexpectedBodyForNoContent { status, content_length } => write!(
+                fmt,
+                "Response body not allowed for HTTP status {status}. content-length: {content_length}"
+            ),
             ValidationError::ResponseEnumIncorrect {
                 incorrect_variant,
             } => write!(
                 fmt,
                 "Response enumeration has non-existent variant {incorrect_variant}"
             ),
+            ValidationError::ResponsePatternMismatch { field, value, pattern } => write!(
+                fmt,
+                "Response string {value:?} in field \"{field}\" does not match pattern {pattern}"
+            ),
+            ValidationError::ResponseBoundViolation { field, value, bound } => write!(
+                fmt,
+                "Response number {value} in field \"{field}\" violates {bound}"
+            ),
             ValidationError::MediaTypeContainsNoSchema => write!(
                 fmt,
                 "The specification does not contain a schema for JSON responses, so the response can not be validated"
@@ -184,17 +318,33 @@ impl std::fmt::Display for ValidationError {
                 fmt,
                 "The specification accepts any schema for this response, which is too flexible for us to validate. \
                 Make sure the schema specifies a type!\nSchema description: {schema_str}"),
+            ValidationError::RateLimitHeaderInvalid { status, retry_after: None } => write!(
+                fmt,
+                "HTTP status {status} returned without a Retry-After header"
+            ),
+            ValidationError::RateLimitHeaderInvalid { status, retry_after: Some(retry_after) } => write!(
+                fmt,
+                "HTTP status {status} returned with malformed Retry-After header \"{retry_after}\""
+            ),
+            ValidationError::ResponseTooSlow { elapsed, threshold } => write!(
+                fmt,
+                "Response took {} ms, longer than the threshold of {} ms",
+                elapsed.as_millis(),
+                threshold.as_millis()
+            ),
         }
     }
 }
 impl Error for ValidationError {}
 
-// Validates whether the response matches the API.
+// Validates whether the response matches the API. If `check_constraints` is set, the
+// `pattern`s of strings and the bounds of numbers are validated as well.
 // The return value contains a description of the particular mismatch.
 pub fn validate_response(
     api: &OpenAPI,
     request: &OpenApiRequest,
     response: &Response,
+    check_constraints: bool,
 ) -> Result<(), ValidationError> {
     let op = super::find_operation(api, &request.path, request.method).ok_or_else(|| {
         ValidationError::OperationNotInSpec {
@@ -203,6 +353,15 @@ pub fn validate_response(
         }
     })?;
 
+    // Some statuses never have a body, whatever the specification says
+    let content_length = response.content_length();
+    if is_bodiless(response.status()) && content_length > 0 {
+        return Err(ValidationError::UnexpectedBodyForNoContent {
+            status: response.status(),
+            content_length,
+        });
+    }
+
     let desired_response = op
         .responses
         .responses
@@ -240,11 +399,89 @@ pub fn validate_response(
         .ok_or_else(|| ValidationError::MediaTypeContainsNoSchema)?
         .resolve(api);
 
+    // A server that honors `Prefer: return=minimal` may leave out the body
+    if response.status().is_success() && content_length == 0 && prefers_minimal_return(request) {
+        return Ok(());
+    }
+
     let response_contents = response
         .json()
         .map_err(|e| ValidationError::ResponseMalformedJSON { error: e })?;
 
-    validate_object_against_schema(api, response_schema, &response_contents)
+    validate_object_against_schema(api, response_schema, &response_contents, check_constraints)
+}
+
+/// Whether the request has a `Prefer` header with the `return=minimal` preference
+/// (RFC 7240), which asks the server to leave out the body of a successful response.
+fn prefers_minimal_return(request: &OpenApiRequest) -> bool {
+    request
+        .parameters
+        .iter()
+        .filter(|((name, kind), _)| {
+            *kind == ParameterKind::Header && name.eq_ignore_ascii_case(PREFER_HEADER)
+        })
+        .any(|(_, value)| {
+            let value = value.to_header_value();
+            let value = value.to_str().unwrap_or_default();
+            value.split(',').any(|preference| {
+                let preference = preference.split(';').next().unwrap_or_default();
+                preference
+                    .split('=')
+                    .map(|token| token.trim().to_ascii_lowercase())
+                    .eq(["return", "minimal"])
+            })
+        })
+}
+
+/// Validates that a 429 Too Many Requests or 503 Service Unavailable response has a
+/// `Retry-After` header with either a number of seconds or an HTTP-date (RFC 9110).
+/// Responses with other statuses are always accepted.
+pub fn validate_rate_limit_headers(response: &Response) -> Result<(), ValidationError> {
+    let status = response.status();
+    if status != StatusCode::TOO_MANY_REQUESTS && status != StatusCode::SERVICE_UNAVAILABLE {
+        return Ok(());
+    }
+    let Some(retry_after) = response.headers().get(RETRY_AFTER) else {
+        return Err(ValidationError::RateLimitHeaderInvalid {
+            status,
+            retry_after: None,
+        });
+    };
+    match retry_after.to_str() {
+        Ok(value) if is_delay_seconds(value) || is_http_date(value) => Ok(()),
+        _ => Err(ValidationError::RateLimitHeaderInvalid {
+            status,
+            retry_after: Some(String::from_utf8_lossy(retry_after.as_bytes()).into_owned()),
+        }),
+    }
+}
+
+/// Validates that the response arrived within `threshold` after sending the request.
+pub fn validate_response_time(
+    elapsed: Duration,
+    threshold: Duration,
+) -> Result<(), ValidationError> {
+    if elapsed > threshold {
+        return Err(ValidationError::ResponseTooSlow { elapsed, threshold });
+    }
+    Ok(())
+}
+
+/// Whether a `Retry-After` value is a non-negative number of seconds.
+fn is_delay_seconds(value: &str) -> bool {
+    !value.is_empty() && value.bytes().all(|byte| byte.is_ascii_digit())
+}
+
+/// Whether a `Retry-After` value is an HTTP-date such as `Sun, 06 Nov 1994 08:49:37 GMT`.
+fn is_http_date(value: &str) -> bool {
+    value.ends_with(" GMT") && chrono::DateTime::parse_from_rfc2822(value).is_ok()
+}
+
+/// Whether responses with this status must not have a body (RFC 9110).
+fn is_bodiless(status: StatusCode) -> bool {
+    status.is_informational()
+        || status == StatusCode::NO_CONTENT
+        || status == StatusCode::NOT_MODIFIED
 }
 
 /// Validates whether an object is correct according to a schema.
@@ -252,10 +489,12 @@ fn validate_object_against_schema(
     api: &OpenAPI,
     schema: &Schema,
     response_contents: &Value,
+    check_constraints: bool,
 ) -> Result<(), ValidationError> {
     match &schema.kind {
         openapiv3::SchemaKind::Type(expected_type) => {
-            validate_object_against_type(api, expected_type, response_contents)
+            validate_object_against_type(api, expected_type, response_contents, check_constraints)?;
+            validate_dependent_required(schema, response_contents)
         }
 
         // AnyOf: the response must validate against at least one of the schemas
@@ -264,7 +503,12 @@ fn validate_object_against_schema(
         } => expected_schemas
             .iter()
             .map(|ref_or_schema| {
-                validate_object_against_ref_or_schema(api, ref_or_schema, response_contents)
+                validate_object_against_ref_or_schema(
+                    api,
+                    ref_or_schema,
+                    response_contents,
+                    check_constraints,
+                )
             })
             // If any schema validates the response, return Ok(())
             .reduce(Result::or)
@@ -278,8 +522,13 @@ fn validate_object_against_schema(
             if expected_schemas
                 .iter()
                 .filter_map(|ref_or_schema| {
-                    validate_object_against_ref_or_schema(api, ref_or_schema, response_contents)
-                        .ok()
+                    validate_object_against_ref_or_schema(
+                        api,
+                        ref_or_schema,
+                        response_contents,
+                        check_constraints,
+                    )
+                    .ok()
                 })
                 // Count the Ok(())s, must be exactly one
                 .count()
@@ -293,16 +542,40 @@ fn validate_object_against_schema(
             }
         }
 
-        // AllOf: the response must validate against all of the schemas
+        // AllOf: the response must validate against all of the schemas. Object schemas
+        // are merged first, since each of them only describes part of the properties.
         openapiv3::SchemaKind::AllOf {
             all_of: expected_schemas,
-        } => expected_schemas.iter().try_for_each(|ref_or_schema| {
-            validate_object_against_ref_or_schema(api, ref_or_schema, response_contents)
-        }),
+        } => {
+            let mut objects = vec![];
+            let mut others = vec![];
+            split_all_of(api, expected_schemas, &mut objects, &mut others);
+            others.into_iter().try_for_each(|schema| {
+                validate_object_against_schema(api, schema, response_contents, check_constraints)
+            })?;
+            if objects.is_empty() {
+                return Ok(());
+            }
+            let merged = merge_object_types(objects.iter().map(|(_, object)| *object));
+            validate_object_against_type(
+                api,
+                &Type::Object(merged),
+                response_contents,
+                check_constraints,
+            )?;
+            objects
+                .into_iter()
+                .try_for_each(|(schema, _)| validate_dependent_required(schema, response_contents))
+        }
 
         // Not: the response must fail to validate the given schema
         openapiv3::SchemaKind::Not { not: ref_or_schema } => {
-            match validate_object_against_ref_or_schema(api, ref_or_schema, response_contents) {
+            match validate_object_against_ref_or_schema(
+                api,
+                ref_or_schema,
+                response_contents,
+                check_constraints,
+            ) {
                 Ok(()) => Err(ValidationError::ResponseObjectIncorrect {
                     msg: format!(
                         "Response content {response_contents} matched schema when it should not."
@@ -317,6 +590,102 @@ fn validate_object_against_schema(
     }
 }
 
+/// Collects the members of an `allOf`, including those of nested `allOf`s, into the
+/// object schemas and the other schemas.
+fn split_all_of<'a>(
+    api: &'a OpenAPI,
+    members: &'a [ReferenceOr<Schema>],
+    objects: &mut Vec<(&'a Schema, &'a ObjectType)>,
+    others: &mut Vec<&'a Schema>,
+) {
+    for member in members {
+        let schema = member.resolve(api);
+        match &schema.kind {
+            openapiv3::SchemaKind::Type(Type::Object(object)) => objects.push((schema, object)),
+            openapiv3::SchemaKind::AllOf { all_of } => split_all_of(api, all_of, objects, others),
+            _ => others.push(schema),
+        }
+    }
+}
+
+/// Merges object types into one with the properties and required properties of all of
+/// them. If several define the same property, the first definition is used.
+fn merge_object_types<'a>(objects: impl Iterator<Item = &'a ObjectType>) -> ObjectType {
+    let mut merged = ObjectType::default();
+    for object in objects {
+        for (name, property) in &object.properties {
+            merged
+                .properties
+                .entry(name.clone())
+                .or_insert_with(|| property.clone());
+        }
+        for name in &object.required {
+            if !merged.required.contains(name) {
+                merged.required.push(name.clone());
+            }
+        }
+    }
+    merged
+}
+
+/// Validates that a number lies within the given (bound, exclusive) minimum and maximum
+fn check_bounds<T: PartialOrd + Display>(
+    value: T,
+    (minimum, exclusive_minimum): (Option<T>, bool),
+    (maximum, exclusive_maximum): (Option<T>, bool),
+) -> Result<(), ValidationError> {
+    let bound = match (minimum, maximum) {
+        (Some(min), _) if exclusive_minimum && value <= min => format!("exclusive minimum {min}"),
+        (Some(min), _) if value < min => format!("minimum {min}"),
+        (_, Some(max)) if exclusive_maximum && value >= max => format!("exclusive maximum {max}"),
+        (_, Some(max)) if value > max => format!("maximum {max}"),
+        _ => return Ok(()),
+    };
+    Err(ValidationError::ResponseBoundViolation {
+        field: String::new(),
+        value: value.to_string(),
+        bound,
+    })
+}
+
+/// Validates that a string matches the given pattern. Patterns that are not a valid
+/// regex are not checked.
+fn check_pattern(value: &str, pattern: &str) -> Result<(), ValidationError> {
+    let mut patterns = PATTERNS.lock().unwrap();
+    let regex = patterns.entry(pattern.to_owned()).or_insert_with(|| {
+        Regex::new(pattern)
+            .inspect_err(|err| log::warn!("Broken regex pattern {pattern}, Error message: {err}"))
+            .ok()
+    });
+    match regex {
+        Some(regex) if !regex.is_match(value) => Err(ValidationError::ResponsePatternMismatch {
+            field: String::new(),
+            value: value.to_owned(),
+            pattern: pattern.to_owned(),
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Validates whether an object contains the fields that the `dependentRequired` keyword
+/// of the schema requires along with the fields it contains
+fn validate_dependent_required(
+    schema: &Schema,
+    response_contents: &Value,
+) -> Result<(), ValidationError> {
+    let Value::Object(o_map) = response_contents else {
+        return Ok(());
+    };
+    match missing_dependents(schema, |field| o_map.contains_key(field)).first() {
+        Some((field, missing)) => Err(ValidationError::ResponseObjectIncorrect {
+            msg: format!(
+                "Response object contains property \"{field}\" but not the properties it requires: {missing:?}."
+            ),
+        }),
+        None => Ok(()),
+    }
+}
+
 /// Validates whether an object is correct by attempting to resolve a `reference_or`
 /// containing a schema, and if it resolves, validating the object against the contained
 /// schema
@@ -324,9 +693,15 @@ fn validate_object_against_ref_or_schema(
     api: &OpenAPI,
     ref_or_schema: &ReferenceOr<Schema>,
     response_contents: &Value,
+    check_constraints: bool,
 ) -> Result<(), ValidationError> {
     // First resolve the ReferenceOr object using the API ... and then use the schema to validate the given response
-    validate_object_against_schema(api, ref_or_schema.resolve(api), response_contents)
+    validate_object_against_schema(
+        api,
+        ref_or_schema.resolve(api),
+        response_contents,
+        check_constraints,
+    )
 }
 
 /// Validates whether an object is correct according to a concrete type
@@ -334,18 +709,29 @@ fn validate_object_against_type(
     api: &OpenAPI,
     expected_type: &Type,
     response_contents: &Value,
+    check_constraints: bool,
 ) -> Result<(), ValidationError> {
     let make_err = |err_str| Err(ValidationError::ResponseObjectIncorrect { msg: err_str });
 
     match (expected_type, response_contents) {
         (Type::Boolean { .. }, Value::Bool(_)) => Ok(()),
-        (Type::Integer(_), Value::Number(n)) => match n.as_i64() {
+        (Type::Integer(i_type), Value::Number(n)) => match n.as_i64() {
+            Some(value) if check_constraints => check_bounds(
+                value,
+                (i_type.minimum, i_type.exclusive_minimum),
+                (i_type.maximum, i_type.exclusive_maximum),
+            ),
             Some(_) => Ok(()),
             None => make_err(
                 format!("Response number {n} does not match expected type Integer (as i64)"),
             ),
         },
-        (Type::Number(_), Value::Number(n)) => match n.as_f64() {
+        (Type::Number(n_type), Value::Number(n)) => match n.as_f64() {
+            Some(value) if check_constraints => check_bounds(
+                value,
+                (n_type.minimum, n_type.exclusive_minimum),
+                (n_type.maximum, n_type.exclusive_maximum),
+            ),
             Some(_) => Ok(()),
             None => make_err(
                 format!("Response number {n} does not match expected type Number (as f64)"),
@@ -358,7 +744,10 @@ fn validate_object_against_type(
                     incorrect_variant: a_string.clone(),
                 });
             }
-            Ok(())
+            match &s_type.pattern {
+                Some(pattern) if check_constraints => check_pattern(a_string, pattern),
+                _ => Ok(()),
+            }
         }
         (Type::Array(a_type), Value::Array(a_vec)) => {
             // Find the schema for the array items. If there is no schema, we accept
@@ -369,7 +758,7 @@ fn validate_object_against_type(
             };
             // Check for each item that it matches the schema
             for (index, value) in a_vec.iter().enumerate() {
-                validate_object_against_schema(api, item_schema, value)
+                validate_object_against_schema(api, item_schema, value, check_constraints)
                     .map_err(|v| v.nested(&format!("{index}")))?;
             }
 
@@ -390,7 +779,7 @@ fn validate_object_against_type(
                         )).map_err(|err| err.nested(key));
                     }
                 };
-                validate_object_against_schema(api, item_schema, value)
+                validate_object_against_schema(api, item_schema, value, check_constraints)
                     .map_err(|err| err.nested(key))?;
             }
 
@@ -411,3 +800,297 @@ fn validate_object_against_type(
         _ => make_err(format!("Expected type {expected_type:?} and actual response type {response_contents:?} do not match.").to_owned()),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use indexmap::IndexMap;
+    use openapiv3::{OpenAPI, Schema};
+    use reqwest::{header::RETRY_AFTER, StatusCode};
+    use serde_json::json;
+
+    use super::{
+        validate_object_against_schema, validate_rate_limit_headers, validate_response,
+        validate_response_time, Response,
+    };
+    use crate::{
+        input::{parameter::ParameterKind, Body, Method, OpenApiRequest, ParameterContents},
+        openapi::dependent_required::preserve_keyword,
+    };
+
+    #[test]
+    fn test_missing_dependent_is_flagged() {
+        let mut raw: serde_yaml::Value = serde_yaml::from_str(
+            "
+            type: object
+            properties:
+              card: {type: string}
+              address: {type: string}
+            dependentRequired:
+              card: [address]
+            ",
+        )
+        .unwrap();
+        preserve_keyword(&mut raw);
+        let schema: Schema = serde_yaml::from_value(raw).unwrap();
+        let api = Default::default();
+
+        assert!(
+            validate_object_against_schema(&api, &schema, &json!({"address": "Dam 1"}), false)
+                .is_ok()
+        );
+        assert!(validate_object_against_schema(
+            &api,
+            &schema,
+            &json!({"card": "1234", "address": "Dam 1"}),
+            false
+        )
+        .is_ok());
+        let error = validate_object_against_schema(&api, &schema, &json!({"card": "1234"}), false)
+            .unwrap_err();
+        assert_eq!(error.kind(), "response_object_incorrect");
+    }
+
+    #[test]
+    fn test_all_of_merges_required_properties() {
+        let api: OpenAPI = serde_json::from_value(json!({
+            "openapi": "3.0.0",
+            "info": {"title": "pets", "version": "1"},
+            "paths": {},
+            "components": {
+                "schemas": {
+                    "Resource": {
+                        "type": "object",
+                        "properties": {"id": {"type": "integer"}},
+                        "required": ["id"]
+                    }
+                }
+            }
+        }))
+        .unwrap();
+        let schema: Schema = serde_json::from_value(json!({
+            "allOf": [
+                {"$ref": "#/components/schemas/Resource"},
+                {
+                    "type": "object",
+                    "properties": {"name": {"type": "string"}},
+                    "required": ["name"]
+                }
+            ]
+        }))
+        .unwrap();
+
+        assert!(validate_object_against_schema(
+            &api,
+            &schema,
+            &json!({"id": 1, "name": "Rex"}),
+            false
+        )
+        .is_ok());
+        // The inherited required property is missing
+        let error = validate_object_against_schema(&api, &schema, &json!({"name": "Rex"}), false)
+            .unwrap_err();
+        assert_eq!(error.kind(), "response_object_incorrect");
+        assert!(error.to_string().contains("\"id\""));
+        // Properties of the other members are still checked
+        assert!(validate_object_against_schema(
+            &api,
+            &schema,
+            &json!({"id": "one", "name": "Rex"}),
+            false
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_body_for_no_content_is_flagged() {
+        let api: OpenAPI = serde_json::from_value(json!({
+            "openapi": "3.0.0",
+            "info": {"title": "pets", "version": "1"},
+            "paths": {
+                "/pets/{id}": {
+                    "delete": {
+                        "responses": {"204": {"description": "Deleted"}}
+                    }
+                }
+            }
+        }))
+        .unwrap();
+        let request = OpenApiRequest {
+            method: Method::Delete,
+            path: "/pets/{id}".to_owned(),
+            body: Body::Empty,
+            parameters: IndexMap::new(),
+        };
+
+        let compliant = Response::new(StatusCode::NO_CONTENT, vec![]);
+        assert!(validate_response(&api, &request, &compliant, false).is_ok());
+
+        let with_body = Response::new(StatusCode::NO_CONTENT, b"{}".to_vec());
+        let error = validate_response(&api, &request, &with_body, false).unwrap_err();
+        assert_eq!(error.kind(), "unexpected_body_for_no_content");
+    }
+
+    #[test]
+    fn test_constraints_are_checked_if_enabled() {
+        let api: OpenAPI = serde_json::from_value(json!({
+            "openapi": "3.0.0",
+            "info": {"title": "pets", "version": "1"},
+            "paths": {
+                "/pets": {
+                    "get": {
+                        "responses": {
+                            "200": {
+                                "description": "A pet",
+                                "content": {
+                                    "application/json": {
+                                        "schema": {
+                                            "type": "object",
+                                            "properties": {
+                                                "tag": {"type": "string", "pattern": "^[A-Z]{2}-[0-9]+$"},
+                                                "age": {"type": "integer", "maximum": 40}
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+        let request = OpenApiRequest {
+            method: Method::Get,
+            path: "/pets".to_owned(),
+            body: Body::Empty,
+            parameters: IndexMap::new(),
+        };
+        let response =
+            |body: serde_json::Value| Response::new(StatusCode::OK, body.to_string().into_bytes());
+
+        let compliant = response(json!({"tag": "AB-12", "age": 40}));
+        assert!(validate_response(&api, &request, &compliant, true).is_ok());
+
+        // Structurally valid, but the tag does not match the pattern
+        let mismatch = response(json!({"tag": "ab12", "age": 3}));
+        assert!(validate_response(&api, &request, &mismatch, false).is_ok());
+        let error = validate_response(&api, &request, &mismatch, true).unwrap_err();
+        assert_eq!(error.kind(), "response_pattern_mismatch");
+        assert!(error.to_string().contains("\"tag\""), "{error}");
+
+        let too_old = response(json!({"tag": "AB-12", "age": 41}));
+        assert!(validate_response(&api, &request, &too_old, false).is_ok());
+        let error = validate_response(&api, &request, &too_old, true).unwrap_err();
+        assert_eq!(error.kind(), "response_bound_violation");
+        assert!(error.to_string().contains("maximum 40"), "{error}");
+    }
+
+    #[test]
+    fn test_empty_body_is_accepted_if_minimal_return_preferred() {
+        let api: OpenAPI = serde_json::from_value(json!({
+            "openapi": "3.0.0",
+            "info": {"title": "pets", "version": "1"},
+            "paths": {
+                "/pets": {
+                    "post": {
+                        "responses": {
+                            "200": {
+                                "description": "The created pet",
+                                "content": {
+                                    "application/json": {
+                                        "schema": {
+                                            "type": "object",
+                                            "properties": {"id": {"type": "integer"}},
+                                            "required": ["id"]
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+        let request = |prefer: Option<&str>| OpenApiRequest {
+            method: Method::Post,
+            path: "/pets".to_owned(),
+            body: Body::Empty,
+            parameters: prefer
+                .map(|prefer| {
+                    (
+                        ("Prefer".to_owned(), ParameterKind::Header),
+                        ParameterContents::Bytes(prefer.as_bytes().to_vec()),
+                    )
+                })
+                .into_iter()
+                .collect(),
+        };
+        let empty = Response::new(StatusCode::OK, vec![]);
+
+        assert!(validate_response(&api, &request(None), &empty, false).is_err());
+        assert!(
+            validate_response(&api, &request(Some("return=representation")), &empty, false)
+                .is_err()
+        );
+        assert!(validate_response(&api, &request(Some("return=minimal")), &empty, false).is_ok());
+        assert!(validate_response(
+            &api,
+            &request(Some("respond-async, return=minimal; foo=bar")),
+            &empty,
+            false
+        )
+        .is_ok());
+        // A body that is sent anyway must still be valid
+        let incomplete = Response::new(StatusCode::OK, b"{}".to_vec());
+        let error = validate_response(&api, &request(Some("return=minimal")), &incomplete, false)
+            .unwrap_err();
+        assert_eq!(error.kind(), "response_object_incorrect");
+    }
+
+    #[test]
+    fn test_retry_after_is_validated() {
+        let too_many = || Response::new(StatusCode::TOO_MANY_REQUESTS, vec![]);
+        let unavailable = || Response::new(StatusCode::SERVICE_UNAVAILABLE, vec![]);
+
+        assert!(validate_rate_limit_headers(&too_many().with_header(RETRY_AFTER, "120")).is_ok());
+        assert!(validate_rate_limit_headers(
+            &unavailable().with_header(RETRY_AFTER, "Wed, 21 Oct 2015 07:28:00 GMT")
+        )
+        .is_ok());
+
+        for malformed in ["soon", "-5", "1.5", "Wed, 21 Oct 2015 07:28:00 +0200"] {
+            let error =
+                validate_rate_limit_headers(&too_many().with_header(RETRY_AFTER, malformed))
+                    .unwrap_err();
+            assert_eq!(error.kind(), "rate_limit_header_invalid");
+            assert!(error.to_string().contains("malformed"), "{error}");
+        }
+
+        let error = validate_rate_limit_headers(&unavailable()).unwrap_err();
+        assert_eq!(error.kind(), "rate_limit_header_invalid");
+        assert!(
+            error.to_string().contains("without a Retry-After"),
+            "{error}"
+        );
+
+        // Other statuses need no Retry-After
+        assert!(validate_rate_limit_headers(&Response::new(StatusCode::OK, vec![])).is_ok());
+    }
+    #[test]
+    fn test_slow_response_is_flagged() {
+        let threshold = Duration::from_millis(500);
+
+        assert!(validate_response_time(Duration::from_millis(120), threshold).is_ok());
+        assert!(validate_response_time(threshold, threshold).is_ok());
+
+        let error = validate_response_time(Duration::from_millis(1500), threshold).unwrap_err();
+        assert_eq!(error.kind(), "response_too_slow");
+        assert_eq!(
+            error.to_string(),
+            "Response took 1500 ms, longer than the threshold of 500 ms"
+        );
+    }
+}
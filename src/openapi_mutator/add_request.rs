@@ -13,30 +13,33 @@ use libafl_bolts::{rands::Rand, Named};
 use openapiv3::{OpenAPI, RequestBody};
 
 use crate::{
+    configuration::Configuration,
     input::{
         new_rand_input, parameter::ParameterKind, Body, OpenApiInput, OpenApiRequest,
         ParameterContents,
     },
-    openapi::JsonContent,
+    openapi::{path_filter::PathFilter, JsonContent},
     state::HasRandAndOpenAPI,
 };
 
 /// The `AddRequestMutator` adds a request to a random path from the specification
 /// to the series of requests. The request is added at the end of the series, and
-/// any parameters are filled with random bytes.
-pub struct AddRequestMutator;
+/// any parameters are filled with random bytes. Series that already have the maximum
+/// length are left alone, and only paths allowed by the path filter are added.
+pub struct AddRequestMutator {
+    max_chain_length: usize,
+    path_filter: PathFilter,
+}
 
 impl AddRequestMutator {
     #[must_use]
-    /// Creates a new AddRequestMutator
-    pub fn new() -> Self {
-        Self {}
-    }
-}
-
-impl Default for AddRequestMutator {
-    fn default() -> Self {
-        Self::new()
+    /// Creates a new AddRequestMutator that leaves series of `max_chain_length`
+    /// requests alone
+    pub fn new(max_chain_length: usize) -> Self {
+        Self {
+            max_chain_length,
+            path_filter: Configuration::must_get().path_filter(),
+        }
     }
 }
 
@@ -51,10 +54,14 @@ where
     S: HasRandAndOpenAPI,
 {
     fn mutate(&mut self, state: &mut S, input: &mut OpenApiInput) -> Result<MutationResult, Error> {
+        if input.0.len() >= self.max_chain_length {
+            return Ok(MutationResult::Skipped);
+        }
         let (rand, api) = state.rand_mut_and_openapi();
 
-        let n_ops = api.operations().count();
-        let new_path_i = rand.below(core::num::NonZero::new(n_ops).unwrap());
+        let Some(&new_path_i) = rand.choose(&self.path_filter.allowed_operations(api)) else {
+            return Ok(MutationResult::Skipped);
+        };
 
         let (new_path, new_method, new_op, _new_path_item) =
             api.operations().nth(new_path_i).unwrap();
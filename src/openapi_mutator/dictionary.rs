@@ -0,0 +1,274 @@
+//! Mutates a string or raw bytes value by replacing it with a token from a dictionary.
+//! Byte-level mutators rarely stumble onto the enum values or example strings that an
+//! endpoint branches on. The dictionary is built once at startup from the enums,
+//! examples and string formats in the specification, and kept in the fuzzer state.
+
+use std::borrow::Cow;
+
+use indexmap::IndexSet;
+pub use libafl::mutators::mutations::*;
+use libafl::{
+    mutators::{MutationResult, Mutator},
+    Error,
+};
+use libafl_bolts::{rands::Rand, Named};
+use openapiv3::{OpenAPI, StringFormat, VariantOrUnknownOrEmpty};
+use serde_json::Value;
+
+use crate::{
+    input::{parameter::SimpleValue, OpenApiInput, ParameterContents},
+    openapi::examples::{invalid_strings_from_format, strings_from_format},
+    state::HasRandAndDictionary,
+};
+
+/// The `DictionaryMutator` replaces a string or raw bytes value anywhere in the chain,
+/// also inside objects and arrays, by a different token from the dictionary.
+pub struct DictionaryMutator;
+
+impl DictionaryMutator {
+    #[must_use]
+    /// Creates a new DictionaryMutator
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl Default for DictionaryMutator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Named for DictionaryMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        &Cow::Borrowed("dictionarymutator")
+    }
+}
+
+impl<S> Mutator<OpenApiInput, S> for DictionaryMutator
+where
+    S: HasRandAndDictionary,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut OpenApiInput) -> Result<MutationResult, Error> {
+        let (rand, dictionary) = state.rand_mut_and_dictionary();
+        if dictionary.is_empty() {
+            return Ok(MutationResult::Skipped);
+        }
+
+        let mut values = vec![];
+        for (_, contents) in input.parameter_filter(&|value| !value.is_reference()) {
+            collect_string_values(contents, &mut values);
+        }
+        let value = match super::choose(rand, values) {
+            Some(value) => value,
+            None => return Ok(MutationResult::Skipped),
+        };
+
+        let current: &[u8] = match value {
+            ParameterContents::LeafValue(SimpleValue::String(string)) => string.as_bytes(),
+            ParameterContents::Bytes(bytes) => bytes,
+            _ => &[],
+        };
+        let others: Vec<&String> = dictionary
+            .iter()
+            .filter(|token| token.as_bytes() != current)
+            .collect();
+        let token = match rand.choose(others) {
+            Some(token) => token.clone(),
+            None => return Ok(MutationResult::Skipped),
+        };
+        *value = match value {
+            ParameterContents::Bytes(_) => ParameterContents::Bytes(token.into_bytes()),
+            _ => ParameterContents::from(token),
+        };
+        Ok(MutationResult::Mutated)
+    }
+}
+
+/// Collects the string and raw bytes values in `contents`, including those nested in
+/// objects and arrays.
+pub(super) fn collect_string_values<'a>(
+    contents: &'a mut ParameterContents,
+    values: &mut Vec<&'a mut ParameterContents>,
+) {
+    match contents {
+        ParameterContents::Object(fields) => fields
+            .values_mut()
+            .for_each(|field| collect_string_values(field, values)),
+        ParameterContents::Array(elements) => elements
+            .iter_mut()
+            .for_each(|element| collect_string_values(element, values)),
+        ParameterContents::LeafValue(SimpleValue::String(_)) | ParameterContents::Bytes(_) => {
+            values.push(contents)
+        }
+        ParameterContents::LeafValue(_) | ParameterContents::Reference { .. } => (),
+    }
+}
+
+/// Builds the dictionary of tokens for an API: all string values of `enum`s, the string
+/// values in `example`s and `examples`, and example values for the string `format`s
+/// used. Tokens are deduplicated and kept in the order in which they occur.
+pub fn dictionary_from_api(api: &OpenAPI) -> Vec<String> {
+    let mut tokens = IndexSet::new();
+    match serde_json::to_value(api) {
+        Ok(api) => collect_tokens(&api, &mut tokens),
+        Err(err) => log::warn!("Could not build the mutation dictionary: {err}"),
+    }
+    tokens.into_iter().collect()
+}
+
+/// Walks the specification as JSON, which also covers the schemas, parameters and
+/// media types that are only reachable through references.
+fn collect_tokens(value: &Value, tokens: &mut IndexSet<String>) {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object {
+                match (key.as_str(), value) {
+                    ("enum", Value::Array(variants)) => {
+                        tokens.extend(variants.iter().filter_map(Value::as_str).map(str::to_owned))
+                    }
+                    ("example", example) => collect_strings(example, tokens),
+                    ("examples", Value::Object(examples)) => examples
+                        .values()
+                        .map(|example| example.get("value").unwrap_or(example))
+                        .for_each(|example| collect_strings(example, tokens)),
+                    ("format", Value::String(format)) => {
+                        let format: VariantOrUnknownOrEmpty<StringFormat> =
+                            serde_json::from_value(Value::String(format.clone()))
+                                .unwrap_or(VariantOrUnknownOrEmpty::Unknown(format.clone()));
+                        tokens.extend(
+                            strings_from_format(&format)
+                                .iter()
+                                .chain(invalid_strings_from_format(&format))
+                                .map(|token| token.to_string()),
+                        );
+                    }
+                    _ => collect_tokens(value, tokens),
+                }
+            }
+        }
+        Value::Array(elements) => elements
+            .iter()
+            .for_each(|element| collect_tokens(element, tokens)),
+        _ => (),
+    }
+}
+
+/// Collects all strings in an example value, including those nested in objects and
+/// arrays.
+fn collect_strings(value: &Value, tokens: &mut IndexSet<String>) {
+    match value {
+        Value::String(string) => {
+            tokens.insert(string.clone());
+        }
+        Value::Object(object) => object
+            .values()
+            .for_each(|value| collect_strings(value, tokens)),
+        Value::Array(elements) => elements
+            .iter()
+            .for_each(|element| collect_strings(element, tokens)),
+        _ => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+    use libafl::mutators::{MutationResult, Mutator};
+    use openapiv3::OpenAPI;
+    use serde_json::json;
+
+    use super::{dictionary_from_api, DictionaryMutator};
+    use crate::{
+        input::{parameter::ParameterKind, Body, Method, OpenApiInput, OpenApiRequest},
+        openapi_mutator::test_state::TestState,
+    };
+
+    fn api() -> OpenAPI {
+        serde_json::from_value(json!({
+            "openapi": "3.0.0",
+            "info": {"title": "pets", "version": "1"},
+            "paths": {
+                "/pets": {
+                    "get": {
+                        "parameters": [
+                            {"name": "status", "in": "query",
+                             "schema": {"type": "string", "enum": ["available", "sold"]}},
+                            {"name": "owner", "in": "query", "example": "mufasa",
+                             "schema": {"type": "string"}}
+                        ],
+                        "responses": {}
+                    }
+                }
+            },
+            "components": {
+                "schemas": {
+                    "Pet": {
+                        "type": "object",
+                        "properties": {"id": {"type": "string", "format": "uuid"}},
+                        "example": {"name": "Rex", "tags": ["good-boy"]}
+                    }
+                }
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_dictionary_from_api() {
+        let dictionary = dictionary_from_api(&api());
+        for token in [
+            "available",
+            "sold",
+            "mufasa",
+            "Rex",
+            "good-boy",
+            "550e8400-e29b-41d4-a716-446655440000",
+        ] {
+            assert!(dictionary.iter().any(|t| t == token), "missing {token}");
+        }
+    }
+
+    #[test]
+    fn test_mutator_produces_dictionary_token() {
+        let mut state = TestState {
+            dictionary: vec!["sold".to_owned()],
+            ..TestState::new(0)
+        };
+        let mut parameters = IndexMap::new();
+        parameters.insert(
+            ("status".to_owned(), ParameterKind::Query),
+            "unknown".to_owned().into(),
+        );
+        let mut input = OpenApiInput(vec![OpenApiRequest {
+            method: Method::Get,
+            path: "/pets".to_owned(),
+            body: Body::ApplicationJson(json!({"nested": [42, "x"]}).into()),
+            parameters,
+        }]);
+
+        let mut seen_parameter = false;
+        let mut seen_nested = false;
+        for _ in 0..20 {
+            let mut mutated = input.clone();
+            let result = DictionaryMutator::new()
+                .mutate(&mut state, &mut mutated)
+                .unwrap();
+            assert_eq!(result, MutationResult::Mutated);
+            let request = &mutated.0[0];
+            seen_parameter |= request.parameters[0].to_value() == json!("sold");
+            if let Body::ApplicationJson(body) = &request.body {
+                seen_nested |= body.to_value() == json!({"nested": [42, "sold"]});
+            }
+        }
+        assert!(seen_parameter && seen_nested);
+
+        // Values that already equal the only token are left alone
+        input.0[0].parameters[0] = "sold".to_owned().into();
+        input.0[0].body = Body::Empty;
+        let result = DictionaryMutator::new()
+            .mutate(&mut state, &mut input)
+            .unwrap();
+        assert_eq!(result, MutationResult::Skipped);
+    }
+}
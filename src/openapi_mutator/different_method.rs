@@ -18,8 +18,9 @@ use crate::{
 };
 
 /// The `DifferentMethodMutator` changes an existing request from the series
-/// to use a different method. Only methods available for the current path
-/// in the specification are used.
+/// to use a different method. Depending on the method mutation strategy, only methods
+/// available for the current path in the specification are used, or methods from a
+/// fixed set.
 pub struct DifferentMethodMutator {
     method_mutation_strategy: MethodMutationStrategy,
 }
@@ -28,8 +29,14 @@ impl DifferentMethodMutator {
     #[must_use]
     /// Creates a new DifferentMethodMutator
     pub fn new() -> Self {
+        Self::with_strategy(Configuration::must_get().method_mutation_strategy.clone())
+    }
+
+    #[must_use]
+    /// Creates a new DifferentMethodMutator with the given method mutation strategy
+    pub fn with_strategy(method_mutation_strategy: MethodMutationStrategy) -> Self {
         Self {
-            method_mutation_strategy: Configuration::must_get().method_mutation_strategy,
+            method_mutation_strategy,
         }
     }
 }
@@ -85,6 +92,11 @@ where
                 ("patch", None),
                 ("delete", None),
             ],
+            MethodMutationStrategy::Custom(ref methods) => methods
+                .iter()
+                .filter(|method| **method != random_input.method)
+                .map(|method| (method.as_str(), None))
+                .collect(),
         };
 
         if available_methods.is_empty() {
@@ -110,3 +122,79 @@ where
         Ok(MutationResult::Mutated)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+    use libafl::mutators::{MutationResult, Mutator};
+    use serde_json::json;
+
+    use super::DifferentMethodMutator;
+    use crate::{
+        configuration::MethodMutationStrategy,
+        input::{Body, Method, OpenApiInput, OpenApiRequest},
+        openapi_mutator::test_state::TestState,
+    };
+
+    #[test]
+    fn test_custom_strategy_only_uses_listed_methods() {
+        let mut state = TestState {
+            api: serde_json::from_value(json!({
+                "openapi": "3.0.0",
+                "info": {"title": "files", "version": "1"},
+                "paths": {
+                    "/files": {
+                        "get": {"responses": {}},
+                        "delete": {"responses": {}}
+                    }
+                }
+            }))
+            .unwrap(),
+            ..TestState::new(0)
+        };
+        let listed = vec![Method::Propfind, Method::Mkcol, Method::Connect];
+        let mut mutator =
+            DifferentMethodMutator::with_strategy(MethodMutationStrategy::Custom(listed.clone()));
+        let mut input = OpenApiInput(vec![OpenApiRequest {
+            method: Method::Get,
+            path: "/files".to_owned(),
+            body: Body::Empty,
+            parameters: IndexMap::new(),
+        }]);
+
+        let mut seen = vec![];
+        for _ in 0..50 {
+            let result = mutator.mutate(&mut state, &mut input).unwrap();
+            assert_eq!(result, MutationResult::Mutated);
+            let method = input.0[0].method;
+            assert!(listed.contains(&method), "mutated into {method}");
+            if !seen.contains(&method) {
+                seen.push(method);
+            }
+        }
+        assert_eq!(seen.len(), listed.len());
+    }
+
+    #[test]
+    fn test_parse_custom_strategy() {
+        assert_eq!(
+            "follow-spec".parse::<MethodMutationStrategy>().unwrap(),
+            MethodMutationStrategy::FollowSpec
+        );
+        assert_eq!(
+            "PROPFIND, mkcol".parse::<MethodMutationStrategy>().unwrap(),
+            MethodMutationStrategy::Custom(vec![Method::Propfind, Method::Mkcol])
+        );
+        assert!("PROPFIND,FETCH".parse::<MethodMutationStrategy>().is_err());
+        let from_yaml: MethodMutationStrategy =
+            serde_yaml::from_str("[PROPFIND, Move, unlock]").unwrap();
+        assert_eq!(
+            from_yaml,
+            MethodMutationStrategy::Custom(vec![Method::Propfind, Method::Move, Method::Unlock])
+        );
+        assert_eq!(
+            serde_yaml::from_str::<MethodMutationStrategy>("common7").unwrap(),
+            MethodMutationStrategy::Common7
+        );
+    }
+}
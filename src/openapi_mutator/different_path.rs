@@ -11,20 +11,26 @@ use libafl::{
 use libafl_bolts::{rands::Rand, Named};
 
 use crate::{
+    configuration::Configuration,
     input::{fix_input_parameters, OpenApiInput},
+    openapi::path_filter::PathFilter,
     state::HasRandAndOpenAPI,
 };
 
 /// The `DifferentPathMutator` changes an existing request from the series
 /// to use a different path-plus-method-combination. Only combinations available
-/// in the specification are used.
-pub struct DifferentPathMutator;
+/// in the specification are used, of the paths allowed by the path filter.
+pub struct DifferentPathMutator {
+    path_filter: PathFilter,
+}
 
 impl DifferentPathMutator {
     #[must_use]
     /// Creates a new DifferentPathMutator
     pub fn new() -> Self {
-        Self {}
+        Self {
+            path_filter: Configuration::must_get().path_filter(),
+        }
     }
 }
 
@@ -46,13 +52,13 @@ where
 {
     fn mutate(&mut self, state: &mut S, input: &mut OpenApiInput) -> Result<MutationResult, Error> {
         let (rand, api) = state.rand_mut_and_openapi();
-        if input.0.is_empty() || api.operations().count() < 2 {
+        let allowed_operations = self.path_filter.allowed_operations(api);
+        if input.0.is_empty() || allowed_operations.is_empty() {
             return Ok(MutationResult::Skipped);
         }
         let random_input = rand.choose(&mut input.0).unwrap();
         for _ in 0..100 {
-            let n_ops = api.operations().count();
-            let new_path_i = rand.below(core::num::NonZero::new(n_ops).unwrap());
+            let new_path_i = *rand.choose(&allowed_operations).unwrap();
             {
                 let (new_path, new_method, _, _) = api.operations().nth(new_path_i).unwrap();
                 // Only set "mutated" if it's actually different
@@ -0,0 +1,254 @@
+//! Mutates a request body whose schema uses a discriminator by switching it to another
+//! variant. Changing only the discriminator field would mostly produce bodies that are
+//! inconsistent with the selected variant, which the server rejects early on. Instead,
+//! the whole body is regenerated to match the newly selected variant.
+
+use std::borrow::Cow;
+
+pub use libafl::mutators::mutations::*;
+use libafl::{
+    mutators::{MutationResult, Mutator},
+    Error,
+};
+use libafl_bolts::{rands::Rand, Named};
+use openapiv3::{OpenAPI, Schema};
+use serde_json::Value;
+
+use crate::{
+    input::{Body, OpenApiInput, OpenApiRequest, ParameterContents},
+    openapi::{
+        examples::{all_discriminator_variants, UserExamples},
+        find_operation, JsonContent, Multipart, WwwForm,
+    },
+    state::HasRandAndOpenAPI,
+};
+
+/// The `DiscriminatorMutator` replaces a discriminated body by an example of a
+/// different variant, with the discriminator field set accordingly.
+pub struct DiscriminatorMutator {
+    user_examples: UserExamples,
+}
+
+impl DiscriminatorMutator {
+    #[must_use]
+    /// Creates a new DiscriminatorMutator that makes the examples of the variants from
+    /// `user_examples` as well as the specification
+    pub fn new(user_examples: UserExamples) -> Self {
+        Self { user_examples }
+    }
+}
+
+impl Named for DiscriminatorMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        &Cow::Borrowed("discriminatormutator")
+    }
+}
+
+impl<S> Mutator<OpenApiInput, S> for DiscriminatorMutator
+where
+    S: HasRandAndOpenAPI,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut OpenApiInput) -> Result<MutationResult, Error> {
+        let (rand, api) = state.rand_mut_and_openapi();
+
+        // Collect bodies together with the variants of other discriminator values
+        let candidates = input.0.iter_mut().filter_map(|request| {
+            let (property_name, variants) = discriminated_body(api, &self.user_examples, request)?;
+            // A body or discriminator that is taken from an earlier response has no value yet
+            let ParameterContents::Object(fields) = body_contents(&request.body)? else {
+                return None;
+            };
+            let current = match fields.get(&property_name) {
+                Some(field) if field.contains_reference() => return None,
+                Some(field) => field.to_value(),
+                None => Value::Null,
+            };
+            let others: Vec<Value> = variants
+                .into_iter()
+                .filter(|variant| variant[&property_name] != current)
+                .collect();
+            (!others.is_empty()).then_some((request, others))
+        });
+
+        let (request, variants) = match super::choose(rand, candidates) {
+            Some(candidate) => candidate,
+            None => return Ok(MutationResult::Skipped),
+        };
+        let variant = ParameterContents::from(rand.choose(variants).unwrap());
+        match &mut request.body {
+            Body::ApplicationJson(contents)
+            | Body::XWwwFormUrlencoded(contents)
+            | Body::Multipart(contents) => *contents = variant,
+            Body::Empty | Body::TextPlain(_) | Body::OctetStream(_) => {
+                unreachable!("Filtered by body_contents")
+            }
+        }
+
+        input.assert_valid(self.name());
+        Ok(MutationResult::Mutated)
+    }
+}
+
+/// Returns the structured contents of the body, if it has any.
+fn body_contents(body: &Body) -> Option<&ParameterContents> {
+    match body {
+        Body::ApplicationJson(contents)
+        | Body::XWwwFormUrlencoded(contents)
+        | Body::Multipart(contents) => Some(contents),
+        Body::Empty | Body::TextPlain(_) | Body::OctetStream(_) => None,
+    }
+}
+
+/// If the body schema of the request has a discriminator, returns the name of the
+/// discriminator field and examples of all variants.
+fn discriminated_body(
+    api: &OpenAPI,
+    user_examples: &UserExamples,
+    request: &OpenApiRequest,
+) -> Option<(String, Vec<Value>)> {
+    body_contents(&request.body)?;
+    let schema = body_schema(api, request)?;
+    let property_name = schema.data.discriminator.as_ref()?.property_name.clone();
+    Some((
+        property_name,
+        all_discriminator_variants(api, user_examples, schema, &[]),
+    ))
+}
+
+/// Returns the schema of the request body in the specification.
+pub(super) fn body_schema<'a>(api: &'a OpenAPI, request: &OpenApiRequest) -> Option<&'a Schema> {
+    let operation = find_operation(api, &request.path, request.method)?;
+    let request_body = operation.request_body.as_ref()?.resolve(api).ok()?;
+    let media_type = request_body
+        .content
+        .get_json_content()
+        .or_else(|| request_body.content.get_www_form_content())
+        .or_else(|| request_body.content.get_multipart_content())?;
+    Some(media_type.schema.as_ref()?.resolve(api))
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+    use libafl::mutators::{MutationResult, Mutator};
+    use openapiv3::OpenAPI;
+    use serde_json::json;
+
+    use super::{DiscriminatorMutator, UserExamples};
+    use crate::{
+        input::{Body, Method, OpenApiInput, OpenApiRequest, ParameterContents},
+        openapi_mutator::test_state::TestState,
+    };
+
+    fn api() -> OpenAPI {
+        serde_json::from_value(json!({
+            "openapi": "3.0.0",
+            "info": {"title": "pets", "version": "1"},
+            "paths": {
+                "/pets": {
+                    "post": {
+                        "requestBody": {
+                            "content": {
+                                "application/json": {
+                                    "schema": {"$ref": "#/components/schemas/Pet"}
+                                }
+                            }
+                        },
+                        "responses": {}
+                    }
+                }
+            },
+            "components": {
+                "schemas": {
+                    "Pet": {
+                        "oneOf": [
+                            {"$ref": "#/components/schemas/Cat"},
+                            {"$ref": "#/components/schemas/Dog"}
+                        ],
+                        "discriminator": {"propertyName": "petType"}
+                    },
+                    "Cat": {
+                        "type": "object",
+                        "properties": {
+                            "petType": {"type": "string"},
+                            "meows": {"type": "boolean"}
+                        }
+                    },
+                    "Dog": {
+                        "type": "object",
+                        "properties": {
+                            "petType": {"type": "string"},
+                            "barkVolume": {"type": "integer"}
+                        }
+                    }
+                }
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_switches_to_consistent_variant() {
+        let mut input = OpenApiInput(vec![OpenApiRequest {
+            method: Method::Post,
+            path: "/pets".to_owned(),
+            body: Body::ApplicationJson(ParameterContents::from(
+                json!({"petType": "Cat", "meows": true}),
+            )),
+            parameters: IndexMap::new(),
+        }]);
+        let mut state = TestState {
+            api: api(),
+            ..TestState::new(0)
+        };
+
+        let result = DiscriminatorMutator::new(UserExamples::default())
+            .mutate(&mut state, &mut input)
+            .unwrap();
+        assert_eq!(result, MutationResult::Mutated);
+        let Body::ApplicationJson(body) = &input.0[0].body else {
+            panic!("Body is no longer JSON");
+        };
+        let body = body.to_value();
+        assert_eq!(body["petType"], "Dog");
+        assert!(body.get("barkVolume").is_some());
+        assert!(body.get("meows").is_none());
+    }
+
+    #[test]
+    fn test_linked_discriminator_is_skipped() {
+        let linked = || ParameterContents::Reference {
+            request_index: 0,
+            parameter_name: "petType".to_owned(),
+        };
+        let mut body = IndexMap::from([
+            ("petType".to_owned(), linked()),
+            ("meows".to_owned(), ParameterContents::from(json!(true))),
+        ]);
+        let request = |body: &IndexMap<String, ParameterContents>| OpenApiRequest {
+            method: Method::Post,
+            path: "/pets".to_owned(),
+            body: Body::ApplicationJson(ParameterContents::Object(body.clone())),
+            parameters: IndexMap::new(),
+        };
+        let mut state = TestState {
+            api: api(),
+            ..TestState::new(0)
+        };
+
+        let mut input = OpenApiInput(vec![request(&body)]);
+        let result = DiscriminatorMutator::new(UserExamples::default())
+            .mutate(&mut state, &mut input)
+            .unwrap();
+        assert_eq!(result, MutationResult::Skipped);
+
+        // A linked field other than the discriminator does not stop the mutation
+        body.insert("petType".to_owned(), ParameterContents::from(json!("Cat")));
+        body.insert("meows".to_owned(), linked());
+        let mut input = OpenApiInput(vec![request(&body)]);
+        let result = DiscriminatorMutator::new(UserExamples::default())
+            .mutate(&mut state, &mut input)
+            .unwrap();
+        assert_eq!(result, MutationResult::Mutated);
+    }
+}
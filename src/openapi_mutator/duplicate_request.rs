@@ -12,20 +12,18 @@ use libafl_bolts::{rands::Rand, Named};
 
 use crate::input::OpenApiInput;
 
-/// The `DuplicateRequestMutator` duplicates an existing request in the series.
-pub struct DuplicateRequestMutator;
+/// The `DuplicateRequestMutator` duplicates an existing request in the series, unless
+/// the series already has the maximum length.
+pub struct DuplicateRequestMutator {
+    max_chain_length: usize,
+}
 
 impl DuplicateRequestMutator {
     #[must_use]
-    /// Creates a new DuplicateRequestMutator
-    pub fn new() -> Self {
-        Self {}
-    }
-}
-
-impl Default for DuplicateRequestMutator {
-    fn default() -> Self {
-        Self::new()
+    /// Creates a new DuplicateRequestMutator that leaves series of `max_chain_length`
+    /// requests alone
+    pub fn new(max_chain_length: usize) -> Self {
+        Self { max_chain_length }
     }
 }
 
@@ -40,7 +38,7 @@ where
     S: HasRand,
 {
     fn mutate(&mut self, state: &mut S, input: &mut OpenApiInput) -> Result<MutationResult, Error> {
-        if input.0.is_empty() {
+        if input.0.is_empty() || input.0.len() >= self.max_chain_length {
             return Ok(MutationResult::Skipped);
         }
         let random_index = state
@@ -66,3 +64,33 @@ where
         Ok(MutationResult::Mutated)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+    use libafl::mutators::Mutator;
+
+    use super::DuplicateRequestMutator;
+    use crate::{
+        input::{Body, Method, OpenApiInput, OpenApiRequest},
+        openapi_mutator::test_state::TestState,
+    };
+
+    #[test]
+    fn test_chain_does_not_exceed_maximum_length() {
+        let mut input = OpenApiInput(vec![OpenApiRequest {
+            method: Method::Get,
+            path: "/pets".to_owned(),
+            body: Body::Empty,
+            parameters: IndexMap::new(),
+        }]);
+        let mut mutator = DuplicateRequestMutator::new(4);
+        let mut state = TestState::new(0);
+
+        for _ in 0..20 {
+            mutator.mutate(&mut state, &mut input).unwrap();
+            assert!(input.0.len() <= 4);
+        }
+        assert_eq!(input.0.len(), 4);
+    }
+}
@@ -0,0 +1,86 @@
+//! Mutates a query parameter by either giving it an empty value (`?x=`) or omitting it
+//! from the request altogether. Many servers treat these two cases differently, and
+//! neither might be handled as intended.
+
+use std::borrow::Cow;
+
+pub use libafl::mutators::mutations::*;
+use libafl::{
+    mutators::{MutationResult, Mutator},
+    state::HasRand,
+    Error,
+};
+use libafl_bolts::{rands::Rand, Named};
+
+use crate::input::{
+    parameter::{ParameterKind, SimpleValue},
+    OpenApiInput, ParameterContents,
+};
+
+/// The `EmptyQueryMutator` empties or removes a concrete query parameter. Parameters
+/// that already have an empty value are always removed.
+pub struct EmptyQueryMutator;
+
+impl EmptyQueryMutator {
+    #[must_use]
+    /// Creates a new EmptyQueryMutator
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl Default for EmptyQueryMutator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Named for EmptyQueryMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        &Cow::Borrowed("emptyquerymutator")
+    }
+}
+
+impl<S> Mutator<OpenApiInput, S> for EmptyQueryMutator
+where
+    S: HasRand,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut OpenApiInput) -> Result<MutationResult, Error> {
+        let query_parameters = input
+            .0
+            .iter()
+            .enumerate()
+            .flat_map(|(request_index, request)| {
+                request
+                    .parameters
+                    .iter()
+                    .filter(|((_, kind), value)| {
+                        *kind == ParameterKind::Query && !value.is_reference()
+                    })
+                    .map(move |(key, _)| (request_index, key.clone()))
+            });
+
+        let (request_index, key) = match super::choose(state.rand_mut(), query_parameters) {
+            Some(parameter) => parameter,
+            None => return Ok(MutationResult::Skipped),
+        };
+
+        let parameters = &mut input.0[request_index].parameters;
+        if is_empty_string(&parameters[&key]) || state.rand_mut().coinflip(0.5) {
+            parameters.shift_remove(&key);
+        } else {
+            parameters[&key] = ParameterContents::from(String::new());
+        }
+
+        input.assert_valid(self.name());
+        Ok(MutationResult::Mutated)
+    }
+}
+
+fn is_empty_string(contents: &ParameterContents) -> bool {
+    match contents {
+        ParameterContents::LeafValue(SimpleValue::String(s)) => s.is_empty(),
+        ParameterContents::Bytes(bytes) => bytes.is_empty(),
+        _ => false,
+    }
+}
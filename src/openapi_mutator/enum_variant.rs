@@ -0,0 +1,253 @@
+//! Mutates a parameter whose schema declares an enumeration by replacing its value with
+//! another declared variant. Other mutators freely change such values, which the server
+//! will mostly reject; this mutator keeps part of the mutations within the enumeration.
+
+use std::borrow::Cow;
+
+pub use libafl::mutators::mutations::*;
+use libafl::{
+    mutators::{MutationResult, Mutator},
+    Error,
+};
+use libafl_bolts::{rands::Rand, Named};
+use openapiv3::{OpenAPI, Operation, ParameterSchemaOrContent, RefOrMap, Schema, SchemaKind, Type};
+use serde_json::Value;
+
+use crate::{
+    input::{Body, OpenApiInput, OpenApiRequest, ParameterContents},
+    openapi::{examples::enum_variants, find_operation, JsonContent, Multipart, WwwForm},
+    state::HasRandAndOpenAPI,
+};
+
+/// The `EnumVariantMutator` replaces the value of a parameter or a top-level body field
+/// by another variant of the enumeration declared for it in the specification.
+pub struct EnumVariantMutator;
+
+impl EnumVariantMutator {
+    #[must_use]
+    /// Creates a new EnumVariantMutator
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl Default for EnumVariantMutator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Named for EnumVariantMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        &Cow::Borrowed("enumvariantmutator")
+    }
+}
+
+impl<S> Mutator<OpenApiInput, S> for EnumVariantMutator
+where
+    S: HasRandAndOpenAPI,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut OpenApiInput) -> Result<MutationResult, Error> {
+        let (rand, api) = state.rand_mut_and_openapi();
+
+        // Collect concrete values together with the other variants they could take
+        let candidates = input
+            .0
+            .iter_mut()
+            .flat_map(|request| enum_parameters(api, request))
+            .filter_map(|(value, variants)| {
+                let current = value.to_value();
+                let others: Vec<Value> = variants.into_iter().filter(|v| *v != current).collect();
+                (!others.is_empty()).then_some((value, others))
+            });
+
+        let (value, variants) = match super::choose(rand, candidates) {
+            Some(candidate) => candidate,
+            None => return Ok(MutationResult::Skipped),
+        };
+        *value = ParameterContents::from(rand.choose(variants).unwrap());
+
+        Ok(MutationResult::Mutated)
+    }
+}
+
+/// Returns the concrete parameters and top-level body fields of the request for which
+/// the specification declares an enumeration, with the variants of that enumeration.
+fn enum_parameters<'a>(
+    api: &OpenAPI,
+    request: &'a mut OpenApiRequest,
+) -> Vec<(&'a mut ParameterContents, Vec<Value>)> {
+    let operation = match find_operation(api, &request.path, request.method) {
+        Some(operation) => operation,
+        None => return vec![],
+    };
+
+    let mut result = vec![];
+    for ((name, kind), value) in request.parameters.iter_mut() {
+        // `to_value` can not turn references into values
+        if value.contains_reference() {
+            continue;
+        }
+        let variants = operation
+            .parameters
+            .iter()
+            .filter_map(|ref_or_parameter| ref_or_parameter.resolve(api).ok())
+            .find(|parameter| parameter.data.name == *name && kind.matches(parameter))
+            .and_then(|parameter| match &parameter.data.format {
+                ParameterSchemaOrContent::Schema(schema) => {
+                    Some(enum_variants(schema.resolve(api)))
+                }
+                ParameterSchemaOrContent::Content(_) => None,
+            })
+            .unwrap_or_default();
+        if !variants.is_empty() {
+            result.push((value, variants));
+        }
+    }
+
+    if let Body::ApplicationJson(ParameterContents::Object(fields))
+    | Body::XWwwFormUrlencoded(ParameterContents::Object(fields))
+    | Body::Multipart(ParameterContents::Object(fields)) = &mut request.body
+    {
+        let properties = body_properties(api, operation);
+        for (name, value) in fields.iter_mut() {
+            if value.contains_reference() {
+                continue;
+            }
+            let variants = properties
+                .and_then(|properties| properties.get(name))
+                .map(|schema| enum_variants(schema.resolve(api)))
+                .unwrap_or_default();
+            if !variants.is_empty() {
+                result.push((value, variants));
+            }
+        }
+    }
+    result
+}
+
+/// Returns the properties of the request body schema, if it is an object.
+fn body_properties<'a>(api: &'a OpenAPI, operation: &'a Operation) -> Option<&'a RefOrMap<Schema>> {
+    let request_body = operation.request_body.as_ref()?.resolve(api).ok()?;
+    let media_type = request_body
+        .content
+        .get_json_content()
+        .or_else(|| request_body.content.get_www_form_content())
+        .or_else(|| request_body.content.get_multipart_content())?;
+    match &media_type.schema.as_ref()?.resolve(api).kind {
+        SchemaKind::Type(Type::Object(object)) => Some(&object.properties),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+    use libafl::mutators::{MutationResult, Mutator};
+    use serde_json::json;
+
+    use super::EnumVariantMutator;
+    use crate::{
+        input::{
+            parameter::ParameterKind, Body, Method, OpenApiInput, OpenApiRequest, ParameterContents,
+        },
+        openapi_mutator::test_state::TestState,
+    };
+
+    fn state() -> TestState {
+        let api = serde_json::from_value(json!({
+            "openapi": "3.0.0",
+            "info": {"title": "pets", "version": "1"},
+            "paths": {
+                "/pets": {
+                    "post": {
+                        "parameters": [{
+                            "name": "size",
+                            "in": "query",
+                            "schema": {"type": "integer", "enum": [1, 2, 3]}
+                        }],
+                        "requestBody": {
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "type": "object",
+                                        "properties": {
+                                            "kind": {"type": "string", "enum": ["cat", "dog"]},
+                                            "owner": {
+                                                "type": "object",
+                                                "enum": [{"id": 1}, {"id": 2}]
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        },
+                        "responses": {"201": {"description": "Created"}}
+                    }
+                }
+            }
+        }))
+        .unwrap();
+        TestState {
+            api,
+            ..TestState::new(0)
+        }
+    }
+
+    fn request(size: ParameterContents, body: serde_json::Value) -> OpenApiRequest {
+        OpenApiRequest {
+            method: Method::Post,
+            path: "/pets".to_owned(),
+            body: Body::ApplicationJson(ParameterContents::from(body)),
+            parameters: IndexMap::from([(("size".to_owned(), ParameterKind::Query), size)]),
+        }
+    }
+
+    #[test]
+    fn test_values_stay_within_enumeration() {
+        let mut state = state();
+        let mut input = OpenApiInput(vec![request(
+            ParameterContents::from(json!(1)),
+            json!({"kind": "cat", "owner": {"id": 1}}),
+        )]);
+        let mut mutator = EnumVariantMutator::new();
+        for _ in 0..50 {
+            let result = mutator.mutate(&mut state, &mut input).unwrap();
+            assert_eq!(result, MutationResult::Mutated);
+            let request = &input.0[0];
+            let size = request.parameters[&("size".to_owned(), ParameterKind::Query)].to_value();
+            assert!([json!(1), json!(2), json!(3)].contains(&size));
+            let Body::ApplicationJson(body) = &request.body else {
+                panic!("The body is no longer JSON");
+            };
+            let body = body.to_value();
+            assert!([json!("cat"), json!("dog")].contains(&body["kind"]));
+            assert!([json!({"id": 1}), json!({"id": 2})].contains(&body["owner"]));
+        }
+    }
+
+    #[test]
+    fn test_skips_values_containing_references() {
+        let mut state = state();
+        let reference = || ParameterContents::Reference {
+            request_index: 0,
+            parameter_name: "id".to_owned(),
+        };
+        let mut owner = IndexMap::new();
+        owner.insert("id".to_owned(), reference());
+        let mut body = IndexMap::new();
+        body.insert("owner".to_owned(), ParameterContents::Object(owner));
+        let mut linked = request(reference(), json!({}));
+        linked.body = Body::ApplicationJson(ParameterContents::Object(body));
+        let mut input = OpenApiInput(vec![
+            request(ParameterContents::from(json!(1)), json!({})),
+            linked,
+        ]);
+        input.0[0].parameters.clear();
+
+        let result = EnumVariantMutator::new()
+            .mutate(&mut state, &mut input)
+            .unwrap();
+        assert_eq!(result, MutationResult::Skipped);
+    }
+}
@@ -11,13 +11,13 @@ use libafl::{
 use libafl_bolts::Named;
 
 use crate::{
-    input::{OpenApiInput, ParameterContents},
+    input::{Body, OpenApiInput, ParameterContents},
     state::HasRandAndOpenAPI,
 };
 
 /// The `EstablishLinkMutator` adds a connection to the series of requests.
 /// A connection is a `ParameterContents::Reference` variant in a named parameter
-/// or a request body.
+/// or a first-level field of a JSON request body.
 pub struct EstablishLinkMutator;
 
 impl EstablishLinkMutator {
@@ -55,8 +55,8 @@ where
             return Ok(MutationResult::Skipped);
         }
 
-        // Build a list of parameters with the same name as a return parameter from
-        // an earlier request
+        // Build a list of parameters, and first-level fields of JSON bodies, with the
+        // same name as a return parameter from an earlier request
         let concrete_parameters = input
             .0
             .iter_mut()
@@ -66,15 +66,23 @@ where
             .flat_map(|(current_request_index, request)| {
                 let request_index_and_parameter_name_pairs =
                     &request_index_and_parameter_name_pairs; // allow the move|| later on
+                let body_fields = match &mut request.body {
+                    Body::ApplicationJson(ParameterContents::Object(fields)) => {
+                        Some(fields.iter_mut())
+                    }
+                    _ => None,
+                };
                 request
                     .parameters
                     .iter_mut()
+                    .map(|((name, _), param)| (name, param))
+                    .chain(body_fields.into_iter().flatten())
                     // only consider non-reference parameters for replacement with
                     // a reference
                     .filter(|(_, v)| !v.is_reference())
                     // filter: this variable occurs in an earlier request's return value
                     // maps to: (&mut param, the relevant index into return_values)
-                    .filter_map(move |((name, _), param)| {
+                    .filter_map(move |(name, param)| {
                         request_index_and_parameter_name_pairs
                             .iter()
                             // Find the first request index that had the desired parameter name in a response
@@ -102,3 +110,86 @@ where
         Ok(MutationResult::Mutated)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+    use libafl::mutators::{MutationResult, Mutator};
+    use openapiv3::OpenAPI;
+    use serde_json::json;
+
+    use super::EstablishLinkMutator;
+    use crate::{
+        input::{
+            parameter::ParameterKind, Body, Method, OpenApiInput, OpenApiRequest, ParameterContents,
+        },
+        openapi_mutator::test_state::TestState,
+    };
+
+    #[test]
+    fn test_body_field_becomes_reference() {
+        let api: OpenAPI = serde_json::from_value(json!({
+            "openapi": "3.0.0",
+            "info": {"title": "pets", "version": "1"},
+            "paths": {
+                "/owners": {
+                    "post": {
+                        "responses": {
+                            "201": {
+                                "description": "The owner",
+                                "content": {
+                                    "application/json": {
+                                        "schema": {
+                                            "type": "object",
+                                            "properties": {"ownerId": {"type": "integer"}}
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                },
+                "/pets": {"post": {"responses": {"201": {"description": "The pet"}}}}
+            }
+        }))
+        .unwrap();
+        let input = OpenApiInput(vec![
+            OpenApiRequest {
+                method: Method::Post,
+                path: "/owners".to_owned(),
+                body: Body::Empty,
+                parameters: IndexMap::new(),
+            },
+            OpenApiRequest {
+                method: Method::Post,
+                path: "/pets".to_owned(),
+                body: Body::ApplicationJson(ParameterContents::from(
+                    json!({"name": "Rex", "ownerId": 7}),
+                )),
+                parameters: IndexMap::new(),
+            },
+        ]);
+        let mut state = TestState {
+            api,
+            ..TestState::new(0)
+        };
+
+        let mut mutated = input.clone();
+        let result = EstablishLinkMutator::new()
+            .mutate(&mut state, &mut mutated)
+            .unwrap();
+
+        assert_eq!(result, MutationResult::Mutated);
+        mutated.assert_valid("test");
+        assert_eq!(
+            mutated.reference_parameters().collect::<Vec<_>>(),
+            vec![(
+                1,
+                "ownerId".to_owned(),
+                ParameterKind::Body,
+                0,
+                "ownerId".to_owned()
+            )]
+        );
+    }
+}
@@ -0,0 +1,264 @@
+//! Mutates a request by bloating its headers: either by adding many synthetic headers,
+//! or by giving an existing header a very large value. Servers and proxies limit the
+//! number and size of headers they accept, and exceeding those limits should result in
+//! a clean error response rather than a crash or a hang.
+
+use std::borrow::Cow;
+
+pub use libafl::mutators::mutations::*;
+use libafl::{
+    mutators::{MutationResult, Mutator},
+    Error,
+};
+use libafl_bolts::{rands::Rand, Named};
+use openapiv3::{APIKeyLocation, OpenAPI, SecurityScheme};
+
+use crate::{
+    configuration::Configuration,
+    input::{parameter::ParameterKind, OpenApiInput, OpenApiRequest, ParameterContents},
+    openapi::find_operation,
+    state::HasRandAndOpenAPI,
+};
+
+/// Prefix of the names of the synthetic headers
+const SYNTHETIC_HEADER_PREFIX: &str = "x-wuppiefuzz-bloat-";
+
+/// Value of the synthetic headers
+const SYNTHETIC_HEADER_VALUE: &str = "wuppie";
+
+/// Headers that carry credentials or framing information, which are never bloated
+const PROTECTED_HEADERS: [&str; 6] = [
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "content-type",
+    "content-length",
+    "host",
+];
+
+/// The `HeaderBloatMutator` adds synthetic headers to a request, or replaces the value
+/// of one of its optional headers by a very large one. In both cases, at most
+/// `max_bloat_size` bytes are added.
+pub struct HeaderBloatMutator {
+    max_bloat_size: usize,
+}
+
+impl HeaderBloatMutator {
+    #[must_use]
+    /// Creates a new HeaderBloatMutator
+    pub fn new() -> Self {
+        Self {
+            max_bloat_size: Configuration::must_get().max_bloat_size,
+        }
+    }
+}
+
+impl Default for HeaderBloatMutator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Named for HeaderBloatMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        &Cow::Borrowed("headerbloatmutator")
+    }
+}
+
+impl<S> Mutator<OpenApiInput, S> for HeaderBloatMutator
+where
+    S: HasRandAndOpenAPI,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut OpenApiInput) -> Result<MutationResult, Error> {
+        if input.0.is_empty() || self.max_bloat_size == 0 {
+            return Ok(MutationResult::Skipped);
+        }
+        let (rand, api) = state.rand_mut_and_openapi();
+        let request = rand.choose(input.0.iter_mut()).unwrap();
+
+        let bloatable = bloatable_headers(api, request);
+        if !bloatable.is_empty() && rand.coinflip(0.5) {
+            let name = rand.choose(bloatable).unwrap();
+            let size = rand.between(1, self.max_bloat_size);
+            request.parameters[&(name, ParameterKind::Header)] =
+                ParameterContents::from(large_value(size));
+            return Ok(MutationResult::Mutated);
+        }
+
+        // Add at least one header if it fits within the bound
+        let smallest_header = SYNTHETIC_HEADER_PREFIX.len() + 1 + SYNTHETIC_HEADER_VALUE.len();
+        let size = rand.between(
+            smallest_header.min(self.max_bloat_size),
+            self.max_bloat_size,
+        );
+        let mut added = 0;
+        let mut index = 0;
+        loop {
+            let name = format!("{SYNTHETIC_HEADER_PREFIX}{index}");
+            index += 1;
+            let key = (name, ParameterKind::Header);
+            if request.parameters.contains_key(&key) {
+                continue;
+            }
+            let header_size = key.0.len() + SYNTHETIC_HEADER_VALUE.len();
+            if added + header_size > size {
+                break;
+            }
+            request.parameters.insert(
+                key,
+                ParameterContents::from(SYNTHETIC_HEADER_VALUE.to_owned()),
+            );
+            added += header_size;
+        }
+        if added == 0 {
+            return Ok(MutationResult::Skipped);
+        }
+        Ok(MutationResult::Mutated)
+    }
+}
+
+/// Returns the names of the concrete header parameters of the request that may be
+/// bloated: those that are not required by the operation and do not carry credentials.
+fn bloatable_headers(api: &OpenAPI, request: &OpenApiRequest) -> Vec<String> {
+    let required: Vec<&str> = find_operation(api, &request.path, request.method)
+        .into_iter()
+        .flat_map(|operation| &operation.parameters)
+        .filter_map(|ref_or_parameter| ref_or_parameter.resolve(api).ok())
+        .filter(|parameter| ParameterKind::Header.matches(parameter) && parameter.data.required)
+        .map(|parameter| parameter.data.name.as_str())
+        .collect();
+    let credentials: Vec<&str> = api
+        .components
+        .security_schemes
+        .values()
+        .filter_map(|ref_or_scheme| ref_or_scheme.as_item())
+        .filter_map(|scheme| match scheme {
+            SecurityScheme::APIKey {
+                location: APIKeyLocation::Header,
+                name,
+                ..
+            } => Some(name.as_str()),
+            _ => None,
+        })
+        .collect();
+    request
+        .parameters
+        .iter()
+        .filter(|((name, kind), value)| {
+            *kind == ParameterKind::Header
+                && !value.is_reference()
+                && !PROTECTED_HEADERS
+                    .iter()
+                    .chain(&required)
+                    .chain(&credentials)
+                    .any(|protected| protected.eq_ignore_ascii_case(name))
+        })
+        .map(|((name, _), _)| name.clone())
+        .collect()
+}
+
+/// A header value of the given size, consisting of visible ASCII characters only so
+/// that it stays a valid header value.
+fn large_value(size: usize) -> String {
+    (b'A'..=b'Z').cycle().take(size).map(char::from).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+    use libafl::mutators::{MutationResult, Mutator};
+    use openapiv3::OpenAPI;
+    use serde_json::json;
+
+    use super::HeaderBloatMutator;
+    use crate::{
+        input::{
+            parameter::ParameterKind, Body, Method, OpenApiInput, OpenApiRequest, ParameterContents,
+        },
+        openapi_mutator::test_state::TestState,
+    };
+
+    fn header_size(request: &OpenApiRequest) -> usize {
+        request
+            .parameters
+            .iter()
+            .filter(|((_, kind), _)| *kind == ParameterKind::Header)
+            .map(|((name, _), value)| name.len() + value.to_string().len())
+            .sum()
+    }
+
+    #[test]
+    fn test_bloat_is_bounded_and_spares_protected_headers() {
+        let api: OpenAPI = serde_json::from_value(json!({
+            "openapi": "3.0.0",
+            "info": {"title": "pets", "version": "1"},
+            "paths": {
+                "/pets": {
+                    "get": {
+                        "parameters": [
+                            {"name": "X-Tenant", "in": "header", "required": true,
+                             "schema": {"type": "string"}},
+                            {"name": "X-Trace", "in": "header",
+                             "schema": {"type": "string"}}
+                        ],
+                        "responses": {}
+                    }
+                }
+            },
+            "components": {
+                "securitySchemes": {
+                    "key": {"type": "apiKey", "in": "header", "name": "X-Api-Key"}
+                }
+            }
+        }))
+        .unwrap();
+        let original = OpenApiRequest {
+            method: Method::Get,
+            path: "/pets".to_owned(),
+            body: Body::Empty,
+            parameters: IndexMap::from([
+                (
+                    ("X-Tenant".to_owned(), ParameterKind::Header),
+                    ParameterContents::from("a".to_owned()),
+                ),
+                (
+                    ("X-Trace".to_owned(), ParameterKind::Header),
+                    ParameterContents::from("b".to_owned()),
+                ),
+                (
+                    ("X-Api-Key".to_owned(), ParameterKind::Header),
+                    ParameterContents::from("c".to_owned()),
+                ),
+                (
+                    ("Authorization".to_owned(), ParameterKind::Header),
+                    ParameterContents::from("d".to_owned()),
+                ),
+            ]),
+        };
+        let max_bloat_size = 200;
+        let mut mutator = HeaderBloatMutator { max_bloat_size };
+
+        let mut added_headers = false;
+        for seed in 0..50 {
+            let mut state = TestState {
+                api: api.clone(),
+                ..TestState::new(seed)
+            };
+            let mut input = OpenApiInput(vec![original.clone()]);
+            let result = mutator.mutate(&mut state, &mut input).unwrap();
+            assert_eq!(result, MutationResult::Mutated);
+
+            let mutated = &input.0[0];
+            assert!(header_size(mutated) <= header_size(&original) + max_bloat_size);
+            for protected in ["X-Tenant", "X-Api-Key", "Authorization"] {
+                let key = (protected.to_owned(), ParameterKind::Header);
+                assert_eq!(
+                    mutated.parameters[&key].to_string(),
+                    original.parameters[&key].to_string()
+                );
+            }
+            added_headers |= mutated.parameters.len() > original.parameters.len();
+        }
+        assert!(added_headers);
+    }
+}
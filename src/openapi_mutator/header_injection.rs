@@ -0,0 +1,184 @@
+//! Mutates a request by adding or changing a header that the specification does not
+//! declare, but that servers, proxies and frameworks commonly act on. Trusting such
+//! headers leads to e.g. IP allow-list bypasses (`X-Forwarded-For`), host header
+//! injection (`Host`) or request smuggling and truncated bodies (`Content-Length`).
+
+use std::borrow::Cow;
+
+pub use libafl::mutators::mutations::*;
+use libafl::{
+    mutators::{MutationResult, Mutator},
+    state::HasRand,
+    Error,
+};
+use libafl_bolts::{rands::Rand, Named};
+
+use crate::input::{parameter::ParameterKind, OpenApiInput, ParameterContents};
+
+/// The headers the mutator injects, each with the values it chooses from
+const INJECTED_HEADERS: [(&str, &[&str]); 9] = [
+    (
+        "X-Forwarded-For",
+        &["127.0.0.1", "::1", "10.0.0.1", "127.0.0.1, 127.0.0.1"],
+    ),
+    ("X-Forwarded-Host", &["localhost", "evil.example.com"]),
+    ("X-Forwarded-Proto", &["http", "https"]),
+    ("X-Real-IP", &["127.0.0.1", "::1"]),
+    ("X-Original-URL", &["/admin", "/"]),
+    ("X-Rewrite-URL", &["/admin", "/"]),
+    ("X-HTTP-Method-Override", &["DELETE", "PUT", "PATCH"]),
+    ("Host", &["localhost", "evil.example.com", "localhost:1"]),
+    ("Content-Length", &["0", "-1", "1", "4294967296"]),
+];
+
+/// Whether the header is one that the `HeaderInjectionMutator` may add to a request
+pub fn is_injected_header(name: &str) -> bool {
+    INJECTED_HEADERS
+        .iter()
+        .any(|(injected, _)| injected.eq_ignore_ascii_case(name))
+}
+
+/// The `HeaderInjectionMutator` sets one of a fixed list of security-sensitive headers
+/// to one of its interesting values, adding the header if the request does not have
+/// it yet.
+pub struct HeaderInjectionMutator;
+
+impl HeaderInjectionMutator {
+    #[must_use]
+    /// Creates a new HeaderInjectionMutator
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl Default for HeaderInjectionMutator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Named for HeaderInjectionMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        &Cow::Borrowed("headerinjectionmutator")
+    }
+}
+
+impl<S> Mutator<OpenApiInput, S> for HeaderInjectionMutator
+where
+    S: HasRand,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut OpenApiInput) -> Result<MutationResult, Error> {
+        if input.0.is_empty() {
+            return Ok(MutationResult::Skipped);
+        }
+        let rand = state.rand_mut();
+        let (name, values) = rand.choose(INJECTED_HEADERS).unwrap();
+        let request = rand.choose(input.0.iter_mut()).unwrap();
+
+        let existing = request
+            .parameters
+            .keys()
+            .find(|(existing, kind)| {
+                *kind == ParameterKind::Header && existing.eq_ignore_ascii_case(name)
+            })
+            .cloned();
+        let key = existing.unwrap_or_else(|| (name.to_owned(), ParameterKind::Header));
+        let current = request
+            .parameters
+            .get(&key)
+            .map(|value| value.to_header_value());
+        // Prefer a value the header does not have yet, so that the mutation changes something
+        let candidates: Vec<&str> = values
+            .iter()
+            .copied()
+            .filter(|value| current.as_ref().is_none_or(|current| current != value))
+            .collect();
+        let Some(value) = rand.choose(candidates) else {
+            return Ok(MutationResult::Skipped);
+        };
+        // As bytes, which are sent as-is rather than as a quoted string
+        request
+            .parameters
+            .insert(key, ParameterContents::Bytes(value.as_bytes().to_vec()));
+        Ok(MutationResult::Mutated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+    use libafl::mutators::{MutationResult, Mutator};
+    use openapiv3::OpenAPI;
+    use serde_json::json;
+
+    use super::HeaderInjectionMutator;
+    use crate::{
+        input::{
+            fix_input_parameters, parameter::ParameterKind, Body, Method, OpenApiInput,
+            OpenApiRequest, ParameterContents,
+        },
+        openapi_mutator::test_state::TestState,
+    };
+
+    #[test]
+    fn test_forwarded_for_header_is_added_and_kept() {
+        let api: OpenAPI = serde_json::from_value(json!({
+            "openapi": "3.0.0",
+            "info": {"title": "pets", "version": "1"},
+            "paths": {
+                "/pets": {
+                    "get": {
+                        "parameters": [{
+                            "name": "limit",
+                            "in": "query",
+                            "schema": {"type": "integer"}
+                        }],
+                        "responses": {"200": {"description": "The pets"}}
+                    }
+                }
+            }
+        }))
+        .unwrap();
+        let mut state = TestState {
+            api,
+            ..TestState::new(0)
+        };
+        let forwarded_for = ("X-Forwarded-For".to_owned(), ParameterKind::Header);
+        let mut mutator = HeaderInjectionMutator::new();
+
+        let mut input = None;
+        for _ in 0..100 {
+            let mut candidate = OpenApiInput(vec![OpenApiRequest {
+                method: Method::Get,
+                path: "/pets".to_owned(),
+                body: Body::Empty,
+                parameters: IndexMap::from([(
+                    ("limit".to_owned(), ParameterKind::Query),
+                    ParameterContents::from(json!(10)),
+                )]),
+            }]);
+            let result = mutator.mutate(&mut state, &mut candidate).unwrap();
+            assert_eq!(result, MutationResult::Mutated);
+            if candidate.0[0].parameters.contains_key(&forwarded_for) {
+                input = Some(candidate);
+                break;
+            }
+        }
+        let mut input = input.expect("The mutator never added an X-Forwarded-For header");
+        assert!(
+            ["127.0.0.1", "::1", "10.0.0.1", "127.0.0.1, 127.0.0.1"].contains(
+                &input.0[0].parameters[&forwarded_for]
+                    .to_header_value()
+                    .to_str()
+                    .unwrap()
+            )
+        );
+
+        // The header is not in the specification, but survives fixing the parameters
+        fix_input_parameters(&mut state, 0, &mut input.0[0]);
+        assert!(input.0[0].parameters.contains_key(&forwarded_for));
+        assert!(input.0[0]
+            .parameters
+            .contains_key(&("limit".to_owned(), ParameterKind::Query)));
+    }
+}
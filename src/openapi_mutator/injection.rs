@@ -0,0 +1,185 @@
+//! Mutates a string or raw bytes value by replacing it with a known injection payload,
+//! such as a SQL injection or a shell command substitution. Random mutations are
+//! unlikely to produce these, while a vulnerable server will often misbehave on them.
+//! Since the payloads may damage the target's data, they are only used with
+//! `--injection`. The built-in payloads can be replaced by those in the file given as
+//! `--injection-dictionary`, one payload per line.
+
+use std::{borrow::Cow, path::Path};
+
+use anyhow::{bail, Context};
+pub use libafl::mutators::mutations::*;
+use libafl::{
+    mutators::{MutationResult, Mutator},
+    state::HasRand,
+    Error,
+};
+use libafl_bolts::{rands::Rand, Named};
+
+use super::dictionary::collect_string_values;
+use crate::input::{OpenApiInput, ParameterContents};
+
+/// Payloads used if no injection dictionary is configured
+const BUILT_IN_PAYLOADS: [&str; 12] = [
+    "' OR 1=1--",
+    "\" OR \"1\"=\"1",
+    "'; DROP TABLE users;--",
+    "1 UNION SELECT NULL--",
+    "${jndi:ldap://x}",
+    "$(id)",
+    "`id`",
+    "; cat /etc/passwd",
+    "| whoami",
+    "../../etc/passwd",
+    "{{7*7}}",
+    "<script>alert(1)</script>",
+];
+
+/// Loads the payloads from an injection dictionary: one payload per line, skipping
+/// empty lines. Without a dictionary, the built-in payloads are used. If injection is
+/// not `enabled`, there are no payloads at all.
+pub fn load_payloads(
+    enabled: bool,
+    dictionary: Option<&Path>,
+) -> Result<Vec<String>, anyhow::Error> {
+    if !enabled {
+        return Ok(vec![]);
+    }
+    let Some(dictionary) = dictionary else {
+        return Ok(BUILT_IN_PAYLOADS.map(str::to_owned).to_vec());
+    };
+    let payloads: Vec<String> = std::fs::read_to_string(dictionary)
+        .with_context(|| format!("Could not read injection dictionary {dictionary:?}"))?
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(str::to_owned)
+        .collect();
+    if payloads.is_empty() {
+        bail!("Injection dictionary {dictionary:?} contains no payloads");
+    }
+    Ok(payloads)
+}
+
+/// The `InjectionMutator` replaces a string or raw bytes value anywhere in the chain,
+/// also inside objects and arrays, by an injection payload. Without payloads, it skips
+/// every input.
+pub struct InjectionMutator {
+    payloads: Vec<String>,
+}
+
+impl InjectionMutator {
+    #[must_use]
+    /// Creates a new InjectionMutator that uses the given payloads
+    pub fn new(payloads: Vec<String>) -> Self {
+        Self { payloads }
+    }
+}
+
+impl Named for InjectionMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        &Cow::Borrowed("injectionmutator")
+    }
+}
+
+impl<S> Mutator<OpenApiInput, S> for InjectionMutator
+where
+    S: HasRand,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut OpenApiInput) -> Result<MutationResult, Error> {
+        if self.payloads.is_empty() {
+            return Ok(MutationResult::Skipped);
+        }
+        let mut values = vec![];
+        for (_, contents) in input.parameter_filter(&|value| !value.is_reference()) {
+            collect_string_values(contents, &mut values);
+        }
+        let rand = state.rand_mut();
+        let value = match super::choose(rand, values) {
+            Some(value) => value,
+            None => return Ok(MutationResult::Skipped),
+        };
+        let payload = match rand.choose(&self.payloads) {
+            Some(payload) => payload.clone(),
+            None => return Ok(MutationResult::Skipped),
+        };
+        *value = match value {
+            ParameterContents::Bytes(_) => ParameterContents::Bytes(payload.into_bytes()),
+            _ => ParameterContents::from(payload),
+        };
+        Ok(MutationResult::Mutated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use indexmap::IndexMap;
+    use libafl::{
+        mutators::{MutationResult, Mutator},
+        state::NopState,
+    };
+    use serde_json::json;
+
+    use super::{load_payloads, InjectionMutator, BUILT_IN_PAYLOADS};
+    use crate::input::{
+        parameter::ParameterKind, Body, Method, OpenApiInput, OpenApiRequest, ParameterContents,
+    };
+
+    #[test]
+    fn test_mutation_yields_configured_payload() {
+        let mut dictionary = tempfile::NamedTempFile::new().unwrap();
+        writeln!(dictionary, "' OR 1=1--\n\n$(id)").unwrap();
+        let payloads = load_payloads(true, Some(dictionary.path())).unwrap();
+        assert_eq!(payloads, vec!["' OR 1=1--", "$(id)"]);
+
+        let mut state = NopState::<OpenApiInput>::new();
+        let mut mutator = InjectionMutator::new(payloads.clone());
+        for _ in 0..10 {
+            let mut input = OpenApiInput(vec![OpenApiRequest {
+                method: Method::Get,
+                path: "/pets".to_owned(),
+                body: Body::Empty,
+                parameters: IndexMap::from([(
+                    ("name".to_owned(), ParameterKind::Query),
+                    ParameterContents::from("Rex".to_owned()),
+                )]),
+            }]);
+            let result = mutator.mutate(&mut state, &mut input).unwrap();
+            assert_eq!(result, MutationResult::Mutated);
+            let value = input.0[0].parameters[0].to_value();
+            assert!(payloads.iter().any(|payload| json!(payload) == value));
+        }
+    }
+
+    #[test]
+    fn test_built_in_payloads() {
+        assert_eq!(
+            load_payloads(true, None).unwrap().len(),
+            BUILT_IN_PAYLOADS.len()
+        );
+        let empty = tempfile::NamedTempFile::new().unwrap();
+        assert!(load_payloads(true, Some(empty.path())).is_err());
+    }
+
+    #[test]
+    fn test_disabled_injection_leaves_input_alone() {
+        let payloads = load_payloads(false, None).unwrap();
+        assert!(payloads.is_empty());
+
+        let mut state = NopState::<OpenApiInput>::new();
+        let mut mutator = InjectionMutator::new(payloads);
+        let mut input = OpenApiInput(vec![OpenApiRequest {
+            method: Method::Get,
+            path: "/pets".to_owned(),
+            body: Body::Empty,
+            parameters: IndexMap::from([(
+                ("name".to_owned(), ParameterKind::Query),
+                ParameterContents::from("Rex".to_owned()),
+            )]),
+        }]);
+        let result = mutator.mutate(&mut state, &mut input).unwrap();
+        assert_eq!(result, MutationResult::Skipped);
+        assert_eq!(input.0[0].parameters[0].to_value(), json!("Rex"));
+    }
+}
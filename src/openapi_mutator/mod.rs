@@ -14,7 +14,7 @@ use libafl::{
     inputs::{BytesInput, HasMutatorBytes, Input},
     mutators::{MutationResult, Mutator},
     state::HasRand,
-    Error,
+    Error, HasMetadata,
 };
 use libafl_bolts::{
     rands::Rand,
@@ -23,7 +23,9 @@ use libafl_bolts::{
 };
 
 use crate::{
-    input::{new_rand_input, parameter::SimpleValue, OpenApiInput, ParameterContents},
+    input::{new_rand_input, parameter::SimpleValue, Body, OpenApiInput, ParameterContents},
+    openapi::examples::UserExamples,
+    reporting::lineage::MutationLineage,
     state::OpenApiFuzzerState,
 };
 
@@ -45,9 +47,55 @@ pub mod establish_link;
 use establish_link::EstablishLinkMutator;
 pub mod string_interesting;
 use string_interesting::StringInterestingMutator;
+pub mod type_mismatch;
+use type_mismatch::TypeMismatchMutator;
+pub mod replay_response;
+use replay_response::ReplayResponseMutator;
+pub mod empty_query;
+use empty_query::EmptyQueryMutator;
+pub mod enum_variant;
+use enum_variant::EnumVariantMutator;
+pub mod wrap_array;
+use wrap_array::WrapArrayMutator;
+pub mod discriminator;
+use discriminator::DiscriminatorMutator;
+pub mod header_bloat;
+use header_bloat::HeaderBloatMutator;
+pub mod dictionary;
+use dictionary::DictionaryMutator;
+pub mod injection;
+use injection::InjectionMutator;
+pub mod splice_requests;
+use splice_requests::SpliceRequestsMutator;
+pub mod prefer;
+use prefer::PreferMutator;
+pub mod header_injection;
+use header_injection::HeaderInjectionMutator;
+pub mod optional_field;
+use optional_field::OptionalFieldMutator;
 
-/// Creates a tuple list containing all available mutators from this module.
-pub fn havoc_mutations_openapi<C, I, R, SC>() -> tuple_list_type!(
+/// Creates a tuple list containing all available mutators from this module. The
+/// injection mutator uses the given payloads, the mutators that lengthen a series keep
+/// it at most `max_chain_length` requests long, and those that make new values also
+/// draw them from `user_examples`.
+pub fn havoc_mutations_openapi<C, I, R, SC>(
+    injection_payloads: Vec<String>,
+    max_chain_length: usize,
+    user_examples: &UserExamples,
+) -> tuple_list_type!(
+    OpenApiMutator<OpenApiFuzzerState<I, C, R, SC>>,
+    OpenApiMutator<OpenApiFuzzerState<I, C, R, SC>>,
+    OpenApiMutator<OpenApiFuzzerState<I, C, R, SC>>,
+    OpenApiMutator<OpenApiFuzzerState<I, C, R, SC>>,
+    OpenApiMutator<OpenApiFuzzerState<I, C, R, SC>>,
+    OpenApiMutator<OpenApiFuzzerState<I, C, R, SC>>,
+    OpenApiMutator<OpenApiFuzzerState<I, C, R, SC>>,
+    OpenApiMutator<OpenApiFuzzerState<I, C, R, SC>>,
+    OpenApiMutator<OpenApiFuzzerState<I, C, R, SC>>,
+    OpenApiMutator<OpenApiFuzzerState<I, C, R, SC>>,
+    OpenApiMutator<OpenApiFuzzerState<I, C, R, SC>>,
+    OpenApiMutator<OpenApiFuzzerState<I, C, R, SC>>,
+    OpenApiMutator<OpenApiFuzzerState<I, C, R, SC>>,
     OpenApiMutator<OpenApiFuzzerState<I, C, R, SC>>,
     OpenApiMutator<OpenApiFuzzerState<I, C, R, SC>>,
     OpenApiMutator<OpenApiFuzzerState<I, C, R, SC>>,
@@ -81,7 +129,7 @@ pub fn havoc_mutations_openapi<C, I, R, SC>() -> tuple_list_type!(
     OpenApiMutator<OpenApiFuzzerState<I, C, R, SC>>,
 )
 where
-    C: Corpus + 'static,
+    C: Corpus<Input = OpenApiInput> + 'static,
     I: Input + 'static,
     R: Rand + 'static,
     SC: Corpus + 'static,
@@ -110,17 +158,57 @@ where
         OpenApiMutator::from_bytes_mutator(Box::new(StringInterestingMutator::new())),
         OpenApiMutator::from_bytes_mutator(Box::new(WordAddMutator::new())),
         OpenApiMutator::from_bytes_mutator(Box::new(WordInterestingMutator::new())),
-        OpenApiMutator::from_series_mutator(Box::new(AddRequestMutator::new())),
+        OpenApiMutator::from_series_mutator(Box::new(AddRequestMutator::new(max_chain_length))),
         OpenApiMutator::from_series_mutator(Box::new(DifferentPathMutator::new())),
         OpenApiMutator::from_series_mutator(Box::new(DifferentMethodMutator::new())),
-        OpenApiMutator::from_series_mutator(Box::new(DuplicateRequestMutator::new())),
+        OpenApiMutator::from_series_mutator(Box::new(DuplicateRequestMutator::new(
+            max_chain_length,
+        ))),
         OpenApiMutator::from_series_mutator(Box::new(SwapRequestsMutator::new())),
         OpenApiMutator::from_series_mutator(Box::new(RemoveRequestMutator::new())),
         OpenApiMutator::from_series_mutator(Box::new(BreakLinkMutator::new())),
         OpenApiMutator::from_series_mutator(Box::new(EstablishLinkMutator::new())),
+        OpenApiMutator::from_series_mutator(Box::new(TypeMismatchMutator::new())),
+        OpenApiMutator::from_series_mutator(Box::new(ReplayResponseMutator::new())),
+        OpenApiMutator::from_series_mutator(Box::new(EmptyQueryMutator::new())),
+        OpenApiMutator::from_series_mutator(Box::new(EnumVariantMutator::new())),
+        OpenApiMutator::from_series_mutator(Box::new(WrapArrayMutator::new())),
+        OpenApiMutator::from_series_mutator(Box::new(DiscriminatorMutator::new(
+            user_examples.clone()
+        ))),
+        OpenApiMutator::from_series_mutator(Box::new(HeaderBloatMutator::new())),
+        OpenApiMutator::from_series_mutator(Box::new(DictionaryMutator::new())),
+        OpenApiMutator::from_series_mutator(Box::new(InjectionMutator::new(injection_payloads))),
+        OpenApiMutator::from_series_mutator(Box::new(SpliceRequestsMutator::new(max_chain_length))),
+        OpenApiMutator::from_series_mutator(Box::new(PreferMutator::new())),
+        OpenApiMutator::from_series_mutator(Box::new(HeaderInjectionMutator::new())),
+        OpenApiMutator::from_series_mutator(Box::new(OptionalFieldMutator::new(
+            user_examples.clone()
+        ))),
     )
 }
 
+/// Weights of the mutators that are scheduled more often than the others, which have
+/// weight 1, by name. Changes within an enumeration are weighed against the byte
+/// mutators, which mostly take values out of it.
+const MUTATOR_WEIGHTS: [(&str, f32); 1] = [("enumvariantmutator", 8.0)];
+
+/// The probability of scheduling each of the mutators with the given names, according
+/// to their weights
+pub fn mutation_probabilities(names: &[Cow<'static, str>]) -> Vec<f32> {
+    let weights: Vec<f32> = names
+        .iter()
+        .map(|name| {
+            MUTATOR_WEIGHTS
+                .iter()
+                .find(|(weighted, _)| weighted == name)
+                .map_or(1.0, |(_, weight)| *weight)
+        })
+        .collect();
+    let total: f32 = weights.iter().sum();
+    weights.into_iter().map(|weight| weight / total).collect()
+}
+
 /// The main mutator for our inputs (series of REST API requests).
 /// It has two variants, Contents mutates the contents of parameter values using
 /// LibAFL mutators, while Series inserts or deletes requests, or changes their order.
@@ -138,8 +226,12 @@ impl<S> Named for OpenApiMutator<S>
 where
     S: HasRand,
 {
+    /// Name of the wrapped mutator
     fn name(&self) -> &Cow<'static, str> {
-        &Cow::Borrowed("OpenApiMutator")
+        match self {
+            OpenApiMutator::Contents(contents_mutator) => contents_mutator.name(),
+            OpenApiMutator::Series(series_mutator) => series_mutator.name(),
+        }
     }
 }
 
@@ -171,11 +263,66 @@ where
 
 impl<S> Mutator<OpenApiInput, S> for OpenApiMutator<S>
 where
-    S: HasRand,
+    S: HasRand + HasMetadata,
 {
     fn mutate(&mut self, state: &mut S, input: &mut OpenApiInput) -> Result<MutationResult, Error> {
+        // Only keep track of the lineage if the lineage log is enabled, since it requires
+        // hashing the input before and after the mutation.
+        if !state.has_metadata::<MutationLineage>() {
+            return self.mutate_untracked(state, input);
+        }
+        let before = input.generate_name(None);
+        let result = self.mutate_untracked(state, input)?;
+        if result == MutationResult::Mutated {
+            let mutator = self.name().to_string();
+            let after = input.generate_name(None);
+            state
+                .metadata_mut::<MutationLineage>()?
+                .record(before, &mutator, after);
+        }
+        Ok(result)
+    }
+}
+
+impl<S> OpenApiMutator<S>
+where
+    S: HasRand,
+{
+    fn mutate_untracked(
+        &mut self,
+        state: &mut S,
+        input: &mut OpenApiInput,
+    ) -> Result<MutationResult, Error> {
         match self {
             OpenApiMutator::Contents(contents_mutator) => {
+                // Raw binary bodies have no parameters, they are mutated as a whole. Every
+                // body is as likely to be picked as every parameter.
+                let binary_bodies = input
+                    .0
+                    .iter()
+                    .filter(|request| matches!(request.body, Body::OctetStream(_)))
+                    .count();
+                if binary_bodies > 0 {
+                    let parameters = input
+                        .parameter_filter(&|value| !value.is_reference())
+                        .count();
+                    let index = state
+                        .rand_mut()
+                        .below(NonZero::new(binary_bodies + parameters).unwrap());
+                    if index < binary_bodies {
+                        let body = input
+                            .0
+                            .iter_mut()
+                            .filter_map(|request| match &mut request.body {
+                                Body::OctetStream(bytes) => Some(bytes),
+                                _ => None,
+                            })
+                            .nth(index)
+                            .unwrap();
+                        return mutate_bytes(body, state, contents_mutator.as_mut());
+                    }
+                }
+
                 // We want a list of all parameter values that we can change.
                 // Hence we visit each input and collect references to any parameter contents
                 // that are not references to earlier requests' outputs.
@@ -202,6 +349,13 @@ fn mutate_leaf_value<S: HasRand>(
     contents_mutator: &mut dyn Mutator<BytesInput, S>,
     leaf_value: &mut SimpleValue,
 ) -> MutationResult {
+    // A small chance to send null instead, which targets may not handle
+    if !matches!(leaf_value, SimpleValue::Null)
+        && state.rand_mut().below(NonZero::new(100).unwrap()) == 0
+    {
+        *leaf_value = SimpleValue::Null;
+        return MutationResult::Mutated;
+    }
     match leaf_value {
         SimpleValue::Null => MutationResult::Skipped,
         SimpleValue::Bool(ref mut b) => {
@@ -289,6 +443,27 @@ fn mutate_string<S: HasRand>(
     MutationResult::Mutated
 }
 
+/// Mutate raw bytes in-place
+fn mutate_bytes<S: HasRand>(
+    contents: &mut Vec<u8>,
+    state: &mut S,
+    contents_mutator: &mut dyn Mutator<BytesInput, S>,
+) -> Result<MutationResult, Error> {
+    // The ASCII mutators operate on the LibAFL `BytesInput` type. This requires
+    // conversions.
+    let mut new_value = BytesInput::from(contents.clone());
+    let mutation_result = contents_mutator.mutate(state, &mut new_value);
+    if mutation_result.is_ok() {
+        // The ASCII mutators might not actually change a value, since bit 0 of all bytes is always 0.
+        // To prevent duplicate inputs, we check explicitly if it actually changed anything.
+        if contents == new_value.bytes() {
+            return Ok(MutationResult::Skipped);
+        }
+        *contents = new_value.bytes().to_owned();
+    }
+    mutation_result
+}
+
 /// Mutate parameter contents in-place
 fn mutate_parameter_contents<S: HasRand>(
     param_contents: &mut ParameterContents,
@@ -321,19 +496,7 @@ fn mutate_parameter_contents<S: HasRand>(
             return Ok(mutate_leaf_value(state, contents_mutator, leaf))
         }
         ParameterContents::Bytes(contents) => {
-            // The ASCII mutators operate on the LibAFL `BytesInput` type. This requires
-            // conversions.
-            let mut new_value = BytesInput::from(contents.clone());
-            let mutation_result = contents_mutator.mutate(state, &mut new_value);
-            if mutation_result.is_ok() {
-                // The ASCII mutators might not actually change a value, since bit 0 of all bytes is always 0.
-                // To prevent duplicate inputs, we check explicitly if it actually changed anything.
-                if contents == new_value.bytes() {
-                    return Ok(MutationResult::Skipped);
-                }
-                *param_contents = ParameterContents::Bytes(new_value.bytes().to_owned())
-            }
-            return mutation_result;
+            return mutate_bytes(contents, state, contents_mutator);
         }
         ParameterContents::Reference { .. } => unreachable!(
             "Non-nested reference parameters should have been filtered out of concrete_parameters"
@@ -372,3 +535,194 @@ where
             }
         })
 }
+
+/// The state the mutators are tested with, which provides everything a mutator may ask
+/// of the fuzzer state. Tests set the fields they need and take the rest from `new`.
+#[cfg(test)]
+pub(crate) mod test_state {
+    use libafl::{
+        corpus::InMemoryCorpus,
+        state::{HasCorpus, HasRand},
+        HasMetadata,
+    };
+    use libafl_bolts::{rands::StdRand, serdeany::SerdeAnyMap};
+    use openapiv3::OpenAPI;
+    use serde_json::json;
+
+    use crate::{
+        input::OpenApiInput,
+        state::{HasRandAndDictionary, HasRandAndOpenAPI},
+    };
+
+    pub struct TestState {
+        pub rand: StdRand,
+        pub api: OpenAPI,
+        pub dictionary: Vec<String>,
+        pub corpus: InMemoryCorpus<OpenApiInput>,
+        pub metadata: SerdeAnyMap,
+    }
+
+    impl TestState {
+        /// A state with a random generator seeded with `seed`, an API without paths, and
+        /// an empty dictionary, corpus and metadata
+        pub fn new(seed: u64) -> Self {
+            Self {
+                rand: StdRand::with_seed(seed),
+                api: serde_json::from_value(json!({
+                    "openapi": "3.0.0",
+                    "info": {"title": "test", "version": "1"},
+                    "paths": {}
+                }))
+                .unwrap(),
+                dictionary: Vec::new(),
+                corpus: InMemoryCorpus::new(),
+                metadata: SerdeAnyMap::new(),
+            }
+        }
+    }
+
+    impl HasRand for TestState {
+        type Rand = StdRand;
+
+        fn rand(&self) -> &StdRand {
+            &self.rand
+        }
+
+        fn rand_mut(&mut self) -> &mut StdRand {
+            &mut self.rand
+        }
+    }
+
+    impl HasRandAndOpenAPI for TestState {
+        type Rand = StdRand;
+
+        fn rand_mut_and_openapi(&mut self) -> (&mut StdRand, &OpenAPI) {
+            (&mut self.rand, &self.api)
+        }
+    }
+
+    impl HasRandAndDictionary for TestState {
+        type Rand = StdRand;
+
+        fn rand_mut_and_dictionary(&mut self) -> (&mut StdRand, &[String]) {
+            (&mut self.rand, &self.dictionary)
+        }
+    }
+
+    impl HasCorpus for TestState {
+        type Corpus = InMemoryCorpus<OpenApiInput>;
+
+        fn corpus(&self) -> &Self::Corpus {
+            &self.corpus
+        }
+
+        fn corpus_mut(&mut self) -> &mut Self::Corpus {
+            &mut self.corpus
+        }
+    }
+
+    impl HasMetadata for TestState {
+        fn metadata_map(&self) -> &SerdeAnyMap {
+            &self.metadata
+        }
+
+        fn metadata_map_mut(&mut self) -> &mut SerdeAnyMap {
+            &mut self.metadata
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use indexmap::IndexMap;
+    use libafl::{
+        inputs::Input,
+        mutators::{MutationResult, Mutator},
+        HasMetadata,
+    };
+
+    use super::{
+        mutation_probabilities, ByteFlipMutator, DuplicateRequestMutator, OpenApiMutator,
+        SwapRequestsMutator,
+    };
+    use crate::{
+        input::{Body, Method, OpenApiInput, OpenApiRequest},
+        openapi_mutator::test_state::TestState,
+        reporting::lineage::MutationLineage,
+    };
+
+    fn request(path: &str) -> OpenApiRequest {
+        OpenApiRequest {
+            method: Method::Get,
+            path: path.to_owned(),
+            body: Body::Empty,
+            parameters: IndexMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_lineage_of_two_mutations() {
+        let mut state = TestState::new(0);
+        state.add_metadata(MutationLineage::default());
+        let mut input = OpenApiInput(vec![request("/a"), request("/b")]);
+        let parent = input.generate_name(None);
+
+        let mut duplicate: OpenApiMutator<TestState> =
+            OpenApiMutator::from_series_mutator(Box::new(DuplicateRequestMutator::new(10)));
+        let mut swap: OpenApiMutator<TestState> =
+            OpenApiMutator::from_series_mutator(Box::new(SwapRequestsMutator::new()));
+        assert_eq!(
+            duplicate.mutate(&mut state, &mut input).unwrap(),
+            MutationResult::Mutated
+        );
+        assert_eq!(
+            swap.mutate(&mut state, &mut input).unwrap(),
+            MutationResult::Mutated
+        );
+
+        let lineage = state.metadata::<MutationLineage>().unwrap();
+        assert_eq!(lineage.parent, parent);
+        assert_eq!(
+            lineage.mutators,
+            vec!["duplicaterequestmutator", "swaprequestsmutator"]
+        );
+        assert_eq!(lineage.input, input.generate_name(None));
+    }
+
+    #[test]
+    fn test_contents_mutator_mutates_binary_body() {
+        let mut state = TestState::new(0);
+        let original = b"\x00\x01\x02\x03".to_vec();
+        let mut input = OpenApiInput(vec![OpenApiRequest {
+            body: Body::OctetStream(original.clone()),
+            ..request("/upload")
+        }]);
+
+        let mut flip: OpenApiMutator<TestState> =
+            OpenApiMutator::from_bytes_mutator(Box::new(ByteFlipMutator::new()));
+        assert_eq!(
+            flip.mutate(&mut state, &mut input).unwrap(),
+            MutationResult::Mutated
+        );
+        let Body::OctetStream(bytes) = &input.0[0].body else {
+            panic!("Body is no longer binary");
+        };
+        assert_eq!(bytes.len(), original.len());
+        assert_ne!(bytes, &original);
+    }
+
+    #[test]
+    fn test_enum_variant_mutator_is_weighted() {
+        let names = [
+            Cow::Borrowed("byteflipmutator"),
+            Cow::Borrowed("enumvariantmutator"),
+            Cow::Borrowed("swaprequestsmutator"),
+        ];
+        let probabilities = mutation_probabilities(&names);
+        assert!((probabilities.iter().sum::<f32>() - 1.0).abs() < 1e-6);
+        assert_eq!(probabilities[0], probabilities[2]);
+        assert!((probabilities[1] - 8.0 * probabilities[0]).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,208 @@
+//! Mutates a JSON object body by leaving out one of its optional fields, adding back a
+//! field that the specification declares but the body lacks, or changing the order of
+//! its fields. Servers validate which fields are present separately from their values,
+//! and may depend on the order of fields, so these paths are not reached by mutating
+//! the values alone. Fields that the specification requires are never left out.
+
+use core::num::NonZero;
+use std::borrow::Cow;
+
+use indexmap::IndexMap;
+pub use libafl::mutators::mutations::*;
+use libafl::{
+    mutators::{MutationResult, Mutator},
+    Error,
+};
+use libafl_bolts::{rands::Rand, Named};
+use openapiv3::{ObjectType, OpenAPI, SchemaKind, Type};
+
+use super::discriminator::body_schema;
+use crate::{
+    input::{Body, OpenApiInput, OpenApiRequest, ParameterContents},
+    openapi::examples::{example_from_schema, UserExamples},
+    state::HasRandAndOpenAPI,
+};
+
+/// The ways in which the fields of a body can be changed
+enum FieldMutation {
+    /// Leave out the optional field with this name
+    Remove(String),
+    /// Add the field with this name and value
+    Add(String, ParameterContents),
+    /// Swap two of the fields
+    Swap,
+}
+
+/// The `OptionalFieldMutator` removes an optional field from a JSON object body, adds a
+/// declared field that is missing from it, or swaps two of its fields.
+pub struct OptionalFieldMutator {
+    user_examples: UserExamples,
+}
+
+impl OptionalFieldMutator {
+    #[must_use]
+    /// Creates a new OptionalFieldMutator that makes the values of added fields from
+    /// `user_examples` as well as the specification
+    pub fn new(user_examples: UserExamples) -> Self {
+        Self { user_examples }
+    }
+}
+
+impl Named for OptionalFieldMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        &Cow::Borrowed("optionalfieldmutator")
+    }
+}
+
+impl<S> Mutator<OpenApiInput, S> for OptionalFieldMutator
+where
+    S: HasRandAndOpenAPI,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut OpenApiInput) -> Result<MutationResult, Error> {
+        let (rand, api) = state.rand_mut_and_openapi();
+
+        let candidates = input.0.iter_mut().filter_map(|request| {
+            let object = object_schema(api, request)?;
+            let Body::ApplicationJson(ParameterContents::Object(fields)) = &request.body else {
+                return None;
+            };
+            let mutations = field_mutations(api, &self.user_examples, object, fields);
+            (!mutations.is_empty()).then_some((request, mutations))
+        });
+        let (request, mutations) = match super::choose(rand, candidates) {
+            Some(candidate) => candidate,
+            None => return Ok(MutationResult::Skipped),
+        };
+        let Body::ApplicationJson(ParameterContents::Object(fields)) = &mut request.body else {
+            unreachable!("Filtered by the candidates")
+        };
+        match rand.choose(mutations).unwrap() {
+            FieldMutation::Remove(name) => {
+                fields.shift_remove(&name);
+            }
+            FieldMutation::Add(name, value) => {
+                fields.insert(name, value);
+            }
+            FieldMutation::Swap => {
+                let first = rand.below(NonZero::new(fields.len()).unwrap());
+                let offset = rand.below(NonZero::new(fields.len() - 1).unwrap());
+                fields.swap_indices(first, (first + 1 + offset) % fields.len());
+            }
+        }
+
+        input.assert_valid(self.name());
+        Ok(MutationResult::Mutated)
+    }
+}
+
+/// Returns the object schema of the request body in the specification, if it has one.
+fn object_schema<'a>(api: &'a OpenAPI, request: &OpenApiRequest) -> Option<&'a ObjectType> {
+    match &body_schema(api, request)?.kind {
+        SchemaKind::Type(Type::Object(object)) => Some(object),
+        _ => None,
+    }
+}
+
+/// All mutations of a body with the given fields, according to its schema
+fn field_mutations(
+    api: &OpenAPI,
+    user_examples: &UserExamples,
+    object: &ObjectType,
+    fields: &IndexMap<String, ParameterContents>,
+) -> Vec<FieldMutation> {
+    let mut mutations: Vec<FieldMutation> = fields
+        .keys()
+        .filter(|name| !object.required.contains(name))
+        .map(|name| FieldMutation::Remove(name.clone()))
+        .collect();
+    for (name, property) in &object.properties {
+        if fields.contains_key(name) {
+            continue;
+        }
+        if let Some(example) = example_from_schema(api, user_examples, property.resolve(api)) {
+            mutations.push(FieldMutation::Add(
+                name.clone(),
+                ParameterContents::from(example),
+            ));
+        }
+    }
+    if fields.len() > 1 {
+        mutations.push(FieldMutation::Swap);
+    }
+    mutations
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+    use libafl::mutators::{MutationResult, Mutator};
+    use openapiv3::OpenAPI;
+    use serde_json::json;
+
+    use super::{OptionalFieldMutator, UserExamples};
+    use crate::{
+        input::{Body, Method, OpenApiInput, OpenApiRequest, ParameterContents},
+        openapi_mutator::test_state::TestState,
+    };
+
+    #[test]
+    fn test_required_fields_are_never_removed() {
+        let api: OpenAPI = serde_json::from_value(json!({
+            "openapi": "3.0.0",
+            "info": {"title": "pets", "version": "1"},
+            "paths": {
+                "/pets": {
+                    "post": {
+                        "requestBody": {
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "type": "object",
+                                        "required": ["name", "species"],
+                                        "properties": {
+                                            "name": {"type": "string"},
+                                            "species": {"type": "string"},
+                                            "age": {"type": "integer"},
+                                            "tag": {"type": "string", "example": "good boy"}
+                                        }
+                                    }
+                                }
+                            }
+                        },
+                        "responses": {"201": {"description": "Created"}}
+                    }
+                }
+            }
+        }))
+        .unwrap();
+        let mut state = TestState {
+            api,
+            ..TestState::new(0)
+        };
+        let mut input = OpenApiInput(vec![OpenApiRequest {
+            method: Method::Post,
+            path: "/pets".to_owned(),
+            body: Body::ApplicationJson(ParameterContents::from(
+                json!({"name": "Rex", "species": "dog", "age": 3}),
+            )),
+            parameters: IndexMap::new(),
+        }]);
+
+        let mut mutator = OptionalFieldMutator::new(UserExamples::default());
+        let (mut removed_age, mut added_tag) = (false, false);
+        for _ in 0..200 {
+            let result = mutator.mutate(&mut state, &mut input).unwrap();
+            assert_eq!(result, MutationResult::Mutated);
+            let Body::ApplicationJson(ParameterContents::Object(fields)) = &input.0[0].body else {
+                panic!("The body is no longer an object");
+            };
+            assert!(fields.contains_key("name") && fields.contains_key("species"));
+            removed_age |= !fields.contains_key("age");
+            added_tag |= fields
+                .get("tag")
+                .is_some_and(|tag| tag.to_value() == json!("good boy"));
+        }
+        assert!(removed_age);
+        assert!(added_tag);
+    }
+}
@@ -0,0 +1,82 @@
+//! Mutates a request by setting its `Prefer` header (RFC 7240) to one of the `return`
+//! preferences, or removing it. APIs that honor `return=minimal` leave out the response
+//! body, and `return=representation` asks for the full resource, so both variants lead
+//! to different code paths on the server.
+
+use std::borrow::Cow;
+
+pub use libafl::mutators::mutations::*;
+use libafl::{
+    mutators::{MutationResult, Mutator},
+    state::HasRand,
+    Error,
+};
+use libafl_bolts::{rands::Rand, Named};
+
+use crate::input::{parameter::ParameterKind, OpenApiInput, ParameterContents};
+
+/// Name of the header that carries the preferences
+pub const PREFER_HEADER: &str = "Prefer";
+
+/// The `return` preferences the mutator chooses from
+const RETURN_PREFERENCES: [&str; 2] = ["return=minimal", "return=representation"];
+
+/// The `PreferMutator` sets the `Prefer` header of a request to a random `return`
+/// preference. If the header already had that value, it is removed instead.
+pub struct PreferMutator;
+
+impl PreferMutator {
+    #[must_use]
+    /// Creates a new PreferMutator
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl Default for PreferMutator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Named for PreferMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        &Cow::Borrowed("prefermutator")
+    }
+}
+
+impl<S> Mutator<OpenApiInput, S> for PreferMutator
+where
+    S: HasRand,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut OpenApiInput) -> Result<MutationResult, Error> {
+        if input.0.is_empty() {
+            return Ok(MutationResult::Skipped);
+        }
+        let rand = state.rand_mut();
+        let preference = rand.choose(RETURN_PREFERENCES).unwrap();
+        let request = rand.choose(input.0.iter_mut()).unwrap();
+
+        let existing = request
+            .parameters
+            .keys()
+            .find(|(name, kind)| {
+                *kind == ParameterKind::Header && name.eq_ignore_ascii_case(PREFER_HEADER)
+            })
+            .cloned();
+        let key = existing.unwrap_or_else(|| (PREFER_HEADER.to_owned(), ParameterKind::Header));
+        match request.parameters.get(&key) {
+            Some(value) if value.to_header_value() == preference => {
+                request.parameters.shift_remove(&key);
+            }
+            _ => {
+                // As bytes, which are sent as-is rather than as a quoted string
+                request.parameters.insert(
+                    key,
+                    ParameterContents::Bytes(preference.as_bytes().to_vec()),
+                );
+            }
+        }
+        Ok(MutationResult::Mutated)
+    }
+}
@@ -0,0 +1,247 @@
+//! Mutates a request series by using the complete response body of a request as the
+//! JSON body of a later request. Servers that do not expect their own output as input
+//! may mishandle it, e.g. because of read-only fields such as identifiers. Only
+//! responses that the specification declares as JSON of the schema of the body are
+//! replayed, as other responses would just be rejected.
+
+use std::borrow::Cow;
+
+pub use libafl::mutators::mutations::*;
+use libafl::{
+    mutators::{MutationResult, Mutator},
+    Error,
+};
+use libafl_bolts::Named;
+use openapiv3::{OpenAPI, Schema, StatusCode};
+
+use super::discriminator::body_schema;
+use crate::{
+    input::{Body, OpenApiInput, OpenApiRequest, ParameterContents},
+    openapi::{find_operation, JsonContent},
+    parameter_feedback::RESPONSE_BODY_PARAMETER,
+    state::HasRandAndOpenAPI,
+};
+
+/// The `ReplayResponseMutator` replaces the JSON body of a request by a reference to
+/// the response body of an earlier request in the series.
+pub struct ReplayResponseMutator;
+
+impl ReplayResponseMutator {
+    #[must_use]
+    /// Creates a new ReplayResponseMutator
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl Default for ReplayResponseMutator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Named for ReplayResponseMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        &Cow::Borrowed("replayresponsemutator")
+    }
+}
+
+impl<S> Mutator<OpenApiInput, S> for ReplayResponseMutator
+where
+    S: HasRandAndOpenAPI,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut OpenApiInput) -> Result<MutationResult, Error> {
+        let (rand, api) = state.rand_mut_and_openapi();
+        // Pairs of a request that sends a JSON body, and an earlier request whose
+        // response has the schema of that body.
+        let candidates = input
+            .0
+            .iter()
+            .enumerate()
+            .filter(|(_, request)| matches!(request.body, Body::ApplicationJson(_)))
+            .filter_map(|(request_index, request)| {
+                Some((request_index, body_schema(api, request)?))
+            })
+            .flat_map(|(request_index, schema)| {
+                input.0[..request_index]
+                    .iter()
+                    .enumerate()
+                    .filter(move |(_, earlier)| {
+                        json_response_schemas(api, earlier).contains(&schema)
+                    })
+                    .map(move |(response_index, _)| (request_index, response_index))
+            });
+
+        let (request_index, response_index) = match super::choose(rand, candidates) {
+            Some(candidate) => candidate,
+            None => return Ok(MutationResult::Skipped),
+        };
+        input.0[request_index].body = Body::ApplicationJson(ParameterContents::Reference {
+            request_index: response_index,
+            parameter_name: RESPONSE_BODY_PARAMETER.to_owned(),
+        });
+
+        input.assert_valid(self.name());
+        Ok(MutationResult::Mutated)
+    }
+}
+
+/// The schemas of the JSON bodies of the successful responses to the request that the
+/// specification declares.
+fn json_response_schemas<'a>(api: &'a OpenAPI, request: &OpenApiRequest) -> Vec<&'a Schema> {
+    let Some(operation) = find_operation(api, &request.path, request.method) else {
+        return vec![];
+    };
+    operation
+        .responses
+        .responses
+        .iter()
+        .filter(|(status, _)| match status {
+            StatusCode::Code(code) => (200..300).contains(code),
+            StatusCode::Range(range) => *range == 2,
+        })
+        .filter_map(|(_, response)| response.resolve(api).ok())
+        .filter_map(|response| response.content.get_json_content()?.schema.as_ref())
+        .map(|schema| schema.resolve(api))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+    use libafl::mutators::{MutationResult, Mutator};
+    use serde_json::json;
+
+    use super::ReplayResponseMutator;
+    use crate::{
+        input::{Body, Method, OpenApiInput, OpenApiRequest, ParameterContents},
+        openapi_mutator::test_state::TestState,
+        parameter_feedback::ParameterFeedback,
+    };
+
+    /// A specification in which a pet is read from `/pets/{petId}` and written to `/pets`,
+    /// while `/owners` returns text.
+    fn state() -> TestState {
+        let api = serde_json::from_value(json!({
+            "openapi": "3.0.0",
+            "info": {"title": "pets", "version": "1"},
+            "paths": {
+                "/pets": {
+                    "post": {
+                        "requestBody": {
+                            "content": {
+                                "application/json": {
+                                    "schema": {"$ref": "#/components/schemas/Pet"}
+                                }
+                            }
+                        },
+                        "responses": {"201": {"description": "Created"}}
+                    }
+                },
+                "/pets/{petId}": {
+                    "get": {
+                        "responses": {
+                            "200": {
+                                "description": "The pet",
+                                "content": {
+                                    "application/json": {
+                                        "schema": {"$ref": "#/components/schemas/Pet"}
+                                    }
+                                }
+                            }
+                        }
+                    }
+                },
+                "/owners": {
+                    "get": {
+                        "responses": {
+                            "200": {
+                                "description": "The owners",
+                                "content": {
+                                    "text/plain": {
+                                        "schema": {"$ref": "#/components/schemas/Pet"}
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            },
+            "components": {
+                "schemas": {
+                    "Pet": {
+                        "type": "object",
+                        "properties": {"name": {"type": "string"}}
+                    }
+                }
+            }
+        }))
+        .unwrap();
+        TestState {
+            api,
+            ..TestState::new(0)
+        }
+    }
+
+    fn request(method: Method, path: &str, body: Body) -> OpenApiRequest {
+        OpenApiRequest {
+            method,
+            path: path.to_owned(),
+            body,
+            parameters: IndexMap::new(),
+        }
+    }
+
+    fn pet_body() -> Body {
+        Body::ApplicationJson(ParameterContents::from(json!({"name": "Wuppie"})))
+    }
+
+    #[test]
+    fn test_replays_stored_response_body() {
+        let mut input = OpenApiInput(vec![
+            request(Method::Get, "/owners", Body::Empty),
+            request(Method::Get, "/pets/{petId}", Body::Empty),
+            request(Method::Post, "/pets", pet_body()),
+        ]);
+        let result = ReplayResponseMutator::new()
+            .mutate(&mut state(), &mut input)
+            .unwrap();
+        assert_eq!(result, MutationResult::Mutated);
+
+        // Only the JSON response of the pet has the schema of the body
+        let response_body = json!({"id": 37, "name": "Wuppie", "tags": ["fuzzer"]});
+        let mut parameter_feedback = ParameterFeedback::new(3);
+        parameter_feedback.set_response_body(1, response_body.clone());
+
+        let mut replayed = input.0[2].clone();
+        replayed
+            .resolve_parameter_references(&parameter_feedback)
+            .unwrap();
+        match replayed.body {
+            Body::ApplicationJson(body) => assert_eq!(body.to_value(), response_body),
+            other => panic!("Expected a JSON body, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_skips_without_matching_response() {
+        let mut input = OpenApiInput(vec![
+            request(Method::Get, "/pets/{petId}", Body::Empty),
+            request(Method::Get, "/pets/{petId}", Body::Empty),
+        ]);
+        let result = ReplayResponseMutator::new()
+            .mutate(&mut state(), &mut input)
+            .unwrap();
+        assert_eq!(result, MutationResult::Skipped);
+
+        // The response of the owners is not JSON
+        let mut input = OpenApiInput(vec![
+            request(Method::Get, "/owners", Body::Empty),
+            request(Method::Post, "/pets", pet_body()),
+        ]);
+        let result = ReplayResponseMutator::new()
+            .mutate(&mut state(), &mut input)
+            .unwrap();
+        assert_eq!(result, MutationResult::Skipped);
+    }
+}
@@ -0,0 +1,168 @@
+//! Mutates a request series by appending requests from another series in the corpus.
+//! Requests that the fuzzer learned to chain for one endpoint thus get combined with
+//! those for another, which can reveal how the state of one endpoint affects another.
+
+use std::borrow::Cow;
+
+pub use libafl::mutators::mutations::*;
+use libafl::{
+    corpus::Corpus,
+    mutators::{MutationResult, Mutator},
+    random_corpus_id,
+    state::{HasCorpus, HasRand},
+    Error,
+};
+use libafl_bolts::{rands::Rand, Named};
+
+use crate::input::OpenApiInput;
+
+/// The `SpliceRequestsMutator` appends a suffix of a random other series from the
+/// corpus to the series, as far as the maximum length of the series allows.
+pub struct SpliceRequestsMutator {
+    max_chain_length: usize,
+}
+
+impl SpliceRequestsMutator {
+    #[must_use]
+    /// Creates a new SpliceRequestsMutator that makes series of at most
+    /// `max_chain_length` requests
+    pub fn new(max_chain_length: usize) -> Self {
+        Self { max_chain_length }
+    }
+}
+
+impl Named for SpliceRequestsMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        &Cow::Borrowed("splicerequestsmutator")
+    }
+}
+
+impl<S> Mutator<OpenApiInput, S> for SpliceRequestsMutator
+where
+    S: HasCorpus + HasRand,
+    S::Corpus: Corpus<Input = OpenApiInput>,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut OpenApiInput) -> Result<MutationResult, Error> {
+        let room = self.max_chain_length.saturating_sub(input.0.len());
+        if room == 0 || state.corpus().count() == 0 {
+            return Ok(MutationResult::Skipped);
+        }
+        let id = random_corpus_id!(state.corpus(), state.rand_mut());
+        // Splicing the input with itself is what the duplicate request mutator does
+        if state.corpus().current() == &Some(id) {
+            return Ok(MutationResult::Skipped);
+        }
+        let other = {
+            let mut testcase = state.corpus().get(id)?.borrow_mut();
+            testcase.load_input(state.corpus())?.clone()
+        };
+        let Some(other_len) = core::num::NonZero::new(other.0.len()) else {
+            return Ok(MutationResult::Skipped);
+        };
+
+        let start = state.rand_mut().below(other_len);
+        let end = other.0.len().min(start + room);
+        let offset = input.0.len();
+        let mut spliced = OpenApiInput(other.0[start..end].to_vec());
+
+        // References to requests that were copied along move with them. Those to
+        // requests that were left behind are made to point nowhere, so that they are
+        // replaced by `fix_broken_references`.
+        for (_, param) in spliced.parameter_filter(&|v| v.is_reference()) {
+            let reference_index = param
+                .reference_index()
+                .expect("filtered by parameter_filter");
+            *reference_index = match reference_index.checked_sub(start) {
+                Some(index) => index + offset,
+                None => usize::MAX,
+            };
+        }
+        input.0.append(&mut spliced.0);
+
+        input.fix_broken_references(state.rand_mut());
+        input.assert_valid(self.name());
+        Ok(MutationResult::Mutated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+    use libafl::{
+        corpus::{Corpus, Testcase},
+        mutators::{MutationResult, Mutator},
+    };
+
+    use super::SpliceRequestsMutator;
+    use crate::{
+        input::{
+            parameter::ParameterKind, Body, Method, OpenApiInput, OpenApiRequest, ParameterContents,
+        },
+        openapi_mutator::test_state::TestState,
+    };
+
+    /// A chain that creates a resource at `path` and then uses its id in `len - 1`
+    /// requests, each referring to the one before it
+    fn chain(path: &str, len: usize) -> OpenApiInput {
+        let request = |method, parameters| OpenApiRequest {
+            method,
+            path: path.to_owned(),
+            body: Body::Empty,
+            parameters,
+        };
+        let mut requests = vec![request(
+            Method::Post,
+            IndexMap::from([(
+                ("id".to_owned(), ParameterKind::Query),
+                ParameterContents::from("1".to_owned()),
+            )]),
+        )];
+        for index in 1..len {
+            requests.push(request(
+                Method::Get,
+                IndexMap::from([(
+                    ("id".to_owned(), ParameterKind::Path),
+                    ParameterContents::Reference {
+                        request_index: index - 1,
+                        parameter_name: "id".to_owned(),
+                    },
+                )]),
+            ));
+        }
+        OpenApiInput(requests)
+    }
+
+    #[test]
+    fn test_spliced_chain_is_valid() {
+        let mut state = TestState::new(0);
+        state
+            .corpus
+            .add(Testcase::new(chain("/owners", 4)))
+            .unwrap();
+        let mut mutator = SpliceRequestsMutator::new(6);
+
+        for _ in 0..50 {
+            let mut input = chain("/pets", 2);
+            let result = mutator.mutate(&mut state, &mut input).unwrap();
+            assert_eq!(result, MutationResult::Mutated);
+            assert!(input.0.len() > 2 && input.0.len() <= 6);
+            assert!(input.0[2..].iter().all(|request| request.path == "/owners"));
+            // Panics if a reference points forward
+            input.assert_valid("test");
+            // The requests of the receiving chain are unchanged
+            let original: Vec<String> = chain("/pets", 2)
+                .0
+                .iter()
+                .map(ToString::to_string)
+                .collect();
+            let prefix: Vec<String> = input.0[..2].iter().map(ToString::to_string).collect();
+            assert_eq!(prefix, original);
+            // References within the spliced part point to the spliced requests
+            for (appears_in, param) in input.parameter_filter(&|v| v.is_reference()) {
+                if appears_in > 2 {
+                    assert_eq!(*param.reference_index().unwrap(), appears_in - 1);
+                }
+            }
+        }
+    }
+}
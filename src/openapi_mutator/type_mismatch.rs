@@ -0,0 +1,306 @@
+//! Mutates a parameter value by replacing it with a value of a different JSON type, e.g. a
+//! string by a number, or a number by a boolean. Servers that deserialize loosely typed
+//! input may mishandle such values. If the value is a field in an object, a sibling field
+//! of a different type may be used as the replacement value.
+
+use std::borrow::Cow;
+
+pub use libafl::mutators::mutations::*;
+use libafl::{
+    mutators::{MutationResult, Mutator},
+    state::HasRand,
+    Error,
+};
+use libafl_bolts::{rands::Rand, Named};
+use serde_json::Number;
+
+use crate::input::{parameter::SimpleValue, Body, OpenApiInput, OpenApiRequest, ParameterContents};
+
+/// The `TypeMismatchMutator` replaces a leaf value somewhere in the parameters or body
+/// of a request with a value of a different JSON type. Object keys are kept.
+pub struct TypeMismatchMutator;
+
+impl TypeMismatchMutator {
+    #[must_use]
+    /// Creates a new TypeMismatchMutator
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl Default for TypeMismatchMutator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Named for TypeMismatchMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        &Cow::Borrowed("typemismatchmutator")
+    }
+}
+
+impl<S> Mutator<OpenApiInput, S> for TypeMismatchMutator
+where
+    S: HasRand,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut OpenApiInput) -> Result<MutationResult, Error> {
+        let concrete_parameters = input
+            .0
+            .iter_mut()
+            .flat_map(mutable_contents)
+            .filter(|v| !v.is_reference());
+
+        let random_param = match super::choose(state.rand_mut(), concrete_parameters) {
+            Some(parameter) => parameter,
+            None => return Ok(MutationResult::Skipped),
+        };
+
+        Ok(mismatch_contents(state.rand_mut(), random_param))
+    }
+}
+
+/// The parameters and body fields of the request that may be mutated. Form bodies must
+/// stay objects, so only their fields are mutated, not the body as a whole.
+fn mutable_contents(request: &mut OpenApiRequest) -> Vec<&mut ParameterContents> {
+    let body = match &mut request.body {
+        Body::ApplicationJson(ParameterContents::Object(fields))
+        | Body::XWwwFormUrlencoded(ParameterContents::Object(fields))
+        | Body::Multipart(ParameterContents::Object(fields)) => fields.values_mut().collect(),
+        Body::ApplicationJson(ParameterContents::Array(elements))
+        | Body::Multipart(ParameterContents::Array(elements)) => elements.iter_mut().collect(),
+        Body::ApplicationJson(contents) | Body::TextPlain(contents) | Body::Multipart(contents) => {
+            vec![contents]
+        }
+        Body::Empty | Body::OctetStream(_) | Body::XWwwFormUrlencoded(_) => vec![],
+    };
+    request.parameters.values_mut().chain(body).collect()
+}
+
+/// Descends into objects and arrays to find a leaf value, and replaces it with a value
+/// of a different type.
+fn mismatch_contents<R: Rand>(rand: &mut R, contents: &mut ParameterContents) -> MutationResult {
+    match contents {
+        ParameterContents::Object(fields) => {
+            if fields.is_empty() {
+                return MutationResult::Skipped;
+            }
+            let index = rand.below(core::num::NonZero::new(fields.len()).unwrap());
+            if !is_leaf(&fields[index]) {
+                return mismatch_contents(rand, &mut fields[index]);
+            }
+            // Prefer the value of a sibling field of a different type, if there is one
+            let chosen_type = json_type(&fields[index]);
+            let siblings = fields
+                .values()
+                .filter(|sibling| is_leaf(sibling) && json_type(sibling) != chosen_type);
+            match super::choose(rand, siblings).cloned() {
+                Some(sibling) if rand.coinflip(0.5) => fields[index] = sibling,
+                _ => fields[index] = mismatched_value(rand, &fields[index]),
+            }
+            MutationResult::Mutated
+        }
+        ParameterContents::Array(elements) => match rand.choose(elements.iter_mut()) {
+            Some(element) => mismatch_contents(rand, element),
+            None => MutationResult::Skipped,
+        },
+        ParameterContents::LeafValue(_) | ParameterContents::Bytes(_) => {
+            *contents = mismatched_value(rand, contents);
+            MutationResult::Mutated
+        }
+        ParameterContents::Reference { .. } => MutationResult::Skipped,
+    }
+}
+
+fn is_leaf(contents: &ParameterContents) -> bool {
+    matches!(
+        contents,
+        ParameterContents::LeafValue(_) | ParameterContents::Bytes(_)
+    )
+}
+
+/// The JSON types that values are serialized as
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum JsonType {
+    Object,
+    Array,
+    Null,
+    Boolean,
+    Number,
+    String,
+    /// Not serialized itself, but replaced by the value it refers to
+    Reference,
+}
+
+/// The JSON type the value will be serialized as.
+fn json_type(contents: &ParameterContents) -> JsonType {
+    match contents {
+        ParameterContents::Object(_) => JsonType::Object,
+        ParameterContents::Array(_) => JsonType::Array,
+        ParameterContents::LeafValue(SimpleValue::Null) => JsonType::Null,
+        ParameterContents::LeafValue(SimpleValue::Bool(_)) => JsonType::Boolean,
+        ParameterContents::LeafValue(SimpleValue::Number(_)) => JsonType::Number,
+        ParameterContents::LeafValue(SimpleValue::String(_)) | ParameterContents::Bytes(_) => {
+            JsonType::String
+        }
+        ParameterContents::Reference { .. } => JsonType::Reference,
+    }
+}
+
+/// Creates a value of a different type than the given leaf value. Where possible,
+/// the new value is derived from the old one, e.g. "12" becomes 12.
+fn mismatched_value<R: Rand>(rand: &mut R, leaf: &ParameterContents) -> ParameterContents {
+    let as_string = match leaf {
+        ParameterContents::LeafValue(SimpleValue::String(s)) => s.clone(),
+        ParameterContents::Bytes(bytes) => String::from_utf8_lossy(bytes).into_owned(),
+        other => other.to_string(),
+    };
+    let as_number = match leaf {
+        ParameterContents::LeafValue(SimpleValue::Number(n)) => n.clone(),
+        ParameterContents::LeafValue(SimpleValue::Bool(b)) => Number::from(*b as u8),
+        _ => as_string
+            .trim()
+            .parse::<i64>()
+            .map(Number::from)
+            .unwrap_or_else(|_| Number::from(as_string.len())),
+    };
+    let as_bool = match leaf {
+        ParameterContents::LeafValue(SimpleValue::Number(n)) => n.as_f64() != Some(0.0),
+        ParameterContents::LeafValue(SimpleValue::Null) => false,
+        _ => !as_string.is_empty(),
+    };
+    let empty_object = ParameterContents::Object(Default::default());
+
+    let candidates = match json_type(leaf) {
+        JsonType::String => vec![
+            ParameterContents::from(as_number),
+            ParameterContents::from(as_bool),
+            empty_object,
+        ],
+        JsonType::Number => vec![
+            ParameterContents::from(as_string),
+            ParameterContents::from(as_bool),
+            empty_object,
+        ],
+        JsonType::Boolean => vec![
+            ParameterContents::from(as_string),
+            ParameterContents::from(as_number),
+            empty_object,
+        ],
+        _ => vec![
+            ParameterContents::from(as_string),
+            ParameterContents::from(as_number),
+            ParameterContents::from(as_bool),
+        ],
+    };
+    rand.choose(candidates).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+    use libafl::mutators::{MutationResult, Mutator};
+
+    use super::TypeMismatchMutator;
+    use crate::{
+        input::{
+            parameter::{ParameterKind, SimpleValue},
+            Body, Method, OpenApiInput, OpenApiRequest, ParameterContents,
+        },
+        openapi_mutator::test_state::TestState,
+    };
+
+    fn input_with_parameter(value: ParameterContents) -> OpenApiInput {
+        OpenApiInput(vec![OpenApiRequest {
+            method: Method::Get,
+            path: "/".to_owned(),
+            body: Body::Empty,
+            parameters: IndexMap::from([(("x".to_owned(), ParameterKind::Query), value)]),
+        }])
+    }
+
+    /// Mutates the value with many different seeds, and returns all results
+    fn mutated_values(value: ParameterContents) -> Vec<ParameterContents> {
+        (0..64)
+            .map(|seed| {
+                let mut state = TestState::new(seed);
+                let mut input = input_with_parameter(value.clone());
+                let result = TypeMismatchMutator::new()
+                    .mutate(&mut state, &mut input)
+                    .unwrap();
+                assert_eq!(result, MutationResult::Mutated);
+                input.0[0].parameters[&("x".to_owned(), ParameterKind::Query)].clone()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_string_to_number() {
+        let values = mutated_values(ParameterContents::from("12".to_owned()));
+        assert!(values
+            .iter()
+            .all(|v| !matches!(v, ParameterContents::LeafValue(SimpleValue::String(_)))));
+        assert!(values.iter().any(
+            |v| matches!(v, ParameterContents::LeafValue(SimpleValue::Number(n)) if n.as_i64() == Some(12))
+        ));
+    }
+
+    #[test]
+    fn test_number_to_string() {
+        let values = mutated_values(ParameterContents::from(serde_json::Number::from(12)));
+        assert!(values
+            .iter()
+            .all(|v| !matches!(v, ParameterContents::LeafValue(SimpleValue::Number(_)))));
+        assert!(values.iter().any(
+            |v| matches!(v, ParameterContents::LeafValue(SimpleValue::String(s)) if s == "12")
+        ));
+    }
+
+    #[test]
+    fn test_form_body_stays_an_object() {
+        for seed in 0..64 {
+            let mut state = TestState::new(seed);
+            let mut input = input_with_parameter(ParameterContents::Reference {
+                request_index: 0,
+                parameter_name: "x".to_owned(),
+            });
+            input.0[0].body = Body::XWwwFormUrlencoded(ParameterContents::from(
+                serde_json::json!({"name": "rex", "age": 3}),
+            ));
+            let result = TypeMismatchMutator::new()
+                .mutate(&mut state, &mut input)
+                .unwrap();
+            assert_eq!(result, MutationResult::Mutated);
+            let Body::XWwwFormUrlencoded(ParameterContents::Object(fields)) = &input.0[0].body
+            else {
+                panic!("The form body is no longer an object");
+            };
+            assert_eq!(fields.len(), 2);
+        }
+
+        // A form body that is not an object is left alone
+        let mut state = TestState::new(0);
+        let mut input = input_with_parameter(ParameterContents::Reference {
+            request_index: 0,
+            parameter_name: "x".to_owned(),
+        });
+        input.0[0].body = Body::XWwwFormUrlencoded(ParameterContents::from("rex".to_owned()));
+        let result = TypeMismatchMutator::new()
+            .mutate(&mut state, &mut input)
+            .unwrap();
+        assert_eq!(result, MutationResult::Skipped);
+    }
+
+    #[test]
+    fn test_skips_references() {
+        let mut state = TestState::new(0);
+        let mut input = input_with_parameter(ParameterContents::Reference {
+            request_index: 0,
+            parameter_name: "x".to_owned(),
+        });
+        let result = TypeMismatchMutator::new()
+            .mutate(&mut state, &mut input)
+            .unwrap();
+        assert_eq!(result, MutationResult::Skipped);
+    }
+}
@@ -0,0 +1,143 @@
+//! Mutates a JSON request body between a bare array and the same array wrapped in an
+//! object under a configured key (e.g. `{"items": [...]}`). Some APIs expect the wrapped
+//! form even though their specification declares a top-level array, so both shapes are
+//! worth probing.
+
+use std::borrow::Cow;
+
+pub use libafl::mutators::mutations::*;
+use libafl::{
+    mutators::{MutationResult, Mutator},
+    state::HasRand,
+    Error,
+};
+use libafl_bolts::Named;
+
+use crate::{
+    configuration::Configuration,
+    input::{Body, OpenApiInput, ParameterContents},
+};
+
+/// The `WrapArrayMutator` toggles a JSON body between its wrapped and unwrapped form.
+/// It does nothing if no wrapping key is configured.
+pub struct WrapArrayMutator {
+    key: Option<String>,
+}
+
+impl WrapArrayMutator {
+    #[must_use]
+    /// Creates a new WrapArrayMutator
+    pub fn new() -> Self {
+        Self {
+            key: Configuration::must_get().wrap_array_key.clone(),
+        }
+    }
+}
+
+impl Default for WrapArrayMutator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Named for WrapArrayMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        &Cow::Borrowed("wraparraymutator")
+    }
+}
+
+impl<S> Mutator<OpenApiInput, S> for WrapArrayMutator
+where
+    S: HasRand,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut OpenApiInput) -> Result<MutationResult, Error> {
+        let Some(key) = &self.key else {
+            return Ok(MutationResult::Skipped);
+        };
+
+        let toggleable = input
+            .0
+            .iter_mut()
+            .filter(|request| is_toggleable(&request.body, key));
+        let request = match super::choose(state.rand_mut(), toggleable) {
+            Some(request) => request,
+            None => return Ok(MutationResult::Skipped),
+        };
+
+        if !request.body.unwrap_json_array(key) {
+            request.body.wrap_json_array(key);
+        }
+
+        input.assert_valid(self.name());
+        Ok(MutationResult::Mutated)
+    }
+}
+
+/// Whether the body is a bare JSON array, or an array wrapped under the given key
+fn is_toggleable(body: &Body, key: &str) -> bool {
+    match body {
+        Body::ApplicationJson(ParameterContents::Array(_)) => true,
+        Body::ApplicationJson(ParameterContents::Object(fields)) => {
+            fields.len() == 1 && matches!(fields.get(key), Some(ParameterContents::Array(_)))
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+    use libafl::mutators::{MutationResult, Mutator};
+    use serde_json::{json, Value};
+
+    use super::WrapArrayMutator;
+    use crate::{
+        input::{
+            form::FormArrayStyle, Body, Method, OpenApiInput, OpenApiRequest, ParameterContents,
+        },
+        openapi_mutator::test_state::TestState,
+    };
+
+    /// The JSON document that would be sent as the body of the request
+    fn sent_body(request: &OpenApiRequest) -> Value {
+        let body = request.reqwest_body(FormArrayStyle::default()).unwrap();
+        serde_json::from_slice(body.as_bytes().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_toggles_between_wrapped_and_bare_array() {
+        let mut input = OpenApiInput(vec![OpenApiRequest {
+            method: Method::Post,
+            path: "/pets".to_owned(),
+            body: Body::ApplicationJson(ParameterContents::from(json!([1, 2]))),
+            parameters: IndexMap::new(),
+        }]);
+        let mut mutator = WrapArrayMutator {
+            key: Some("items".to_owned()),
+        };
+        let mut state = TestState::new(0);
+
+        let result = mutator.mutate(&mut state, &mut input).unwrap();
+        assert_eq!(result, MutationResult::Mutated);
+        assert_eq!(sent_body(&input.0[0]), json!({"items": [1, 2]}));
+
+        let result = mutator.mutate(&mut state, &mut input).unwrap();
+        assert_eq!(result, MutationResult::Mutated);
+        assert_eq!(sent_body(&input.0[0]), json!([1, 2]));
+    }
+
+    #[test]
+    fn test_skips_without_key() {
+        let mut input = OpenApiInput(vec![OpenApiRequest {
+            method: Method::Post,
+            path: "/pets".to_owned(),
+            body: Body::ApplicationJson(ParameterContents::from(json!([1, 2]))),
+            parameters: IndexMap::new(),
+        }]);
+        let mut mutator = WrapArrayMutator { key: None };
+        let mut state = TestState::new(0);
+
+        let result = mutator.mutate(&mut state, &mut input).unwrap();
+        assert_eq!(result, MutationResult::Skipped);
+    }
+}
@@ -7,6 +7,12 @@ use crate::{
     openapi::validate_response::Response,
 };
 
+/// Name under which the complete (JSON) body of a response is stored next to its
+/// fields. A reference to a request names a parameter of it, so storing the body as a
+/// parameter lets it be referenced, resolved and minimized like any other. The name is
+/// unlikely to be used by a field of a response, which would overwrite the body.
+pub const RESPONSE_BODY_PARAMETER: &str = "<response body>";
+
 /// ParameterFeedbackMetadata collects parameter values from requests as they
 /// are made. This allows the harness to insert the values in subsequent requests
 /// if a parameter contains a backreference to an earlier request.
@@ -65,15 +71,25 @@ impl ParameterFeedback {
             .is_some()
     }
 
+    /// Saves the complete body of the response to the given request, so it can be
+    /// referenced as `RESPONSE_BODY_PARAMETER`. Returns whether successful.
+    pub fn set_response_body(&mut self, request_index: usize, body: Value) -> bool {
+        self.set(request_index, RESPONSE_BODY_PARAMETER.to_owned(), body)
+    }
+
     /// Processes the values returned in a Response.
     ///
     /// The body is parsed as a json object or an array of objects, and if successful,
-    /// the fields are saved as parameter values. Cookies set as a `Set-Cookie` header
-    /// are saved in their `param=value` form.
+    /// the fields are saved as parameter values. The body as a whole is saved as well.
+    /// Cookies set as a `Set-Cookie` header are saved in their `param=value` form.
     pub fn process_response(&mut self, request_index: usize, mut response: Response) {
+        let body = response.json::<serde_json::Value>();
+        if let Ok(body) = &body {
+            self.set_response_body(request_index, body.clone());
+        }
         // We take any returned json values and save key-value parameters we find
         // (e.g. id = 37) for use as parameters in later requests.
-        match response.json::<serde_json::Value>() {
+        match body {
             // Objects in responses: save all field/value combinations
             Ok(serde_json::Value::Object(hashmap)) => {
                 for (param, value) in hashmap.into_iter() {
@@ -108,6 +124,7 @@ impl ParameterFeedback {
         match request.body {
             Body::ApplicationJson(Object(obj_contents))
             | Body::XWwwFormUrlencoded(Object(obj_contents))
+            | Body::Multipart(Object(obj_contents))
                 if request.method == Method::Post =>
             {
                 for (param, value) in obj_contents {
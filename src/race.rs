@@ -0,0 +1,341 @@
+//! Race mode sends several identical copies of a write request at the same time, to
+//! surface concurrency bugs in the program under test. If the responses are not
+//! consistent with each other, e.g. the same resource is created or deleted twice,
+//! the request is reported as an anomaly.
+
+use std::fmt::Display;
+
+use reqwest::{blocking::Request, header::LOCATION, StatusCode};
+use serde_json::Value;
+
+use crate::{input::Method, openapi::validate_response::Response};
+
+/// An inconsistency between the responses to concurrent identical requests.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RaceAnomaly {
+    /// More than one request created the same resource (status 201), or created one
+    /// despite a client-supplied idempotency key
+    MultipleCreated { count: usize },
+    /// More than one request deleted the resource successfully
+    MultipleDeleted { count: usize },
+    /// The requests got different status codes, while they should all be handled alike
+    InconsistentStatus { statuses: Vec<u16> },
+    /// The requests got the same successful status, but response bodies of a different
+    /// JSON shape
+    InconsistentBodies,
+}
+
+impl RaceAnomaly {
+    /// Short name of the kind of anomaly, without any details of the particular case.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            RaceAnomaly::MultipleCreated { .. } => "race_multiple_created",
+            RaceAnomaly::MultipleDeleted { .. } => "race_multiple_deleted",
+            RaceAnomaly::InconsistentStatus { .. } => "race_inconsistent_status",
+            RaceAnomaly::InconsistentBodies => "race_inconsistent_bodies",
+        }
+    }
+}
+
+impl Display for RaceAnomaly {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RaceAnomaly::MultipleCreated { count } => {
+                write!(
+                    f,
+                    "{count} concurrent identical requests each created a resource"
+                )
+            }
+            RaceAnomaly::MultipleDeleted { count } => {
+                write!(
+                    f,
+                    "{count} concurrent identical requests each deleted the resource"
+                )
+            }
+            RaceAnomaly::InconsistentStatus { statuses } => write!(
+                f,
+                "concurrent identical requests got different statuses: {statuses:?}"
+            ),
+            RaceAnomaly::InconsistentBodies => write!(
+                f,
+                "concurrent identical requests got the same status but differently shaped bodies"
+            ),
+        }
+    }
+}
+
+/// Headers with which a client asks the server to handle copies of a request only once
+const IDEMPOTENCY_KEY_HEADERS: [&str; 2] = ["idempotency-key", "x-idempotency-key"];
+
+/// Whether race mode applies to requests with this method
+pub fn is_write(method: Method) -> bool {
+    matches!(
+        method,
+        Method::Post | Method::Put | Method::Patch | Method::Delete
+    )
+}
+
+/// Sends `count` copies of the request concurrently with `send`, and returns the
+/// results in the order the copies were made. Requests that can not be cloned (e.g.
+/// because of a streaming body) are sent once.
+pub fn send_concurrently<F>(
+    request: Request,
+    count: usize,
+    send: F,
+) -> Vec<Result<Response, reqwest::Error>>
+where
+    F: Fn(Request) -> reqwest::Result<reqwest::blocking::Response> + Sync,
+{
+    let mut copies: Vec<_> = std::iter::repeat_with(|| request.try_clone())
+        .take(count - 1)
+        .map_while(|copy| copy)
+        .collect();
+    copies.push(request);
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = copies
+            .into_iter()
+            .map(|copy| scope.spawn(|| send(copy).map(Response::from)))
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("Thread sending a request panicked"))
+            .collect()
+    })
+}
+
+/// Sends the request as in `send_concurrently`, and checks the responses for anomalies.
+/// Returns the first response (or the first error if no response was received), so
+/// that the rest of the request chain can continue as if the request was sent once.
+pub fn race_request<F>(
+    request: Request,
+    method: Method,
+    count: usize,
+    send: F,
+) -> (Result<Response, reqwest::Error>, Vec<RaceAnomaly>)
+where
+    F: Fn(Request) -> reqwest::Result<reqwest::blocking::Response> + Sync,
+{
+    let has_client_key = IDEMPOTENCY_KEY_HEADERS
+        .iter()
+        .any(|name| request.headers().contains_key(*name));
+    let mut responses = vec![];
+    let mut first_error = None;
+    for result in send_concurrently(request, count, send) {
+        match result {
+            Ok(response) => responses.push(response),
+            Err(error) => {
+                first_error.get_or_insert(error);
+            }
+        }
+    }
+    let anomalies = detect_anomalies(method, has_client_key, &responses);
+    match first_error {
+        Some(error) if responses.is_empty() => (Err(error), anomalies),
+        _ => (Ok(responses.swap_remove(0)), anomalies),
+    }
+}
+
+/// Compares the responses to concurrent identical requests with the given method,
+/// and returns any inconsistencies found.
+///
+/// Many POST endpoints legitimately create a new resource for every copy, so several
+/// creations are only an anomaly if they evidently concern the same resource: the
+/// responses share a `Location` header or an `id` in the body. If the request carried
+/// a client-supplied idempotency key, any second creation is an anomaly, as the server
+/// should have recognized the copies. A DELETE may only delete the resource once.
+/// PUT and PATCH requests are expected to be handled alike: the same status, and for
+/// successful requests a JSON body of the same shape. The values in the bodies are not
+/// compared, as they may differ without a race, e.g. in timestamps.
+pub fn detect_anomalies(
+    method: Method,
+    has_client_key: bool,
+    responses: &[Response],
+) -> Vec<RaceAnomaly> {
+    let mut anomalies = vec![];
+    let count_where = |f: fn(&Response) -> bool| responses.iter().filter(|r| f(r)).count();
+    match method {
+        Method::Post => {
+            let created: Vec<&Response> = responses
+                .iter()
+                .filter(|r| r.status() == StatusCode::CREATED)
+                .collect();
+            let count = if has_client_key {
+                created.len()
+            } else {
+                most_shared_identity(&created)
+            };
+            if count > 1 {
+                anomalies.push(RaceAnomaly::MultipleCreated { count });
+            }
+        }
+        Method::Delete => {
+            let deleted = count_where(|r| r.status().is_success());
+            if deleted > 1 {
+                anomalies.push(RaceAnomaly::MultipleDeleted { count: deleted });
+            }
+        }
+        Method::Put | Method::Patch => {
+            let mut statuses: Vec<u16> = responses.iter().map(|r| r.status().as_u16()).collect();
+            statuses.sort_unstable();
+            statuses.dedup();
+            if statuses.len() > 1 {
+                anomalies.push(RaceAnomaly::InconsistentStatus { statuses });
+            } else if responses.windows(2).any(|pair| {
+                pair[0].status().is_success()
+                    && match (pair[0].json(), pair[1].json()) {
+                        (Ok(left), Ok(right)) => json_shape(&left) != json_shape(&right),
+                        _ => false,
+                    }
+            }) {
+                anomalies.push(RaceAnomaly::InconsistentBodies);
+            }
+        }
+        _ => (),
+    }
+    anomalies
+}
+
+/// The largest number of responses that identify the same created resource, by their
+/// `Location` header or else the `id` in their body. Responses without either are not
+/// counted.
+fn most_shared_identity(responses: &[&Response]) -> usize {
+    let identities: Vec<String> = responses
+        .iter()
+        .filter_map(|response| {
+            response
+                .headers()
+                .get(LOCATION)
+                .and_then(|location| location.to_str().ok())
+                .map(str::to_owned)
+                .or_else(|| Some(response.json::<Value>().ok()?.get("id")?.to_string()))
+        })
+        .collect();
+    identities
+        .iter()
+        .map(|identity| identities.iter().filter(|other| *other == identity).count())
+        .max()
+        .unwrap_or(0)
+}
+
+/// The shape of a JSON value: its structure and the types of its leaves. The elements
+/// of arrays are reduced to their distinct shapes, as their number may vary.
+fn json_shape(value: &Value) -> Value {
+    match value {
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(name, value)| (name.clone(), json_shape(value)))
+                .collect(),
+        ),
+        Value::Array(elements) => {
+            let mut shapes: Vec<Value> = vec![];
+            for shape in elements.iter().map(json_shape) {
+                if !shapes.contains(&shape) {
+                    shapes.push(shape);
+                }
+            }
+            Value::Array(shapes)
+        }
+        Value::Null => Value::from("null"),
+        Value::Bool(_) => Value::from("boolean"),
+        Value::Number(_) => Value::from("number"),
+        Value::String(_) => Value::from("string"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::{header::LOCATION, StatusCode};
+
+    use super::{detect_anomalies, RaceAnomaly};
+    use crate::{input::Method, openapi::validate_response::Response};
+
+    fn response(status: u16, body: &str) -> Response {
+        Response::new(
+            StatusCode::from_u16(status).unwrap(),
+            body.as_bytes().to_vec(),
+        )
+    }
+
+    #[test]
+    fn test_consistent_responses() {
+        let responses = [response(201, "{\"id\":1}"), response(409, "exists")];
+        assert!(detect_anomalies(Method::Post, false, &responses).is_empty());
+
+        // Distinct resources, or ones that can not be told apart, may each be created
+        let responses = [response(201, "{\"id\":1}"), response(201, "{\"id\":2}")];
+        assert!(detect_anomalies(Method::Post, false, &responses).is_empty());
+        let responses = [response(201, ""), response(201, "")];
+        assert!(detect_anomalies(Method::Post, false, &responses).is_empty());
+
+        let responses = [response(204, ""), response(404, "")];
+        assert!(detect_anomalies(Method::Delete, false, &responses).is_empty());
+
+        let responses = [response(200, "{}"), response(200, "{}")];
+        assert!(detect_anomalies(Method::Put, false, &responses).is_empty());
+
+        // Volatile values, such as timestamps, do not make the bodies inconsistent
+        let responses = [
+            response(200, "{\"v\":1,\"at\":\"10:00:00\"}"),
+            response(200, "{\"v\":1,\"at\":\"10:00:01\"}"),
+        ];
+        assert!(detect_anomalies(Method::Put, false, &responses).is_empty());
+        let responses = [response(200, "updated 1"), response(200, "updated 2")];
+        assert!(detect_anomalies(Method::Put, false, &responses).is_empty());
+    }
+
+    #[test]
+    fn test_inconsistent_responses() {
+        let responses = [
+            response(201, "{\"id\":1}"),
+            response(201, "{\"id\":1}"),
+            response(201, "{\"id\":2}"),
+        ];
+        assert_eq!(
+            detect_anomalies(Method::Post, false, &responses),
+            vec![RaceAnomaly::MultipleCreated { count: 2 }]
+        );
+        let responses = [
+            response(201, "{\"id\":1}").with_header(LOCATION, "/pets/1"),
+            response(201, "{\"id\":2}").with_header(LOCATION, "/pets/1"),
+        ];
+        assert_eq!(
+            detect_anomalies(Method::Post, false, &responses),
+            vec![RaceAnomaly::MultipleCreated { count: 2 }]
+        );
+        // With an idempotency key, the server should have created only one resource
+        let responses = [response(201, "{\"id\":1}"), response(201, "{\"id\":2}")];
+        assert_eq!(
+            detect_anomalies(Method::Post, true, &responses),
+            vec![RaceAnomaly::MultipleCreated { count: 2 }]
+        );
+
+        let responses = [response(204, ""), response(204, ""), response(404, "")];
+        assert_eq!(
+            detect_anomalies(Method::Delete, false, &responses),
+            vec![RaceAnomaly::MultipleDeleted { count: 2 }]
+        );
+
+        let responses = [response(200, "{}"), response(409, "{}")];
+        assert_eq!(
+            detect_anomalies(Method::Put, false, &responses),
+            vec![RaceAnomaly::InconsistentStatus {
+                statuses: vec![200, 409]
+            }]
+        );
+
+        let responses = [
+            response(200, "{\"v\":1}"),
+            response(200, "{\"error\":\"busy\"}"),
+        ];
+        assert_eq!(
+            detect_anomalies(Method::Patch, false, &responses),
+            vec![RaceAnomaly::InconsistentBodies]
+        );
+        let responses = [response(200, "{\"v\":1}"), response(200, "{\"v\":null}")];
+        assert_eq!(
+            detect_anomalies(Method::Patch, false, &responses),
+            vec![RaceAnomaly::InconsistentBodies]
+        );
+    }
+}
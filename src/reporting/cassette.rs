@@ -0,0 +1,359 @@
+//! Records every request of a run with its response in a cassette, so that the responses
+//! can later be validated again, e.g. with a stricter crash criterion, without sending
+//! the requests to the target again. The cassette is a file with one JSON object per
+//! line, holding a request as the fuzzer generated it and the response to it.
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufRead, BufReader, LineWriter, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use log::warn;
+use openapiv3::OpenAPI;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    configuration::{Configuration, CrashCriterion},
+    input::OpenApiRequest,
+    openapi::{
+        curl_request::CurlRequest,
+        validate_response::{validate_rate_limit_headers, validate_response, Response},
+    },
+    reporting::Reporting,
+};
+
+/// Instantiates a cassette recorder if a cassette file is given in the configuration
+pub fn get_reporter(config: &Configuration) -> Result<Option<CassetteRecorder>> {
+    config
+        .record_cassette
+        .as_ref()
+        .map(|path| CassetteRecorder::new(path.clone()))
+        .transpose()
+}
+
+/// A request and the response to it, as stored in the cassette
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CassetteEntry {
+    pub request: OpenApiRequest,
+    pub response: RecordedResponse,
+}
+
+/// A response as stored in the cassette. The body is base64-encoded.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl From<&Response> for RecordedResponse {
+    fn from(response: &Response) -> Self {
+        Self {
+            status: response.status().as_u16(),
+            headers: response
+                .headers()
+                .iter()
+                .map(|(name, value)| {
+                    (
+                        name.to_string(),
+                        String::from_utf8_lossy(value.as_bytes()).into_owned(),
+                    )
+                })
+                .collect(),
+            body: STANDARD.encode(response.body()),
+        }
+    }
+}
+
+impl TryFrom<&RecordedResponse> for Response {
+    type Error = anyhow::Error;
+
+    fn try_from(recorded: &RecordedResponse) -> Result<Self> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &recorded.headers {
+            headers.append(
+                HeaderName::from_bytes(name.as_bytes())?,
+                HeaderValue::from_str(value)?,
+            );
+        }
+        Ok(Response::from_parts(
+            reqwest::StatusCode::from_u16(recorded.status)?,
+            headers,
+            STANDARD.decode(&recorded.body)?,
+        ))
+    }
+}
+
+/// Reporter that appends every request with its response to the cassette
+pub struct CassetteRecorder {
+    path: PathBuf,
+    /// Line-buffered, so that the cassette is complete up to the last response even
+    /// if the run is interrupted
+    file: Mutex<LineWriter<File>>,
+    /// Requests that await their response, by the id handed out when reporting them
+    pending: Mutex<HashMap<usize, OpenApiRequest>>,
+    next_id: Mutex<usize>,
+}
+
+impl CassetteRecorder {
+    pub fn new(path: PathBuf) -> Result<Self> {
+        let file = File::create(&path)
+            .with_context(|| format!("Could not create cassette {}", path.to_string_lossy()))?;
+        Ok(Self {
+            path,
+            file: Mutex::new(LineWriter::new(file)),
+            pending: Mutex::new(HashMap::new()),
+            next_id: Mutex::new(0),
+        })
+    }
+}
+
+impl Reporting<usize> for CassetteRecorder {
+    fn report_request(
+        &self,
+        request: &OpenApiRequest,
+        _curl: &CurlRequest,
+        _input_id: usize,
+    ) -> usize {
+        let mut next_id = self.next_id.lock().unwrap();
+        let id = *next_id;
+        *next_id += 1;
+        self.pending.lock().unwrap().insert(id, request.clone());
+        id
+    }
+
+    fn report_response(&self, response: &Response, request_id: usize) {
+        let Some(request) = self.pending.lock().unwrap().remove(&request_id) else {
+            return;
+        };
+        let entry = CassetteEntry {
+            request,
+            response: response.into(),
+        };
+        let line = serde_json::to_string(&entry).expect("Cassette entries are serializable");
+        if let Err(err) = writeln!(self.file.lock().unwrap(), "{line}") {
+            warn!("Could not write to cassette {:?}: {err}", self.path);
+        }
+    }
+
+    fn report_response_error(&self, _error: &str, request_id: usize) {
+        self.pending.lock().unwrap().remove(&request_id);
+    }
+
+    fn report_coverage(
+        &self,
+        _line_coverage: u64,
+        _line_coverage_total: u64,
+        _endpoint_coverage: u64,
+        _endpoint_coverage_total: u64,
+    ) {
+    }
+}
+
+/// Reads all entries of a cassette
+pub fn read_cassette(path: &Path) -> Result<Vec<CassetteEntry>> {
+    let file = File::open(path)
+        .with_context(|| format!("Could not open cassette {}", path.to_string_lossy()))?;
+    BufReader::new(file)
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| line.as_ref().map_or(true, |line| !line.trim().is_empty()))
+        .map(|(index, line)| {
+            serde_json::from_str(&line?)
+                .with_context(|| format!("Invalid entry on line {} of the cassette", index + 1))
+        })
+        .collect()
+}
+
+/// The checks applied to the recorded responses
+pub struct Criteria {
+    pub crash_criterion: CrashCriterion,
+    pub check_response_constraints: bool,
+    pub check_rate_limit_headers: bool,
+}
+
+/// Validates the recorded responses again, and returns the index of every entry that
+/// would have been reported as a crash with the given criteria, with the kind of bug.
+/// The checks are those of the fuzzer that need no state of the run.
+pub fn revalidate(
+    api: &OpenAPI,
+    entries: &[CassetteEntry],
+    criteria: &Criteria,
+) -> Result<Vec<(usize, String)>> {
+    let mut crashes = vec![];
+    for (index, entry) in entries.iter().enumerate() {
+        let response = Response::try_from(&entry.response)
+            .with_context(|| format!("Invalid response in cassette entry {index}"))?;
+        let rate_limit_check = if criteria.check_rate_limit_headers {
+            validate_rate_limit_headers(&response).map_err(|err| err.kind().to_owned())
+        } else {
+            Ok(())
+        };
+        let crash = match rate_limit_check {
+            Err(kind) => Some(kind),
+            Ok(()) if response.status().is_server_error() => {
+                Some(format!("http_{}", response.status().as_u16()))
+            }
+            Ok(()) if criteria.crash_criterion == CrashCriterion::AllErrors => validate_response(
+                api,
+                &entry.request,
+                &response,
+                criteria.check_response_constraints,
+            )
+            .err()
+            .map(|err| err.kind().to_owned()),
+            Ok(()) => None,
+        };
+        if let Some(kind) = crash {
+            crashes.push((index, kind));
+        }
+    }
+    Ok(crashes)
+}
+
+/// Validates the responses in the cassette again with the configured criteria, and
+/// prints the crashes that would have been found.
+pub fn revalidate_cassette(cassette: &Path) -> Result<()> {
+    let config = Configuration::get().map_err(anyhow::Error::msg)?;
+    crate::setup_logging(config);
+    let api = crate::get_api_spec(
+        config
+            .openapi_spec
+            .as_ref()
+            .ok_or_else(|| anyhow!("No OpenAPI specification given"))?,
+    )?;
+    let entries = read_cassette(cassette)?;
+    let criteria = Criteria {
+        crash_criterion: config.crash_criterion,
+        check_response_constraints: config.check_response_constraints,
+        check_rate_limit_headers: config.check_rate_limit_headers,
+    };
+    let crashes = revalidate(&api, &entries, &criteria)?;
+    for (index, kind) in &crashes {
+        let entry = &entries[*index];
+        println!(
+            "{kind}: {} {} ({}), cassette entry {index}",
+            entry.request.method, entry.request.path, entry.response.status
+        );
+    }
+    println!(
+        "{} of {} recorded responses would have been reported as crashes",
+        crashes.len(),
+        entries.len()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+    use openapiv3::OpenAPI;
+    use reqwest::{blocking::Request, Method as HttpMethod, StatusCode, Url};
+    use serde_json::json;
+
+    use super::{read_cassette, revalidate, CassetteRecorder, Criteria};
+    use crate::{
+        authentication::Authentication,
+        configuration::CrashCriterion,
+        input::{Body, Method, OpenApiRequest},
+        openapi::{curl_request::CurlRequest, validate_response::Response},
+        reporting::Reporting,
+    };
+
+    #[test]
+    fn test_stricter_criteria_find_more_crashes() {
+        let api: OpenAPI = serde_json::from_value(json!({
+            "openapi": "3.0.0",
+            "info": {"title": "pets", "version": "1"},
+            "paths": {
+                "/pets": {
+                    "get": {
+                        "responses": {
+                            "200": {
+                                "description": "A pet",
+                                "content": {
+                                    "application/json": {
+                                        "schema": {
+                                            "type": "object",
+                                            "properties": {
+                                                "tag": {"type": "string", "pattern": "^[A-Z]+$"}
+                                            },
+                                            "required": ["tag"]
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cassette.jsonl");
+        let recorder = CassetteRecorder::new(path.clone()).unwrap();
+        let request = OpenApiRequest {
+            method: Method::Get,
+            path: "/pets".to_owned(),
+            body: Body::Empty,
+            parameters: IndexMap::new(),
+        };
+        let http_request = Request::new(
+            HttpMethod::GET,
+            Url::parse("http://localhost:8080/pets").unwrap(),
+        );
+        for (status, body) in [
+            (StatusCode::OK, r#"{"tag": "REX"}"#),
+            (StatusCode::INTERNAL_SERVER_ERROR, "Oops"),
+            // Missing a required field
+            (StatusCode::OK, "{}"),
+            // Violates the pattern
+            (StatusCode::OK, r#"{"tag": "rex"}"#),
+        ] {
+            let id = recorder.report_request(
+                &request,
+                &CurlRequest(&http_request, &Authentication::None, None),
+                0,
+            );
+            recorder.report_response(&Response::new(status, body.as_bytes().to_vec()), id);
+        }
+        drop(recorder);
+
+        let entries = read_cassette(&path).unwrap();
+        assert_eq!(entries.len(), 4);
+        let crashes = |crash_criterion, check_response_constraints| {
+            let criteria = Criteria {
+                crash_criterion,
+                check_response_constraints,
+                check_rate_limit_headers: false,
+            };
+            revalidate(&api, &entries, &criteria).unwrap()
+        };
+
+        assert_eq!(
+            crashes(CrashCriterion::Only5xx, false),
+            vec![(1, "http_500".to_owned())]
+        );
+        assert_eq!(
+            crashes(CrashCriterion::AllErrors, false),
+            vec![
+                (1, "http_500".to_owned()),
+                (2, "response_object_incorrect".to_owned())
+            ]
+        );
+        assert_eq!(
+            crashes(CrashCriterion::AllErrors, true),
+            vec![
+                (1, "http_500".to_owned()),
+                (2, "response_object_incorrect".to_owned()),
+                (3, "response_pattern_mismatch".to_owned())
+            ]
+        );
+    }
+}
@@ -0,0 +1,215 @@
+//! Coverage attribution keeps per-input snapshots of the coverage maps, so that after a
+//! run we can tell which corpus or crash inputs contributed coverage that no other input
+//! reached. Inputs without any unique coverage are candidates for pruning.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs::{create_dir_all, File},
+    io::Write,
+    path::Path,
+};
+
+use indexmap::IndexMap;
+
+use crate::coverage_clients::CoverageClient;
+
+/// Collects coverage snapshots for inputs during a fuzzing run.
+///
+/// Each snapshot is the set of coverage bits hit by one input. Bits are numbered
+/// consecutively over all coverage sources given to `record`, in order, so the
+/// sources must be given in the same order every time.
+#[derive(Debug, Default)]
+pub struct CoverageAttribution {
+    snapshots: IndexMap<String, Snapshot>,
+    seen: BTreeSet<usize>,
+}
+
+#[derive(Debug)]
+struct Snapshot {
+    bits: BTreeSet<usize>,
+    is_solution: bool,
+}
+
+impl CoverageAttribution {
+    /// Creates a new, empty, coverage attribution.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Takes a snapshot of the current contents of the coverage maps of `sources`,
+    /// and records it for the input named `input_name`.
+    ///
+    /// Snapshots are only kept for inputs that are likely to end up in the corpus
+    /// or among the crashes: those that cover a bit no earlier input covered, and
+    /// those for which `is_solution` is set. Keeping all snapshots would make the
+    /// memory usage grow with the number of executions.
+    pub fn record(
+        &mut self,
+        input_name: String,
+        is_solution: bool,
+        sources: &mut [&mut dyn CoverageClient],
+    ) {
+        let mut bits = BTreeSet::new();
+        let mut offset = 0;
+        for source in sources.iter_mut() {
+            let len = source.get_coverage_len();
+            // Safety: the coverage client guarantees the pointer refers to a map of
+            // `get_coverage_len` bytes, and nothing writes to it while we read it here.
+            let map = unsafe { std::slice::from_raw_parts(source.get_coverage_ptr(), len) };
+            for (byte_index, byte) in map.iter().enumerate() {
+                for bit in 0..8 {
+                    if byte & (0b10000000 >> bit) != 0 {
+                        bits.insert(offset + byte_index * 8 + bit);
+                    }
+                }
+            }
+            offset += len * 8;
+        }
+
+        let is_novel = !bits.is_subset(&self.seen);
+        if !is_novel && !is_solution {
+            return;
+        }
+        self.seen.extend(bits.iter().copied());
+        self.snapshots
+            .entry(input_name)
+            .and_modify(|snapshot| {
+                snapshot.bits.extend(bits.iter().copied());
+                snapshot.is_solution |= is_solution;
+            })
+            .or_insert(Snapshot { bits, is_solution });
+    }
+
+    /// Returns, for each recorded input, the coverage bits that no other recorded
+    /// input covers.
+    pub fn unique_coverage(&self) -> IndexMap<&str, Vec<usize>> {
+        let mut hit_counts: HashMap<usize, usize> = HashMap::new();
+        for snapshot in self.snapshots.values() {
+            for bit in &snapshot.bits {
+                *hit_counts.entry(*bit).or_default() += 1;
+            }
+        }
+        self.snapshots
+            .iter()
+            .map(|(name, snapshot)| {
+                (
+                    name.as_str(),
+                    snapshot
+                        .bits
+                        .iter()
+                        .copied()
+                        .filter(|bit| hit_counts[bit] == 1)
+                        .collect(),
+                )
+            })
+            .collect()
+    }
+
+    /// Writes a markdown report listing, for each recorded input, how many coverage
+    /// bits it hit and which of them were hit by this input only.
+    pub fn write_report(&self, report_path: &Path) -> std::io::Result<()> {
+        let corpus_path = report_path.join("corpus");
+        create_dir_all(&corpus_path)?;
+        let mut file = File::create(corpus_path.join("coverage_attribution.md"))?;
+
+        writeln!(file, "# Coverage attribution\n")?;
+        writeln!(file, "For each corpus or crash input, this document lists the coverage bits it covered that no other input covered. Inputs without unique coverage may be redundant.\n")?;
+        writeln!(file, "| Input | Kind | Covered | Unique | Unique bits |")?;
+        writeln!(file, "| ----- | ---- | ------- | ------ | ----------- |")?;
+        for (name, unique) in self.unique_coverage() {
+            let snapshot = &self.snapshots[name];
+            writeln!(
+                file,
+                "| {} | {} | {} | {} | {} |",
+                name,
+                if snapshot.is_solution {
+                    "crash"
+                } else {
+                    "corpus"
+                },
+                snapshot.bits.len(),
+                unique.len(),
+                unique
+                    .iter()
+                    .map(|bit| bit.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::CoverageAttribution;
+    use crate::coverage_clients::CoverageClient;
+
+    /// Coverage source that yields a fixed coverage map.
+    struct StubCoverageClient(Vec<u8>);
+
+    impl CoverageClient for StubCoverageClient {
+        fn fetch_coverage(&mut self, _reset: bool) {}
+
+        fn get_coverage_ptr(&mut self) -> *mut u8 {
+            self.0.as_mut_ptr()
+        }
+
+        fn get_coverage_len(&self) -> usize {
+            self.0.len()
+        }
+
+        fn max_coverage_ratio(&mut self) -> (u64, u64) {
+            (0, 0)
+        }
+
+        fn generate_coverage_report(&self, _report_path: &Path) {}
+    }
+
+    #[test]
+    fn test_unique_bit_is_attributed() {
+        let mut attribution = CoverageAttribution::new();
+        attribution.record(
+            "first".to_owned(),
+            false,
+            &mut [&mut StubCoverageClient(vec![0b11000000, 0])],
+        );
+        attribution.record(
+            "second".to_owned(),
+            false,
+            &mut [&mut StubCoverageClient(vec![0b10000000, 0b00000001])],
+        );
+
+        let unique = attribution.unique_coverage();
+        assert_eq!(unique["first"], vec![1]);
+        assert_eq!(unique["second"], vec![15]);
+    }
+
+    #[test]
+    fn test_redundant_input_is_not_recorded() {
+        let mut attribution = CoverageAttribution::new();
+        attribution.record(
+            "first".to_owned(),
+            false,
+            &mut [&mut StubCoverageClient(vec![0b11000000])],
+        );
+        attribution.record(
+            "second".to_owned(),
+            false,
+            &mut [&mut StubCoverageClient(vec![0b01000000])],
+        );
+        attribution.record(
+            "crash".to_owned(),
+            true,
+            &mut [&mut StubCoverageClient(vec![0b01000000])],
+        );
+
+        let unique = attribution.unique_coverage();
+        assert!(!unique.contains_key("second"));
+        assert_eq!(unique["first"], vec![0]);
+        assert_eq!(unique["crash"], Vec::<usize>::new());
+    }
+}
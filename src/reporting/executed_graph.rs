@@ -0,0 +1,173 @@
+//! After a run, shows which request chains were actually executed: every request is a
+//! node, and every pair of consecutive requests in a chain is an edge, colored by
+//! whether the second request succeeded. Unlike the dependency graph, which shows what
+//! could be chained based on the specification, this shows what the fuzzer did.
+
+use std::{
+    fs::{create_dir_all, File},
+    io::Write,
+    path::Path,
+};
+
+use indexmap::{IndexMap, IndexSet};
+
+/// Number of times the requests of an edge followed each other, by outcome of the
+/// second request
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct EdgeOutcomes {
+    pub succeeded: usize,
+    pub failed: usize,
+}
+
+impl EdgeOutcomes {
+    fn color(&self) -> &'static str {
+        match (self.succeeded, self.failed) {
+            (_, 0) => "green",
+            (0, _) => "red",
+            _ => "orange",
+        }
+    }
+}
+
+/// The executed request graph, with requests identified by method and path
+#[derive(Debug, Default)]
+pub struct ExecutedGraph {
+    nodes: IndexSet<String>,
+    edges: IndexMap<(usize, usize), EdgeOutcomes>,
+    /// The chain and node of the last recorded request
+    last: Option<(i64, usize)>,
+}
+
+impl ExecutedGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records an executed request. Requests must be recorded in the order in which
+    /// they were sent; requests with the same `chain_id` belong to one chain.
+    pub fn record(&mut self, chain_id: i64, method: &str, path: &str, succeeded: bool) {
+        let (node, _) = self.nodes.insert_full(format!("{method} {path}"));
+        if let Some((last_chain, last_node)) = self.last {
+            if last_chain == chain_id {
+                let outcomes = self.edges.entry((last_node, node)).or_default();
+                if succeeded {
+                    outcomes.succeeded += 1;
+                } else {
+                    outcomes.failed += 1;
+                }
+            }
+        }
+        self.last = Some((chain_id, node));
+    }
+
+    /// The edges, as the labels of their source and target, with their outcomes
+    pub fn edges(&self) -> impl Iterator<Item = (&str, &str, EdgeOutcomes)> {
+        self.edges.iter().map(|((source, target), outcomes)| {
+            (
+                self.nodes[*source].as_str(),
+                self.nodes[*target].as_str(),
+                *outcomes,
+            )
+        })
+    }
+
+    /// The graph as a Mermaid flowchart in a markdown document
+    pub fn to_mermaid(&self) -> String {
+        let mut mermaid = String::from("# Executed request graph\n\n");
+        mermaid.push_str("This markdown document can be rendered using a Mermaid plugin. It shows the request chains that were sent, with green edges to requests that succeeded, red edges to requests that failed, and orange edges to requests that did both.\n\n");
+        mermaid.push_str("```mermaid\ngraph LR;\n  %% Nodes\n");
+        for (index, label) in self.nodes.iter().enumerate() {
+            mermaid.push_str(&format!("  n{index}(\"{label}\");\n"));
+        }
+        mermaid.push_str("  %% Edges\n");
+        for ((source, target), outcomes) in &self.edges {
+            mermaid.push_str(&format!(
+                "  n{source} -->|{} ok, {} failed| n{target};\n",
+                outcomes.succeeded, outcomes.failed
+            ));
+        }
+        for (index, outcomes) in self.edges.values().enumerate() {
+            mermaid.push_str(&format!(
+                "  linkStyle {index} stroke:{};\n",
+                outcomes.color()
+            ));
+        }
+        mermaid.push_str("```\n");
+        mermaid
+    }
+
+    /// The graph in the Graphviz DOT format
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph executed_requests {\n  rankdir=LR;\n");
+        for label in &self.nodes {
+            dot.push_str(&format!("  \"{label}\";\n"));
+        }
+        for (source, target, outcomes) in self.edges() {
+            dot.push_str(&format!(
+                "  \"{source}\" -> \"{target}\" [label=\"{} ok, {} failed\", color={}];\n",
+                outcomes.succeeded,
+                outcomes.failed,
+                outcomes.color()
+            ));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Writes the graph to `executed_graph.md` and `executed_graph.dot` in the report
+    /// directory.
+    pub fn write_report(&self, report_path: &Path) -> std::io::Result<()> {
+        create_dir_all(report_path)?;
+        File::create(report_path.join("executed_graph.md"))?
+            .write_all(self.to_mermaid().as_bytes())?;
+        File::create(report_path.join("executed_graph.dot"))?
+            .write_all(self.to_dot().as_bytes())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EdgeOutcomes, ExecutedGraph};
+
+    #[test]
+    fn test_edges_follow_chains() {
+        let mut graph = ExecutedGraph::new();
+        graph.record(1, "POST", "/pets", true);
+        graph.record(1, "GET", "/pets/{id}", true);
+        graph.record(1, "DELETE", "/pets/{id}", false);
+        // A new chain does not continue from the previous one
+        graph.record(2, "GET", "/pets/{id}", true);
+
+        let edges: Vec<_> = graph.edges().collect();
+        assert_eq!(
+            edges,
+            vec![
+                (
+                    "POST /pets",
+                    "GET /pets/{id}",
+                    EdgeOutcomes {
+                        succeeded: 1,
+                        failed: 0
+                    }
+                ),
+                (
+                    "GET /pets/{id}",
+                    "DELETE /pets/{id}",
+                    EdgeOutcomes {
+                        succeeded: 0,
+                        failed: 1
+                    }
+                ),
+            ]
+        );
+        let dot = graph.to_dot();
+        assert!(dot.contains(
+            "\"POST /pets\" -> \"GET /pets/{id}\" [label=\"1 ok, 0 failed\", color=green]"
+        ));
+        assert!(dot.contains(
+            "\"GET /pets/{id}\" -> \"DELETE /pets/{id}\" [label=\"0 ok, 1 failed\", color=red]"
+        ));
+        assert!(graph.to_mermaid().contains("linkStyle 1 stroke:red;"));
+    }
+}
@@ -0,0 +1,231 @@
+//! Exports sample request/response pairs as JSON fixtures, for building mock servers or
+//! contract tests from real traffic. For every combination of method, path and status
+//! code, the first successful response and the request that caused it are written to a
+//! file in the fixture directory.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs::create_dir_all,
+    path::PathBuf,
+    sync::Mutex,
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use libafl_bolts::fs::write_file_atomic;
+use log::warn;
+use serde_json::{json, Value};
+
+use crate::{
+    configuration::Configuration,
+    input::{Method, OpenApiRequest},
+    openapi::{curl_request::CurlRequest, validate_response::Response},
+    reporting::Reporting,
+};
+
+/// Instantiates a fixture exporter if a fixture directory is given in the configuration
+pub fn get_reporter(config: &Configuration) -> Option<FixtureExporter> {
+    config
+        .export_fixtures
+        .as_ref()
+        .map(|dir| FixtureExporter::new(dir.clone()))
+}
+
+/// Reporter that writes a fixture for each new (method, path, status) combination with
+/// a successful response.
+pub struct FixtureExporter {
+    dir: PathBuf,
+    /// Requests that await their response, by the id handed out when reporting them
+    pending: Mutex<HashMap<usize, PendingRequest>>,
+    next_id: Mutex<usize>,
+    exported: Mutex<HashSet<(Method, String, u16)>>,
+}
+
+/// A request that was sent, in the form in which it is written to the fixture
+struct PendingRequest {
+    method: Method,
+    path: String,
+    fixture: Value,
+}
+
+impl FixtureExporter {
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            pending: Mutex::new(HashMap::new()),
+            next_id: Mutex::new(0),
+            exported: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Writes the fixture for a request and its response
+    fn export(&self, request: PendingRequest, response: &Response) {
+        let status = response.status().as_u16();
+        let name = format!(
+            "{}_{}_{status}.json",
+            request.method,
+            file_name_part(&request.path)
+        );
+        let fixture = json!({
+            "request": request.fixture,
+            "response": {
+                "status": status,
+                "body": body_value(response.body()),
+            },
+        });
+        let contents = serde_json::to_vec_pretty(&fixture).expect("JSON is serializable");
+        if let Err(err) = create_dir_all(&self.dir).and_then(|()| {
+            write_file_atomic(self.dir.join(&name), &contents).map_err(std::io::Error::other)
+        }) {
+            warn!("Could not write fixture {name} to {:?}: {err}", self.dir);
+        }
+    }
+}
+
+impl Reporting<usize> for FixtureExporter {
+    fn report_request(
+        &self,
+        request: &OpenApiRequest,
+        curl: &CurlRequest,
+        _input_id: usize,
+    ) -> usize {
+        let mut next_id = self.next_id.lock().unwrap();
+        let id = *next_id;
+        *next_id += 1;
+        let headers: serde_json::Map<String, Value> = curl
+            .headers()
+            .into_iter()
+            .map(|(name, value)| (name, Value::String(value)))
+            .collect();
+        let mut fixture = json!({
+            "method": curl.method(),
+            "path": request.path,
+            "url": curl.url(),
+            "headers": headers,
+        });
+        if let Some(body) = curl.body() {
+            fixture["body"] = body_value(body);
+        }
+        self.pending.lock().unwrap().insert(
+            id,
+            PendingRequest {
+                method: request.method,
+                path: request.path.clone(),
+                fixture,
+            },
+        );
+        id
+    }
+
+    fn report_response(&self, response: &Response, request_id: usize) {
+        let Some(request) = self.pending.lock().unwrap().remove(&request_id) else {
+            return;
+        };
+        if !response.status().is_success() {
+            return;
+        }
+        let key = (
+            request.method,
+            request.path.clone(),
+            response.status().as_u16(),
+        );
+        if self.exported.lock().unwrap().insert(key) {
+            self.export(request, response);
+        }
+    }
+
+    fn report_response_error(&self, _error: &str, request_id: usize) {
+        self.pending.lock().unwrap().remove(&request_id);
+    }
+
+    fn report_coverage(
+        &self,
+        _line_coverage: u64,
+        _line_coverage_total: u64,
+        _endpoint_coverage: u64,
+        _endpoint_coverage_total: u64,
+    ) {
+    }
+}
+
+/// A body as JSON if it is JSON, as a string if it is other text, and base64-encoded
+/// otherwise.
+fn body_value(body: &[u8]) -> Value {
+    match (serde_json::from_slice(body), std::str::from_utf8(body)) {
+        (Ok(json), _) => json,
+        (Err(_), Ok(text)) => Value::String(text.to_owned()),
+        (Err(_), Err(_)) => json!({"base64": STANDARD.encode(body)}),
+    }
+}
+
+/// A path turned into a part of a file name, e.g. `/pets/{id}` becomes `pets_id`
+fn file_name_part(path: &str) -> String {
+    let part = path
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>()
+        .join("_");
+    if part.is_empty() {
+        "root".to_owned()
+    } else {
+        part
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+    use reqwest::{blocking::Request, Method as HttpMethod, StatusCode, Url};
+    use serde_json::Value;
+
+    use super::FixtureExporter;
+    use crate::{
+        authentication::Authentication,
+        input::{Body, Method, OpenApiRequest},
+        openapi::{curl_request::CurlRequest, validate_response::Response},
+        reporting::Reporting,
+    };
+
+    #[test]
+    fn test_one_fixture_per_triplet() {
+        let dir = tempfile::tempdir().unwrap();
+        let exporter = FixtureExporter::new(dir.path().to_path_buf());
+        let request = OpenApiRequest {
+            method: Method::Get,
+            path: "/pets/{id}".to_owned(),
+            body: Body::Empty,
+            parameters: IndexMap::new(),
+        };
+        let send = |id: usize, status: StatusCode, body: &str| {
+            let http_request = Request::new(
+                HttpMethod::GET,
+                Url::parse(&format!("http://localhost:8080/pets/{id}")).unwrap(),
+            );
+            let request_id = exporter.report_request(
+                &request,
+                &CurlRequest(&http_request, &Authentication::None, None),
+                id,
+            );
+            exporter.report_response(&Response::new(status, body.as_bytes().to_vec()), request_id);
+        };
+
+        for id in 0..10 {
+            send(id, StatusCode::OK, &format!(r#"{{"id": {id}}}"#));
+        }
+        send(10, StatusCode::NO_CONTENT, "");
+        // Unsuccessful responses are not exported
+        send(11, StatusCode::NOT_FOUND, "Not found");
+
+        let mut files: Vec<String> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        files.sort();
+        assert_eq!(files, vec!["GET_pets_id_200.json", "GET_pets_id_204.json"]);
+
+        let fixture: Value =
+            serde_json::from_slice(&std::fs::read(dir.path().join(&files[0])).unwrap()).unwrap();
+        assert_eq!(fixture["request"]["url"], "http://localhost:8080/pets/0");
+        assert_eq!(fixture["request"]["path"], "/pets/{id}");
+        assert_eq!(fixture["response"]["body"]["id"], 0);
+    }
+}
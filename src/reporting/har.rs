@@ -0,0 +1,292 @@
+//! Records all requests sent and responses received as an HTTP Archive (HAR 1.2), so the
+//! traffic of a run can be inspected or replayed in browser devtools or Postman. The
+//! archive is written to the report directory when the run ends.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::Instant,
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, SecondsFormat, Utc};
+use libafl_bolts::fs::write_file_atomic;
+use log::warn;
+use serde_json::{json, Value};
+
+use crate::{
+    input::OpenApiRequest,
+    openapi::{curl_request::CurlRequest, validate_response::Response},
+    reporting::Reporting,
+    wuppie_version::get_wuppie_version,
+};
+
+/// Name of the archive in the report directory
+const HAR_FILE_NAME: &str = "requests.har";
+
+/// Instantiates a HAR exporter if a report is requested
+pub fn get_reporter(report_path: Option<&Path>) -> Option<HarExporter> {
+    report_path.map(|report_path| HarExporter::new(report_path.join(HAR_FILE_NAME)))
+}
+
+/// Reporter that keeps all requests and responses in memory, and writes them to an
+/// archive on `finish` or when it is dropped.
+pub struct HarExporter {
+    path: PathBuf,
+    entries: Mutex<Vec<Entry>>,
+}
+
+/// A request, and the response to it once it has arrived
+struct Entry {
+    started: DateTime<Utc>,
+    started_instant: Instant,
+    request: Value,
+    response: Option<Value>,
+    time_ms: f64,
+}
+
+impl HarExporter {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            entries: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Writes the archive with all requests and responses reported so far.
+    pub fn finish(&self) {
+        let archive = self.archive();
+        let contents = serde_json::to_vec_pretty(&archive).expect("JSON is serializable");
+        if let Err(err) = write_file_atomic(&self.path, &contents) {
+            warn!("Could not write HTTP archive {:?}: {err}", self.path);
+        }
+    }
+
+    fn archive(&self) -> Value {
+        let entries: Vec<Value> = self
+            .entries
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|entry| {
+                json!({
+                    "startedDateTime": entry.started.to_rfc3339_opts(SecondsFormat::Millis, true),
+                    "time": entry.time_ms,
+                    "request": entry.request,
+                    "response": entry.response.clone().unwrap_or_else(|| har_response(0, "", &[])),
+                    "cache": {},
+                    "timings": {"send": 0, "wait": entry.time_ms, "receive": 0},
+                })
+            })
+            .collect();
+        json!({
+            "log": {
+                "version": "1.2",
+                "creator": {"name": "WuppieFuzz", "version": get_wuppie_version()},
+                "entries": entries,
+            }
+        })
+    }
+
+    /// Sets the response of the entry with the given index
+    fn set_response(&self, entry_index: usize, response: Value) {
+        if let Some(entry) = self.entries.lock().unwrap().get_mut(entry_index) {
+            entry.time_ms = entry.started_instant.elapsed().as_secs_f64() * 1000.0;
+            entry.response = Some(response);
+        }
+    }
+}
+
+impl Drop for HarExporter {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+impl Reporting<usize> for HarExporter {
+    fn report_request(
+        &self,
+        _request: &OpenApiRequest,
+        curl: &CurlRequest,
+        _input_id: usize,
+    ) -> usize {
+        let mut entries = self.entries.lock().unwrap();
+        entries.push(Entry {
+            started: Utc::now(),
+            started_instant: Instant::now(),
+            request: har_request(curl),
+            response: None,
+            time_ms: 0.0,
+        });
+        entries.len() - 1
+    }
+
+    fn report_response(&self, response: &Response, entry_index: usize) {
+        let status = response.status();
+        self.set_response(
+            entry_index,
+            har_response(
+                status.as_u16(),
+                status.canonical_reason().unwrap_or_default(),
+                response.body(),
+            ),
+        );
+    }
+
+    fn report_response_error(&self, error: &str, entry_index: usize) {
+        let mut response = har_response(0, "", &[]);
+        response["_error"] = json!(error);
+        self.set_response(entry_index, response);
+    }
+
+    fn report_coverage(
+        &self,
+        _line_coverage: u64,
+        _line_coverage_total: u64,
+        _endpoint_coverage: u64,
+        _endpoint_coverage_total: u64,
+    ) {
+    }
+}
+
+/// The request in the HAR format
+fn har_request(curl: &CurlRequest) -> Value {
+    let headers: Vec<Value> = curl
+        .headers()
+        .into_iter()
+        .map(|(name, value)| json!({"name": name, "value": value}))
+        .collect();
+    let query: Vec<Value> = curl
+        .0
+        .url()
+        .query_pairs()
+        .map(|(name, value)| json!({"name": name, "value": value}))
+        .collect();
+    let mut request = json!({
+        "method": curl.method(),
+        "url": curl.url(),
+        "httpVersion": "HTTP/1.1",
+        "cookies": [],
+        "headers": headers,
+        "queryString": query,
+        "headersSize": -1,
+        "bodySize": curl.body().map_or(0, <[u8]>::len),
+    });
+    if let Some(body) = curl.body() {
+        let mime_type = curl
+            .headers()
+            .into_iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+            .map(|(_, value)| value)
+            .unwrap_or_default();
+        request["postData"] = json!({
+            "mimeType": mime_type,
+            "text": String::from_utf8_lossy(body),
+        });
+    }
+    request
+}
+
+/// A response in the HAR format. Bodies that are not valid text are base64-encoded.
+fn har_response(status: u16, status_text: &str, body: &[u8]) -> Value {
+    let content = match std::str::from_utf8(body) {
+        Ok(text) => json!({"size": body.len(), "mimeType": "", "text": text}),
+        Err(_) => json!({
+            "size": body.len(),
+            "mimeType": "",
+            "text": STANDARD.encode(body),
+            "encoding": "base64",
+        }),
+    };
+    json!({
+        "status": status,
+        "statusText": status_text,
+        "httpVersion": "HTTP/1.1",
+        "cookies": [],
+        "headers": [],
+        "content": content,
+        "redirectURL": "",
+        "headersSize": -1,
+        "bodySize": body.len(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+    use reqwest::{blocking::Request, Method as HttpMethod, StatusCode, Url};
+    use serde_json::Value;
+
+    use super::HarExporter;
+    use crate::{
+        authentication::Authentication,
+        input::{Body, Method, OpenApiRequest},
+        openapi::{curl_request::CurlRequest, validate_response::Response},
+        reporting::Reporting,
+    };
+
+    #[test]
+    fn test_archive_has_entry_per_request() {
+        let path = std::env::temp_dir().join(format!("wuppiefuzz-{}.har", std::process::id()));
+        let exporter = HarExporter::new(path.clone());
+        let authentication = Authentication::Raw("Bearer secret".to_owned());
+        let openapi_request = OpenApiRequest {
+            method: Method::Get,
+            path: "/pets".to_owned(),
+            body: Body::Empty,
+            parameters: IndexMap::new(),
+        };
+
+        let get = Request::new(
+            HttpMethod::GET,
+            Url::parse("http://localhost:8080/pets?limit=2").unwrap(),
+        );
+        let id = exporter.report_request(
+            &openapi_request,
+            &CurlRequest(&get, &authentication, None),
+            1,
+        );
+        exporter.report_response(&Response::new(StatusCode::OK, b"[]".to_vec()), id);
+
+        let mut post = Request::new(
+            HttpMethod::POST,
+            Url::parse("http://localhost:8080/pets").unwrap(),
+        );
+        *post.body_mut() = Some(r#"{"name":"Wuppie"}"#.into());
+        let id = exporter.report_request(
+            &openapi_request,
+            &CurlRequest(&post, &authentication, None),
+            2,
+        );
+        exporter.report_response(
+            &Response::new(StatusCode::INTERNAL_SERVER_ERROR, b"Oops".to_vec()),
+            id,
+        );
+
+        // Dropping the exporter writes the archive
+        drop(exporter);
+        let archive: Value = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(archive["log"]["version"], "1.2");
+        let entries = archive["log"]["entries"].as_array().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0]["request"]["method"], "GET");
+        assert_eq!(
+            entries[0]["request"]["queryString"][0]["value"],
+            Value::from("2")
+        );
+        assert_eq!(
+            entries[0]["request"]["headers"][0]["value"],
+            "Bearer secret"
+        );
+        assert_eq!(entries[0]["response"]["status"], 200);
+        assert_eq!(entries[1]["request"]["method"], "POST");
+        assert_eq!(
+            entries[1]["request"]["postData"]["text"],
+            r#"{"name":"Wuppie"}"#
+        );
+        assert_eq!(entries[1]["response"]["status"], 500);
+        assert_eq!(entries[1]["response"]["content"]["text"], "Oops");
+    }
+}
@@ -0,0 +1,140 @@
+//! The lineage log records, for every input added to the corpus or to the crashes, which
+//! input it was derived from and which mutators were applied to get there. This makes it
+//! possible to trace how the fuzzer arrived at a particular input.
+//!
+//! The mutators keep track of the chain of mutations in a `MutationLineage` in the state
+//! (see `OpenApiMutator::mutate`), and the `LineageFeedback` writes it to the log when
+//! an input is kept.
+
+use std::{
+    borrow::Cow,
+    fs::OpenOptions,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use libafl::{
+    corpus::Testcase,
+    feedbacks::{Feedback, StateInitializer},
+    inputs::Input,
+    Error, HasMetadata,
+};
+use libafl_bolts::Named;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::input::OpenApiInput;
+
+/// The chain of mutations that led to the most recently mutated input.
+///
+/// Inputs are identified by their name, which is also their file name in the corpus.
+/// A mutation of the input that the chain leads to extends the chain; a mutation of any
+/// other input starts a new chain with that input as the parent.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MutationLineage {
+    /// Name of the input the mutations were applied to
+    pub parent: String,
+    /// Names of the mutators applied, in order
+    pub mutators: Vec<String>,
+    /// Name of the input resulting from the mutations
+    pub input: String,
+}
+
+libafl_bolts::impl_serdeany!(MutationLineage);
+
+impl MutationLineage {
+    /// Records that `mutator` changed the input named `before` into the input named `after`.
+    pub fn record(&mut self, before: String, mutator: &str, after: String) {
+        if self.mutators.is_empty() || self.input != before {
+            self.parent = before;
+            self.mutators.clear();
+        }
+        self.mutators.push(mutator.to_owned());
+        self.input = after;
+    }
+}
+
+/// Feedback that never considers an input interesting by itself, but appends the
+/// lineage of every input that is kept to the lineage log.
+pub struct LineageFeedback {
+    log_path: Option<PathBuf>,
+    kind: &'static str,
+}
+
+impl LineageFeedback {
+    /// Creates a feedback writing to `log_path`, if given. `kind` is written along with each
+    /// lineage to tell corpus entries and crashes apart.
+    pub fn new(log_path: Option<&Path>, kind: &'static str) -> Self {
+        Self {
+            log_path: log_path.map(Path::to_path_buf),
+            kind,
+        }
+    }
+
+    fn write(&self, log_path: &Path, lineage: &MutationLineage) -> std::io::Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(log_path)?;
+        let entry = json!({
+            "kind": self.kind,
+            "input": lineage.input,
+            "parent": lineage.parent,
+            "mutators": lineage.mutators,
+        });
+        writeln!(file, "{entry}")
+    }
+}
+
+impl Named for LineageFeedback {
+    fn name(&self) -> &Cow<'static, str> {
+        &Cow::Borrowed("LineageFeedback")
+    }
+}
+
+impl<S> StateInitializer<S> for LineageFeedback
+where
+    S: HasMetadata,
+{
+    fn init_state(&mut self, state: &mut S) -> Result<(), Error> {
+        if self.log_path.is_some() {
+            state.metadata_or_insert_with(MutationLineage::default);
+        }
+        Ok(())
+    }
+}
+
+impl<EM, OT, S> Feedback<EM, OpenApiInput, OT, S> for LineageFeedback
+where
+    S: HasMetadata,
+{
+    fn append_metadata(
+        &mut self,
+        state: &mut S,
+        _manager: &mut EM,
+        _observers: &OT,
+        testcase: &mut Testcase<OpenApiInput>,
+    ) -> Result<(), Error> {
+        let Some(log_path) = &self.log_path else {
+            return Ok(());
+        };
+        let Ok(lineage) = state.metadata::<MutationLineage>() else {
+            return Ok(());
+        };
+        let input_name = match testcase.input() {
+            Some(input) => input.generate_name(None),
+            None => return Ok(()),
+        };
+        // Inputs that were not produced by the mutators, such as the initial corpus,
+        // have no lineage.
+        if lineage.mutators.is_empty() || lineage.input != input_name {
+            return Ok(());
+        }
+        if let Err(err) = self.write(log_path, lineage) {
+            log::warn!("Could not write to lineage log {log_path:?}: {err}");
+        }
+        let lineage = lineage.clone();
+        testcase.add_metadata(lineage);
+        Ok(())
+    }
+}
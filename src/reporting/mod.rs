@@ -1,3 +1,5 @@
+use std::{collections::HashSet, sync::Mutex};
+
 use libafl::{
     corpus::{Corpus, InMemoryOnDiskCorpus, OnDiskCorpus},
     executors::hooks::inprocess::inprocess_get_state,
@@ -5,12 +7,22 @@ use libafl::{
 };
 
 use crate::{
-    input::{OpenApiInput, OpenApiRequest},
+    input::{Method, OpenApiInput, OpenApiRequest},
     openapi::{curl_request::CurlRequest, validate_response::Response},
     state::OpenApiFuzzerState,
 };
 
+pub mod cassette;
+pub mod coverage_attribution;
+pub mod executed_graph;
+pub mod fixtures;
+pub mod har;
+pub mod lineage;
+pub mod postman;
+pub mod sarif;
 pub mod sqlite;
+pub mod summary;
+pub mod webhook;
 
 // The reporting trait allows reporting requests and responses for later analysis.
 // The type `T` is the type used by the underlying data store to refer to records,
@@ -33,6 +45,10 @@ pub trait Reporting<T> {
         endpoint_coverage: u64,
         endpoint_coverage_total: u64,
     );
+
+    /// Report that the request triggered a bug of the given kind, with the response
+    /// that revealed it. Reporters that do not deal with bugs can ignore this.
+    fn report_crash(&self, _request: &OpenApiRequest, _response: &Response, _error_kind: &str) {}
 }
 
 impl<R, T> Reporting<T> for Option<R>
@@ -75,6 +91,26 @@ where
             )
         }
     }
+
+    fn report_crash(&self, request: &OpenApiRequest, response: &Response, error_kind: &str) {
+        if let Some(reporter) = self.as_ref() {
+            reporter.report_crash(request, response, error_kind)
+        }
+    }
+}
+
+/// The signatures of the crashes reported so far: their method, path and kind of
+/// error. Reporters that report each bug once leave out crashes with a signature seen
+/// before.
+#[derive(Default)]
+pub struct CrashSignatures(Mutex<HashSet<(Method, String, String)>>);
+
+impl CrashSignatures {
+    /// Records the signature of the crash, and returns whether it was not seen before
+    pub fn insert(&self, request: &OpenApiRequest, error_kind: &str) -> bool {
+        let signature = (request.method, request.path.clone(), error_kind.to_owned());
+        self.0.lock().unwrap().insert(signature)
+    }
 }
 
 fn get_current_test_case_file_name() -> Option<String> {
@@ -0,0 +1,271 @@
+//! Exports the crashes found by the fuzzer as a Postman collection (format v2.1), so they
+//! can be replayed and turned into regression tests with Postman or Newman. Every crash
+//! becomes a request item, with the failing response attached as an example.
+
+use std::{path::PathBuf, sync::Mutex};
+
+use libafl_bolts::fs::write_file_atomic;
+use log::warn;
+use openapiv3::OpenAPI;
+use serde_json::{json, Value};
+
+use crate::{
+    configuration::Configuration,
+    input::{form::FormArrayStyle, parameter::ParameterKind, OpenApiRequest},
+    openapi::{build_request::server_url, curl_request::CurlRequest, validate_response::Response},
+    reporting::{CrashSignatures, Reporting},
+};
+
+/// Schema identifying the version of the collection format
+const COLLECTION_SCHEMA: &str =
+    "https://schema.getpostman.com/json/collection/v2.1.0/collection.json";
+
+/// Name of the collection variable holding the URL of the target
+const BASE_URL_VARIABLE: &str = "baseUrl";
+
+/// Instantiates a Postman exporter if a crash collection is given in the configuration
+pub fn get_reporter(config: &Configuration, api: &OpenAPI) -> Option<PostmanExporter> {
+    let base_url = api.servers.first().map(server_url).unwrap_or_default();
+    config
+        .crash_collection
+        .as_ref()
+        .map(|path| PostmanExporter::new(path.clone(), base_url, config.form_array_style))
+}
+
+/// Reporter that keeps a Postman collection of all crashes up to date on disk. Crashes
+/// with the same signature (method, path and kind of error) as an earlier one are left
+/// out, like the webhook does.
+pub struct PostmanExporter {
+    path: PathBuf,
+    base_url: String,
+    /// How arrays in form bodies are written
+    form_array_style: FormArrayStyle,
+    items: Mutex<Vec<Value>>,
+    seen_signatures: CrashSignatures,
+}
+
+impl PostmanExporter {
+    pub fn new(path: PathBuf, base_url: String, form_array_style: FormArrayStyle) -> Self {
+        Self {
+            path,
+            base_url,
+            form_array_style,
+            items: Mutex::new(Vec::new()),
+            seen_signatures: CrashSignatures::default(),
+        }
+    }
+
+    /// The collection containing the given request items
+    fn collection(&self, items: &[Value]) -> Value {
+        json!({
+            "info": {
+                "name": "WuppieFuzz crashes",
+                "schema": COLLECTION_SCHEMA,
+            },
+            "variable": [{"key": BASE_URL_VARIABLE, "value": self.base_url}],
+            "item": items,
+        })
+    }
+}
+
+impl Reporting<()> for PostmanExporter {
+    fn report_request(&self, _request: &OpenApiRequest, _curl: &CurlRequest, _input_id: usize) {}
+
+    fn report_response(&self, _response: &Response, _request_id: ()) {}
+
+    fn report_response_error(&self, _error: &str, _request_id: ()) {}
+
+    fn report_coverage(
+        &self,
+        _line_coverage: u64,
+        _line_coverage_total: u64,
+        _endpoint_coverage: u64,
+        _endpoint_coverage_total: u64,
+    ) {
+    }
+
+    fn report_crash(&self, request: &OpenApiRequest, response: &Response, error_kind: &str) {
+        if !self.seen_signatures.insert(request, error_kind) {
+            return;
+        }
+        let mut items = self.items.lock().unwrap();
+        items.push(crash_item(
+            request,
+            response,
+            error_kind,
+            self.form_array_style,
+        ));
+        let collection = self.collection(&items);
+        let contents = serde_json::to_vec_pretty(&collection).expect("JSON is serializable");
+        if let Err(err) = write_file_atomic(&self.path, &contents) {
+            warn!("Could not write crash collection {:?}: {err}", self.path);
+        }
+    }
+}
+
+/// A collection item that sends the request, with the failing response as an example and
+/// a test that fails for as long as the server responds with an error.
+fn crash_item(
+    request: &OpenApiRequest,
+    response: &Response,
+    error_kind: &str,
+    form_array_style: FormArrayStyle,
+) -> Value {
+    let postman_request = postman_request(request, form_array_style);
+    json!({
+        "name": format!("{} {} ({error_kind})", request.method, request.path),
+        "request": postman_request,
+        "response": [{
+            "name": error_kind,
+            "originalRequest": postman_request,
+            "code": response.status().as_u16(),
+            "status": response.status().canonical_reason().unwrap_or_default(),
+            "body": String::from_utf8_lossy(response.body()),
+        }],
+        "event": [{
+            "listen": "test",
+            "script": {
+                "type": "text/javascript",
+                "exec": [
+                    "pm.test(\"No server error\", function () {",
+                    "    pm.expect(pm.response.code).to.be.below(500);",
+                    "});",
+                ],
+            },
+        }],
+    })
+}
+
+/// The request in the Postman format, relative to the base URL variable, with arrays in
+/// form bodies written in `form_array_style`
+fn postman_request(request: &OpenApiRequest, form_array_style: FormArrayStyle) -> Value {
+    let mut path = request.path.clone();
+    let mut query = Vec::new();
+    let mut headers = Vec::new();
+    let mut cookies = Vec::new();
+    for ((name, kind), value) in &request.parameters {
+        match kind {
+            ParameterKind::Path => {
+                path = path.replace(&format!("{{{name}}}"), &value.to_url_encoding());
+            }
+            ParameterKind::Query => query.push((name, value.to_url_encoding())),
+            ParameterKind::Header => headers.push(json!({
+                "key": name,
+                "value": String::from_utf8_lossy(value.to_header_value().as_bytes()),
+            })),
+            ParameterKind::Cookie => cookies.push(format!("{name}={}", value.to_cookie_value())),
+            ParameterKind::Body => (),
+        }
+    }
+    if !cookies.is_empty() {
+        headers.push(json!({"key": "Cookie", "value": cookies.join("; ")}));
+    }
+
+    let host = format!("{{{{{BASE_URL_VARIABLE}}}}}");
+    let mut raw_url = format!("{host}{path}");
+    if !query.is_empty() {
+        let pairs: Vec<String> = query
+            .iter()
+            .map(|(name, value)| format!("{name}={value}"))
+            .collect();
+        raw_url += &format!("?{}", pairs.join("&"));
+    }
+    let mut postman_request = json!({
+        "method": request.method.to_string(),
+        "header": headers,
+        "url": {
+            "raw": raw_url,
+            "host": [host],
+            "path": path.split('/').filter(|segment| !segment.is_empty()).collect::<Vec<_>>(),
+            "query": query
+                .iter()
+                .map(|(name, value)| json!({"key": name, "value": value}))
+                .collect::<Vec<_>>(),
+        },
+    });
+
+    let body = request
+        .reqwest_body(form_array_style)
+        .and_then(|body| body.as_bytes().map(<[u8]>::to_vec));
+    if let Some(body) = body {
+        postman_request["header"]
+            .as_array_mut()
+            .unwrap()
+            .push(json!({"key": "Content-Type", "value": request.body_content_type()}));
+        postman_request["body"] = json!({
+            "mode": "raw",
+            "raw": String::from_utf8_lossy(&body),
+        });
+    }
+    postman_request
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+    use serde_json::{json, Value};
+
+    use super::PostmanExporter;
+    use crate::{
+        input::{
+            form::FormArrayStyle, parameter::ParameterKind, Body, Method, OpenApiRequest,
+            ParameterContents,
+        },
+        openapi::validate_response::Response,
+        reporting::Reporting,
+    };
+
+    #[test]
+    fn test_crash_is_exported_as_request_item() {
+        let path = std::env::temp_dir().join(format!(
+            "wuppiefuzz-crashes-{}.postman_collection.json",
+            std::process::id()
+        ));
+        let exporter = PostmanExporter::new(
+            path.clone(),
+            "http://localhost:8080/api".to_owned(),
+            FormArrayStyle::default(),
+        );
+        let request = OpenApiRequest {
+            method: Method::Post,
+            path: "/pets/{id}".to_owned(),
+            body: Body::ApplicationJson(ParameterContents::from(json!({"name": "Wuppie"}))),
+            parameters: IndexMap::from([
+                (
+                    ("id".to_owned(), ParameterKind::Path),
+                    ParameterContents::from(json!(7)),
+                ),
+                (
+                    ("verbose".to_owned(), ParameterKind::Query),
+                    ParameterContents::from(json!(true)),
+                ),
+            ]),
+        };
+        let response = Response::new(reqwest::StatusCode::INTERNAL_SERVER_ERROR, b"Oops".to_vec());
+
+        exporter.report_crash(&request, &response, "http_500");
+        let collection: Value = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            collection["variable"][0]["value"],
+            "http://localhost:8080/api"
+        );
+        let items = collection["item"].as_array().unwrap();
+        assert_eq!(items.len(), 1);
+        let item = &items[0];
+        assert_eq!(item["request"]["method"], "POST");
+        assert_eq!(
+            item["request"]["url"]["raw"],
+            "{{baseUrl}}/pets/7?verbose=true"
+        );
+        assert_eq!(item["request"]["url"]["path"], json!(["pets", "7"]));
+        assert_eq!(
+            item["request"]["url"]["query"],
+            json!([{"key": "verbose", "value": "true"}])
+        );
+        assert_eq!(item["request"]["body"]["raw"], r#"{"name":"Wuppie"}"#);
+        assert_eq!(item["response"][0]["code"], 500);
+        assert_eq!(item["response"][0]["body"], "Oops");
+    }
+}
@@ -0,0 +1,237 @@
+//! Exports the crashes found by the fuzzer as a SARIF 2.1.0 log, the format read by code
+//! scanning tools such as GitHub code scanning. Every kind of error, e.g. `http_500` or
+//! `response_object_incorrect`, becomes a rule, and every crash a result located at the
+//! operation (method and path) that triggered it.
+
+use std::{path::PathBuf, sync::Mutex};
+
+use indexmap::IndexSet;
+use libafl_bolts::fs::write_file_atomic;
+use log::warn;
+use serde_json::{json, Value};
+
+use crate::{
+    configuration::Configuration,
+    input::OpenApiRequest,
+    openapi::{curl_request::CurlRequest, validate_response::Response},
+    reporting::{CrashSignatures, Reporting},
+};
+
+/// Schema identifying the version of the SARIF format
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// Instantiates a SARIF exporter if a SARIF output file is given in the configuration
+pub fn get_reporter(config: &Configuration) -> Option<SarifExporter> {
+    config.sarif_output.as_ref().map(|path| {
+        SarifExporter::new(
+            path.clone(),
+            config
+                .openapi_spec
+                .as_ref()
+                .map(|spec| spec.to_string_lossy().into_owned()),
+        )
+    })
+}
+
+/// Reporter that keeps a SARIF log of all crashes up to date on disk. Crashes with the
+/// same signature (method, path and kind of error) as an earlier one are left out, like
+/// the webhook does.
+pub struct SarifExporter {
+    path: PathBuf,
+    /// The OpenAPI specification, used as the file in which the crashes are located
+    spec_uri: Option<String>,
+    log: Mutex<SarifLog>,
+    seen_signatures: CrashSignatures,
+}
+
+/// The rules and results found so far
+#[derive(Default)]
+struct SarifLog {
+    /// Ids of the rules, i.e. the kinds of error, in the order in which they were found
+    rule_ids: IndexSet<String>,
+    results: Vec<Value>,
+}
+
+impl SarifExporter {
+    pub fn new(path: PathBuf, spec_uri: Option<String>) -> Self {
+        Self {
+            path,
+            spec_uri,
+            log: Mutex::new(SarifLog::default()),
+            seen_signatures: CrashSignatures::default(),
+        }
+    }
+
+    /// The SARIF document containing the given rules and results
+    fn document(&self, log: &SarifLog) -> Value {
+        let rules: Vec<Value> = log
+            .rule_ids
+            .iter()
+            .map(|id| {
+                json!({
+                    "id": id,
+                    "shortDescription": {"text": format!("The API responded with an error of kind {id}")},
+                    "defaultConfiguration": {"level": "error"},
+                })
+            })
+            .collect();
+        json!({
+            "$schema": SARIF_SCHEMA,
+            "version": "2.1.0",
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": "WuppieFuzz",
+                        "version": env!("CARGO_PKG_VERSION"),
+                        "informationUri": "https://github.com/TNO-S3/WuppieFuzz",
+                        "rules": rules,
+                    },
+                },
+                "results": log.results,
+            }],
+        })
+    }
+
+    /// The result for a crash, referring to the rule at `rule_index`
+    fn result(
+        &self,
+        request: &OpenApiRequest,
+        response: &Response,
+        error_kind: &str,
+        rule_index: usize,
+    ) -> Value {
+        let operation = format!("{} {}", request.method, request.path);
+        let mut location = json!({
+            "logicalLocations": [{
+                "name": request.path,
+                "fullyQualifiedName": operation,
+                "kind": "function",
+            }],
+        });
+        if let Some(spec_uri) = &self.spec_uri {
+            location["physicalLocation"] = json!({"artifactLocation": {"uri": spec_uri}});
+        }
+        json!({
+            "ruleId": error_kind,
+            "ruleIndex": rule_index,
+            "level": "error",
+            "message": {
+                "text": format!(
+                    "{operation} triggered {error_kind} with HTTP status {}",
+                    response.status()
+                ),
+            },
+            "locations": [location],
+        })
+    }
+}
+
+impl Reporting<()> for SarifExporter {
+    fn report_request(&self, _request: &OpenApiRequest, _curl: &CurlRequest, _input_id: usize) {}
+
+    fn report_response(&self, _response: &Response, _request_id: ()) {}
+
+    fn report_response_error(&self, _error: &str, _request_id: ()) {}
+
+    fn report_coverage(
+        &self,
+        _line_coverage: u64,
+        _line_coverage_total: u64,
+        _endpoint_coverage: u64,
+        _endpoint_coverage_total: u64,
+    ) {
+    }
+
+    fn report_crash(&self, request: &OpenApiRequest, response: &Response, error_kind: &str) {
+        if !self.seen_signatures.insert(request, error_kind) {
+            return;
+        }
+        let mut log = self.log.lock().unwrap();
+        let (rule_index, _) = log.rule_ids.insert_full(error_kind.to_owned());
+        let result = self.result(request, response, error_kind, rule_index);
+        log.results.push(result);
+        let document = self.document(&log);
+        let contents = serde_json::to_vec_pretty(&document).expect("JSON is serializable");
+        if let Err(err) = write_file_atomic(&self.path, &contents) {
+            warn!("Could not write SARIF output {:?}: {err}", self.path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+    use reqwest::StatusCode;
+    use serde_json::Value;
+
+    use super::SarifExporter;
+    use crate::{
+        input::{Body, Method, OpenApiRequest},
+        openapi::validate_response::Response,
+        reporting::Reporting,
+    };
+
+    #[test]
+    fn test_crashes_are_exported_as_sarif() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("crashes.sarif");
+        let exporter = SarifExporter::new(path.clone(), Some("openapi.yaml".to_owned()));
+        let request = |method, path: &str| OpenApiRequest {
+            method,
+            path: path.to_owned(),
+            body: Body::Empty,
+            parameters: IndexMap::new(),
+        };
+        let server_error = Response::new(StatusCode::INTERNAL_SERVER_ERROR, vec![]);
+        let ok = Response::new(StatusCode::OK, b"{}".to_vec());
+
+        exporter.report_crash(
+            &request(Method::Get, "/pets/{id}"),
+            &server_error,
+            "http_500",
+        );
+        exporter.report_crash(
+            &request(Method::Post, "/pets"),
+            &ok,
+            "response_object_incorrect",
+        );
+        // The same crash again, and the same kind of error elsewhere
+        exporter.report_crash(
+            &request(Method::Get, "/pets/{id}"),
+            &server_error,
+            "http_500",
+        );
+        exporter.report_crash(
+            &request(Method::Delete, "/pets/{id}"),
+            &server_error,
+            "http_500",
+        );
+
+        let sarif: Value = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        assert_eq!(sarif["version"], "2.1.0");
+        let run = &sarif["runs"][0];
+        let rule_ids: Vec<&str> = run["tool"]["driver"]["rules"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|rule| rule["id"].as_str().unwrap())
+            .collect();
+        assert_eq!(rule_ids, vec!["http_500", "response_object_incorrect"]);
+
+        let results = run["results"].as_array().unwrap();
+        assert_eq!(results.len(), 3);
+        for result in results {
+            let rule_index = result["ruleIndex"].as_u64().unwrap() as usize;
+            assert_eq!(result["ruleId"], rule_ids[rule_index]);
+        }
+        let location = &results[2]["locations"][0];
+        assert_eq!(
+            location["logicalLocations"][0]["fullyQualifiedName"],
+            "DELETE /pets/{id}"
+        );
+        assert_eq!(
+            location["physicalLocation"]["artifactLocation"]["uri"],
+            "openapi.yaml"
+        );
+    }
+}
@@ -9,7 +9,7 @@ use crate::{
     configuration::Configuration,
     input::OpenApiRequest,
     openapi::{curl_request::CurlRequest, validate_response::Response},
-    reporting::Reporting,
+    reporting::{executed_graph::ExecutedGraph, Reporting},
 };
 
 /// Instantiates a MySqLite reporter if desired by the configuration
@@ -99,6 +99,32 @@ impl MySqLite {
         drop(stmt);
         Ok(MySqLite { conn, run_id })
     }
+
+    /// Builds the graph of the request chains executed in this run. A request succeeded
+    /// if it got a response with a status below 400.
+    pub fn executed_graph(&self) -> anyhow::Result<ExecutedGraph> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT requests.inputid, requests.type, requests.path, responses.status
+                FROM requests LEFT JOIN responses ON responses.reqid = requests.id
+                WHERE requests.runid = ?
+                ORDER BY requests.id",
+            )
+            .context("Could not prepare query for executed requests")?;
+        let mut rows = stmt.query([self.run_id])?;
+        let mut graph = ExecutedGraph::new();
+        while let Some(row) = rows.next()? {
+            let status: Option<i64> = row.get(3)?;
+            graph.record(
+                row.get(0)?,
+                &row.get::<_, String>(1)?,
+                &row.get::<_, String>(2)?,
+                status.is_some_and(|status| status < 400),
+            );
+        }
+        Ok(graph)
+    }
 }
 
 impl Reporting<i64> for MySqLite {
@@ -179,3 +205,51 @@ impl Reporting<i64> for MySqLite {
             .expect("Could not insert coverage into database");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::MySqLite;
+
+    #[test]
+    fn test_executed_graph_has_edge_per_consecutive_pair() {
+        let reporter = MySqLite::new(Path::new(":memory:")).unwrap();
+        // One chain of three requests, and a chain of one request
+        let requests = [
+            (7, "POST", "/pets", 201),
+            (7, "GET", "/pets/{id}", 200),
+            (7, "DELETE", "/pets/{id}", 500),
+            (8, "GET", "/pets", 200),
+        ];
+        for (input_id, method, path, status) in requests {
+            reporter
+                .conn
+                .execute(
+                    "INSERT INTO requests (timestamp, path, type, inputid, runid) VALUES('now', ?, ?, ?, ?)",
+                    (path, method, input_id, reporter.run_id),
+                )
+                .unwrap();
+            reporter
+                .conn
+                .execute(
+                    "INSERT INTO responses (timestamp, status, reqid) VALUES('now', ?, ?)",
+                    (status, reporter.conn.last_insert_rowid()),
+                )
+                .unwrap();
+        }
+
+        let graph = reporter.executed_graph().unwrap();
+        let edges: Vec<_> = graph
+            .edges()
+            .map(|(source, target, outcomes)| (source, target, outcomes.failed))
+            .collect();
+        assert_eq!(
+            edges,
+            vec![
+                ("POST /pets", "GET /pets/{id}", 0),
+                ("GET /pets/{id}", "DELETE /pets/{id}", 1),
+            ]
+        );
+    }
+}
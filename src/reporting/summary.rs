@@ -0,0 +1,98 @@
+//! Summary of a fuzzing run, printed to stdout when the run ends. With the JSON output
+//! format, it is a single JSON object, so that scripts and CI jobs can read the results
+//! of a run without parsing the log.
+
+use std::{fmt::Display, time::Duration};
+
+use serde::Serialize;
+
+use crate::configuration::OutputFormat;
+
+/// The results of a fuzzing run
+#[derive(Debug, Serialize)]
+pub struct RunSummary {
+    pub line_coverage: u64,
+    pub line_total: u64,
+    pub endpoint_coverage: u64,
+    pub endpoint_total: u64,
+    /// Number of request chains executed
+    pub executions: u64,
+    /// Number of crashes found
+    pub crashes: usize,
+    pub elapsed_secs: f64,
+}
+
+impl RunSummary {
+    /// The summary of a run that took `elapsed`, given its coverage as (covered, total)
+    /// ratios
+    pub fn new(
+        (line_coverage, line_total): (u64, u64),
+        (endpoint_coverage, endpoint_total): (u64, u64),
+        executions: u64,
+        crashes: usize,
+        elapsed: Duration,
+    ) -> Self {
+        Self {
+            line_coverage,
+            line_total,
+            endpoint_coverage,
+            endpoint_total,
+            executions,
+            crashes,
+            elapsed_secs: elapsed.as_secs_f64(),
+        }
+    }
+}
+
+impl Display for RunSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Fuzzing finished after {:.1} s: {} executed sequences, {} crashes, coverage: {}/{}, endpoint coverage: {}/{}",
+            self.elapsed_secs,
+            self.executions,
+            self.crashes,
+            self.line_coverage,
+            self.line_total,
+            self.endpoint_coverage,
+            self.endpoint_total,
+        )
+    }
+}
+
+/// Prints the summary to stdout in the given output format
+pub fn print_summary(summary: &RunSummary, output_format: OutputFormat) {
+    match output_format {
+        OutputFormat::Json => match serde_json::to_string(summary) {
+            Ok(json) => println!("{json}"),
+            Err(e) => log::error!("Could not serialize the run summary: {e}"),
+        },
+        OutputFormat::HumanReadable => println!("{summary}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serde_json::json;
+
+    use super::RunSummary;
+
+    #[test]
+    fn test_json_summary_has_expected_keys() {
+        let summary = RunSummary::new((12, 40), (3, 5), 100, 2, Duration::from_millis(1500));
+        assert_eq!(
+            serde_json::to_value(&summary).unwrap(),
+            json!({
+                "line_coverage": 12,
+                "line_total": 40,
+                "endpoint_coverage": 3,
+                "endpoint_total": 5,
+                "executions": 100,
+                "crashes": 2,
+                "elapsed_secs": 1.5,
+            })
+        );
+    }
+}
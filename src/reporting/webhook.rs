@@ -0,0 +1,182 @@
+//! Reports bugs found by the fuzzer to a webhook, for integration with alerting systems.
+//! Each unique bug is posted once as a JSON object. Payloads are posted from a
+//! background thread, so that a slow webhook does not hold up the fuzzer.
+
+use std::{
+    sync::mpsc::{channel, Sender},
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use log::warn;
+use serde_json::{json, Value};
+
+use crate::{
+    configuration::Configuration,
+    input::OpenApiRequest,
+    openapi::{curl_request::CurlRequest, validate_response::Response},
+    reporting::{CrashSignatures, Reporting},
+};
+
+/// Instantiates a webhook reporter if a webhook is given in the configuration. The
+/// webhook is reached through the configured proxy, like the target.
+pub fn get_reporter(config: &Configuration) -> Result<Option<WebhookReporter>, anyhow::Error> {
+    config
+        .webhook
+        .as_ref()
+        .map(|url| {
+            let sink = HttpSink::new(url.clone(), config.proxy.as_deref())?;
+            Ok(WebhookReporter::new(Box::new(sink)))
+        })
+        .transpose()
+}
+
+/// Destination for the JSON payloads sent by the `WebhookReporter`.
+pub trait WebhookSink: Send {
+    fn post(&self, payload: &Value) -> Result<(), anyhow::Error>;
+}
+
+/// Sink that POSTs payloads to a URL.
+pub struct HttpSink {
+    url: String,
+    client: reqwest::blocking::Client,
+}
+
+impl HttpSink {
+    pub fn new(url: String, proxy: Option<&str>) -> Result<Self, anyhow::Error> {
+        let builder = reqwest::blocking::Client::builder().timeout(Duration::from_secs(10));
+        let client = crate::with_proxy(builder, proxy)?
+            .build()
+            .map_err(|e| anyhow!("Could not build HTTP client for the webhook: {e}"))?;
+        Ok(Self { url, client })
+    }
+}
+
+impl WebhookSink for HttpSink {
+    fn post(&self, payload: &Value) -> Result<(), anyhow::Error> {
+        self.client
+            .post(&self.url)
+            .json(payload)
+            .send()?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+/// Reporter that posts every new bug to a webhook. Bugs with the same signature
+/// (method, path and kind of error) as an earlier one are not posted again.
+pub struct WebhookReporter {
+    /// Hands payloads to the thread posting them, `None` once the reporter is dropped
+    payloads: Option<Sender<Value>>,
+    poster: Option<JoinHandle<()>>,
+    seen_signatures: CrashSignatures,
+}
+
+impl WebhookReporter {
+    pub fn new(sink: Box<dyn WebhookSink>) -> Self {
+        let (payloads, received) = channel::<Value>();
+        let poster = std::thread::spawn(move || {
+            for payload in received {
+                if let Err(err) = sink.post(&payload) {
+                    warn!("Could not post bug to webhook: {err}");
+                }
+            }
+        });
+        Self {
+            payloads: Some(payloads),
+            poster: Some(poster),
+            seen_signatures: CrashSignatures::default(),
+        }
+    }
+}
+
+impl Drop for WebhookReporter {
+    /// Waits for the payloads that were not posted yet
+    fn drop(&mut self) {
+        self.payloads.take();
+        if let Some(poster) = self.poster.take() {
+            let _ = poster.join();
+        }
+    }
+}
+
+impl Reporting<()> for WebhookReporter {
+    fn report_request(&self, _request: &OpenApiRequest, _curl: &CurlRequest, _input_id: usize) {}
+
+    fn report_response(&self, _response: &Response, _request_id: ()) {}
+
+    fn report_response_error(&self, _error: &str, _request_id: ()) {}
+
+    fn report_coverage(
+        &self,
+        _line_coverage: u64,
+        _line_coverage_total: u64,
+        _endpoint_coverage: u64,
+        _endpoint_coverage_total: u64,
+    ) {
+    }
+
+    fn report_crash(&self, request: &OpenApiRequest, _response: &Response, error_kind: &str) {
+        if !self.seen_signatures.insert(request, error_kind) {
+            return;
+        }
+        let payload = json!({
+            "method": request.method.to_string(),
+            "path": request.path,
+            "error_kind": error_kind,
+            "request": request,
+        });
+        if let Some(payloads) = &self.payloads {
+            let _ = payloads.send(payload);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use indexmap::IndexMap;
+    use serde_json::Value;
+
+    use super::{WebhookReporter, WebhookSink};
+    use crate::{
+        input::{Body, Method, OpenApiRequest},
+        openapi::validate_response::Response,
+        reporting::Reporting,
+    };
+
+    /// Sink that keeps all payloads posted to it.
+    struct RecordingSink(Arc<Mutex<Vec<Value>>>);
+
+    impl WebhookSink for RecordingSink {
+        fn post(&self, payload: &Value) -> Result<(), anyhow::Error> {
+            self.0.lock().unwrap().push(payload.clone());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_duplicate_crash_is_posted_once() {
+        let posted = Arc::new(Mutex::new(vec![]));
+        let reporter = WebhookReporter::new(Box::new(RecordingSink(posted.clone())));
+        let request = OpenApiRequest {
+            method: Method::Post,
+            path: "/pets".to_owned(),
+            body: Body::Empty,
+            parameters: IndexMap::new(),
+        };
+        let response = Response::new(reqwest::StatusCode::INTERNAL_SERVER_ERROR, vec![]);
+
+        reporter.report_crash(&request, &response, "http_500");
+        reporter.report_crash(&request, &response, "http_500");
+        // Waits until the payloads are posted
+        drop(reporter);
+
+        let posted = posted.lock().unwrap();
+        assert_eq!(posted.len(), 1);
+        assert_eq!(posted[0]["method"], "POST");
+        assert_eq!(posted[0]["path"], "/pets");
+        assert_eq!(posted[0]["error_kind"], "http_500");
+    }
+}
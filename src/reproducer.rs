@@ -1,107 +1,639 @@
-use std::path::Path;
 #[cfg(windows)]
 use std::ptr::write_volatile;
+use std::{
+    fmt::Display,
+    path::Path,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use libafl::inputs::Input;
 #[allow(unused_imports)]
 use libafl::Fuzzer; // This may be marked unused, but will make the compiler give you crucial error messages
 use log::{error, info, warn};
+use openapiv3::OpenAPI;
+use reqwest_cookie_store::CookieStoreMutex;
 
 use crate::{
+    authentication::Authentication,
     configuration::Configuration,
-    input::OpenApiInput,
+    initial_corpus::load_named_inputs,
+    input::{form::FormArrayStyle, OpenApiInput, OpenApiRequest},
     openapi::{
-        build_request::build_request_from_input,
+        build_request::{build_request_from_input, stream_body_chunked, HeaderBudget},
         curl_request::CurlRequest,
-        validate_response::{validate_response, Response},
+        examples::UserExamples,
+        validate_response::{
+            validate_rate_limit_headers, validate_response, validate_response_time, Response,
+        },
     },
     parameter_feedback::ParameterFeedback,
 };
 
+/// A problem found while sending an input: the request it was found for, what kind of
+/// problem it is, and a description
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Problem {
+    request: String,
+    kind: &'static str,
+    message: String,
+}
+
+impl Problem {
+    fn new(request: &OpenApiRequest, kind: &'static str, message: String) -> Self {
+        Self {
+            request: format!("{} {}", request.method, request.path),
+            kind,
+            message,
+        }
+    }
+
+    /// Whether the problems are found for the same request and are of the same kind
+    fn same_as(&self, other: &Self) -> bool {
+        self.request == other.request && self.kind == other.kind
+    }
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.request, self.message)
+    }
+}
+
+/// Everything needed to send inputs to the target and validate its responses
+struct Target<'a> {
+    api: &'a OpenAPI,
+    client: &'a reqwest::blocking::Client,
+    cookie_store: &'a Arc<CookieStoreMutex>,
+    authentication: &'a Authentication,
+    user_examples: UserExamples,
+    form_array_style: FormArrayStyle,
+    header_budget: Option<HeaderBudget>,
+    chunked_bodies: bool,
+    check_rate_limit_headers: bool,
+    check_response_constraints: bool,
+    slow_response: Option<Duration>,
+}
+
 /// Reproduces a given input file generated by the fuzzer (as a crash file or a corpus entry).
 pub fn reproduce(input_file: &Path) -> Result<()> {
     let config = Configuration::get().map_err(anyhow::Error::msg)?;
     crate::setup_logging(config);
-    let api = crate::get_api_spec(
-        config
-            .openapi_spec
-            .as_ref()
-            .ok_or_else(|| anyhow!("No OpenAPI specification given"))?,
-    )?;
+    let api = load_api(config)?;
     let inputs = OpenApiInput::from_file(input_file)?;
 
     let (authentication, cookie_store, client) = crate::build_http_client()?;
+    let target = Target::new(config, &api, &client, &cookie_store, &authentication)?;
 
     println!(
         "Input file {:?} contains {} inputs",
         input_file,
         inputs.0.len()
     );
+    target.send(&inputs);
+    Ok(())
+}
 
-    let mut parameter_feedback = ParameterFeedback::new(inputs.0.len());
+/// Sends every input in `inputs_dir` once, without mutation, and prints for each input
+/// whether the target handled all its requests according to the specification.
+pub fn replay(inputs_dir: &Path) -> Result<()> {
+    let config = Configuration::get().map_err(anyhow::Error::msg)?;
+    crate::setup_logging(config);
+    let api = load_api(config)?;
+    let named_inputs = load_named_inputs(inputs_dir)
+        .map_err(|err| anyhow!("Could not load the inputs to replay: {err}"))?;
 
-    for (request_index, request) in inputs.0.iter().enumerate() {
-        info!("\n-----\nSending request: \n{}", request);
+    let (authentication, cookie_store, client) = crate::build_http_client()?;
+    let target = Target::new(config, &api, &client, &cookie_store, &authentication)?;
 
-        let mut request = request.clone();
-        if let Err(error) = request.resolve_parameter_references(&parameter_feedback) {
-            error!(
-                "Cannot instantiate request: missing value for backreferenced parameter: {}",
-                error
-            );
-            continue;
-        };
+    let outcomes = target.replay(&named_inputs);
+    let failed = outcomes
+        .iter()
+        .filter(|(_, problems)| !problems.is_empty())
+        .count();
+    for (name, problems) in &outcomes {
+        if problems.is_empty() {
+            println!("PASS {name}");
+        } else {
+            println!("FAIL {name}: {}", problems.join("; "));
+        }
+    }
+    println!(
+        "{} of {} inputs passed",
+        outcomes.len() - failed,
+        outcomes.len()
+    );
+    if failed > 0 {
+        bail!("{failed} of {} inputs failed", outcomes.len());
+    }
+    Ok(())
+}
 
-        let request_built = match build_request_from_input(&client, &cookie_store, &api, &request)
-            .map(|builder| builder.build())
-        {
-            None => {
-                warn!("Could not generate a HTTP request from this input. Skipping ...");
-                continue;
-            }
-            Some(Err(message)) => {
-                error!("Error building the request: {}", message);
-                break;
-            }
-            Some(Ok(request)) => {
-                info!(
-                    "Converted to CURL command:\n{}",
-                    CurlRequest(&request, &authentication)
+/// Minimizes the crashes in `crashes_dir` by leaving out the requests that are not needed
+/// to trigger the same problem again, and writes the crashes that are different after
+/// minimization to `output_dir`.
+pub fn minimize_crashes(
+    config: &Configuration,
+    crashes_dir: &Path,
+    output_dir: &Path,
+) -> Result<()> {
+    let api = load_api(config)?;
+    let crashes = crate::minimize::load_crashes(crashes_dir)
+        .with_context(|| format!("Could not load the crashes from {}", crashes_dir.display()))?;
+    if crashes.is_empty() {
+        return Ok(());
+    }
+
+    let (authentication, cookie_store, client) = crate::build_http_client()?;
+    let target = Target::new(config, &api, &client, &cookie_store, &authentication)?;
+
+    let minimized: Vec<OpenApiInput> = crashes
+        .into_iter()
+        .map(|(name, crash)| {
+            info!("Minimizing crash {name}");
+            let Some(problem) = target.problems(&crash).into_iter().next() else {
+                warn!("Crash {name} does not reproduce, it is kept as it is");
+                return crash;
+            };
+            crate::minimize::minimize(crash, |reduced| {
+                target
+                    .problems(reduced)
+                    .iter()
+                    .any(|found| found.same_as(&problem))
+            })
+        })
+        .collect();
+    let unique = crate::minimize::dedup_by_name(minimized);
+
+    std::fs::create_dir_all(output_dir)?;
+    for (name, crash) in &unique {
+        crash
+            .to_file(output_dir.join(name))
+            .with_context(|| format!("Could not write minimized crash {name}"))?;
+    }
+    println!(
+        "Minimized the crashes to {} unique crashes in {}",
+        unique.len(),
+        output_dir.display()
+    );
+    Ok(())
+}
+
+/// Loads the specification from the configuration, with the configured servers
+fn load_api(config: &Configuration) -> Result<OpenAPI> {
+    let mut api = crate::get_api_spec(
+        config
+            .openapi_spec
+            .as_ref()
+            .ok_or_else(|| anyhow!("No OpenAPI specification given"))?,
+    )?;
+    crate::openapi::override_servers(&mut api, config.target.as_deref());
+    crate::openapi::set_server_variables(&mut api, &config.server_variables);
+    Ok(*api)
+}
+
+impl<'a> Target<'a> {
+    fn new(
+        config: &Configuration,
+        api: &'a OpenAPI,
+        client: &'a reqwest::blocking::Client,
+        cookie_store: &'a Arc<CookieStoreMutex>,
+        authentication: &'a Authentication,
+    ) -> Result<Self> {
+        Ok(Self {
+            api,
+            client,
+            cookie_store,
+            authentication,
+            user_examples: UserExamples::load(
+                config.format_values.as_deref(),
+                config.examples_override.as_deref(),
+            )?,
+            form_array_style: config.form_array_style,
+            header_budget: crate::header_budget(authentication)?,
+            chunked_bodies: config.chunked_bodies,
+            check_rate_limit_headers: config.check_rate_limit_headers,
+            check_response_constraints: config.check_response_constraints,
+            slow_response: config.slow_response_ms.map(Duration::from_millis),
+        })
+    }
+
+    /// Sends each of the named inputs once, and returns the problems found per input
+    fn replay(&self, inputs: &[(String, OpenApiInput)]) -> Vec<(String, Vec<String>)> {
+        inputs
+            .iter()
+            .map(|(name, input)| {
+                info!("\n=====\nReplaying input {name}");
+                (name.clone(), self.send(input))
+            })
+            .collect()
+    }
+
+    /// Sends the requests of an input in order, and returns the problems found: server
+    /// errors, responses that do not match the specification, and requests that could
+    /// not be sent.
+    fn send(&self, inputs: &OpenApiInput) -> Vec<String> {
+        self.problems(inputs)
+            .iter()
+            .map(Problem::to_string)
+            .collect()
+    }
+
+    /// Sends the requests of an input in order, like `send`, and returns the problems
+    /// found with the request and kind of each
+    fn problems(&self, inputs: &OpenApiInput) -> Vec<Problem> {
+        let mut problems = vec![];
+        let mut parameter_feedback = ParameterFeedback::new(inputs.0.len());
+
+        for (request_index, request) in inputs.0.iter().enumerate() {
+            info!("\n-----\nSending request: \n{}", request);
+
+            let mut request = request.clone();
+            if let Err(error) = request.resolve_parameter_references(&parameter_feedback) {
+                error!(
+                    "Cannot instantiate request: missing value for backreferenced parameter: {}",
+                    error
                 );
-                request
-            }
-        };
+                continue;
+            };
+
+            let mut request_built = match build_request_from_input(
+                self.client,
+                self.cookie_store,
+                self.authentication,
+                self.api,
+                &self.user_examples,
+                self.form_array_style,
+                &request,
+            )
+            .map(|builder| builder.build())
+            {
+                None => {
+                    warn!("Could not generate a HTTP request from this input. Skipping ...");
+                    continue;
+                }
+                Some(Err(message)) => {
+                    error!("Error building the request: {}", message);
+                    problems.push(Problem::new(
+                        &request,
+                        "build_error",
+                        format!("error building the request: {message}"),
+                    ));
+                    break;
+                }
+                Some(Ok(mut request_built)) => {
+                    if let Some(header_budget) = &self.header_budget {
+                        header_budget.enforce(self.api, &request, &mut request_built);
+                    }
+                    info!(
+                        "Converted to CURL command:\n{}",
+                        CurlRequest(&request_built, self.authentication, Some(self.cookie_store))
+                    );
+                    request_built
+                }
+            };
 
-        match client.execute(request_built) {
-            Ok(response) => {
-                let response: Response = response.into();
-                if response.status().is_server_error() {
-                    warn!("Crash reported by server: {}", response.status());
-                    if let Ok(text) = response.text() {
-                        info!("Response contents printed below: \n{}", text)
+            if self.chunked_bodies {
+                stream_body_chunked(&mut request_built);
+            }
+            let sent = Instant::now();
+            match self.authentication.execute(self.client, request_built) {
+                Ok(response) => {
+                    let response: Response = response.into();
+                    if let Some(threshold) = self.slow_response {
+                        if let Err(e) = validate_response_time(sent.elapsed(), threshold) {
+                            warn!("Validation error: {}", e);
+                            problems.push(Problem::new(&request, e.kind(), e.to_string()));
+                        }
+                    }
+                    if self.check_rate_limit_headers {
+                        if let Err(e) = validate_rate_limit_headers(&response) {
+                            warn!("Validation error: {}", e);
+                            problems.push(Problem::new(&request, e.kind(), e.to_string()));
+                        }
+                    }
+                    if response.status().is_server_error() {
+                        warn!("Crash reported by server: {}", response.status());
+                        if let Ok(text) = response.text() {
+                            info!("Response contents printed below: \n{}", text)
+                        }
+                        problems.push(Problem::new(
+                            &request,
+                            "server_error",
+                            format!("server error {}", response.status()),
+                        ));
+                        break;
+                    } else {
+                        info!("Request successful ({})", response.status());
+                        match validate_response(
+                            self.api,
+                            &request,
+                            &response,
+                            self.check_response_constraints,
+                        ) {
+                            Ok(()) => info!("Response matches specification"),
+                            Err(e) => {
+                                warn!("Validation error: {}", e);
+                                problems.push(Problem::new(&request, e.kind(), e.to_string()));
+                            }
+                        }
+                        if let Ok(text) = response.text() {
+                            info!("Response contents printed below: \n{}", text)
+                        }
+                        if response.status().is_success() {
+                            parameter_feedback.process_response(request_index, response);
+                        }
                     }
+                }
+                Err(e) => {
+                    error!("Error sending the request: {}", e);
+                    problems.push(Problem::new(
+                        &request,
+                        "send_error",
+                        format!("error sending the request: {e}"),
+                    ));
                     break;
+                }
+            }
+            parameter_feedback.process_post_request(request_index, request);
+        }
+        problems
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+        sync::Arc,
+    };
+
+    use indexmap::IndexMap;
+    use openapiv3::OpenAPI;
+    use reqwest_cookie_store::CookieStoreMutex;
+    use serde_json::json;
+
+    use super::Target;
+    use crate::{
+        authentication::Authentication,
+        input::{
+            form::FormArrayStyle, parameter::ParameterKind, Body, Method, OpenApiInput,
+            OpenApiRequest,
+        },
+        openapi::{
+            examples::{example_from_qualified_operation, UserExamples},
+            QualifiedOperation,
+        },
+    };
+
+    /// Answers `count` requests: those to /pets with a pet, others with a server error
+    fn stub_server(listener: TcpListener, count: usize) -> std::thread::JoinHandle<()> {
+        std::thread::spawn(move || {
+            for stream in listener.incoming().take(count) {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                // Skip the headers, the requests have no body
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+                let (status, body) = if request_line.starts_with("GET /pets") {
+                    ("200 OK", r#"{"name": "Rex"}"#)
                 } else {
-                    info!("Request successful ({})", response.status());
-                    match validate_response(&api, &request, &response) {
-                        Ok(()) => info!("Response matches specification"),
-                        Err(e) => warn!("Validation error: {}", e),
-                    }
-                    if let Ok(text) = response.text() {
-                        info!("Response contents printed below: \n{}", text)
+                    ("500 Internal Server Error", "")
+                };
+                write!(
+                    stream,
+                    "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                )
+                .unwrap();
+            }
+        })
+    }
+
+    #[test]
+    fn test_replay_reports_failing_inputs() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let api: OpenAPI = serde_json::from_value(json!({
+            "openapi": "3.0.0",
+            "info": {"title": "pets", "version": "1"},
+            "servers": [{"url": format!("http://{}", listener.local_addr().unwrap())}],
+            "paths": {
+                "/pets": {
+                    "get": {
+                        "responses": {
+                            "200": {
+                                "description": "A pet",
+                                "content": {
+                                    "application/json": {
+                                        "schema": {
+                                            "type": "object",
+                                            "properties": {"name": {"type": "string"}},
+                                            "required": ["name"]
+                                        }
+                                    }
+                                }
+                            }
+                        }
                     }
-                    if response.status().is_success() {
-                        parameter_feedback.process_response(request_index, response);
+                },
+                "/crash": {"get": {"responses": {"200": {"description": "Never"}}}}
+            }
+        }))
+        .unwrap();
+        let get = |path: &str| OpenApiRequest {
+            method: Method::Get,
+            path: path.to_owned(),
+            body: Body::Empty,
+            parameters: IndexMap::new(),
+        };
+        let inputs = vec![
+            (
+                "0".to_owned(),
+                OpenApiInput(vec![get("/pets"), get("/pets")]),
+            ),
+            (
+                "1".to_owned(),
+                OpenApiInput(vec![get("/crash"), get("/pets")]),
+            ),
+        ];
+        // The second input stops at the crash
+        let server = stub_server(listener, 3);
+
+        let client = reqwest::blocking::Client::new();
+        let cookie_store = Arc::new(CookieStoreMutex::default());
+        let target = Target {
+            api: &api,
+            client: &client,
+            cookie_store: &cookie_store,
+            authentication: &Authentication::None,
+            user_examples: UserExamples::default(),
+            form_array_style: FormArrayStyle::default(),
+            header_budget: None,
+            chunked_bodies: false,
+            check_rate_limit_headers: false,
+            check_response_constraints: false,
+            slow_response: None,
+        };
+        let outcomes = target.replay(&inputs);
+        server.join().unwrap();
+
+        assert_eq!(outcomes[0], ("0".to_owned(), vec![]));
+        assert_eq!(outcomes[1].0, "1");
+        assert_eq!(outcomes[1].1.len(), 1);
+        assert!(
+            outcomes[1].1[0].contains("/crash: server error 500"),
+            "{:?}",
+            outcomes[1]
+        );
+    }
+
+    #[test]
+    fn test_cookie_set_by_server_is_sent_by_next_request() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let api: OpenAPI = serde_json::from_value(json!({
+            "openapi": "3.0.0",
+            "info": {"title": "session", "version": "1"},
+            "servers": [{"url": format!("http://{}", listener.local_addr().unwrap())}],
+            "paths": {
+                "/login": {"post": {"responses": {"204": {"description": "Logged in"}}}},
+                "/profile": {"get": {"responses": {"200": {"description": "The profile"}}}}
+            }
+        }))
+        .unwrap();
+        // Sets a session cookie on login, and requires it for the profile
+        let server = std::thread::spawn(move || {
+            for stream in listener.incoming().take(2) {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut has_session = false;
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    has_session |= line.to_ascii_lowercase().starts_with("cookie:")
+                        && line.contains("session=s3cr3t");
+                    line.clear();
+                }
+                let response = match (request_line.starts_with("POST /login"), has_session) {
+                    (true, _) => "204 No Content\r\nSet-Cookie: session=s3cr3t; Path=/",
+                    (false, true) => "200 OK",
+                    (false, false) => "401 Unauthorized",
+                };
+                write!(
+                    stream,
+                    "HTTP/1.1 {response}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                )
+                .unwrap();
+            }
+        });
+
+        let cookie_store = Arc::new(CookieStoreMutex::default());
+        let client = crate::with_cookie_store(&cookie_store).build().unwrap();
+        let target = Target {
+            api: &api,
+            client: &client,
+            cookie_store: &cookie_store,
+            authentication: &Authentication::None,
+            user_examples: UserExamples::default(),
+            form_array_style: FormArrayStyle::default(),
+            header_budget: None,
+            chunked_bodies: false,
+            check_rate_limit_headers: false,
+            check_response_constraints: false,
+            slow_response: None,
+        };
+        let request = |method, path: &str| OpenApiRequest {
+            method,
+            path: path.to_owned(),
+            body: Body::Empty,
+            parameters: IndexMap::new(),
+        };
+        let problems = target.send(&OpenApiInput(vec![
+            request(Method::Post, "/login"),
+            request(Method::Get, "/profile"),
+        ]));
+        server.join().unwrap();
+
+        assert_eq!(problems, Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_cookie_parameter_is_sent_in_cookie_header() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let api: OpenAPI = serde_json::from_value(json!({
+            "openapi": "3.0.0",
+            "info": {"title": "cart", "version": "1"},
+            "servers": [{"url": format!("http://{}", listener.local_addr().unwrap())}],
+            "paths": {
+                "/cart": {
+                    "get": {
+                        "parameters": [{
+                            "name": "cartId",
+                            "in": "cookie",
+                            "required": true,
+                            "schema": {"type": "string"},
+                            "example": "c4rt"
+                        }],
+                        "responses": {"200": {"description": "The cart"}}
                     }
                 }
             }
-            Err(e) => {
-                error!("Error sending the request: {}", e);
-                break;
+        }))
+        .unwrap();
+        // Answers a single request, and returns its Cookie headers
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut cookies = vec![];
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 2 {
+                if line.to_ascii_lowercase().starts_with("cookie:") {
+                    cookies.push(line.trim_end().to_owned());
+                }
+                line.clear();
             }
-        }
-        parameter_feedback.process_post_request(request_index, request);
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+            )
+            .unwrap();
+            cookies
+        });
+
+        let (path, path_item) = api.paths.paths.first().unwrap();
+        let path_item = path_item.as_item().unwrap();
+        let operation =
+            QualifiedOperation::new(path, "get", path_item.get.as_ref().unwrap(), path_item)
+                .unwrap();
+        let request = example_from_qualified_operation(&api, &UserExamples::default(), operation);
+        assert!(request
+            .parameters
+            .contains_key(&("cartId".to_owned(), ParameterKind::Cookie)));
+
+        let cookie_store = Arc::new(CookieStoreMutex::default());
+        let client = crate::with_cookie_store(&cookie_store).build().unwrap();
+        let target = Target {
+            api: &api,
+            client: &client,
+            cookie_store: &cookie_store,
+            authentication: &Authentication::None,
+            user_examples: UserExamples::default(),
+            form_array_style: FormArrayStyle::default(),
+            header_budget: None,
+            chunked_bodies: false,
+            check_rate_limit_headers: false,
+            check_response_constraints: false,
+            slow_response: None,
+        };
+        let problems = target.send(&OpenApiInput(vec![request]));
+
+        assert_eq!(problems, Vec::<String>::new());
+        assert_eq!(server.join().unwrap(), ["cookie: cartId=c4rt"]);
     }
-    Ok(())
 }
@@ -0,0 +1,136 @@
+//! Retries of requests that failed for reasons that are likely transient, such as a reset
+//! connection or a 502 from a load balancer in front of the target. Without retries, a
+//! flaky target makes the fuzzer record such failures as timeouts or crashes.
+//!
+//! A request is retried with exponential backoff, and only the result of the last attempt
+//! is processed, so a request that is retried is still one request to the fuzzer.
+
+use std::time::Duration;
+
+use reqwest::blocking::Request;
+
+/// Delay before the first retry, doubled for every next retry
+const BASE_DELAY: Duration = Duration::from_millis(100);
+
+/// Status codes that are retried if none are configured
+pub const DEFAULT_RETRY_STATUSES: [u16; 3] = [502, 503, 504];
+
+/// How often, and on which outcomes, requests are retried
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    retries: u32,
+    statuses: Vec<u16>,
+}
+
+/// The outcome of an attempt to send a request, as far as retrying is concerned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// The target responded with this status code
+    Status(u16),
+    /// No connection could be made to the target
+    ConnectionFailed,
+    /// Any other error, such as a timeout, which is not retried
+    OtherError,
+}
+
+impl From<&reqwest::Result<reqwest::blocking::Response>> for Outcome {
+    fn from(result: &reqwest::Result<reqwest::blocking::Response>) -> Self {
+        match result {
+            Ok(response) => Self::Status(response.status().as_u16()),
+            Err(err) if err.is_connect() => Self::ConnectionFailed,
+            Err(_) => Self::OtherError,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Retries requests up to `retries` times if they fail to connect or get a response
+    /// with one of the `statuses`
+    pub fn new(retries: u32, statuses: Vec<u16>) -> Self {
+        Self { retries, statuses }
+    }
+
+    /// How long to wait before retrying a request of which `attempt` attempts, counting
+    /// from 1, have been made, the last of which had `outcome`. `None` if the request is
+    /// not retried.
+    pub fn retry_delay(&self, attempt: u32, outcome: Outcome) -> Option<Duration> {
+        if attempt > self.retries {
+            return None;
+        }
+        let retryable = match outcome {
+            Outcome::Status(status) => self.statuses.contains(&status),
+            Outcome::ConnectionFailed => true,
+            Outcome::OtherError => false,
+        };
+        retryable.then(|| BASE_DELAY.saturating_mul(2u32.saturating_pow(attempt - 1)))
+    }
+
+    /// Sends the request with `send` and retries it according to the policy, returning
+    /// the result of the last attempt. Requests with a streamed body can not be sent
+    /// again, and are not retried.
+    pub fn execute<F>(
+        &self,
+        mut request: Request,
+        mut send: F,
+    ) -> reqwest::Result<reqwest::blocking::Response>
+    where
+        F: FnMut(Request) -> reqwest::Result<reqwest::blocking::Response>,
+    {
+        let mut attempt = 1;
+        loop {
+            let retry = request.try_clone();
+            let result = send(request);
+            match (self.retry_delay(attempt, Outcome::from(&result)), retry) {
+                (Some(delay), Some(retry)) => {
+                    log::debug!("Retrying the request in {} ms", delay.as_millis());
+                    std::thread::sleep(delay);
+                    request = retry;
+                    attempt += 1;
+                }
+                _ => return result,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Outcome, RetryPolicy};
+
+    #[test]
+    fn test_retry_delay_backs_off_until_retries_are_used() {
+        let policy = RetryPolicy::new(3, vec![502, 503]);
+        // The outcomes of a request that is retried three times, and then given up on
+        let outcomes = [
+            Outcome::Status(502),
+            Outcome::ConnectionFailed,
+            Outcome::Status(503),
+            Outcome::Status(502),
+        ];
+        let delays: Vec<Option<Duration>> = outcomes
+            .into_iter()
+            .zip(1..)
+            .map(|(outcome, attempt)| policy.retry_delay(attempt, outcome))
+            .collect();
+        assert_eq!(
+            delays,
+            [
+                Some(Duration::from_millis(100)),
+                Some(Duration::from_millis(200)),
+                Some(Duration::from_millis(400)),
+                None
+            ]
+        );
+
+        // Other statuses and errors are final
+        assert_eq!(policy.retry_delay(1, Outcome::Status(200)), None);
+        assert_eq!(policy.retry_delay(1, Outcome::Status(500)), None);
+        assert_eq!(policy.retry_delay(1, Outcome::OtherError), None);
+        assert_eq!(
+            RetryPolicy::new(0, vec![502]).retry_delay(1, Outcome::Status(502)),
+            None
+        );
+    }
+}
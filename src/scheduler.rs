@@ -0,0 +1,222 @@
+//! The corpus scheduler decides which corpus entry is fuzzed next. The default is the
+//! power schedule, which walks the corpus as a queue and skips entries that are not
+//! favoured by the minimizer most of the time. The other strategies choose the next
+//! entry themselves, but keep the wrapped power schedule informed of all corpus changes,
+//! so that the metadata used by the calibration and power stages stays up to date.
+
+use indexmap::IndexMap;
+use libafl::{
+    corpus::{Corpus, CorpusId, HasTestcase, Testcase},
+    schedulers::{on_next_metadata_default, RemovableScheduler, Scheduler},
+    state::HasCorpus,
+    Error,
+};
+use libafl_bolts::tuples::MatchName;
+
+use crate::{
+    configuration::SchedulerStrategy,
+    input::{Method, OpenApiInput},
+};
+
+/// Scheduler that follows the configured strategy, on top of the power schedule `base`.
+#[derive(Debug)]
+pub struct CorpusScheduler<CS> {
+    strategy: SchedulerStrategy,
+    base: CS,
+    /// Number of entries scheduled by the endpoint round-robin strategy so far
+    round: usize,
+}
+
+impl<CS> CorpusScheduler<CS> {
+    pub fn new(strategy: SchedulerStrategy, base: CS) -> Self {
+        Self {
+            strategy,
+            base,
+            round: 0,
+        }
+    }
+}
+
+/// The endpoint whose operation an input starts with
+fn endpoint(testcase: &Testcase<OpenApiInput>) -> Option<(Method, String)> {
+    let request = testcase.input().as_ref()?.0.first()?;
+    Some((request.method, request.path.clone()))
+}
+
+/// Takes every endpoint in turn, in the order in which they first appear in the
+/// corpus, and the entries of an endpoint in turn as well. The `round`th entry
+/// scheduled is returned.
+fn endpoint_round_robin<C>(corpus: &C, round: usize) -> Result<CorpusId, Error>
+where
+    C: Corpus<Input = OpenApiInput>,
+{
+    let mut endpoints: IndexMap<Option<(Method, String)>, Vec<CorpusId>> = IndexMap::new();
+    for id in corpus.ids() {
+        let endpoint = endpoint(&corpus.get(id)?.borrow());
+        endpoints.entry(endpoint).or_default().push(id);
+    }
+    let entries = &endpoints[round % endpoints.len()];
+    Ok(entries[(round / endpoints.len()) % entries.len()])
+}
+
+/// The entry that was fuzzed least often; the newest of those, as it is the one that
+/// most recently found new coverage.
+fn novelty<C>(corpus: &C) -> Result<CorpusId, Error>
+where
+    C: Corpus<Input = OpenApiInput>,
+{
+    let mut least_fuzzed = None;
+    for id in corpus.ids() {
+        let scheduled_count = corpus.get(id)?.borrow().scheduled_count();
+        if least_fuzzed.is_none_or(|(_, least)| scheduled_count <= least) {
+            least_fuzzed = Some((id, scheduled_count));
+        }
+    }
+    least_fuzzed
+        .map(|(id, _)| id)
+        .ok_or_else(|| Error::empty("No entries in corpus"))
+}
+
+impl<CS, S> Scheduler<OpenApiInput, S> for CorpusScheduler<CS>
+where
+    CS: Scheduler<OpenApiInput, S>,
+    S: HasCorpus + HasTestcase,
+    S::Corpus: Corpus<Input = OpenApiInput>,
+{
+    fn on_add(&mut self, state: &mut S, id: CorpusId) -> Result<(), Error> {
+        self.base.on_add(state, id)
+    }
+
+    fn on_evaluation<OT>(
+        &mut self,
+        state: &mut S,
+        input: &OpenApiInput,
+        observers: &OT,
+    ) -> Result<(), Error>
+    where
+        OT: MatchName,
+    {
+        self.base.on_evaluation(state, input, observers)
+    }
+
+    fn next(&mut self, state: &mut S) -> Result<CorpusId, Error> {
+        if state.corpus().count() == 0 {
+            return Err(Error::empty("No entries in corpus"));
+        }
+        let id = match self.strategy {
+            SchedulerStrategy::Powerschedule => return self.base.next(state),
+            SchedulerStrategy::EndpointRr => {
+                self.round += 1;
+                endpoint_round_robin(state.corpus(), self.round - 1)?
+            }
+            SchedulerStrategy::Novelty => novelty(state.corpus())?,
+        };
+        self.set_current_scheduled(state, Some(id))?;
+        Ok(id)
+    }
+
+    fn set_current_scheduled(
+        &mut self,
+        state: &mut S,
+        next_id: Option<CorpusId>,
+    ) -> Result<(), Error> {
+        on_next_metadata_default(state)?;
+        *state.corpus_mut().current_mut() = next_id;
+        Ok(())
+    }
+}
+
+impl<CS, S> RemovableScheduler<OpenApiInput, S> for CorpusScheduler<CS>
+where
+    CS: RemovableScheduler<OpenApiInput, S>,
+{
+    fn on_remove(
+        &mut self,
+        state: &mut S,
+        id: CorpusId,
+        testcase: &Option<Testcase<OpenApiInput>>,
+    ) -> Result<(), Error> {
+        self.base.on_remove(state, id, testcase)
+    }
+
+    fn on_replace(
+        &mut self,
+        state: &mut S,
+        id: CorpusId,
+        prev: &Testcase<OpenApiInput>,
+    ) -> Result<(), Error> {
+        self.base.on_replace(state, id, prev)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+    use libafl::{
+        corpus::{Corpus, InMemoryCorpus, SchedulerTestcaseMetadata, Testcase},
+        schedulers::{QueueScheduler, Scheduler},
+        state::HasCorpus,
+        HasMetadata,
+    };
+    use libafl_bolts::rands::StdRand;
+    use openapiv3::OpenAPI;
+    use serde_json::json;
+
+    use super::CorpusScheduler;
+    use crate::{
+        configuration::SchedulerStrategy,
+        input::{Body, Method, OpenApiInput, OpenApiRequest},
+        state::OpenApiFuzzerState,
+    };
+
+    /// The ids of the first `count` entries scheduled from a corpus with an input for
+    /// each of the paths
+    fn schedule(strategy: SchedulerStrategy, paths: &[&str], count: usize) -> Vec<usize> {
+        let api: OpenAPI = serde_json::from_value(json!({
+            "openapi": "3.0.0",
+            "info": {"title": "empty", "version": "1"},
+            "paths": {}
+        }))
+        .unwrap();
+        let mut state = OpenApiFuzzerState::new(
+            StdRand::with_seed(0),
+            InMemoryCorpus::<OpenApiInput>::new(),
+            InMemoryCorpus::<OpenApiInput>::new(),
+            &mut (),
+            &mut (),
+            api,
+        )
+        .unwrap();
+        for path in paths {
+            let input = OpenApiInput(vec![OpenApiRequest {
+                method: Method::Get,
+                path: path.to_string(),
+                body: Body::Empty,
+                parameters: IndexMap::new(),
+            }]);
+            let mut testcase = Testcase::new(input);
+            testcase.add_metadata(SchedulerTestcaseMetadata::new(0));
+            state.corpus_mut().add(testcase).unwrap();
+        }
+
+        let mut scheduler = CorpusScheduler::new(strategy, QueueScheduler::new());
+        (0..count)
+            .map(|_| scheduler.next(&mut state).unwrap().into())
+            .collect()
+    }
+
+    #[test]
+    fn test_endpoint_round_robin_takes_endpoints_in_turn() {
+        let paths = ["/a", "/a", "/b", "/c", "/a"];
+        // The base scheduler walks the corpus in order
+        assert_eq!(
+            schedule(SchedulerStrategy::Powerschedule, &paths, 6),
+            vec![0, 1, 2, 3, 4, 0]
+        );
+        // Round robin alternates between the endpoints, and between their entries
+        assert_eq!(
+            schedule(SchedulerStrategy::EndpointRr, &paths, 9),
+            vec![0, 2, 3, 1, 2, 3, 4, 2, 3]
+        );
+    }
+}
@@ -64,6 +64,8 @@ pub struct OpenApiFuzzerState<I, C, R, SC> {
     remaining_initial_files: Option<Vec<PathBuf>>,
     phantom: PhantomData<I>,
     api: OpenAPI,
+    /// Tokens from the specification, used by the dictionary mutator
+    dictionary: Vec<String>,
 }
 
 impl<I, C, R, SC> State for OpenApiFuzzerState<I, C, R, SC>
@@ -353,6 +355,8 @@ where
         C: Serialize + DeserializeOwned,
         SC: Serialize + DeserializeOwned,
     {
+        let dictionary = crate::openapi_mutator::dictionary::dictionary_from_api(&api);
+        log::debug!("Built a dictionary of {} tokens", dictionary.len());
         let mut state = Self {
             rand,
             executions: 0,
@@ -367,6 +371,7 @@ where
             remaining_initial_files: None,
             phantom: PhantomData,
             api,
+            dictionary,
             current_stage: None,
             current_corpus_id: None,
             last_found_time: Duration::default(),
@@ -397,3 +402,73 @@ where
         (&mut self.rand, &self.api)
     }
 }
+
+// Necessary because of borrow checking conflicts
+pub trait HasRandAndDictionary {
+    type Rand: Rand;
+    fn rand_mut_and_dictionary(&mut self) -> (&mut Self::Rand, &[String]);
+}
+
+impl<C, I, R, SC> HasRandAndDictionary for OpenApiFuzzerState<I, C, R, SC>
+where
+    I: Input,
+    C: Corpus,
+    R: Rand,
+    SC: Corpus,
+{
+    type Rand = <Self as HasRand>::Rand;
+    fn rand_mut_and_dictionary(&mut self) -> (&mut Self::Rand, &[String]) {
+        (&mut self.rand, &self.dictionary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl::{corpus::InMemoryCorpus, state::HasRand};
+    use libafl_bolts::rands::StdRand;
+    use openapiv3::OpenAPI;
+
+    use super::OpenApiFuzzerState;
+    use crate::input::{new_rand_input, OpenApiInput};
+
+    type TestState = OpenApiFuzzerState<
+        OpenApiInput,
+        InMemoryCorpus<OpenApiInput>,
+        StdRand,
+        InMemoryCorpus<OpenApiInput>,
+    >;
+
+    fn state_with_seed(seed: u64) -> TestState {
+        let api: OpenAPI = serde_json::from_value(serde_json::json!({
+            "openapi": "3.0.0",
+            "info": {"title": "seeded", "version": "1"},
+            "paths": {}
+        }))
+        .unwrap();
+        OpenApiFuzzerState::new(
+            StdRand::with_seed(seed),
+            InMemoryCorpus::new(),
+            InMemoryCorpus::new(),
+            &mut (),
+            &mut (),
+            api,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_same_seed_gives_same_inputs() {
+        let mut first = state_with_seed(1234);
+        let mut second = state_with_seed(1234);
+        let mut other = state_with_seed(4321);
+
+        let inputs = |state: &mut TestState| {
+            (0..16)
+                .map(|_| new_rand_input(state.rand_mut()))
+                .collect::<Vec<_>>()
+        };
+        let first_inputs = inputs(&mut first);
+        assert_eq!(first_inputs, inputs(&mut second));
+        assert_ne!(first_inputs, inputs(&mut other));
+    }
+}
@@ -0,0 +1,58 @@
+//! A state file lets an interrupted campaign continue where it left off. The corpus
+//! already writes its queue to `./queue` while fuzzing, which can be loaded again with
+//! `--initial-corpus queue`; the state file holds what is only kept in memory: the
+//! endpoint coverage found so far and the state of the random number generator.
+//!
+//! The state is saved when the campaign ends on its time-out or on Ctrl-C, and loaded
+//! at the start of the next campaign with the same state file, if the file exists.
+
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, ErrorKind},
+    path::Path,
+};
+
+use anyhow::Context;
+use libafl_bolts::rands::StdRand;
+use serde::{Deserialize, Serialize};
+
+use crate::coverage_clients::endpoint::SavedEndpointCoverage;
+
+/// The state of a campaign that is not stored in the corpus
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FuzzerStateFile {
+    pub rand: StdRand,
+    pub endpoint_coverage: SavedEndpointCoverage,
+}
+
+impl FuzzerStateFile {
+    /// Loads the state saved by an earlier campaign, or None if there is no state file yet
+    pub fn load(path: &Path) -> Result<Option<Self>, anyhow::Error> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => {
+                return Err(err).with_context(|| {
+                    format!("Could not open state file {}", path.to_string_lossy())
+                })
+            }
+        };
+        serde_json::from_reader(BufReader::new(file))
+            .map(Some)
+            .with_context(|| format!("Could not read state file {}", path.to_string_lossy()))
+    }
+
+    /// Saves the state, replacing the state file only once it is written completely
+    pub fn save(&self, path: &Path) -> Result<(), anyhow::Error> {
+        let partial_path = path.with_extension("partial");
+        let file = File::create(&partial_path).with_context(|| {
+            format!(
+                "Could not create state file {}",
+                partial_path.to_string_lossy()
+            )
+        })?;
+        serde_json::to_writer(BufWriter::new(file), self)?;
+        std::fs::rename(&partial_path, path)
+            .with_context(|| format!("Could not write state file {}", path.to_string_lossy()))
+    }
+}
@@ -0,0 +1,186 @@
+//! Think time mimics the pacing of real clients, which wait a while between the
+//! requests they send. Some bugs, e.g. around session expiry or caching, only show up
+//! under such pacing. The delay before each request of a chain, except the first, is
+//! drawn from a distribution with a seeded random number generator, so that the delays
+//! of a run can be reproduced.
+
+use std::{fmt::Display, str::FromStr, time::Duration};
+
+use anyhow::{bail, Context};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+/// Distribution of the delay between requests, in milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Distribution {
+    /// Always the same delay
+    Constant(f64),
+    /// Uniformly distributed between the two bounds
+    Uniform(f64, f64),
+    /// Exponentially distributed with the given mean
+    Exponential(f64),
+}
+
+/// The think time distribution and its seed, written as `constant:<ms>`,
+/// `uniform:<min ms>-<max ms>` or `exponential:<mean ms>`, optionally followed by
+/// `,seed=<n>`. The seed defaults to 0.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize)]
+#[serde(try_from = "String")]
+pub struct ThinkTime {
+    pub distribution: Distribution,
+    pub seed: u64,
+}
+
+// Parsing rejects NaN, so the delays are always comparable
+impl Eq for ThinkTime {}
+
+impl ThinkTime {
+    /// A sampler of delays from this distribution
+    pub fn sampler(&self) -> ThinkTimeSampler {
+        ThinkTimeSampler {
+            distribution: self.distribution,
+            rng: StdRng::seed_from_u64(self.seed),
+        }
+    }
+}
+
+/// Draws the delays between requests.
+#[derive(Debug)]
+pub struct ThinkTimeSampler {
+    distribution: Distribution,
+    rng: StdRng,
+}
+
+impl ThinkTimeSampler {
+    /// The next delay, in milliseconds
+    fn sample_millis(&mut self) -> f64 {
+        match self.distribution {
+            Distribution::Constant(millis) => millis,
+            Distribution::Uniform(min, max) if min < max => self.rng.gen_range(min..=max),
+            Distribution::Uniform(min, _) => min,
+            // Inverse transform sampling; 1 - u is in (0, 1], so the logarithm is finite
+            Distribution::Exponential(mean) => -mean * (1.0 - self.rng.gen::<f64>()).ln(),
+        }
+    }
+
+    /// The next delay
+    pub fn sample(&mut self) -> Duration {
+        Duration::from_secs_f64(self.sample_millis() / 1000.0)
+    }
+}
+
+/// Parses a number of milliseconds, which must be finite and not negative.
+fn parse_millis(millis: &str, think_time: &str) -> Result<f64, anyhow::Error> {
+    let value: f64 = millis.trim().parse().with_context(|| {
+        format!("Invalid number of milliseconds '{millis}' in think time '{think_time}'")
+    })?;
+    if !value.is_finite() || value < 0.0 {
+        bail!("Think time '{think_time}' needs delays of at least 0 milliseconds");
+    }
+    Ok(value)
+}
+
+impl FromStr for ThinkTime {
+    type Err = anyhow::Error;
+
+    fn from_str(think_time: &str) -> Result<Self, Self::Err> {
+        let (distribution, seed) = match think_time.split_once(',') {
+            Some((distribution, seed)) => {
+                let Some(seed) = seed.trim().strip_prefix("seed=") else {
+                    bail!(
+                        "Expected ',seed=<n>' after the distribution in think time '{think_time}'"
+                    );
+                };
+                let seed = seed
+                    .parse()
+                    .with_context(|| format!("Invalid seed in think time '{think_time}'"))?;
+                (distribution, seed)
+            }
+            None => (think_time, 0),
+        };
+        let Some((name, params)) = distribution.split_once(':') else {
+            bail!("Expected '<distribution>:<parameters>' in think time '{think_time}'");
+        };
+        let distribution = match name.trim().to_ascii_lowercase().as_str() {
+            "constant" => Distribution::Constant(parse_millis(params, think_time)?),
+            "uniform" => {
+                let Some((min, max)) = params.split_once('-') else {
+                    bail!("Expected 'uniform:<min>-<max>' in think time '{think_time}'");
+                };
+                let (min, max) = (
+                    parse_millis(min, think_time)?,
+                    parse_millis(max, think_time)?,
+                );
+                if min > max {
+                    bail!("The minimum exceeds the maximum in think time '{think_time}'");
+                }
+                Distribution::Uniform(min, max)
+            }
+            "exponential" => Distribution::Exponential(parse_millis(params, think_time)?),
+            _ => bail!(
+                "Unknown distribution '{name}' in think time '{think_time}', expected constant, uniform or exponential"
+            ),
+        };
+        Ok(Self { distribution, seed })
+    }
+}
+
+impl TryFrom<String> for ThinkTime {
+    type Error = anyhow::Error;
+
+    fn try_from(think_time: String) -> Result<Self, Self::Error> {
+        think_time.parse()
+    }
+}
+
+impl Display for ThinkTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.distribution {
+            Distribution::Constant(millis) => write!(f, "constant:{millis}")?,
+            Distribution::Uniform(min, max) => write!(f, "uniform:{min}-{max}")?,
+            Distribution::Exponential(mean) => write!(f, "exponential:{mean}")?,
+        }
+        write!(f, ",seed={}", self.seed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Distribution, ThinkTime};
+
+    /// Mean of many samples of the think time, in milliseconds
+    fn sample_mean(think_time: &str) -> f64 {
+        const SAMPLES: usize = 100_000;
+        let mut sampler = think_time.parse::<ThinkTime>().unwrap().sampler();
+        (0..SAMPLES).map(|_| sampler.sample_millis()).sum::<f64>() / SAMPLES as f64
+    }
+
+    #[test]
+    fn test_sample_means() {
+        let exponential = sample_mean("exponential:150,seed=7");
+        assert!((exponential - 150.0).abs() < 3.0, "mean was {exponential}");
+        let uniform = sample_mean("uniform:50-250,seed=7");
+        assert!((uniform - 150.0).abs() < 1.5, "mean was {uniform}");
+        assert_eq!(sample_mean("constant:20"), 20.0);
+    }
+
+    #[test]
+    fn test_seed_reproduces_delays() {
+        let think_time: ThinkTime = "exponential:100,seed=3".parse().unwrap();
+        let (mut first, mut second) = (think_time.sampler(), think_time.sampler());
+        for _ in 0..10 {
+            assert_eq!(first.sample(), second.sample());
+        }
+    }
+
+    #[test]
+    fn test_parse_think_time() {
+        let think_time: ThinkTime = "Uniform:10-20".parse().unwrap();
+        assert_eq!(think_time.distribution, Distribution::Uniform(10.0, 20.0));
+        assert_eq!(think_time.seed, 0);
+        assert_eq!(think_time.to_string(), "uniform:10-20,seed=0");
+        assert!("uniform:20-10".parse::<ThinkTime>().is_err());
+        assert!("exponential:-5".parse::<ThinkTime>().is_err());
+        assert!("normal:10".parse::<ThinkTime>().is_err());
+        assert!("constant:10,salt=1".parse::<ThinkTime>().is_err());
+    }
+}
@@ -0,0 +1,101 @@
+//! Throttling caps the number of requests per second sent to the program under test, so
+//! that fuzzing a shared environment does not overwhelm it. It is a token bucket: every
+//! request, and every retry of it, takes a token, and tokens are refilled at the maximum
+//! rate, up to one second worth of them. When the bucket is empty, the fuzzer sleeps
+//! until enough tokens have been refilled.
+
+use std::{
+    num::NonZeroU32,
+    time::{Duration, Instant},
+};
+
+/// Token bucket that allows bursts of up to one second worth of requests.
+#[derive(Debug)]
+pub struct TokenBucket {
+    /// Tokens refilled per second
+    rate: f64,
+    /// Maximum number of tokens
+    capacity: f64,
+    /// Tokens available at `last_refill`. Negative if requests are waiting for tokens.
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    /// A full bucket for at most `max_rps` requests per second
+    pub fn new(max_rps: NonZeroU32) -> Self {
+        Self::new_at(max_rps, Instant::now())
+    }
+
+    fn new_at(max_rps: NonZeroU32, now: Instant) -> Self {
+        let rate = f64::from(max_rps.get());
+        Self {
+            rate,
+            capacity: rate,
+            tokens: rate,
+            last_refill: now,
+        }
+    }
+
+    /// Adds the tokens refilled since the last refill
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_refill);
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * self.rate).min(self.capacity);
+        self.last_refill = now;
+    }
+
+    /// Takes tokens for `requests` requests, and returns how long to wait before they may
+    /// be sent
+    fn take_at(&mut self, requests: usize, now: Instant) -> Duration {
+        self.refill(now);
+        self.tokens -= requests as f64;
+        if self.tokens < 0.0 {
+            Duration::from_secs_f64(-self.tokens / self.rate)
+        } else {
+            Duration::ZERO
+        }
+    }
+
+    /// Sleeps until `requests` requests may be sent
+    pub fn wait(&mut self, requests: usize) {
+        let delay = self.take_at(requests, Instant::now());
+        if !delay.is_zero() {
+            std::thread::sleep(delay);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        num::NonZeroU32,
+        time::{Duration, Instant},
+    };
+
+    use super::TokenBucket;
+
+    #[test]
+    fn test_tokens_refill_at_rate() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new_at(NonZeroU32::new(5).unwrap(), start);
+
+        // A full bucket allows a burst of 5 requests
+        for _ in 0..5 {
+            assert_eq!(bucket.take_at(1, start), Duration::ZERO);
+        }
+        // The sixth waits for one token, which takes a fifth of a second
+        assert_eq!(bucket.take_at(1, start), Duration::from_millis(200));
+
+        // One second later, 5 tokens were refilled, one of which the sixth request took
+        let later = start + Duration::from_secs(1);
+        for _ in 0..4 {
+            assert_eq!(bucket.take_at(1, later), Duration::ZERO);
+        }
+        assert_eq!(bucket.take_at(1, later), Duration::from_millis(200));
+
+        // Idle time does not build up more than one second worth of tokens
+        let much_later = later + Duration::from_secs(60);
+        assert_eq!(bucket.take_at(5, much_later), Duration::ZERO);
+        assert_eq!(bucket.take_at(2, much_later), Duration::from_millis(400));
+    }
+}
@@ -0,0 +1,149 @@
+//! Some endpoints, e.g. those that generate reports or long-poll for events, take longer
+//! to respond than the others. With a single request time-out they either time out
+//! spuriously, or the time-out has to be so long that hanging requests to other
+//! endpoints go unnoticed for a long time. Time-out overrides give such endpoints a time-out
+//! of their own.
+//!
+//! The overrides are read from a YAML file that maps `METHOD PATH` globs to time-outs in
+//! milliseconds, e.g.
+//!
+//! ```yaml
+//! "POST /reports/*": 60000
+//! "GET /events/**": 30000
+//! "* /export": 20000
+//! ```
+//!
+//! In a path, `*` matches any characters except `/`, and `**` also matches `/`. A method
+//! of `*` matches every method. The first matching override in the file is used.
+
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use indexmap::IndexMap;
+
+use crate::{input::Method, openapi::glob::glob_matches};
+
+/// A time-out for the requests whose method and path match the globs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimeoutOverride {
+    method: String,
+    path: String,
+    pub timeout: u64,
+}
+
+impl TimeoutOverride {
+    /// Parses an override from its `METHOD PATH` key and the time-out in milliseconds
+    fn new(key: &str, timeout: u64) -> Result<Self, anyhow::Error> {
+        let Some((method, path)) = key.trim().split_once(char::is_whitespace) else {
+            bail!("Expected 'METHOD PATH' in time-out override '{key}'");
+        };
+        let path = path.trim();
+        if !path.starts_with('/') {
+            bail!("The path of time-out override '{key}' must start with '/'");
+        }
+        Ok(Self {
+            method: method.to_owned(),
+            path: path.to_owned(),
+            timeout,
+        })
+    }
+
+    /// Whether the override applies to requests with this method and path
+    pub fn matches(&self, method: Method, path: &str) -> bool {
+        (self.method == "*" || self.method.eq_ignore_ascii_case(&method.to_string()))
+            && glob_matches(self.path.as_bytes(), path.as_bytes())
+    }
+}
+
+/// The time-out overrides, in the order of the file they were read from
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(try_from = "PathBuf")]
+pub struct TimeoutOverrides(Vec<TimeoutOverride>);
+
+impl TimeoutOverrides {
+    /// Reads the overrides from a YAML file, see the module documentation for the format
+    pub fn from_file(path: &Path) -> Result<Self, anyhow::Error> {
+        let file = File::open(path).with_context(|| {
+            format!(
+                "Could not open time-out overrides {}",
+                path.to_string_lossy()
+            )
+        })?;
+        let overrides: IndexMap<String, u64> =
+            serde_yaml::from_reader(file).with_context(|| {
+                format!(
+                    "Time-out overrides {} must map 'METHOD PATH' to milliseconds",
+                    path.to_string_lossy()
+                )
+            })?;
+        Self::from_map(overrides)
+    }
+
+    fn from_map(overrides: IndexMap<String, u64>) -> Result<Self, anyhow::Error> {
+        overrides
+            .iter()
+            .map(|(key, timeout)| TimeoutOverride::new(key, *timeout))
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+
+    /// The time-out in milliseconds of the first override that matches the request, if any
+    pub fn timeout(&self, method: Method, path: &str) -> Option<u64> {
+        self.0
+            .iter()
+            .find(|timeout_override| timeout_override.matches(method, path))
+            .map(|timeout_override| timeout_override.timeout)
+    }
+}
+
+impl TryFrom<PathBuf> for TimeoutOverrides {
+    type Error = anyhow::Error;
+
+    fn try_from(path: PathBuf) -> Result<Self, Self::Error> {
+        Self::from_file(&path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+
+    use super::TimeoutOverrides;
+    use crate::input::Method;
+
+    #[test]
+    fn test_first_matching_glob_selects_timeout() {
+        let overrides = TimeoutOverrides::from_map(IndexMap::from([
+            ("POST /reports/*".to_owned(), 60000),
+            ("GET /events/**".to_owned(), 30000),
+            ("* /export".to_owned(), 20000),
+            ("get /pets/{petId}".to_owned(), 5000),
+            ("GET /pets/*".to_owned(), 1000),
+        ]))
+        .unwrap();
+
+        assert_eq!(
+            overrides.timeout(Method::Post, "/reports/{id}"),
+            Some(60000)
+        );
+        assert_eq!(overrides.timeout(Method::Post, "/reports/"), Some(60000));
+        // A single * stays within its segment
+        assert_eq!(overrides.timeout(Method::Post, "/reports/{id}/pdf"), None);
+        assert_eq!(overrides.timeout(Method::Post, "/reports"), None);
+        assert_eq!(overrides.timeout(Method::Get, "/reports/{id}"), None);
+        assert_eq!(overrides.timeout(Method::Get, "/events/a/b"), Some(30000));
+        assert_eq!(overrides.timeout(Method::Delete, "/export"), Some(20000));
+        assert_eq!(overrides.timeout(Method::Get, "/exports"), None);
+        // Methods match case-insensitively, and the first match wins
+        assert_eq!(overrides.timeout(Method::Get, "/pets/{petId}"), Some(5000));
+        assert_eq!(
+            overrides.timeout(Method::Get, "/pets/{ownerId}"),
+            Some(1000)
+        );
+
+        assert!(TimeoutOverrides::from_map(IndexMap::from([("/reports".to_owned(), 1)])).is_err());
+    }
+}